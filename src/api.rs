@@ -0,0 +1,358 @@
+use anyhow::{Result, anyhow};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::machine::Machine;
+use crate::syncqueue::SyncQueue;
+use crate::{Config, ToolConfig, get_config_path};
+
+/// Environment variable read for the `tkit serve` token before the stored one.
+pub const TOKEN_ENV: &str = "TKIT_API_TOKEN";
+
+/// Largest request head `tkit serve` reads.
+pub const MAX_HEAD_LEN: usize = 16 * 1024;
+
+/// The request line and headers of an HTTP/1.1 request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: BTreeMap<String, String>,
+    /// Header values by lowercase name.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Request {
+    /// Parses a request head, everything before the blank line.
+    pub fn parse(head: &str) -> Result<Self> {
+        let mut lines = head.lines();
+        let request_line = lines.next().ok_or_else(|| anyhow!("Empty request"))?;
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(target), Some(version)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(anyhow!("Malformed request line '{}'", request_line));
+        };
+        if !version.starts_with("HTTP/1.") {
+            return Err(anyhow!("Unsupported protocol '{}'", version));
+        }
+
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+
+        let mut headers = BTreeMap::new();
+        for line in lines.take_while(|line| !line.is_empty()) {
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Malformed header '{}'", line))?;
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+
+        Ok(Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query,
+            headers,
+        })
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    pub fn content_length(&self) -> usize {
+        self.header("content-length")
+            .and_then(|len| len.parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// The bearer token, or the `token` query parameter for clients such as
+    /// `EventSource` that can't set headers.
+    pub fn token(&self) -> Option<&str> {
+        self.header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .or_else(|| self.query.get("token").map(String::as_str))
+    }
+}
+
+/// What a job started through the API does with its tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobAction {
+    Install,
+    Update,
+    Run,
+}
+
+impl JobAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobAction::Install => "install",
+            JobAction::Update => "update",
+            JobAction::Run => "run",
+        }
+    }
+
+    pub fn parse(action: &str) -> Option<Self> {
+        match action {
+            "install" => Some(JobAction::Install),
+            "update" => Some(JobAction::Update),
+            "run" => Some(JobAction::Run),
+            _ => None,
+        }
+    }
+}
+
+/// An endpoint of the `tkit serve` API.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Route {
+    /// `GET /tools`
+    Tools,
+    /// `GET /tools/<name>`
+    Tool(String),
+    /// `GET /status`
+    Status,
+    /// `POST /tools/<name>/install|update|run`
+    Start(String, JobAction),
+    /// `GET /jobs`
+    Jobs,
+    /// `GET /jobs/<id>`
+    Job(u64),
+    /// `GET /jobs/<id>/logs`, as server-sent events.
+    JobLogs(u64),
+}
+
+/// The endpoint for a method and path, or the status to answer with: 404 for
+/// an unknown path, 405 for a known one with the wrong method.
+pub fn route(method: &str, path: &str) -> std::result::Result<Route, u16> {
+    let segments: Vec<&str> = path
+        .trim_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let (route, expected) = match segments.as_slice() {
+        ["tools"] => (Route::Tools, "GET"),
+        ["tools", name] => (Route::Tool(name.to_string()), "GET"),
+        ["tools", name, action] => match JobAction::parse(action) {
+            Some(action) => (Route::Start(name.to_string(), action), "POST"),
+            None => return Err(404),
+        },
+        ["status"] => (Route::Status, "GET"),
+        ["jobs"] => (Route::Jobs, "GET"),
+        ["jobs", id] => (Route::Job(id.parse().map_err(|_| 404u16)?), "GET"),
+        ["jobs", id, "logs"] => (Route::JobLogs(id.parse().map_err(|_| 404u16)?), "GET"),
+        _ => return Err(404),
+    };
+    if method != expected {
+        return Err(405);
+    }
+    Ok(route)
+}
+
+/// A tool as listed by `tkit serve` and `tkit rpc`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ToolSummary {
+    pub name: String,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub installed: bool,
+    pub disabled: bool,
+    pub tags: Vec<String>,
+}
+
+impl ToolSummary {
+    pub fn new(name: &str, tool: &ToolConfig) -> Self {
+        ToolSummary {
+            name: name.to_string(),
+            description: tool.description.clone(),
+            category: tool.category.clone(),
+            installed: tool.installed,
+            disabled: tool.disabled,
+            tags: tool.tags.clone(),
+        }
+    }
+
+    pub fn all(config: &Config) -> Vec<Self> {
+        config
+            .tools
+            .iter()
+            .map(|(name, tool)| ToolSummary::new(name, tool))
+            .collect()
+    }
+}
+
+/// This machine at a glance, as reported by `tkit serve` and `tkit rpc`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct StatusSummary {
+    pub version: &'static str,
+    pub machine: String,
+    pub tools: usize,
+    pub installed: usize,
+    pub last_sync: Option<String>,
+    pub queued_pushes: usize,
+    pub running_jobs: usize,
+}
+
+impl StatusSummary {
+    pub fn new(config: &Config, running_jobs: usize) -> Self {
+        StatusSummary {
+            version: env!("CARGO_PKG_VERSION"),
+            machine: Machine::current()
+                .map(|machine| machine.name)
+                .unwrap_or_default(),
+            tools: config.tools.len(),
+            installed: config.tools.values().filter(|tool| tool.installed).count(),
+            last_sync: config.sync.last_sync.clone(),
+            queued_pushes: SyncQueue::load()
+                .map(|queue| queue.pushes.len())
+                .unwrap_or(0),
+            running_jobs,
+        }
+    }
+}
+
+/// Compares tokens in time independent of where they first differ.
+pub fn token_matches(expected: &str, given: Option<&str>) -> bool {
+    let Some(given) = given else {
+        return false;
+    };
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+pub fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+/// A complete response; the connection is closed after it.
+pub fn response(status: u16, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        status_text(status),
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// The head of a server-sent event stream.
+pub fn sse_head() -> &'static str {
+    "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n"
+}
+
+/// One server-sent event; each line of `data` gets its own `data:` field.
+pub fn sse_event(event: &str, data: &str) -> String {
+    let mut out = format!("event: {}\n", event);
+    for line in data.split('\n') {
+        out.push_str(&format!("data: {}\n", line));
+    }
+    out.push('\n');
+    out
+}
+
+/// The token clients must send, from `TKIT_API_TOKEN` or the token file,
+/// which is created on first use.
+pub fn load_or_create_token() -> Result<String> {
+    if let Ok(token) = std::env::var(TOKEN_ENV)
+        && !token.is_empty()
+    {
+        return Ok(token);
+    }
+    let path = get_api_token_path()?;
+    if let Ok(token) = fs::read_to_string(&path)
+        && !token.trim().is_empty()
+    {
+        return Ok(token.trim().to_string());
+    }
+
+    let token = generate_token()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, format!("{}\n", token))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(token)
+}
+
+fn generate_token() -> Result<String> {
+    let mut bytes = [0u8; 24];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| anyhow!("Could not generate a token"))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+pub fn get_api_token_path() -> Result<PathBuf> {
+    Ok(get_config_path()?.with_file_name("api-token"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let request = Request::parse(
+            "POST /tools/jq/install?token=abc&x=1 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n",
+        )
+        .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/tools/jq/install");
+        assert_eq!(request.content_length(), 2);
+        assert_eq!(request.token(), Some("abc"));
+
+        let request =
+            Request::parse("GET /tools HTTP/1.1\r\nAuthorization: Bearer secret\r\n").unwrap();
+        assert_eq!(request.token(), Some("secret"));
+        assert!(Request::parse("GET /tools\r\n").is_err());
+    }
+
+    #[test]
+    fn test_route() {
+        assert_eq!(route("GET", "/tools"), Ok(Route::Tools));
+        assert_eq!(
+            route("GET", "/tools/jq/"),
+            Ok(Route::Tool("jq".to_string()))
+        );
+        assert_eq!(
+            route("POST", "/tools/jq/update"),
+            Ok(Route::Start("jq".to_string(), JobAction::Update))
+        );
+        assert_eq!(route("GET", "/jobs/3/logs"), Ok(Route::JobLogs(3)));
+        assert_eq!(route("GET", "/tools/jq/install"), Err(405));
+        assert_eq!(route("POST", "/tools/jq/remove"), Err(404));
+        assert_eq!(route("GET", "/jobs/x"), Err(404));
+    }
+
+    #[test]
+    fn test_token_and_events() {
+        assert!(token_matches("secret", Some("secret")));
+        assert!(!token_matches("secret", Some("secreT")));
+        assert!(!token_matches("secret", None));
+        assert_eq!(sse_event("log", "a\nb"), "event: log\ndata: a\ndata: b\n\n");
+    }
+}
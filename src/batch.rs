@@ -0,0 +1,167 @@
+use anyhow::{Result, anyhow};
+
+/// What one line of a batch manifest asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchAction {
+    /// `install <tool> [key=value...]`
+    Install {
+        tool: String,
+        params: Vec<(String, String)>,
+    },
+    /// `update <tool>`
+    Update { tool: String },
+    /// `remove <tool>`
+    Remove { tool: String },
+    /// `add <url> [name]`: adds a tool from a recipe URL.
+    Add { url: String, name: Option<String> },
+    /// `group install <name>`: installs every tool whose category or tag is
+    /// `name`.
+    GroupInstall { group: String },
+    /// `apply`: converges the machine to the config.
+    Apply,
+}
+
+/// A step of a manifest with the line it came from, for the summary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchStep {
+    pub line: usize,
+    pub text: String,
+    pub action: BatchAction,
+}
+
+/// Parses a manifest: one action per line, in the order they run. Blank
+/// lines and `#` comments are skipped.
+///
+/// ```text
+/// # New laptop
+/// install git
+/// install node version=20
+/// add https://example.com/recipes/jq.yaml
+/// group install DevOps
+/// ```
+pub fn parse(content: &str) -> Result<Vec<BatchStep>> {
+    let mut steps = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        // A `#` inside a word, as in a URL's fragment, doesn't start a comment
+        let text = line
+            .split_once(" #")
+            .map_or(line, |(before, _)| before)
+            .trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let action = parse_action(text).map_err(|e| anyhow!("Line {}: {}", i + 1, e))?;
+        steps.push(BatchStep {
+            line: i + 1,
+            text: text.to_string(),
+            action,
+        });
+    }
+    Ok(steps)
+}
+
+fn parse_action(text: &str) -> Result<BatchAction> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let one = |what: &str| match &words[1..] {
+        [name] => Ok(name.to_string()),
+        _ => Err(anyhow!("expected '{} <{}>'", words[0], what)),
+    };
+    match words[0] {
+        "install" => {
+            let (tool, params) = words
+                .get(1..)
+                .and_then(|rest| rest.split_first())
+                .ok_or_else(|| anyhow!("expected 'install <tool> [key=value...]'"))?;
+            let params = params
+                .iter()
+                .map(|param| {
+                    param
+                        .split_once('=')
+                        .map(|(key, value)| (key.to_string(), value.to_string()))
+                        .ok_or_else(|| anyhow!("expected key=value, got '{}'", param))
+                })
+                .collect::<Result<_>>()?;
+            Ok(BatchAction::Install {
+                tool: tool.to_string(),
+                params,
+            })
+        }
+        "update" => Ok(BatchAction::Update { tool: one("tool")? }),
+        "remove" => Ok(BatchAction::Remove { tool: one("tool")? }),
+        "add" => match &words[1..] {
+            [url] => Ok(BatchAction::Add {
+                url: url.to_string(),
+                name: None,
+            }),
+            [url, name] => Ok(BatchAction::Add {
+                url: url.to_string(),
+                name: Some(name.to_string()),
+            }),
+            _ => Err(anyhow!("expected 'add <url> [name]'")),
+        },
+        "group" => match &words[1..] {
+            ["install", group] => Ok(BatchAction::GroupInstall {
+                group: group.to_string(),
+            }),
+            _ => Err(anyhow!("expected 'group install <category or tag>'")),
+        },
+        "apply" if words.len() == 1 => Ok(BatchAction::Apply),
+        "apply" => Err(anyhow!("'apply' takes no arguments")),
+        other => Err(anyhow!(
+            "unknown action '{}' (expected install, update, remove, add, group install or apply)",
+            other
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let steps = parse(
+            "# New laptop\n\ninstall git\ninstall node version=20 lts=yes  # pinned\nadd https://example.com/jq.yaml#v2 jq\ngroup install DevOps\nremove nano\napply\n",
+        )
+        .unwrap();
+        let actions: Vec<&BatchAction> = steps.iter().map(|step| &step.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                &BatchAction::Install {
+                    tool: "git".to_string(),
+                    params: vec![],
+                },
+                &BatchAction::Install {
+                    tool: "node".to_string(),
+                    params: vec![
+                        ("version".to_string(), "20".to_string()),
+                        ("lts".to_string(), "yes".to_string()),
+                    ],
+                },
+                &BatchAction::Add {
+                    url: "https://example.com/jq.yaml#v2".to_string(),
+                    name: Some("jq".to_string()),
+                },
+                &BatchAction::GroupInstall {
+                    group: "DevOps".to_string(),
+                },
+                &BatchAction::Remove {
+                    tool: "nano".to_string(),
+                },
+                &BatchAction::Apply,
+            ]
+        );
+        assert_eq!(steps[1].line, 4);
+        assert_eq!(steps[1].text, "install node version=20 lts=yes");
+    }
+
+    #[test]
+    fn test_parse_manifest_errors() {
+        let err = parse("install git\ninstall\n").unwrap_err();
+        assert!(err.to_string().starts_with("Line 2:"), "{}", err);
+        assert!(parse("install node 20").is_err());
+        assert!(parse("group DevOps").is_err());
+        assert!(parse("upgrade git").is_err());
+    }
+}
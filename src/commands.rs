@@ -1,39 +1,556 @@
 use anyhow::{Result, anyhow};
 use base64::{Engine as _, engine::general_purpose};
-use clap::Subcommand;
+use clap::{Subcommand, ValueEnum};
 use colored::*;
-use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue, USER_AGENT};
+use reqwest::StatusCode;
+use reqwest::header::{AUTHORIZATION, ETAG, HeaderMap, HeaderValue, IF_NONE_MATCH, USER_AGENT};
 use serde::{Deserialize, Serialize};
-use std::{fs, process::Command};
-use tkit::{Config, ToolConfig, get_config_path};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tkit::batch::{self, BatchAction, BatchStep};
+use tkit::converge::{self, Change, Drift, PlannedChange, Selection};
+use tkit::crypto::{self, PASSPHRASE_ENV};
+use tkit::downloads::{get_download_cache_dir, read_url, sha256_hex};
+use tkit::exitcode::{ExitKind, aborted, tool_not_found};
+use tkit::files::{FileState, expand_tilde};
+use tkit::github_cache::{CachedFile, GitHubCache};
+use tkit::history::{self, HistoryEvent, Summary};
+use tkit::intent::{self, Intent, get_intents_path};
+use tkit::journal;
+use tkit::lockfile::{LockedTool, recipe_hash};
+use tkit::mock;
+use tkit::script;
+use tkit::shellenv::{self, Shell};
+use tkit::state::get_state_path;
+use tkit::trash::Trash;
+use tkit::{
+    BIN_DIR, Checkpoint, CommandShell, Config, DEFAULT_RUN, DesiredState, ExecTarget, Lockfile,
+    MachineReport, PRIMARY_REMOTE, PackageManager, ReleaseSpec, SECRET_SETTING_KEYS, SETTING_KEYS,
+    Schedule, Settings, SnapshotTrigger, State, Step, StepTiming, SyncConfig, SyncLayout,
+    SyncRemote, Timing, ToolAction, ToolConfig, ToolStats, VersionCache,
+    audit::{self, Finding, Severity},
+    fleet, get_bin_dir, get_config_path, get_log_dir,
+    gitsync::{self, GitRemote},
+    http_client, is_glob, layout,
+    machine::{self, Machine},
+    merge::{self, Conflict, DiffLine},
+    notify::NotifyCache,
+    on_path, overrides,
+    redact::{MASK, redact, register_secret},
+    releases::{self, Archive, Release},
+    render_commands, render_template, schema,
+    syncqueue::SyncQueue,
+    template_values, write_atomic, write_private,
+};
+use tokio::sync::Semaphore;
+
+use crate::examples::{PRESETS, find_installed, find_preset};
+use crate::prompt::{assume_yes, confirm, multi_select, read_line, read_secret};
 
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Install a tool
-    Install { tool: String },
-    /// Remove a tool
-    Remove { tool: String },
-    /// Update a tool
-    Update { tool: String },
+    /// Install a tool (or every tool matching a pattern such as 'k8s-*')
+    Install {
+        #[arg(required_unless_present_any = ["all", "interactive"])]
+        tool: Option<String>,
+        /// Install every enabled tool
+        #[arg(long, conflicts_with = "tool")]
+        all: bool,
+        /// Pick the tools to install from a checklist of those not installed
+        #[arg(short, long, conflicts_with_all = ["all", "locked", "from_registry"])]
+        interactive: bool,
+        /// Keep going when a tool fails and report all failures at the end
+        #[arg(long)]
+        continue_on_error: bool,
+        /// Install even if a conflicting tool is installed
+        #[arg(long)]
+        force: bool,
+        /// Install the versions recorded in tkit.lock
+        #[arg(long, conflicts_with = "from_registry")]
+        locked: bool,
+        /// Skip the steps an earlier, failed install of the tool completed
+        #[arg(long)]
+        resume: bool,
+        /// Import the recipe from the community registry before installing
+        #[arg(long, conflicts_with = "all")]
+        from_registry: bool,
+        /// Set a recipe parameter without prompting (repeatable)
+        #[arg(
+            long = "param",
+            visible_alias = "input",
+            value_name = "KEY=VALUE",
+            value_parser = parse_param
+        )]
+        params: Vec<(String, String)>,
+    },
+    /// Remove a tool (or every tool matching a pattern)
+    Remove {
+        #[arg(required_unless_present = "interactive")]
+        tool: Option<String>,
+        /// Pick the tools to remove from a checklist of installed ones
+        #[arg(short, long)]
+        interactive: bool,
+        /// Keep going when a tool fails and report all failures at the end
+        #[arg(long)]
+        continue_on_error: bool,
+        /// Set a recipe parameter without prompting (repeatable)
+        #[arg(
+            long = "param",
+            visible_alias = "input",
+            value_name = "KEY=VALUE",
+            value_parser = parse_param
+        )]
+        params: Vec<(String, String)>,
+    },
+    /// Update a tool (or every tool matching a pattern)
+    Update {
+        #[arg(required_unless_present_any = ["all", "interactive"])]
+        tool: Option<String>,
+        /// Update every enabled tool
+        #[arg(long, conflicts_with = "tool")]
+        all: bool,
+        /// Pick the tools to update from a checklist of installed ones
+        #[arg(short, long, conflicts_with = "all")]
+        interactive: bool,
+        /// Keep going when a tool fails and report all failures at the end
+        #[arg(long)]
+        continue_on_error: bool,
+        /// Update pinned tools too
+        #[arg(long)]
+        force: bool,
+        /// Show the release notes since the installed version first
+        /// (tools with a github_repo or release)
+        #[arg(long)]
+        changelog: bool,
+        /// Set a recipe parameter without prompting (repeatable)
+        #[arg(
+            long = "param",
+            visible_alias = "input",
+            value_name = "KEY=VALUE",
+            value_parser = parse_param
+        )]
+        params: Vec<(String, String)>,
+    },
     /// List available tools
-    List,
+    List {
+        /// Order of the listed tools
+        #[arg(long, value_enum, default_value_t = ListSort::Name)]
+        sort: ListSort,
+        /// Only list tools in this category
+        #[arg(long)]
+        category: Option<String>,
+        /// Only list installed tools
+        #[arg(long, conflicts_with = "missing")]
+        installed: bool,
+        /// Only list tools that are not installed
+        #[arg(long)]
+        missing: bool,
+        /// Also show when each tool was added and last modified
+        #[arg(long)]
+        long: bool,
+    },
+    /// Show a tool's full definition
+    Show { tool: String },
+    /// Show usage statistics for each tool and a summary of past activity
+    Stats {
+        /// Only summarize activity from the last N days
+        #[arg(long)]
+        days: Option<u32>,
+    },
+    /// Show reports built from what tkit recorded on this machine
+    Report {
+        #[command(subcommand)]
+        action: ReportAction,
+    },
+    /// Print PATH additions and exports of installed tools for your shell rc,
+    /// e.g. eval "$(tkit env)"
+    Env {
+        /// Shell syntax to print (defaults to $SHELL)
+        #[arg(long)]
+        shell: Option<Shell>,
+    },
+    /// Print the line that puts tkit's bin directory on PATH
+    Path {
+        /// Shell syntax to print (defaults to $SHELL)
+        #[arg(long)]
+        shell: Option<Shell>,
+        /// Add the line to your shell's startup file instead of printing it
+        #[arg(long)]
+        install: bool,
+    },
+    /// Check for tool updates and remote config changes; with --quiet, for
+    /// shell rc files, print a one-line nudge from the last check or nothing
+    Notify {
+        /// Print only the nudge, from a cached check refreshed in the background
+        #[arg(long)]
+        quiet: bool,
+    },
+    /// Find well-known tools already installed here (on PATH or in the system
+    /// package manager) and add them to the config, marked installed
+    Scan {
+        /// Add every tool found without asking
+        #[arg(long)]
+        all: bool,
+    },
+    /// Show where a tool's executable is
+    Which {
+        /// Tool name
+        tool: String,
+    },
     /// Add a new tool configuration
-    Add { tool: String },
-    /// Delete a tool configuration
+    Add {
+        /// Tool name (overrides the name in a downloaded definition; the package name with --pm)
+        #[arg(required_unless_present = "from_url")]
+        tool: Option<String>,
+        /// Download a single-tool YAML definition instead of entering it interactively
+        #[arg(long, value_name = "URL", conflicts_with = "pm")]
+        from_url: Option<String>,
+        /// Create the tool from a package manager package without prompts
+        #[arg(long, value_name = "MANAGER")]
+        pm: Option<PackageManager>,
+        /// Executable name when it differs from the package (with --pm)
+        #[arg(long, value_name = "NAME", requires = "pm")]
+        bin: Option<String>,
+        /// Tool description (with --pm)
+        #[arg(long, requires = "pm")]
+        description: Option<String>,
+        /// Set a recipe parameter without prompting (repeatable)
+        #[arg(
+            long = "param",
+            visible_alias = "input",
+            value_name = "KEY=VALUE",
+            value_parser = parse_param
+        )]
+        params: Vec<(String, String)>,
+    },
+    /// Delete a tool configuration (or every tool matching a pattern); it
+    /// goes to the trash
     Delete { tool: String },
+    /// Bring a deleted tool configuration back from the trash
+    Restore { tool: String },
+    /// Manage deleted tool configurations
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+    /// Copy a tool configuration under a new name
+    Copy {
+        /// Existing tool to copy
+        src: String,
+        /// Name for the copy
+        dst: String,
+    },
+    /// Disable a tool so bulk operations skip it
+    Disable { tool: String },
+    /// Re-enable a disabled tool
+    Enable { tool: String },
+    /// Hold a tool at a version so updates skip it
+    Pin {
+        tool: String,
+        /// Version to pin; defaults to the installed or last used version
+        version: Option<String>,
+    },
+    /// Release a pinned tool
+    Unpin { tool: String },
+    /// Rename a tool configuration
+    Rename {
+        /// Current tool name
+        old: String,
+        /// New tool name
+        new: String,
+    },
     /// Run a tool
-    Run { tool: String },
+    Run {
+        /// Tool name, optionally with a run entry as tool:entry
+        tool: String,
+        /// Named run entry to use instead of 'default'
+        #[arg(long = "cmd", value_name = "NAME")]
+        entry: Option<String>,
+        /// Arguments after `--`, appended to the last run command
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+    /// Install and remove tools until the machine matches the config
+    Apply {
+        /// Converge to the tools in this category instead of those marked present
+        #[arg(long)]
+        category: Option<String>,
+        /// Converge to the tools with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Keep going when a tool fails and report all failures at the end
+        #[arg(long)]
+        continue_on_error: bool,
+        /// Install tools from a config at a URL or in a GitHub repository
+        /// (owner/repo) instead, without setting up sync
+        #[arg(long, value_name = "URL|OWNER/REPO")]
+        from_url: Option<String>,
+    },
+    /// Run the actions in a manifest file one after another, e.g. a
+    /// machine-setup runbook checked into a repository
+    Batch {
+        /// One action per line: install <tool> [key=value...], update <tool>,
+        /// remove <tool>, add <url> [name], group install <category or tag>, apply
+        file: std::path::PathBuf,
+        /// Keep going when an action fails and report all failures at the end
+        #[arg(long)]
+        continue_on_error: bool,
+    },
+    /// Show what 'tkit apply' would install, update and remove
+    Plan {
+        /// Converge to the tools in this category instead of those marked present
+        #[arg(long)]
+        category: Option<String>,
+        /// Converge to the tools with this tag
+        #[arg(long)]
+        tag: Option<String>,
+    },
+    /// Report tools installed, removed or upgraded outside of tkit
+    Drift {
+        /// Record what is on the machine in the local state and tkit.lock
+        #[arg(long)]
+        reconcile: bool,
+    },
+    /// Scan the config for embedded credentials, loose file permissions and
+    /// tokens in the synced copy
+    Audit,
+    /// Validate a repository's tkit config for CI: schema, dangerous
+    /// commands, credentials and optionally installs in containers
+    CiValidate {
+        /// The config to check (default tkit-config.yaml; split layouts are
+        /// joined with their tools/ files)
+        file: Option<std::path::PathBuf>,
+        /// Install the changed tools, each in a fresh container
+        #[arg(long)]
+        install: bool,
+        /// Git revision to find changed tools against (default: the pull
+        /// request's base branch on GitHub Actions; all tools otherwise)
+        #[arg(long, requires = "install")]
+        base: Option<String>,
+        /// Container image the installs run in
+        #[arg(long, default_value = "ubuntu:24.04", requires = "install")]
+        image: String,
+    },
+    /// Run every installed tool's verify (or run) commands and report pass/fail
+    Check {
+        /// Seconds each tool's checks may take before it counts as failed
+        /// (default: settings.timeout, or 30)
+        #[arg(long)]
+        timeout: Option<u64>,
+    },
+    /// List installed tools whose GitHub repository has a newer release
+    Outdated {
+        /// Show the notes of the newer releases
+        #[arg(long)]
+        changelog: bool,
+    },
+    /// Search the recipe registry
+    Search { query: String },
+    /// Preview a recipe from the registry
+    Info { name: String },
+    /// Share a definition outside your config
+    Export {
+        #[command(subcommand)]
+        what: ExportAction,
+    },
+    /// Open a pull request adding a tool definition to the registry
+    Publish { tool: String },
     /// Show examples of tool configurations
-    Examples,
+    Examples {
+        /// List the example names
+        #[arg(long, conflicts_with_all = ["show", "add"])]
+        list: bool,
+        /// Print an example's tool definition
+        #[arg(long, value_name = "NAME", conflicts_with = "add")]
+        show: Option<String>,
+        /// Add an example to your configuration
+        #[arg(long, value_name = "NAME")]
+        add: Option<String>,
+    },
     /// Initialize the tkit configuration
-    Init,
+    Init {
+        /// Bootstrap from an existing config: a GitHub repo (owner/repo) or a raw URL
+        #[arg(long, value_name = "REPO_OR_URL")]
+        from: Option<String>,
+        /// GitHub token for syncing with the repo given to --from
+        #[arg(long, requires = "from")]
+        token: Option<String>,
+        /// Install every enabled tool once the config is in place
+        #[arg(long, requires = "from")]
+        install: bool,
+        /// Start with a bundled preset of tools: web-dev, devops, data-science or rust-dev
+        #[arg(long, conflicts_with = "from")]
+        preset: Option<String>,
+    },
+    /// Read and change tkit's settings and variables
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Open the whole config in your editor, checking it before it is saved
+    EditConfig,
+    /// Revert the most recent configuration change
+    Undo,
     /// Reset configuration (clear all tools and settings)
     Reset,
+    /// Show or rename this machine's identity used in sync and history
+    Machine {
+        #[command(subcommand)]
+        action: MachineAction,
+    },
+    /// Serve a local HTTP API for editors, launchers and dashboards
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:7878")]
+        listen: String,
+        /// Token clients must send (default: $TKIT_API_TOKEN, or one generated and stored)
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Speak JSON-RPC on stdin/stdout, for editor and IDE plugins
+    Rpc,
     /// Sync configuration with GitHub
     Sync {
         #[command(subcommand)]
         action: SyncAction,
     },
+    /// Manage the config files that travel with tools
+    Files {
+        #[command(subcommand)]
+        action: FilesAction,
+    },
+    /// Manage the shell aliases declared by tools
+    Alias {
+        #[command(subcommand)]
+        action: AliasAction,
+    },
+    /// Run the scheduled run commands of installed tools
+    Scheduler {
+        #[command(subcommand)]
+        action: SchedulerAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum FilesAction {
+    /// Link or copy the config files of enabled tools into place
+    Apply {
+        /// Only this tool's files
+        tool: Option<String>,
+        /// Replace differing files (the old file is kept as <file>.tkit-backup)
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show whether each managed file is in place
+    Status {
+        /// Only this tool's files
+        tool: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print the value of a setting, e.g. 'sync.auto_sync'
+    Get { key: String },
+    /// Set a setting, e.g. 'tkit config set settings.shell bash'
+    Set { key: String, value: String },
+    /// Reset a setting to its default
+    Unset { key: String },
+    /// List every setting with its current value
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum ExportAction {
+    /// Print a tool's recipe as YAML, or upload it as a gist
+    Tool {
+        name: String,
+        /// Upload it as a secret GitHub Gist and print the URL
+        #[arg(long)]
+        gist: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TrashAction {
+    /// List deleted tools that can be restored
+    List,
+    /// Permanently drop every deleted tool
+    Empty,
+}
+
+#[derive(Subcommand)]
+pub enum AliasAction {
+    /// Write the aliases of installed tools to files your shell can source
+    Sync,
+    /// List the aliases declared by each tool
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum SchedulerAction {
+    /// Stay in the foreground and run entries when their schedule is due
+    Run,
+    /// List scheduled run entries
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum ReportAction {
+    /// Show how long each tool's latest install, update and run took, slowest first
+    Timings {
+        /// Also list the duration of every step
+        #[arg(long)]
+        steps: bool,
+    },
+}
+
+/// How `tkit sync pull` settles tools changed both here and on the remote.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PullStrategy {
+    /// Ask about each conflicting tool
+    Merge,
+    /// Keep this machine's version of conflicting tools
+    Ours,
+    /// Take the remote's version of conflicting tools
+    Theirs,
+    /// Only add tools that don't exist here; never touch existing ones
+    AppendNew,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ListSort {
+    Name,
+    /// Installed first, then missing, then disabled
+    Status,
+    /// Most recently added first
+    Added,
+    /// Most recently modified first
+    Modified,
+    /// Most recently run (or installed/updated) first
+    LastUsed,
+}
+
+/// Which tools `tkit list` shows.
+pub struct ListFilter<'a> {
+    pub category: Option<&'a str>,
+    pub installed: bool,
+    pub missing: bool,
+}
+
+#[derive(Subcommand)]
+pub enum MachineAction {
+    /// Show this machine's name, platform and when tkit first ran here
+    Show,
+    /// Rename this machine in sync commits, history and the fleet view
+    SetName {
+        /// New name, e.g. work-laptop
+        name: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -41,10 +558,20 @@ pub enum SyncAction {
     /// Setup GitHub integration
     Setup {
         /// GitHub repository (username/repo-name)
-        repo: String,
+        #[arg(required_unless_present = "ssh", conflicts_with = "ssh")]
+        repo: Option<String>,
         /// GitHub personal access token
         #[arg(short, long)]
         token: Option<String>,
+        /// Sync with git over SSH instead of a token, e.g. git@github.com:user/repo.git
+        #[arg(long, value_name = "URL", conflicts_with = "token")]
+        ssh: Option<String>,
+        /// Private key to use with --ssh (a new deploy key is generated when omitted)
+        #[arg(long, requires = "ssh")]
+        key: Option<String>,
+        /// Keep the config on this branch, created on first push, instead of the default branch
+        #[arg(long)]
+        branch: Option<String>,
     },
     /// Create a new GitHub repository
     CreateRepo {
@@ -60,12 +587,66 @@ pub enum SyncAction {
         #[arg(short, long)]
         token: Option<String>,
     },
-    /// Push local config to GitHub
-    Push,
+    /// Push local config to GitHub and every other remote
+    Push {
+        /// Push even when the config hasn't changed since the last push
+        #[arg(long)]
+        force: bool,
+    },
     /// Pull config from GitHub
-    Pull,
+    Pull {
+        /// Remote to pull from (defaults to the main repository)
+        #[arg(long)]
+        remote: Option<String>,
+        /// How to settle tools changed on both sides
+        #[arg(long, value_enum, default_value = "merge")]
+        strategy: PullStrategy,
+        /// Pull only this tool, leaving the rest of the config alone (repeatable)
+        #[arg(long = "tool", value_name = "NAME")]
+        tools: Vec<String>,
+    },
+    /// Add another place to push the config to
+    AddRemote {
+        /// Name used with 'tkit sync pull --remote'
+        name: String,
+        /// GitHub repository (username/repo-name)
+        #[arg(long, conflicts_with = "path", required_unless_present = "path")]
+        repo: Option<String>,
+        /// Local file to write the config to, e.g. on a backup drive
+        #[arg(long)]
+        path: Option<String>,
+        /// Token for the repository (defaults to the main sync token)
+        #[arg(short, long, requires = "repo")]
+        token: Option<String>,
+        /// Branch of the repository to keep the config on
+        #[arg(long, requires = "repo")]
+        branch: Option<String>,
+    },
+    /// Encrypt the config with a passphrase before pushing it
+    Encrypt {
+        /// Push unencrypted again
+        #[arg(long)]
+        off: bool,
+    },
+    /// Remove a remote added with add-remote
+    RemoveRemote {
+        /// Remote name
+        name: String,
+    },
     /// Show sync status
-    Status,
+    Status {
+        /// List every machine that has pushed the config, with its tool counts and drift
+        #[arg(long)]
+        machines: bool,
+    },
+    /// Push the changes auto-sync queued while offline
+    Flush,
+    /// Check that the pushed config parses, and that no token was pushed with it
+    Verify {
+        /// Remote to check (defaults to the main repository)
+        #[arg(long)]
+        remote: Option<String>,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -84,11 +665,24 @@ struct GitHubFile {
     encoding: Option<String>,
 }
 
+/// Response to a contents API PUT; only the new blob SHA is needed.
+#[derive(Debug, Deserialize)]
+struct GitHubPutFile {
+    content: GitHubPutContent,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubPutContent {
+    sha: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct GitHubCreateFile {
     message: String,
     content: String,
     sha: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -100,6 +694,77 @@ struct GitHubRepo {
     private: bool,
     html_url: String,
     clone_url: String,
+    default_branch: String,
+}
+
+/// A branch as the git refs API returns it.
+#[derive(Debug, Deserialize)]
+struct GitHubRef {
+    object: GitHubObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubObject {
+    sha: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateRefRequest {
+    #[serde(rename = "ref")]
+    reference: String,
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubCommit {
+    tree: GitHubObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubTree {
+    tree: Vec<GitHubTreeEntry>,
+}
+
+/// An entry of a tree listing, or of a directory listing from the contents API.
+#[derive(Debug, Deserialize)]
+struct GitHubTreeEntry {
+    path: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateTreeRequest {
+    base_tree: String,
+    tree: Vec<TreeChange>,
+}
+
+#[derive(Debug, Serialize)]
+struct TreeChange {
+    path: String,
+    mode: &'static str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(flatten)]
+    blob: TreeBlob,
+}
+
+/// A file's new content, or a null `sha`, which deletes it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum TreeBlob {
+    Content(String),
+    Sha(Option<String>),
+}
+
+#[derive(Debug, Serialize)]
+struct CreateCommitRequest {
+    message: String,
+    tree: String,
+    parents: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct UpdateRefRequest {
+    sha: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -110,7 +775,77 @@ struct CreateRepoRequest {
     auto_init: bool,
 }
 
-pub async fn execute_commands(commands: &[String], tool_name: &str, action: &str) -> Result<()> {
+/// A step that exited unsuccessfully, carrying its exit code so `main` can
+/// exit with it.
+#[derive(Debug)]
+pub struct CommandFailed {
+    pub command: String,
+    pub code: Option<i32>,
+    /// The step's `timeout` when it was killed for outliving it.
+    pub timed_out: Option<u64>,
+    /// The tool's log file holding the step's full output.
+    pub log: Option<std::path::PathBuf>,
+}
+
+impl std::fmt::Display for CommandFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Command failed: {} ({})",
+            self.command,
+            failure_reason(self.code, self.timed_out)
+        )?;
+        if let Some(log) = &self.log {
+            write!(f, "\nFull output: {}", log.display())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CommandFailed {}
+
+fn failure_reason(code: Option<i32>, timed_out: Option<u64>) -> String {
+    match (timed_out, code) {
+        (Some(secs), _) => format!("timed out after {}s", secs),
+        (None, Some(code)) => format!("exit code {}", code),
+        (None, None) => "terminated by a signal".to_string(),
+    }
+}
+
+/// Runs rendered steps in order, skipping those whose conditions say so;
+/// `vars` are what condition expressions are evaluated with. Values that
+/// steps `register` are filled into the steps after them.
+pub async fn execute_commands(
+    commands: &[Step],
+    vars: &BTreeMap<String, String>,
+    tool_name: &str,
+    action: &str,
+) -> Result<()> {
+    execute_commands_with_args(commands, &[], vars, tool_name, action).await
+}
+
+/// Like [`execute_commands`], with `extra_args` appended to the last step.
+pub async fn execute_commands_with_args(
+    commands: &[Step],
+    extra_args: &[String],
+    vars: &BTreeMap<String, String>,
+    tool_name: &str,
+    action: &str,
+) -> Result<()> {
+    run_steps(commands, extra_args, vars, tool_name, action, false).await
+}
+
+/// Runs steps as [`execute_commands_with_args`] does. Install steps record a
+/// checkpoint after each one, and with `resume` those done by the last,
+/// unfinished attempt are not run again.
+async fn run_steps(
+    commands: &[Step],
+    extra_args: &[String],
+    vars: &BTreeMap<String, String>,
+    tool_name: &str,
+    action: &str,
+    resume: bool,
+) -> Result<()> {
     if commands.is_empty() {
         println!(
             "{}",
@@ -126,25 +861,193 @@ pub async fn execute_commands(commands: &[String], tool_name: &str, action: &str
             .bold()
     );
 
-    for (i, cmd) in commands.iter().enumerate() {
-        println!("{}", format!("  Step {}: {}", i + 1, cmd).cyan());
-
-        let mut parts = cmd.split_whitespace();
-        let program = parts.next().ok_or_else(|| anyhow!("Empty command"))?;
-        let args: Vec<&str> = parts.collect();
+    let config = Config::load().unwrap_or_default();
+    let output_lines = config.settings.output_lines;
+    let tool_shell = config
+        .tools
+        .get(tool_name)
+        .and_then(|tool| tool.shell)
+        .or(config.settings.shell);
+    let target = config
+        .tools
+        .get(tool_name)
+        .map(|tool| tool.target)
+        .unwrap_or_default();
+    let hooks = config
+        .tools
+        .get(tool_name)
+        .map(|tool| tool.hooks.clone())
+        .unwrap_or_default();
+    let mut log = StepLog::open(tool_name, action);
+    let started = std::time::Instant::now();
+    let mut steps = Vec::new();
+    let mut vars = template_values(vars);
+    let mut registered = BTreeMap::new();
+
+    let tracked = action == "install";
+    let fingerprint = Checkpoint::fingerprint(commands);
+    let save_checkpoint = |completed: usize, registered: &BTreeMap<String, String>| {
+        if tracked {
+            let checkpoint = Checkpoint {
+                at: chrono::Utc::now().to_rfc3339(),
+                fingerprint: fingerprint.clone(),
+                completed,
+                total: commands.len(),
+                registered: registered.clone(),
+            };
+            record_usage(tool_name, |stats, _| stats.checkpoint = Some(checkpoint));
+        }
+    };
+    let mut start = 0;
+    if resume {
+        let checkpoint = State::load()?
+            .tools
+            .remove(tool_name)
+            .and_then(|stats| stats.checkpoint);
+        match checkpoint {
+            Some(checkpoint) => match checkpoint.resume_at(commands) {
+                Some(completed) => {
+                    println!(
+                        "{}",
+                        format!("  Resuming at step {} of {}", completed + 1, commands.len())
+                            .cyan()
+                    );
+                    start = completed;
+                    vars.extend(checkpoint.registered.clone());
+                    registered = checkpoint.registered;
+                }
+                None => println!(
+                    "{}",
+                    "  The steps changed since the last attempt; starting over".yellow()
+                ),
+            },
+            None => println!(
+                "{}",
+                "  No unfinished install to resume; starting from the first step".dimmed()
+            ),
+        }
+    }
 
-        let output = Command::new(program).args(&args).output()?;
+    if let Some((name, hook)) = hooks.before(action) {
+        run_hook(name, hook, &vars, target, tool_name, &mut log)?;
+    }
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow!("Command failed: {}\nError: {}", cmd, stderr));
+    for (i, step) in commands.iter().enumerate() {
+        if i < start {
+            let shown = step.description.as_deref().unwrap_or(step.summary());
+            println!(
+                "{}",
+                format!("  Step {}: {} (done)", i + 1, redact(shown)).dimmed()
+            );
+            continue;
+        }
+        let step = &step.render(&registered);
+        let cmd = step.command.as_str();
+        let shell = step.shell.or(tool_shell);
+        let skipped =
+            step.skip_reason(&vars, |condition| condition_holds(condition, shell, target))?;
+        if let Some(reason) = skipped {
+            println!(
+                "{}",
+                format!(
+                    "  Step {}: {} (skipped: {})",
+                    i + 1,
+                    redact(step.summary()),
+                    redact(&reason)
+                )
+                .dimmed()
+            );
+            log.write(&format!("# skipped {} ({})", cmd, reason));
+            if let Some(name) = &step.register {
+                vars.insert(name.clone(), String::new());
+                registered.insert(name.clone(), String::new());
+            }
+            save_checkpoint(i + 1, &registered);
+            continue;
         }
+        let shown = step.description.as_deref().unwrap_or(step.summary());
+        println!("{}", format!("  Step {}: {}", i + 1, redact(shown)).cyan());
+        log.write(&format!("$ {}", cmd));
+
+        let extra_args = if i + 1 == commands.len() {
+            extra_args
+        } else {
+            &[]
+        };
+        let (cmd, invocation) = match step.script {
+            true => (step.summary(), None),
+            false => {
+                let (cmd, program, args) =
+                    step_invocation(cmd, shell, step.sudo, target, extra_args, &step.env, &vars)?;
+                (cmd, Some((program, args)))
+            }
+        };
 
-        // Print stdout if there's any
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        if !stdout.trim().is_empty() {
-            println!("    {}", stdout.trim());
+        let step_started = std::time::Instant::now();
+        let (code, output) = match (invocation, mock::active()) {
+            (None, _) => run_script(step, &vars, target, i + 1, output_lines, &mut log),
+            (Some(_), Some(mock)) => {
+                run_mocked(mock.respond(cmd), step, i + 1, output_lines, &mut log)
+            }
+            (Some((program, args)), None) => {
+                run_streaming(&program, &args, step, i + 1, output_lines, &mut log)?
+            }
+        };
+        let succeeded = code == Some(0);
+        steps.push(StepTiming {
+            command: redact(cmd),
+            seconds: step_started.elapsed().as_secs_f64(),
+        });
+        if let Some(name) = &step.register {
+            let value = output.captured.trim().to_string();
+            vars.insert(name.clone(), value.clone());
+            registered.insert(name.clone(), value);
+        }
+        let timed_out = output.timed_out.then_some(step.timeout).flatten();
+        if !succeeded && step.allow_failure {
+            let reason = failure_reason(code, timed_out);
+            println!(
+                "{}",
+                format!("  ⚠ Step {} failed ({}), continuing", i + 1, reason).yellow()
+            );
+            log.write(&format!("# step {} failed ({}), allowed", i + 1, reason));
+        } else if !succeeded {
+            if output.truncated {
+                println!(
+                    "{}",
+                    format!("  Last {} lines:", output.tail.len()).yellow()
+                );
+                for line in &output.tail {
+                    println!("    {}", line);
+                }
+            }
+            if show_timings() {
+                print_timings(tool_name, action, started.elapsed().as_secs_f64(), &steps);
+            }
+            if tracked && i > 0 && !config.settings.rollback_on_failure {
+                println!(
+                    "{}",
+                    format!(
+                        "  Once fixed, 'tkit install {} --resume' picks up at step {}",
+                        tool_name,
+                        i + 1
+                    )
+                    .dimmed()
+                );
+            }
+            return Err(CommandFailed {
+                command: redact(cmd),
+                code,
+                timed_out,
+                log: log.path,
+            }
+            .into());
         }
+        save_checkpoint(i + 1, &registered);
+    }
+
+    if let Some((name, hook)) = hooks.after(action) {
+        run_hook(name, hook, &vars, target, tool_name, &mut log)?;
     }
 
     println!(
@@ -153,494 +1056,5579 @@ pub async fn execute_commands(commands: &[String], tool_name: &str, action: &str
             .green()
             .bold()
     );
+    let seconds = started.elapsed().as_secs_f64();
+    if show_timings() {
+        print_timings(tool_name, action, seconds, &steps);
+    }
+    record_usage(tool_name, |stats, now| {
+        stats.record_timing(action, now, seconds, steps);
+        if tracked {
+            stats.checkpoint = None;
+        }
+    });
     Ok(())
 }
 
-trait ToTitleCase {
-    fn to_title_case(&self) -> String;
-}
+static SHOW_TIMINGS: AtomicBool = AtomicBool::new(false);
 
-impl ToTitleCase for str {
-    fn to_title_case(&self) -> String {
-        let mut chars = self.chars();
-        match chars.next() {
-            None => String::new(),
-            Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
-        }
-    }
+/// Turns on the per-step breakdown printed after each tool's commands (`--timings`).
+pub fn set_show_timings(enabled: bool) {
+    SHOW_TIMINGS.store(enabled, Ordering::Relaxed);
 }
 
-// install_tool is used to install a particular configured tool
-pub async fn install_tool(tool_name: &str) -> Result<()> {
-    let mut config = Config::load()?;
-
-    let tool = config.tools.get_mut(tool_name).ok_or_else(|| {
-        anyhow!(
-            "Tool '{}' not found. Use 'tkit add {}' to add it first.",
-            tool_name,
-            tool_name
-        )
-    })?;
+fn show_timings() -> bool {
+    SHOW_TIMINGS.load(Ordering::Relaxed)
+}
 
-    if tool.installed {
+fn print_timings(tool_name: &str, action: &str, seconds: f64, steps: &[StepTiming]) {
+    println!(
+        "{}",
+        format!("  Timings for {} {}:", action, tool_name).bold()
+    );
+    for (i, step) in steps.iter().enumerate() {
         println!(
-            "{}",
-            format!("Tool '{}' is already installed.", tool_name).yellow()
+            "    {:>7.1}s  {} {}",
+            step.seconds,
+            format!("Step {}:", i + 1).dimmed(),
+            step.command
         );
-        return Ok(());
     }
+    println!("    {:>7.1}s  {}", seconds, "Total".bold());
+}
 
-    execute_commands(&tool.install_commands, tool_name, "install").await?;
-
-    tool.installed = true;
-    config.save()?;
+/// Whether a command condition holds, i.e. exits 0. It runs in the step's
+/// `#!shell`, the tool's shell or the platform shell, since conditions are
+/// usually shell tests, and its output is discarded.
+fn condition_holds(
+    condition: &str,
+    shell: Option<CommandShell>,
+    target: ExecTarget,
+) -> Result<bool> {
+    let shell = shell.or(Some(CommandShell::Auto));
+    let (condition, program, args) = step_invocation(
+        condition,
+        shell,
+        false,
+        target,
+        &[],
+        &BTreeMap::new(),
+        &BTreeMap::new(),
+    )?;
+    if let Some(mock) = mock::active() {
+        return Ok(mock.respond(condition).success());
+    }
+    Ok(std::process::Command::new(&program)
+        .args(&args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success()))
+}
 
-    auto_sync_if_enabled(&config).await?;
+/// Program and arguments for one step: through its `#!shell` prefix or the
+/// step's or tool's shell when any is set, otherwise the command split into
+/// words as a shell would, after expanding variables from the step's `env`,
+/// the environment and then tkit's `vars`. It then runs through `sudo` if
+/// asked and is wrapped for the tool's target. Also returns the command
+/// without its prefix.
+fn step_invocation<'a>(
+    step: &'a str,
+    shell: Option<CommandShell>,
+    sudo: bool,
+    target: ExecTarget,
+    extra_args: &[String],
+    env: &BTreeMap<String, String>,
+    vars: &BTreeMap<String, String>,
+) -> Result<(&'a str, String, Vec<String>)> {
+    let (step_shell, cmd) = shellenv::split_step_shell(step).map_err(|e| anyhow!(e))?;
+    let (program, args) = match step_shell.or(shell) {
+        Some(shell) => {
+            let mut script = cmd.to_string();
+            for arg in extra_args {
+                script.push(' ');
+                script.push_str(&shell.quote(arg));
+            }
+            shell.command(&script)
+        }
+        None => {
+            let expanded = shellenv::expand_vars(cmd, |name| {
+                env.get(name)
+                    .cloned()
+                    .or_else(|| std::env::var(name).ok())
+                    .or_else(|| vars.get(name).cloned())
+            });
+            let mut parts = shellenv::split_words(&expanded)
+                .map_err(|e| anyhow!(e))?
+                .into_iter();
+            let program = parts.next().ok_or_else(|| anyhow!("Empty command"))?;
+            (program, parts.chain(extra_args.iter().cloned()).collect())
+        }
+    };
+    let (program, args) = if sudo {
+        (
+            "sudo".to_string(),
+            std::iter::once(program).chain(args).collect(),
+        )
+    } else {
+        (program, args)
+    };
+    let (program, args) = target.wrap(program, args).map_err(|e| anyhow!(e))?;
+    Ok((cmd, program, args))
+}
 
-    Ok(())
+/// Per-tool log (`~/.config/tkit/logs/<tool>.log`) receiving every line of
+/// step output. Logging is best effort: when the file can't be opened, output
+/// is only shown on the terminal.
+struct StepLog {
+    path: Option<std::path::PathBuf>,
+    file: Option<fs::File>,
 }
 
-pub async fn remove_tool(tool_name: &str) -> Result<()> {
-    let mut config = Config::load()?;
+impl StepLog {
+    fn open(tool_name: &str, action: &str) -> Self {
+        let path = get_log_dir().map(|dir| dir.join(format!("{}.log", tool_name)));
+        let file = path.as_ref().ok().and_then(|path| {
+            fs::create_dir_all(path.parent()?).ok()?;
+            fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .ok()
+        });
+        let mut log = StepLog {
+            path: path.ok().filter(|_| file.is_some()),
+            file,
+        };
+        log.write(&format!(
+            "=== {} {} at {} ===",
+            action,
+            tool_name,
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+        ));
+        log
+    }
 
-    let tool = config
-        .tools
-        .get_mut(tool_name)
-        .ok_or_else(|| anyhow!("Tool '{}' not found.", tool_name))?;
+    fn write(&mut self, line: &str) {
+        use std::io::Write;
 
-    if !tool.installed {
-        println!(
-            "{}",
-            format!("Tool '{}' is not installed.", tool_name).yellow()
-        );
-        return Ok(());
+        if let Some(file) = &mut self.file {
+            let _ = writeln!(file, "{}", redact(line));
+        }
     }
+}
 
-    execute_commands(&tool.remove_commands, tool_name, "remove").await?;
+/// What a step printed, as far as needed after it exits.
+struct StepOutput {
+    /// The last lines of output, up to the `output_lines` setting.
+    tail: std::collections::VecDeque<String>,
+    /// Whether some lines were not shown on the terminal.
+    truncated: bool,
+    /// Whether the step was killed for outliving its `timeout`.
+    timed_out: bool,
+    /// All of stdout, when the step registers it.
+    captured: String,
+}
 
-    tool.installed = false;
-    config.save()?;
+/// Shows and logs the output of one step as its lines arrive.
+struct StepLines<'a> {
+    log: &'a mut StepLog,
+    step: usize,
+    limit: Option<usize>,
+    register: bool,
+    shown: usize,
+    output: StepOutput,
+}
 
-    // Auto-sync if enabled
-    auto_sync_if_enabled(&config).await?;
+impl<'a> StepLines<'a> {
+    fn new(log: &'a mut StepLog, options: &Step, step: usize, limit: Option<usize>) -> Self {
+        StepLines {
+            log,
+            step,
+            limit,
+            register: options.register.is_some(),
+            shown: 0,
+            output: StepOutput {
+                tail: Default::default(),
+                truncated: false,
+                timed_out: false,
+                captured: String::new(),
+            },
+        }
+    }
+
+    fn handle(&mut self, line: String, is_stderr: bool) {
+        let step = self.step;
+        let output = &mut self.output;
+        if !is_stderr && self.register {
+            output.captured.push_str(&line);
+            output.captured.push('\n');
+        }
+        let line = redact(&line);
+        let marker = if is_stderr { "!" } else { "" };
+        self.log.write(&format!("[{}{}] {}", step, marker, line));
+
+        if self.limit.is_none_or(|limit| self.shown < limit) {
+            self.shown += 1;
+            if is_stderr {
+                eprintln!("{} {}", format!("    [{}!]", step).red(), line.yellow());
+            } else {
+                println!("{} {}", format!("    [{}]", step).dimmed(), line);
+            }
+        } else if !output.truncated {
+            output.truncated = true;
+            println!(
+                "{}",
+                format!("    ... further output of step {} goes to the log", step).dimmed()
+            );
+        }
+
+        if let Some(limit) = self.limit {
+            if output.tail.len() == limit {
+                output.tail.pop_front();
+            }
+            output
+                .tail
+                .push_back(format!("[{}{}] {}", step, marker, line));
+        }
+    }
+}
+
+/// Runs one step, printing its stdout and stderr line by line as they arrive.
+/// Each line is prefixed with the step number; stderr lines go to stderr with
+/// a `!` marker and in yellow so they stand out from regular output. After
+/// `limit` lines the rest is only written to the log. Returns the step's exit
+/// code, `None` when it was killed.
+fn run_streaming(
+    program: &str,
+    args: &[String],
+    options: &Step,
+    step: usize,
+    limit: Option<usize>,
+    log: &mut StepLog,
+) -> Result<(Option<i32>, StepOutput)> {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::sync::Mutex;
+    use std::sync::mpsc::{self, RecvTimeoutError};
+
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .envs(&options.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(cwd) = &options.cwd {
+        let dir = expand_tilde(cwd);
+        if !dir.is_dir() {
+            return Err(anyhow!(
+                "Working directory '{}' of step {} does not exist",
+                cwd,
+                step
+            ));
+        }
+        command.current_dir(dir);
+    }
+    // In its own process group, a step that times out can be killed along
+    // with everything it started
+    #[cfg(unix)]
+    if options.timeout.is_some() {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|e| anyhow!("Could not run '{}': {}", program, e))?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    let shared = Mutex::new(StepLines::new(log, options, step, limit));
+    let handle = |line: String, is_stderr: bool| shared.lock().unwrap().handle(line, is_stderr);
+
+    let (done, finished) = mpsc::channel::<()>();
+    let timed_out = std::thread::scope(|scope| {
+        let child = &mut child;
+        let watchdog = options.timeout.map(|secs| {
+            scope.spawn(move || {
+                let expired = finished.recv_timeout(std::time::Duration::from_secs(secs))
+                    == Err(RecvTimeoutError::Timeout);
+                if expired {
+                    kill_step(child);
+                }
+                expired
+            })
+        });
+        let errors = scope.spawn(|| {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                handle(line, true);
+            }
+        });
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            handle(line, false);
+        }
+        let _ = errors.join();
+        drop(done);
+        watchdog.is_some_and(|watchdog| watchdog.join().unwrap_or(false))
+    });
+
+    let status = child.wait()?;
+    let mut output = shared.into_inner().unwrap().output;
+    output.timed_out = timed_out;
+    Ok((status.code(), output))
+}
+
+/// Plays back a mocked step the way [`run_streaming`] shows a real one,
+/// after the configured delay. A delay longer than the step's `timeout`
+/// times it out.
+fn run_mocked(
+    response: mock::MockResponse,
+    options: &Step,
+    step: usize,
+    limit: Option<usize>,
+    log: &mut StepLog,
+) -> (Option<i32>, StepOutput) {
+    let timeout = options.timeout.map(std::time::Duration::from_secs);
+    if let Some(timeout) = timeout.filter(|timeout| *timeout < response.delay) {
+        std::thread::sleep(timeout);
+        let mut lines = StepLines::new(log, options, step, limit);
+        lines.output.timed_out = true;
+        return (None, lines.output);
+    }
+    response.wait();
+    let mut lines = StepLines::new(log, options, step, limit);
+    for line in response.stdout {
+        lines.handle(line, false);
+    }
+    for line in response.stderr {
+        lines.handle(line, true);
+    }
+    (Some(response.exit_code), lines.output)
+}
+
+/// Runs a `script:` step with Rhai. The lines it prints are the step's
+/// output, and a script that throws fails with exit code 1.
+fn run_script(
+    step: &Step,
+    vars: &BTreeMap<String, String>,
+    target: ExecTarget,
+    number: usize,
+    limit: Option<usize>,
+    log: &mut StepLog,
+) -> (Option<i32>, StepOutput) {
+    let (env, cwd) = (step.env.clone(), step.cwd.clone());
+    let run = script::run(&step.command, vars, move |command| {
+        script_exec(command, target, &env, cwd.as_deref())
+    });
+    let mut lines = StepLines::new(log, step, number, limit);
+    for line in run.lines {
+        lines.handle(line, false);
+    }
+    let code = match run.error {
+        Some(error) => {
+            lines.handle(error, true);
+            1
+        }
+        None => 0,
+    };
+    (Some(code), lines.output)
+}
+
+/// Runs a tool's `before_`/`after_` hook; a hook that throws stops the action.
+fn run_hook(
+    name: &str,
+    hook: &str,
+    vars: &BTreeMap<String, String>,
+    target: ExecTarget,
+    tool_name: &str,
+    log: &mut StepLog,
+) -> Result<()> {
+    println!("{}", format!("  Hook {}", name).cyan());
+    log.write(&format!("# hook {}", name));
+    let run = script::run(hook, vars, move |command| {
+        script_exec(command, target, &BTreeMap::new(), None)
+    });
+    for line in &run.lines {
+        let line = redact(line);
+        log.write(&format!("[{}] {}", name, line));
+        println!("{} {}", format!("    [{}]", name).dimmed(), line);
+    }
+    match &run.error {
+        Some(error) => {
+            log.write(&format!("# hook {} failed: {}", name, error));
+            Err(anyhow!(
+                "Hook {} of '{}' failed: {}",
+                name,
+                tool_name,
+                redact(error)
+            ))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Runs a command for a script's `exec` in the platform shell, or answers
+/// it from the mock executor.
+fn script_exec(
+    command: &str,
+    target: ExecTarget,
+    env: &BTreeMap<String, String>,
+    cwd: Option<&str>,
+) -> script::Exec {
+    if let Some(mock) = mock::active() {
+        let response = mock.respond(command);
+        response.wait();
+        return script::Exec {
+            code: response.exit_code,
+            stdout: response.stdout.join("\n"),
+            stderr: response.stderr.join("\n"),
+        };
+    }
+    let output = step_invocation(
+        command,
+        Some(CommandShell::Auto),
+        false,
+        target,
+        &[],
+        env,
+        &BTreeMap::new(),
+    )
+    .map_err(|e| e.to_string())
+    .and_then(|(_, program, args)| {
+        let mut process = Command::new(&program);
+        process.args(&args).envs(env).stdin(Stdio::null());
+        if let Some(cwd) = cwd {
+            process.current_dir(expand_tilde(cwd));
+        }
+        process.output().map_err(|e| e.to_string())
+    });
+    match output {
+        Ok(output) => script::Exec {
+            code: output.status.code().unwrap_or(-1),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        },
+        Err(e) => script::Exec {
+            code: 127,
+            stdout: String::new(),
+            stderr: format!("Could not run '{}': {}", command, e),
+        },
+    }
+}
+
+/// Kills a step that outlived its timeout, with its process group on Unix.
+fn kill_step(child: &mut std::process::Child) {
+    #[cfg(unix)]
+    let _ = Command::new("kill")
+        .args(["-KILL", "--", &format!("-{}", child.id())])
+        .stderr(std::process::Stdio::null())
+        .status();
+    let _ = child.kill();
+}
+
+trait ToTitleCase {
+    fn to_title_case(&self) -> String;
+}
+
+impl ToTitleCase for str {
+    fn to_title_case(&self) -> String {
+        let mut chars = self.chars();
+        match chars.next() {
+            None => String::new(),
+            Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+        }
+    }
+}
+
+pub fn parse_param(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", s))?;
+    if key.trim().is_empty() {
+        return Err(format!("missing parameter name in '{}'", s));
+    }
+    Ok((key.trim().to_string(), value.to_string()))
+}
+
+/// Values for a tool's `{{name}}` placeholders: the config's `variables`,
+/// overridden by the tool's parameter values.
+fn template_vars(
+    variables: &BTreeMap<String, String>,
+    values: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let mut vars = variables.clone();
+    vars.extend(values.iter().map(|(k, v)| (k.clone(), v.clone())));
+    vars
+}
+
+/// Works out a value for every parameter the tool declares. Values passed on
+/// the command line win; otherwise the user is asked, with the value used last
+/// time (or the recipe default) offered as the default answer. When `ask_all`
+/// is false, previously chosen values are reused without asking.
+pub fn resolve_parameters(
+    tool: &ToolConfig,
+    overrides: &[(String, String)],
+    ask_all: bool,
+) -> Result<BTreeMap<String, String>> {
+    for (key, _) in overrides {
+        if !tool.parameters.iter().any(|p| &p.name == key) {
+            return Err(anyhow!(
+                "Tool '{}' has no parameter named '{}'",
+                tool.name,
+                key
+            ));
+        }
+    }
+
+    let mut values = BTreeMap::new();
+    for param in &tool.parameters {
+        if let Some((_, value)) = overrides.iter().rev().find(|(k, _)| k == &param.name) {
+            values.insert(param.name.clone(), value.clone());
+            continue;
+        }
+
+        if let (PIN_PARAM, Some(version)) = (param.name.as_str(), &tool.pinned) {
+            values.insert(param.name.clone(), version.clone());
+            continue;
+        }
+
+        let previous = tool.param_values.get(&param.name);
+        if let (Some(value), false) = (previous, ask_all) {
+            values.insert(param.name.clone(), value.clone());
+            continue;
+        }
+
+        let default = previous.or(param.default.as_ref());
+        let question = param.prompt.as_deref().unwrap_or(&param.name);
+        let answer = match (default, param.secret) {
+            (Some(_), true) => read_secret(&format!("{} [hidden default]: ", question))?,
+            (None, true) => read_secret(&format!("{}: ", question))?,
+            (Some(default), false) => read_line(&format!("{} [{}]: ", question, default))?,
+            (None, false) => read_line(&format!("{}: ", question))?,
+        };
+
+        let value = match (answer.is_empty(), default) {
+            (false, _) => answer,
+            (true, Some(default)) => default.clone(),
+            (true, None) => {
+                return Err(anyhow!("Parameter '{}' requires a value", param.name));
+            }
+        };
+        values.insert(param.name.clone(), value);
+    }
+
+    for param in tool.parameters.iter().filter(|param| param.secret) {
+        if let Some(value) = values.get(&param.name) {
+            register_secret(value);
+        }
+    }
+    Ok(values)
+}
+
+/// Makes a tool's downloads available locally, reusing verified cached
+/// copies, checks its signature if it has one, and returns the download
+/// paths keyed by name.
+async fn fetch_downloads(tool: &ToolConfig) -> Result<BTreeMap<String, String>> {
+    let mut paths = BTreeMap::new();
+    if tool.downloads.is_empty() && tool.verify.is_none() {
+        return Ok(paths);
+    }
+
+    let cache_dir = get_download_cache_dir()?;
+    for download in &tool.downloads {
+        let path = match download.cached(&cache_dir) {
+            Some(path) => {
+                println!("  {} {}", "Using cached".dimmed(), download.file_name());
+                path
+            }
+            None => {
+                println!("  {} {}", "Downloading".cyan(), download.url);
+                download.fetch(&cache_dir).await?
+            }
+        };
+        paths.insert(download.name.clone(), path.display().to_string());
+    }
+
+    if let Some(check) = &tool.verify {
+        check.verify(&paths).await?;
+        println!("  {} {}", "✓ Signature verified for".green(), check.file);
+    }
+    Ok(paths)
+}
+
+/// Installs a tool from the asset of its GitHub release matching the
+/// recipe's pattern: the pinned version, or the latest stable one. The
+/// asset is checked against the release's published checksum when there is
+/// one, unpacked, and its executable copied into the release bin dir.
+/// Returns the version installed, or `None` when `installed` is already the
+/// latest.
+async fn install_release(
+    tool_name: &str,
+    tool: &ToolConfig,
+    spec: &ReleaseSpec,
+    vars: &BTreeMap<String, String>,
+    installed: Option<&str>,
+    token: Option<&str>,
+) -> Result<Option<String>> {
+    let found = releases::fetch_releases(&spec.repo, token).await?;
+    let release = match &tool.pinned {
+        Some(pinned) => found
+            .iter()
+            .find(|release| release.version() == releases::version_of(pinned))
+            .ok_or_else(|| anyhow!("{} has no release {}", spec.repo, pinned))?,
+        None => releases::latest(&found)
+            .ok_or_else(|| anyhow!("{} has no stable release", spec.repo))?,
+    };
+    if !releases::is_newer(release, installed) {
+        println!(
+            "{}",
+            format!(
+                "  {} {} is the latest release",
+                tool_name,
+                release.version()
+            )
+            .dimmed()
+        );
+        return Ok(None);
+    }
+
+    let asset = spec.find_asset(release, vars).ok_or_else(|| {
+        anyhow!(
+            "No asset of {} {} matches '{}'",
+            spec.repo,
+            release.tag_name,
+            render_template(&spec.asset_pattern, vars)
+        )
+    })?;
+    println!("  {} {}", "Downloading".cyan(), asset.browser_download_url);
+    let data = read_url(&asset.browser_download_url).await?;
+
+    match releases::checksum_asset(release, asset) {
+        Some(sums) => {
+            let content =
+                String::from_utf8_lossy(&read_url(&sums.browser_download_url).await?).into_owned();
+            let expected = releases::parse_checksum(&content, &asset.name).ok_or_else(|| {
+                anyhow!("{} does not list a checksum for {}", sums.name, asset.name)
+            })?;
+            if sha256_hex(&data) != expected {
+                return Err(anyhow!(
+                    "Checksum mismatch for {}: expected {}, got {}",
+                    asset.name,
+                    expected,
+                    sha256_hex(&data)
+                ));
+            }
+            println!("  {} {}", "✓ Checksum verified for".green(), asset.name);
+        }
+        None => println!(
+            "{}",
+            format!(
+                "  ⚠️  {} {} publishes no checksum; installing unverified",
+                spec.repo, release.tag_name
+            )
+            .yellow()
+        ),
+    }
+
+    let work_dir = get_download_cache_dir()?
+        .join("releases")
+        .join(format!("{}-{}", tool_name, release.tag_name));
+    if work_dir.exists() {
+        fs::remove_dir_all(&work_dir)?;
+    }
+    fs::create_dir_all(&work_dir)?;
+    let archive = work_dir.join(&asset.name);
+    fs::write(&archive, &data)?;
+
+    let binary = match Archive::of(&asset.name) {
+        Archive::Plain => archive,
+        packed => {
+            let mut unpack = match packed {
+                Archive::Zip if !cfg!(windows) => {
+                    let mut unzip = Command::new("unzip");
+                    unzip.arg("-q").arg("-o").arg(&archive);
+                    unzip
+                }
+                _ => {
+                    let mut tar = Command::new("tar");
+                    tar.arg("-xf").arg(&archive);
+                    tar
+                }
+            };
+            let status = unpack.current_dir(&work_dir).status()?;
+            if !status.success() {
+                return Err(anyhow!("Could not unpack {}", asset.name));
+            }
+            releases::find_binary(&work_dir, tool.binary_name()).ok_or_else(|| {
+                anyhow!("{} has no '{}' executable", asset.name, tool.binary_name())
+            })?
+        }
+    };
+
+    let target = releases::release_executable(tool)?;
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::copy(&binary, &target)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o755))?;
+    }
+    let tracked = target.display().to_string();
+    record_usage(tool_name, |stats, _| {
+        if !stats.files.contains(&tracked) {
+            stats.files.push(tracked);
+        }
+    });
+    println!(
+        "  {} {} {} to {}",
+        "✓ Installed".green(),
+        tool_name,
+        release.version(),
+        target.display()
+    );
+    if let Some(dir) = target.parent()
+        && !on_path(dir)
+    {
+        println!(
+            "{}",
+            format!(
+                "  ⚠️  {} is not on PATH; 'tkit path --install' adds it",
+                dir.display()
+            )
+            .yellow()
+        );
+    }
+    Ok(Some(release.version().to_string()))
+}
+
+/// Expands a tool argument into the tools it names. A shell-style pattern
+/// lists its matches and asks for confirmation before anything runs; when
+/// `skip_disabled` is set, disabled tools are left out of the matches.
+pub fn select_tools(
+    config: &Config,
+    pattern: &str,
+    action: &str,
+    skip_disabled: bool,
+) -> Result<Vec<String>> {
+    if !is_glob(pattern) {
+        return Ok(vec![config.resolve_name(pattern)]);
+    }
+
+    let mut names = config.match_tools(pattern);
+    if names.is_empty() {
+        return Err(ExitKind::ToolNotFound.error(format!("No tools match '{}'.", pattern)));
+    }
+
+    if skip_disabled {
+        let (disabled, enabled): (Vec<String>, Vec<String>) = names
+            .into_iter()
+            .partition(|name| config.tools[name].disabled);
+        if !disabled.is_empty() {
+            println!(
+                "{}",
+                format!("Skipping disabled tools: {}", disabled.join(", ")).dimmed()
+            );
+        }
+        if enabled.is_empty() {
+            println!("{}", "All matching tools are disabled.".yellow());
+            return Ok(Vec::new());
+        }
+        names = enabled;
+    }
+
+    println!("{}", format!("Tools matching '{}':", pattern).blue().bold());
+    for name in &names {
+        println!("  • {}", name);
+    }
+
+    if !confirm(
+        &format!("{} {} tool(s)?", action.to_title_case(), names.len()),
+        false,
+    )? {
+        return Err(aborted());
+    }
+    Ok(names)
+}
+
+/// Lets the user tick which tools matching `pattern` to `action`: enabled
+/// tools that aren't installed for install, installed ones otherwise.
+pub fn pick_tools(config: &Config, pattern: &str, action: &str) -> Result<Vec<String>> {
+    let want_installed = action != "install";
+    let candidates: Vec<String> = config
+        .match_tools(pattern)
+        .into_iter()
+        .filter(|name| {
+            let tool = &config.tools[name];
+            !tool.disabled && tool.installed == want_installed
+        })
+        .collect();
+    if candidates.is_empty() {
+        println!("{}", format!("No tools to {}.", action).yellow());
+        return Ok(Vec::new());
+    }
+
+    let labels: Vec<String> = candidates
+        .iter()
+        .map(|name| match &config.tools[name].description {
+            Some(description) => format!("{} - {}", name, description),
+            None => name.clone(),
+        })
+        .collect();
+    let picked = multi_select(
+        &format!("Select tools to {}:", action)
+            .blue()
+            .bold()
+            .to_string(),
+        &labels,
+    )?;
+    if picked.is_empty() {
+        println!("{}", "Nothing selected.".yellow());
+    }
+    Ok(picked.into_iter().map(|i| candidates[i].clone()).collect())
+}
+
+/// Runs `run` for each tool. Without `continue_on_error` the first failure
+/// aborts the batch; with it every tool is attempted and a summary is printed.
+/// Returns whether any tool changed.
+async fn run_batch<F, Fut>(
+    names: &[String],
+    action: &str,
+    continue_on_error: bool,
+    mut run: F,
+) -> Result<bool>
+where
+    F: FnMut(String) -> Fut,
+    Fut: std::future::Future<Output = Result<bool>>,
+{
+    let mut results = Vec::new();
+    for name in names {
+        let result = run(name.clone()).await;
+        if result.is_err() && !continue_on_error {
+            return result;
+        }
+        results.push((name, result));
+    }
+
+    let changed = results.iter().any(|(_, result)| matches!(result, Ok(true)));
+    if names.len() < 2 {
+        return match results.pop() {
+            Some((_, result)) => result,
+            None => Ok(false),
+        };
+    }
+
+    let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+    println!();
+    println!(
+        "{}",
+        format!("{} summary:", action.to_title_case()).blue().bold()
+    );
+    let mut failed = 0;
+    for (name, result) in &results {
+        match result {
+            Ok(true) => println!("  {:<width$}  {}", name, "ok".green()),
+            Ok(false) => println!("  {:<width$}  {}", name, "unchanged".dimmed()),
+            Err(e) => {
+                failed += 1;
+                let reason = e.to_string();
+                let reason = reason.lines().next().unwrap_or_default();
+                println!("  {:<width$}  {}  {}", name, "failed".red().bold(), reason);
+            }
+        }
+    }
+
+    if failed > 0 {
+        if changed {
+            // Still share what did succeed before reporting the failures
+            auto_sync_if_enabled(&Config::load()?, action, &names.join(", ")).await?;
+        }
+        return Err(ExitKind::CommandFailed.error(format!(
+            "{} of {} tools failed to {}",
+            failed,
+            results.len(),
+            action
+        )));
+    }
+    Ok(changed)
+}
+
+pub async fn install_tool(
+    pattern: &str,
+    params: &[(String, String)],
+    continue_on_error: bool,
+    force: bool,
+    locked: bool,
+    interactive: bool,
+    resume: bool,
+) -> Result<()> {
+    let config = Config::load()?;
+    let lock = locked.then(Lockfile::load).transpose()?;
+    let names = match &lock {
+        _ if interactive => pick_tools(&config, pattern, "install")?,
+        Some(lock) if is_glob(pattern) => {
+            let mut scoped = config.clone();
+            scoped.tools.retain(|name, _| lock.tools.contains_key(name));
+            select_tools(&scoped, pattern, "install", true)?
+        }
+        Some(lock) => {
+            let name = config.resolve_name(pattern);
+            if !lock.tools.contains_key(&name) {
+                return Err(anyhow!("Tool '{}' is not in tkit.lock", name));
+            }
+            vec![name]
+        }
+        None => select_tools(&config, pattern, "install", true)?,
+    };
+    if names.len() > 1 {
+        take_snapshot(&config, SnapshotTrigger::Install, &names)?;
+    }
+
+    let _pending = intent::begin(&get_intents_path()?, "install", &names)?;
+    let changed = run_batch(&names, "install", continue_on_error, |name| {
+        let params = match &lock {
+            Some(lock) => locked_params(&config.tools[&name], &lock.tools[&name], params),
+            None => params.to_vec(),
+        };
+        async move { tracked("install", &name, install_one(&name, &params, force, resume)).await }
+    })
+    .await?;
+
+    if changed {
+        auto_sync_if_enabled(&Config::load()?, "install", &names.join(", ")).await?;
+    }
+    Ok(())
+}
+
+/// Runs the configured snapshot command before `trigger` changes `tools`.
+/// A failed snapshot stops the operation unless the hook allows failure.
+fn take_snapshot(config: &Config, trigger: SnapshotTrigger, tools: &[String]) -> Result<()> {
+    let Some(hook) = &config.settings.snapshot else {
+        return Ok(());
+    };
+    if !hook.runs_before(trigger) {
+        return Ok(());
+    }
+
+    // Compact, as snapshot names can't hold every character of RFC 3339
+    let now = chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string();
+    let command = hook.render(trigger, tools, &now);
+    println!("{} {}", "Taking a system snapshot:".cyan(), command);
+    let (program, args) = config
+        .settings
+        .shell
+        .unwrap_or(CommandShell::Auto)
+        .command(&command);
+    let status = match mock::active() {
+        Some(mock) => {
+            let response = mock.respond(&command);
+            response.wait();
+            Ok(response.exit_code)
+        }
+        None => Command::new(&program)
+            .args(&args)
+            .status()
+            .map(|status| status.code().unwrap_or(-1)),
+    };
+    let failure = match status {
+        Ok(0) => {
+            println!("  {}", "✓ Snapshot taken".green());
+            return Ok(());
+        }
+        Ok(code) => failure_reason(Some(code), None),
+        Err(e) => e.to_string(),
+    };
+    if hook.allow_failure {
+        println!(
+            "{}",
+            format!(
+                "  ⚠️  Snapshot failed ({}); going ahead without one",
+                failure
+            )
+            .yellow()
+        );
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Snapshot failed ({}); nothing was changed. Set settings.snapshot.allow_failure to go ahead without one.",
+        failure
+    ))
+}
+
+/// Computes and prints what `apply` would change, terraform-plan style.
+fn plan_changes(selection: &Selection) -> Result<Vec<PlannedChange>> {
+    let config = Config::load()?;
+    let changes = converge::plan(&config, &Lockfile::load()?, selection, converge::observe);
+    if changes.is_empty() {
+        println!(
+            "{}",
+            "✓ Nothing to do; this machine matches the config."
+                .green()
+                .bold()
+        );
+        return Ok(changes);
+    }
+
+    println!("{}", "Planned changes:".blue().bold());
+    for change in &changes {
+        let marker = match change.change {
+            Change::Install => "+".green(),
+            Change::Update => "~".yellow(),
+            Change::Remove => "-".red(),
+        };
+        println!(
+            "  {} {} {} {}",
+            marker,
+            change.change,
+            change.tool.bold(),
+            format!("({})", change.reason).dimmed()
+        );
+    }
+
+    let count = |kind: Change| changes.iter().filter(|c| c.change == kind).count();
+    println!();
+    println!(
+        "Plan: {} to install, {} to update, {} to remove.",
+        count(Change::Install).to_string().green().bold(),
+        count(Change::Update).to_string().yellow().bold(),
+        count(Change::Remove).to_string().red().bold()
+    );
+    Ok(changes)
+}
+
+/// Prints what `tkit apply` would do without changing anything.
+pub fn show_plan(selection: Selection) -> Result<()> {
+    plan_changes(&selection)?;
+    Ok(())
+}
+
+/// Looks for credentials written into tool commands, tightens the config
+/// file to 0600 when others can read it, and checks that no sync token or
+/// secret parameter value made it into the config file or a pushed copy. Fails when a high-severity problem remains.
+pub async fn run_audit() -> Result<()> {
+    let config = Config::load()?;
+    let config_path = get_config_path()?;
+    let mut findings = audit::scan_config(&config);
+    if let Ok(content) = fs::read_to_string(&config_path) {
+        findings.extend(audit::scan_secret_values(
+            &config_path.display().to_string(),
+            &content,
+            &config,
+        ));
+    }
+
+    let mut fixed = Vec::new();
+    let config_dir = config_path
+        .parent()
+        .ok_or_else(|| anyhow!("Config path has no parent directory"))?;
+    for (path, mode) in audit::loose_files(config_dir)? {
+        audit::restrict_permissions(&path)?;
+        fixed.push(format!(
+            "{} was mode {:o}; now 600 (owner only)",
+            path.display(),
+            mode
+        ));
+    }
+
+    for remote in config.sync.all_remotes() {
+        match fetch_remote_content(&remote).await {
+            Ok(Some(content)) => {
+                findings.extend(audit::scan_synced_copy(&remote.name, &content, &config))
+            }
+            Ok(None) => {}
+            Err(e) => println!(
+                "{}",
+                format!(
+                    "  ⚠️  Could not check remote '{}': {}",
+                    remote.name,
+                    redact(&e.to_string())
+                )
+                .yellow()
+            ),
+        }
+    }
+    findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+
+    for fix in &fixed {
+        println!("{} {}", "  fixed ".green().bold(), fix);
+    }
+    if findings.is_empty() {
+        println!("{}", "✓ No security problems found.".green().bold());
+        return Ok(());
+    }
+
+    println!("{}", "Security audit:".blue().bold());
+    for finding in &findings {
+        print_finding(finding);
+    }
+
+    let high = findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::High)
+        .count();
+    if high > 0 {
+        return Err(anyhow!("{} high-severity problem(s) found", high));
+    }
+    Ok(())
+}
+
+fn print_finding(finding: &Finding) {
+    let severity = format!("{:<7}", finding.severity.to_string());
+    let severity = match finding.severity {
+        Severity::High => severity.red().bold(),
+        Severity::Medium => severity.yellow(),
+        Severity::Low => severity.dimmed(),
+    };
+    println!(
+        "  {} {}: {}",
+        severity,
+        finding.location.bold(),
+        finding.message
+    );
+}
+
+/// Reports a problem found by `tkit ci-validate`, also as an error
+/// annotation when running in GitHub Actions.
+fn ci_problem(location: &str, message: &str) {
+    println!("  {} {}: {}", "✗".red(), location.bold(), message);
+    if std::env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true") {
+        println!("::error title=tkit ci-validate::{}: {}", location, message);
+    }
+}
+
+/// Reads a file from the working tree, or as it was at git revision `base`;
+/// `None` when it doesn't exist there.
+fn read_revision(base: Option<&str>, path: &Path) -> Result<Option<String>> {
+    let Some(base) = base else {
+        return Ok(fs::read_to_string(path).ok());
+    };
+    let output = Command::new("git")
+        .arg("show")
+        .arg(format!("{}:./{}", base, path.display()))
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| anyhow!("Could not run git: {}", e))?;
+    Ok(output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// The tool files of a split config next to `index`, by tool name, in the
+/// working tree or at revision `base`.
+fn read_split_tools(base: Option<&str>, index: &Path) -> Result<BTreeMap<String, String>> {
+    let dir = index.parent().unwrap_or(Path::new(""));
+    let files: Vec<String> = match base {
+        None => fs::read_dir(dir.join(layout::TOOLS_DIR))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| {
+                        format!(
+                            "{}/{}",
+                            layout::TOOLS_DIR,
+                            entry.file_name().to_string_lossy()
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        Some(base) => {
+            let output = Command::new("git")
+                .args(["ls-tree", "--name-only", base, "--"])
+                .arg(format!("./{}/", dir.join(layout::TOOLS_DIR).display()))
+                .output()
+                .map_err(|e| anyhow!("Could not run git: {}", e))?;
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|path| {
+                    path.rsplit_once('/')
+                        .map(|(_, file)| format!("{}/{}", layout::TOOLS_DIR, file))
+                })
+                .collect()
+        }
+    };
+
+    let mut tools = BTreeMap::new();
+    for file in files {
+        if let Some(name) = layout::tool_name(&file)
+            && let Some(content) = read_revision(base, &dir.join(&file))?
+        {
+            tools.insert(name.to_string(), content);
+        }
+    }
+    Ok(tools)
+}
+
+/// The config at `path` as one YAML document, joining the tool files of the
+/// split layout.
+fn read_ci_config(base: Option<&str>, path: &Path) -> Result<Option<String>> {
+    let Some(content) = read_revision(base, path)? else {
+        return Ok(None);
+    };
+    if !layout::is_split(&content) {
+        return Ok(Some(content));
+    }
+    let tools = read_split_tools(base, path)?;
+    Ok(Some(layout::join(&content, &tools)?))
+}
+
+/// The git revision CI builds compare against: `base`, or the target branch
+/// of a GitHub pull request.
+fn ci_base(base: Option<String>) -> Option<String> {
+    base.or_else(|| {
+        std::env::var("GITHUB_BASE_REF")
+            .ok()
+            .filter(|branch| !branch.is_empty())
+            .map(|branch| format!("origin/{}", branch))
+    })
+}
+
+/// Validates a repository's tkit config for CI: the schema, dangerous
+/// commands and embedded credentials, and with `install` the install steps
+/// of the tools changed since `base`, each run in a fresh container.
+/// Fails when anything is wrong.
+pub fn ci_validate(
+    file: Option<std::path::PathBuf>,
+    base: Option<String>,
+    install: bool,
+    image: &str,
+) -> Result<()> {
+    let path = file.unwrap_or_else(|| std::path::PathBuf::from(gitsync::CONFIG_FILE));
+    println!(
+        "{}",
+        format!("Validating {}...", path.display()).blue().bold()
+    );
+    let content = read_ci_config(None, &path)?
+        .ok_or_else(|| ExitKind::Config.error(format!("{} not found", path.display())))?;
+    if crypto::is_encrypted(&content) {
+        return Err(ExitKind::Config.error(format!(
+            "{} is encrypted; CI can only validate a plain config",
+            path.display()
+        )));
+    }
+
+    let mut problems = 0;
+    let config = match Config::parse_and_validate(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            ci_problem(&path.display().to_string(), &e.to_string());
+            return Err(ExitKind::Config.error("The config is invalid"));
+        }
+    };
+    let unknown = schema::unknown_fields::<Config>(&content)?;
+    for field in &unknown {
+        ci_problem(field, "unknown field (misspelled, or needs a newer tkit)");
+    }
+    problems += unknown.len();
+    if unknown.is_empty() {
+        println!("  {} Schema: {} tools", "✓".green(), config.tools.len());
+    }
+
+    let mut findings = audit::lint_config(&config);
+    findings.extend(audit::scan_config(&config));
+    findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+    let (serious, minor): (Vec<&Finding>, Vec<&Finding>) = findings
+        .iter()
+        .partition(|finding| finding.severity > Severity::Low);
+    for finding in &serious {
+        ci_problem(&finding.location, &finding.message);
+    }
+    for finding in &minor {
+        print_finding(finding);
+    }
+    problems += serious.len();
+    if serious.is_empty() {
+        println!("  {} No dangerous commands or credentials", "✓".green());
+    }
+
+    let mut failed_installs = 0;
+    if install {
+        let base = ci_base(base);
+        let before = match &base {
+            Some(base) => read_ci_config(Some(base), &path)?
+                .and_then(|content| serde_yaml::from_str::<Config>(&content).ok()),
+            None => None,
+        };
+        let changed: Vec<&String> = config
+            .tools
+            .iter()
+            .filter(|(name, tool)| {
+                !tool.disabled
+                    && before.as_ref().is_none_or(|before| {
+                        before.tools.get(*name).is_none_or(|old| {
+                            serde_yaml::to_string(old).ok() != serde_yaml::to_string(tool).ok()
+                        })
+                    })
+            })
+            .map(|(name, _)| name)
+            .collect();
+        match &base {
+            Some(base) => println!(
+                "{}",
+                format!(
+                    "Installing the {} tool(s) changed since {}:",
+                    changed.len(),
+                    base
+                )
+                .blue()
+                .bold()
+            ),
+            None => println!(
+                "{}",
+                format!("Installing all {} tool(s):", changed.len())
+                    .blue()
+                    .bold()
+            ),
+        }
+        for name in changed {
+            match install_in_container(&config, name, image) {
+                Ok(()) => println!("  {} {}", "✓".green(), name),
+                Err(e) => {
+                    failed_installs += 1;
+                    ci_problem(name, &e.to_string());
+                }
+            }
+        }
+    }
+
+    if problems > 0 {
+        return Err(
+            ExitKind::Config.error(format!("{} problem(s) found", problems + failed_installs))
+        );
+    }
+    if failed_installs > 0 {
+        return Err(
+            ExitKind::CommandFailed.error(format!("{} tool(s) failed to install", failed_installs))
+        );
+    }
+    println!("{}", "✓ The config is valid.".green().bold());
+    Ok(())
+}
+
+/// The container runtime on this machine: docker, or else podman.
+fn container_runtime() -> Result<&'static str> {
+    ["docker", "podman"]
+        .into_iter()
+        .find(|runtime| which::which(runtime).is_ok())
+        .ok_or_else(|| anyhow!("Installing in a container needs docker or podman"))
+}
+
+/// Runs a tool's install steps in a fresh `image` container, with its
+/// parameters at their defaults.
+fn install_in_container(config: &Config, name: &str, image: &str) -> Result<()> {
+    let tool = &config.tools[name];
+    let steps = tool.commands_for(ToolAction::Install);
+    if steps.is_empty() {
+        return Err(anyhow!("no install commands for this platform"));
+    }
+    if steps.iter().any(|step| step.script) || !tool.hooks.is_empty() {
+        return Err(anyhow!("Rhai scripts can't run in a container"));
+    }
+    let values = resolve_parameters(tool, &[], false)?;
+    let vars = template_vars(&config.variables, &values);
+    let script: Vec<String> = render_commands(&steps, &vars)
+        .into_iter()
+        .map(|step| step.command)
+        .collect();
+
+    let output = Command::new(container_runtime()?)
+        .args(["run", "--rm", image, "sh", "-c"])
+        .arg(format!("set -e\n{}", script.join("\n")))
+        .stdin(Stdio::null())
+        .output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let last = stderr.lines().rev().find(|line| !line.trim().is_empty());
+    Err(anyhow!(
+        "install failed in {} ({}){}",
+        image,
+        failure_reason(output.status.code(), None),
+        last.map(|line| format!(": {}", line.trim()))
+            .unwrap_or_default()
+    ))
+}
+
+/// Reports where the machine differs from the recorded state, and with
+/// `reconcile` records what is actually there, as if tkit had made the change.
+pub async fn show_drift(reconcile: bool) -> Result<()> {
+    let mut config = Config::load()?;
+    let mut lock = Lockfile::load()?;
+    let drifts =
+        converge::detect_drift(&config, &lock, converge::observe, converge::observe_version);
+    if drifts.is_empty() {
+        println!(
+            "{}",
+            "✓ No drift; installed tools match the recorded state."
+                .green()
+                .bold()
+        );
+        return Ok(());
+    }
+
+    println!("{}", "Drift detected:".blue().bold());
+    for drift in &drifts {
+        let marker = match drift.drift {
+            Drift::Appeared => "+".green(),
+            Drift::Vanished => "-".red(),
+            Drift::Version { .. } => "~".yellow(),
+        };
+        println!("  {} {} {}", marker, drift.tool.bold(), drift.drift);
+    }
+
+    if !reconcile {
+        println!();
+        println!(
+            "{}",
+            "Run 'tkit drift --reconcile' to record this state, or 'tkit apply' to restore the config."
+                .dimmed()
+        );
+        return Ok(());
+    }
+
+    // Only what is observed on this machine is recorded; which tools are
+    // wanted stays as the shared config says until the user changes it
+    let now = chrono::Utc::now().to_rfc3339();
+    for drift in &drifts {
+        let Some(tool) = config.tools.get_mut(&drift.tool) else {
+            continue;
+        };
+        match &drift.drift {
+            Drift::Appeared => {
+                tool.installed = true;
+                lock.record(tool, converge::observe_version(tool), &now);
+            }
+            Drift::Vanished => {
+                tool.installed = false;
+                lock.tools.remove(&drift.tool);
+            }
+            Drift::Version { observed, .. } => {
+                if let Some(locked) = lock.tools.get_mut(&drift.tool) {
+                    locked.version = Some(observed.clone());
+                }
+            }
+        }
+    }
+    config.save()?;
+    lock.save()?;
+    println!(
+        "{}",
+        format!("✓ Reconciled {} tool(s)", drifts.len())
+            .green()
+            .bold()
+    );
+
+    let unwanted: Vec<&str> = drifts
+        .iter()
+        .filter(|drift| {
+            let tool = &config.tools[&drift.tool];
+            tool.installed != tool.desired_state.is_present()
+        })
+        .map(|drift| drift.tool.as_str())
+        .collect();
+    if !unwanted.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "Not what the config wants: {}. 'tkit install' or 'tkit remove' them to change that, or run 'tkit apply' to restore them.",
+                unwanted.join(", ")
+            )
+            .dimmed()
+        );
+    }
+    Ok(())
+}
+
+/// Converges the machine to `selection`: installs tools that should be there
+/// but aren't and removes the ones that shouldn't, after showing the plan.
+pub async fn apply_config(selection: Selection, continue_on_error: bool) -> Result<()> {
+    let changes = plan_changes(&selection)?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+    if !confirm(&format!("Apply {} change(s)?", changes.len()), false)? {
+        return Err(aborted());
+    }
+
+    let names: Vec<String> = changes.iter().map(|c| c.tool.clone()).collect();
+    take_snapshot(&Config::load()?, SnapshotTrigger::Apply, &names)?;
+    let _pending = intent::begin(&get_intents_path()?, "apply", &names)?;
+    let changes = &changes;
+    let changed = run_batch(&names, "apply", continue_on_error, |name| async move {
+        let change = changes.iter().find(|c| c.tool == name).unwrap();
+        match change.change {
+            Change::Install => {
+                // A tool recorded as installed but missing is installed afresh
+                let mut config = Config::load()?;
+                if let Some(tool) = config.tools.get_mut(&name)
+                    && tool.installed
+                {
+                    tool.installed = false;
+                    config.save()?;
+                }
+                tracked("install", &name, install_one(&name, &[], false, false)).await
+            }
+            // Pinned tools are updated too: a new pin is why they're here
+            Change::Update => tracked("update", &name, update_one(&name, &[], true, false)).await,
+            Change::Remove => tracked("remove", &name, remove_one(&name, &[])).await,
+        }
+    })
+    .await?;
+
+    if changed {
+        auto_sync_if_enabled(&Config::load()?, "apply", &names.join(", ")).await?;
+    }
+    Ok(())
+}
+
+/// Runs the steps of a batch manifest in order and summarizes them. Without
+/// `continue_on_error` the first failure stops the rest.
+pub async fn run_manifest(path: &Path, continue_on_error: bool) -> Result<()> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Could not read {}: {}", path.display(), e))?;
+    let steps = batch::parse(&content)
+        .map_err(|e| ExitKind::Config.error(format!("{}: {}", path.display(), e)))?;
+    if steps.is_empty() {
+        println!("{}", format!("{} has no actions.", path.display()).yellow());
+        return Ok(());
+    }
+
+    let mut results: Vec<(&BatchStep, Result<()>)> = Vec::new();
+    for step in &steps {
+        println!();
+        println!(
+            "{}",
+            format!("[{}/{}] {}", results.len() + 1, steps.len(), step.text)
+                .blue()
+                .bold()
+        );
+        let result = run_batch_action(&step.action).await;
+        let stop = result.is_err() && !continue_on_error;
+        results.push((step, result));
+        if stop {
+            break;
+        }
+    }
+
+    let width = steps.iter().map(|step| step.text.len()).max().unwrap_or(0);
+    println!();
+    println!("{}", "Batch summary:".blue().bold());
+    let mut failed = 0;
+    for (step, result) in &results {
+        match result {
+            Ok(()) => println!("  {:<width$}  {}", step.text, "ok".green()),
+            Err(e) => {
+                failed += 1;
+                let reason = e.to_string();
+                let reason = reason.lines().next().unwrap_or_default();
+                println!(
+                    "  {:<width$}  {}  line {}: {}",
+                    step.text,
+                    "failed".red().bold(),
+                    step.line,
+                    reason
+                );
+            }
+        }
+    }
+    for step in &steps[results.len()..] {
+        println!("  {:<width$}  {}", step.text, "skipped".dimmed());
+    }
+
+    if !continue_on_error && let Some((_, Err(_))) = results.last() {
+        return results.pop().unwrap().1;
+    }
+    if failed > 0 {
+        return Err(ExitKind::CommandFailed.error(format!(
+            "{} of {} actions failed",
+            failed,
+            steps.len()
+        )));
+    }
+    Ok(())
+}
+
+async fn run_batch_action(action: &BatchAction) -> Result<()> {
+    match action {
+        BatchAction::Install { tool, params } => {
+            install_tool(tool, params, false, false, false, false, false).await
+        }
+        BatchAction::Update { tool } => update_tool(tool, &[], false, false, false, false).await,
+        BatchAction::Remove { tool } => remove_tool(tool, &[], false, false).await,
+        BatchAction::Add { url, name } => add_tool_from_url(url, name.as_deref(), &[]).await,
+        BatchAction::GroupInstall { group } => install_group(group).await,
+        BatchAction::Apply => apply_config(Selection::default(), false).await,
+    }
+}
+
+/// Installs every enabled tool whose category or one of whose tags is
+/// `group`.
+async fn install_group(group: &str) -> Result<()> {
+    let config = Config::load()?;
+    let by_category = Selection {
+        category: Some(group.to_string()),
+        tag: None,
+    };
+    let by_tag = Selection {
+        category: None,
+        tag: Some(group.to_string()),
+    };
+    let names: Vec<String> = config
+        .tools
+        .iter()
+        .filter(|(_, tool)| !tool.disabled && (by_category.matches(tool) || by_tag.matches(tool)))
+        .map(|(name, _)| name.clone())
+        .collect();
+    if names.is_empty() {
+        return Err(
+            ExitKind::ToolNotFound.error(format!("No tools have the category or tag '{}'.", group))
+        );
+    }
+
+    install_names(&config, &names, false).await
+}
+
+/// Installs the named tools as one batch, after the snapshot, and shares
+/// the result if anything changed.
+async fn install_names(config: &Config, names: &[String], continue_on_error: bool) -> Result<()> {
+    take_snapshot(config, SnapshotTrigger::Install, names)?;
+    let _pending = intent::begin(&get_intents_path()?, "install", names)?;
+    let changed = run_batch(names, "install", continue_on_error, |name| async move {
+        tracked("install", &name, install_one(&name, &[], false, false)).await
+    })
+    .await?;
+    if changed {
+        auto_sync_if_enabled(&Config::load()?, "install", &names.join(", ")).await?;
+    }
+    Ok(())
+}
+
+/// Where the config for `tkit apply --from-url` is downloaded from: the URL
+/// itself, or the config pushed to a GitHub repository, read without a
+/// token so public repositories need no setup.
+fn remote_config_url(source: &str) -> Result<String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        return Ok(source.to_string());
+    }
+    match source.split('/').collect::<Vec<_>>().as_slice() {
+        [owner, repo] if !owner.is_empty() && !repo.is_empty() => Ok(format!(
+            "https://raw.githubusercontent.com/{}/{}/HEAD/{}",
+            owner,
+            repo,
+            gitsync::CONFIG_FILE
+        )),
+        _ => Err(anyhow!(
+            "'{}' is neither a URL nor a GitHub repository (owner/repo)",
+            source
+        )),
+    }
+}
+
+/// Installs tools from a config published at a URL or in a repository,
+/// without replacing this machine's config or setting up sync: the config
+/// is downloaded and validated, the tools it would install are shown, and
+/// the ones picked are added to the local config and installed.
+pub async fn apply_from_url(
+    source: &str,
+    selection: Selection,
+    continue_on_error: bool,
+) -> Result<()> {
+    let url = remote_config_url(source)?;
+    println!("{}", format!("Downloading {}...", url).blue().bold());
+    let content = fetch_text(&url).await.map_err(sync_failure)?;
+    let content = match crypto::is_encrypted(&content) {
+        true => crypto::decrypt(&content, &sync_passphrase()?)?,
+        false => content,
+    };
+    let mut remote = Config::parse_and_validate(&content)
+        .map_err(|e| ExitKind::Config.error(format!("Invalid tkit config at '{}': {}", url, e)))?;
+    remote.migrate_installed_flags();
+
+    let mut config = Config::load()?;
+    let candidates: Vec<(&String, &ToolConfig)> = remote
+        .tools
+        .iter()
+        .filter(|(_, tool)| !tool.disabled && (selection.is_empty() || selection.matches(tool)))
+        .collect();
+    let (installed, candidates): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .partition(|(name, _)| config.tools.get(*name).is_some_and(|tool| tool.installed));
+    if candidates.is_empty() {
+        println!(
+            "{}",
+            "✓ Nothing to do; every tool in that config is already installed."
+                .green()
+                .bold()
+        );
+        return Ok(());
+    }
+
+    // Placeholders are shown with the values the install would start from
+    let mut variables = remote.variables.clone();
+    variables.extend(config.variables.clone());
+    println!("{}", "Planned changes:".blue().bold());
+    for (name, tool) in &candidates {
+        let (reason, tool) = match config.tools.get(*name) {
+            Some(local) => ("configured here, not installed", local),
+            None => ("new to this machine's config", *tool),
+        };
+        let description = match &tool.description {
+            Some(description) => format!(" - {}", description),
+            None => String::new(),
+        };
+        println!(
+            "  {} install {}{} {}",
+            "+".green(),
+            name.bold(),
+            description,
+            format!("({})", reason).dimmed()
+        );
+        let defaults: BTreeMap<String, String> = tool
+            .parameters
+            .iter()
+            .filter(|param| !param.secret)
+            .filter_map(|param| Some((param.name.clone(), param.default.clone()?)))
+            .collect();
+        let steps = render_commands(
+            &tool.commands_for(ToolAction::Install),
+            &template_vars(&variables, &defaults),
+        );
+        let hook = |hook: Option<(&str, &str)>| {
+            if let Some((name, source)) = hook {
+                print_script(&format!("{}:", name), source);
+            }
+        };
+        hook(tool.hooks.before("install"));
+        for step in &steps {
+            match step.script {
+                true => print_script("script:", &step.command),
+                false => println!("      {}", format!("$ {}", redact(&step.command)).dimmed()),
+            }
+        }
+        if let (true, Some(spec)) = (steps.is_empty(), &tool.release) {
+            println!(
+                "      {}",
+                format!("download the latest {} release", spec.repo).dimmed()
+            );
+        }
+        hook(tool.hooks.after("install"));
+    }
+    if !installed.is_empty() {
+        let names: Vec<&str> = installed.iter().map(|(name, _)| name.as_str()).collect();
+        println!(
+            "  {}",
+            format!("Already installed: {}", names.join(", ")).dimmed()
+        );
+    }
+    println!();
+
+    let names: Vec<String> = if assume_yes() {
+        candidates
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect()
+    } else {
+        let items: Vec<String> = candidates
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        multi_select("Select the tools to install:", &items)?
+            .into_iter()
+            .map(|i| items[i].clone())
+            .collect()
+    };
+    if names.is_empty() {
+        println!("{}", "Nothing selected.".yellow());
+        return Ok(());
+    }
+
+    for name in &names {
+        let tool = config.tools.entry(name.clone()).or_insert_with(|| {
+            // Installation state describes the machine the config came from
+            let mut tool = remote.tools[name].clone();
+            tool.installed = false;
+            tool.param_values.clear();
+            tool
+        });
+        tool.desired_state = DesiredState::Present;
+    }
+    // Recipes can refer to the config's variables
+    for (key, value) in &remote.variables {
+        config
+            .variables
+            .entry(key.clone())
+            .or_insert_with(|| value.clone());
+    }
+    config.save_journaled(&format!("apply --from-url {}", source))?;
+
+    println!();
+    install_names(&config, &names, continue_on_error).await
+}
+
+/// Prints a Rhai script in full under `heading`, as part of a plan.
+fn print_script(heading: &str, source: &str) {
+    println!("      {}", heading.dimmed());
+    for line in source.lines() {
+        println!("      {}", format!("| {}", redact(line)).dimmed());
+    }
+}
+
+pub async fn remove_tool(
+    pattern: &str,
+    params: &[(String, String)],
+    continue_on_error: bool,
+    interactive: bool,
+) -> Result<()> {
+    let config = Config::load()?;
+    let mut names = if interactive {
+        pick_tools(&config, pattern, "remove")?
+    } else {
+        select_tools(&config, pattern, "remove", false)?
+    };
+    // Tools that were installed last (and may need the others) go first
+    names.reverse();
+    let installed: Vec<String> = names
+        .iter()
+        .filter(|name| config.tools.get(*name).is_some_and(|tool| tool.installed))
+        .cloned()
+        .collect();
+    if !installed.is_empty() {
+        take_snapshot(&config, SnapshotTrigger::Remove, &installed)?;
+    }
+
+    let _pending = intent::begin(&get_intents_path()?, "remove", &names)?;
+    let changed = run_batch(&names, "remove", continue_on_error, |name| async move {
+        tracked("remove", &name, remove_one(&name, params)).await
+    })
+    .await?;
+
+    // Auto-sync if enabled
+    if changed {
+        auto_sync_if_enabled(&Config::load()?, "remove", &names.join(", ")).await?;
+    }
+    Ok(())
+}
+
+pub async fn update_tool(
+    pattern: &str,
+    params: &[(String, String)],
+    continue_on_error: bool,
+    force: bool,
+    interactive: bool,
+    changelog: bool,
+) -> Result<()> {
+    let config = Config::load()?;
+    let names = if interactive {
+        pick_tools(&config, pattern, "update")?
+    } else {
+        select_tools(&config, pattern, "update", true)?
+    };
+
+    let _pending = intent::begin(&get_intents_path()?, "update", &names)?;
+    run_batch(&names, "update", continue_on_error, |name| async move {
+        tracked("update", &name, update_one(&name, params, force, changelog)).await
+    })
+    .await?;
+    Ok(())
+}
+
+/// Prints the version the package manager reports, and returns it.
+fn report_installed_version(tool: &ToolConfig) -> Option<String> {
+    let spec = tool.package.as_ref()?;
+    if !spec.manager.supports_version_detection() {
+        return None;
+    }
+    match spec.installed_version() {
+        Ok(Some(version)) => {
+            println!("  Installed version: {}", version.green());
+            return Some(version);
+        }
+        Ok(None) => println!(
+            "{}",
+            format!(
+                "  ⚠️  {} does not list '{}' as installed",
+                spec.manager, spec.name
+            )
+            .yellow()
+        ),
+        Err(e) => println!(
+            "{}",
+            format!("  ⚠️  Could not detect installed version: {}", e).yellow()
+        ),
+    }
+    None
+}
+
+/// Runs a tool's rollback steps after a failed install. Failures here are
+/// reported but the install error is what the caller sees.
+async fn roll_back(tool: &ToolConfig, values: &BTreeMap<String, String>) {
+    let commands = render_commands(&tool.rollback_steps(), values);
+    if commands.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "  ⚠️  No rollback or remove commands defined for '{}'",
+                tool.name
+            )
+            .yellow()
+        );
+        return;
+    }
+
+    println!("{}", "Install failed; rolling back...".yellow().bold());
+    match execute_commands(&commands, values, &tool.name, "uninstall").await {
+        Ok(()) => {
+            // Nothing left to resume once the completed steps are undone
+            record_usage(&tool.name, |stats, _| stats.checkpoint = None);
+            println!("{}", format!("  ✓ Rolled back {}", tool.name).green())
+        }
+        Err(e) => println!(
+            "{}",
+            format!("  ⚠️  Rollback of '{}' failed: {}", tool.name, e).red()
+        ),
+    }
+}
+
+/// Runs a tool's verify commands after install/update.
+async fn verify_install(
+    tool: &ToolConfig,
+    values: &BTreeMap<String, String>,
+    action: &str,
+) -> Result<()> {
+    if tool.verify_commands.is_empty() {
+        return Ok(());
+    }
+
+    let commands = render_commands(&tool.verify_commands, values);
+    execute_commands(&commands, values, &tool.name, "verify")
+        .await
+        .map_err(|e| {
+            anyhow!(
+                "The {} of '{}' finished but verification failed: {}",
+                action,
+                tool.name,
+                e
+            )
+        })?;
+    println!("{}", format!("  ✓ {} verified", tool.name).green());
+    Ok(())
+}
+
+/// Updates a tool's usage stats. Stats are best-effort: failing to write
+/// them must not fail the command that was just carried out.
+fn record_usage(tool_name: &str, update: impl FnOnce(&mut ToolStats, &str)) {
+    let now = chrono::Utc::now().to_rfc3339();
+    let result = State::load().and_then(|mut state| {
+        update(state.stats_mut(tool_name), &now);
+        state.save()
+    });
+    if let Err(e) = result {
+        println!(
+            "{}",
+            format!("  ⚠️  Could not record usage stats: {}", e).yellow()
+        );
+    }
+}
+
+/// Adds an event to the history log behind `tkit stats`. Like usage stats,
+/// this is best-effort.
+fn record_history(action: &str, tool: Option<&str>, ok: bool, seconds: Option<f64>) {
+    let event = HistoryEvent::new(action, tool, ok, seconds);
+    if let Err(e) = history::get_history_path().and_then(|path| history::append(&path, &event)) {
+        println!(
+            "{}",
+            format!("  ⚠️  Could not record history: {}", e).yellow()
+        );
+    }
+}
+
+/// Runs one tool's part of a batch and logs it to the history, unless it
+/// turned out there was nothing to do. Its part of the pending intent is
+/// then done.
+async fn tracked(
+    action: &str,
+    tool_name: &str,
+    run: impl std::future::Future<Output = Result<bool>>,
+) -> Result<bool> {
+    let started = std::time::Instant::now();
+    let result = run.await;
+    if !matches!(result, Ok(false)) {
+        let seconds = started.elapsed().as_secs_f64();
+        record_history(action, Some(tool_name), result.is_ok(), Some(seconds));
+    }
+    if let Err(e) = get_intents_path().and_then(|path| intent::mark_done(&path, tool_name)) {
+        println!(
+            "{}",
+            format!("  ⚠️  Could not update the intent journal: {}", e).yellow()
+        );
+    }
+    result
+}
+
+/// Offers to finish the changes an earlier run was interrupted in, e.g. by
+/// a crash or power loss, so the installed-state bookkeeping catches up
+/// with what actually ran. Declined ones are forgotten after pointing out
+/// how to finish them by hand.
+pub async fn recover_interrupted() -> Result<()> {
+    let path = get_intents_path()?;
+    for intent in intent::interrupted(&path)? {
+        let started = chrono::DateTime::parse_from_rfc3339(&intent.started)
+            .map(|at| {
+                at.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_else(|_| intent.started.clone());
+        println!(
+            "{}",
+            format!(
+                "⚠️  '{} {}' started {} did not finish.",
+                intent.action,
+                intent.tools.join(", "),
+                started
+            )
+            .yellow()
+        );
+        // A new attempt records its own intent, so this one goes first
+        intent::discard(&path, &intent)?;
+        if let Err(e) = finish_interrupted(&intent).await {
+            println!(
+                "{}",
+                format!("⚠️  Could not finish it: {}", redact(&e.to_string())).yellow()
+            );
+        }
+        println!();
+    }
+    Ok(())
+}
+
+async fn finish_interrupted(intent: &Intent) -> Result<()> {
+    let remaining = intent.remaining();
+    if remaining.is_empty() {
+        let config = Config::load()?;
+        if config.should_auto_sync() && confirm("Its changes were saved; sync them now?", false)? {
+            auto_sync_if_enabled(&config, &intent.action, &intent.tools.join(", ")).await?;
+        }
+        return Ok(());
+    }
+
+    let retry = match intent.action.as_str() {
+        "install" => format!("tkit install {} --resume", remaining.join(" ")),
+        "apply" => "tkit apply".to_string(),
+        action => format!("tkit {} {}", action, remaining.join(" ")),
+    };
+    if !confirm(&format!("Finish it now ({})?", retry), false)? {
+        println!(
+            "{}",
+            format!("  Run '{}' to finish it later.", retry).dimmed()
+        );
+        return Ok(());
+    }
+    if intent.action == "apply" {
+        return apply_config(Selection::default(), false).await;
+    }
+    for name in &remaining {
+        match intent.action.as_str() {
+            "install" => install_tool(name, &[], false, false, false, false, true).await?,
+            "update" => update_tool(name, &[], false, false, false, false).await?,
+            "remove" => remove_tool(name, &[], false, false).await?,
+            action => return Err(anyhow!("Unknown action '{}'", action)),
+        }
+    }
+    Ok(())
+}
+
+/// Records the version a tool ended up at in `tkit.lock`, or drops it there
+/// when `tool` is `None` after a removal.
+fn record_lock(tool_name: &str, tool: Option<(&ToolConfig, Option<String>)>) {
+    let now = chrono::Utc::now().to_rfc3339();
+    let result = Lockfile::load().and_then(|mut lock| {
+        match tool {
+            Some((tool, version)) => lock.record(tool, version, &now),
+            None => {
+                lock.tools.remove(tool_name);
+            }
+        }
+        lock.save()
+    });
+    if let Err(e) = result {
+        println!(
+            "{}",
+            format!("  ⚠️  Could not update tkit.lock: {}", e).yellow()
+        );
+    }
+}
+
+/// Parameters that reproduce a locked install: the locked version goes to
+/// the tool's `version` parameter unless one was given on the command line.
+fn locked_params(
+    tool: &ToolConfig,
+    locked: &LockedTool,
+    params: &[(String, String)],
+) -> Vec<(String, String)> {
+    let mut params = params.to_vec();
+    if recipe_hash(tool) != locked.recipe_hash {
+        println!(
+            "{}",
+            format!(
+                "⚠️  The recipe for '{}' changed since tkit.lock was written.",
+                tool.name
+            )
+            .yellow()
+        );
+    }
+    let Some(version) = &locked.version else {
+        return params;
+    };
+    if params.iter().any(|(key, _)| key == PIN_PARAM) {
+        return params;
+    }
+    if tool.parameters.iter().any(|param| param.name == PIN_PARAM) {
+        params.push((PIN_PARAM.to_string(), version.clone()));
+    } else {
+        println!(
+            "{}",
+            format!(
+                "⚠️  '{}' has no version parameter; cannot request locked version {}.",
+                tool.name, version
+            )
+            .yellow()
+        );
+    }
+    params
+}
+
+/// The version to record for a tool: its pin, the `version` parameter it was
+/// installed with, or what its package manager reports.
+fn resolved_version(
+    tool: &ToolConfig,
+    values: &BTreeMap<String, String>,
+    detected: Option<String>,
+) -> Option<String> {
+    tool.pinned
+        .clone()
+        .or_else(|| values.get(PIN_PARAM).cloned())
+        .or(detected)
+}
+
+fn ensure_enabled(tool: &ToolConfig) -> Result<()> {
+    if tool.disabled {
+        return Err(anyhow!(
+            "Tool '{}' is disabled. Run 'tkit enable {}' first.",
+            tool.name,
+            tool.name
+        ));
+    }
+    Ok(())
+}
+
+async fn install_one(
+    tool_name: &str,
+    params: &[(String, String)],
+    force: bool,
+    resume: bool,
+) -> Result<bool> {
+    let mut config = Config::load()?;
+
+    let conflicts = config.installed_conflicts(tool_name);
+    if !conflicts.is_empty() {
+        if !force {
+            return Err(anyhow!(
+                "Tool '{}' conflicts with installed tool(s): {}. Use --force to install anyway.",
+                tool_name,
+                conflicts.join(", ")
+            ));
+        }
+        println!(
+            "{}",
+            format!(
+                "⚠️  '{}' conflicts with installed tool(s): {}; installing anyway.",
+                tool_name,
+                conflicts.join(", ")
+            )
+            .yellow()
+        );
+    }
+
+    let tool = config.tools.get_mut(tool_name).ok_or_else(|| {
+        ExitKind::ToolNotFound.error(format!(
+            "Tool '{}' not found. Use 'tkit add {}' to add it first.",
+            tool_name, tool_name
+        ))
+    })?;
+
+    ensure_enabled(tool)?;
+
+    if tool.installed {
+        println!(
+            "{}",
+            format!("Tool '{}' is already installed.", tool_name).yellow()
+        );
+        return Ok(false);
+    }
+
+    if let Some(requires) = &tool.requires {
+        let failures = requires.check();
+        if !failures.is_empty() {
+            return Err(anyhow!(
+                "Pre-flight checks failed for '{}':\n  - {}",
+                tool_name,
+                failures.join("\n  - ")
+            ));
+        }
+    }
+
+    let values = resolve_parameters(tool, params, true)?;
+    let mut vars = template_vars(&config.variables, &values);
+    vars.extend(fetch_downloads(tool).await?);
+    let commands = render_commands(&tool.commands_for(ToolAction::Install), &vars);
+    let token = config.sync.token.clone();
+    let started = std::time::Instant::now();
+    let result = async {
+        let detected = match &tool.release {
+            Some(spec) if commands.is_empty() => {
+                install_release(tool_name, tool, spec, &vars, None, token.as_deref()).await?
+            }
+            _ => {
+                run_steps(&commands, &[], &vars, tool_name, "install", resume).await?;
+                report_installed_version(tool)
+            }
+        };
+        verify_install(tool, &vars, "install").await?;
+        Ok(detected)
+    }
+    .await;
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let detected = match result {
+        Ok(detected) => detected,
+        Err(e) => {
+            if config.settings.rollback_on_failure {
+                roll_back(tool, &vars).await;
+            }
+            return Err(e);
+        }
+    };
+
+    tool.installed = true;
+    tool.desired_state = DesiredState::Present;
+    let version = resolved_version(tool, &values, detected);
+    tool.param_values = values;
+    tool.touch();
+    record_lock(tool_name, Some((tool, version)));
+    config.save()?;
+    record_usage(tool_name, |stats, now| stats.record_install(now, elapsed));
+
+    Ok(true)
+}
+
+async fn remove_one(tool_name: &str, params: &[(String, String)]) -> Result<bool> {
+    let mut config = Config::load()?;
+
+    let tool = config
+        .tools
+        .get_mut(tool_name)
+        .ok_or_else(|| tool_not_found(tool_name))?;
+
+    if !tool.installed {
+        println!(
+            "{}",
+            format!("Tool '{}' is not installed.", tool_name).yellow()
+        );
+        return Ok(false);
+    }
+
+    let values = resolve_parameters(tool, params, false)?;
+    let vars = template_vars(&config.variables, &values);
+    let commands = render_commands(&tool.commands_for(ToolAction::Remove), &vars);
+    if tool.release.is_none() || !commands.is_empty() {
+        execute_commands(&commands, &vars, tool_name, "remove").await?;
+    }
+    remove_installed_files(tool_name, tool)?;
+    record_lock(tool_name, None);
+
+    tool.installed = false;
+    tool.desired_state = DesiredState::Absent;
+    tool.touch();
+    config.save()?;
+
+    Ok(true)
+}
+
+/// The files tkit put in place itself for a tool: those recorded at install
+/// time, and its release executable.
+fn installed_files(tool_name: &str, tool: &ToolConfig) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = State::load()?
+        .tools
+        .get(tool_name)
+        .map(|stats| stats.files.iter().map(PathBuf::from).collect())
+        .unwrap_or_default();
+    if tool.release.is_some() {
+        let executable = releases::release_executable(tool)?;
+        if !files.contains(&executable) {
+            files.push(executable);
+        }
+    }
+    Ok(files)
+}
+
+/// Deletes the files tkit installed for a tool and forgets them.
+fn remove_installed_files(tool_name: &str, tool: &ToolConfig) -> Result<()> {
+    let files = installed_files(tool_name, tool)?;
+    for file in files.iter().filter(|file| file.exists()) {
+        fs::remove_file(file)?;
+        println!("  {} {}", "Removed".dimmed(), file.display());
+    }
+    if !files.is_empty() {
+        record_usage(tool_name, |stats, _| stats.files.clear());
+    }
+    Ok(())
+}
+
+async fn update_one(
+    tool_name: &str,
+    params: &[(String, String)],
+    force: bool,
+    changelog: bool,
+) -> Result<bool> {
+    let mut config = Config::load()?;
+
+    let tool = config
+        .tools
+        .get_mut(tool_name)
+        .ok_or_else(|| tool_not_found(tool_name))?;
+
+    ensure_enabled(tool)?;
+
+    if !tool.installed {
+        println!(
+            "{}",
+            format!("Tool '{}' is not installed. Install it first.", tool_name).yellow()
+        );
+        return Ok(false);
+    }
+
+    if let Some(version) = &tool.pinned
+        && !force
+    {
+        println!(
+            "{}",
+            format!(
+                "Tool '{}' is pinned to {}; use --force to update it anyway.",
+                tool_name, version
+            )
+            .yellow()
+        );
+        return Ok(false);
+    }
+
+    if changelog {
+        let token = config.sync.token.clone();
+        if let Err(e) = show_release_notes(tool_name, tool, token.as_deref()).await {
+            println!(
+                "{}",
+                format!("  ⚠️  Could not show release notes: {}", e).yellow()
+            );
+        }
+    }
+
+    let values = resolve_parameters(tool, params, false)?;
+    let mut vars = template_vars(&config.variables, &values);
+    vars.extend(fetch_downloads(tool).await?);
+    let commands = render_commands(&tool.commands_for(ToolAction::Update), &vars);
+    let started = std::time::Instant::now();
+    let detected = match &tool.release {
+        Some(spec) if commands.is_empty() => {
+            let installed = installed_version(&Lockfile::load()?, tool_name, tool);
+            let token = config.sync.token.clone();
+            let version = install_release(
+                tool_name,
+                tool,
+                spec,
+                &vars,
+                installed.as_deref(),
+                token.as_deref(),
+            )
+            .await?;
+            if version.is_none() {
+                return Ok(false);
+            }
+            version
+        }
+        _ => {
+            execute_commands(&commands, &vars, tool_name, "update").await?;
+            report_installed_version(tool)
+        }
+    };
+    let elapsed = started.elapsed().as_secs_f64();
+    verify_install(tool, &vars, "update").await?;
+    record_usage(tool_name, |stats, now| stats.record_update(now, elapsed));
+    record_lock(
+        tool_name,
+        Some((tool, resolved_version(tool, &values, detected))),
+    );
+
+    // An update may move to a new version, so remember the values it used
+    tool.param_values = values;
+    tool.touch();
+    config.save()?;
+    Ok(true)
+}
+
+pub fn list_tools(sort: ListSort, filter: ListFilter, long: bool) -> Result<()> {
+    let config = Config::load()?;
+
+    if config.tools.is_empty() {
+        println!(
+            "{}",
+            "No tools configured. Use 'tkit add <tool>' to add some!".yellow()
+        );
+        return Ok(());
+    }
+
+    let state = State::load()?;
+    let mut names: Vec<&String> = config
+        .tools
+        .iter()
+        .filter(|(_, tool)| {
+            filter.category.is_none_or(|category| {
+                tool.category
+                    .as_deref()
+                    .is_some_and(|c| c.eq_ignore_ascii_case(category))
+            })
+        })
+        .filter(|(_, tool)| !filter.installed || tool.installed)
+        .filter(|(_, tool)| !filter.missing || !tool.installed)
+        .map(|(name, _)| name)
+        .collect();
+
+    if names.is_empty() {
+        println!("{}", "No tools match the given filters.".yellow());
+        return Ok(());
+    }
+
+    // Ties in every ordering fall back to the name
+    names.sort();
+    match sort {
+        ListSort::Name => {}
+        ListSort::Status => names.sort_by_key(|name| {
+            let tool = &config.tools[*name];
+            (tool.disabled, !tool.installed)
+        }),
+        // Newest first; tools without a timestamp sink to the bottom
+        ListSort::Added => names.sort_by(|a, b| {
+            config.tools[*b]
+                .created_at
+                .cmp(&config.tools[*a].created_at)
+        }),
+        ListSort::Modified => names.sort_by(|a, b| {
+            config.tools[*b]
+                .updated_at
+                .cmp(&config.tools[*a].updated_at)
+        }),
+        ListSort::LastUsed => {
+            let last_used = |name: &str| state.tools.get(name).and_then(ToolStats::last_used);
+            names.sort_by(|a, b| last_used(b).cmp(&last_used(a)));
+        }
+    }
+
+    let mut versions = VersionCache::default();
+    let rows: Vec<(&String, Vec<String>)> = names
+        .into_iter()
+        .map(|name| {
+            (
+                name,
+                list_row(name, &config.tools[name], &state, &mut versions),
+            )
+        })
+        .collect();
+
+    // Group by category, alphabetically, with uncategorized tools last
+    let mut groups: Vec<(Option<&str>, Vec<&Vec<String>>)> = Vec::new();
+    for (name, row) in &rows {
+        let category = config.tools[*name].category.as_deref();
+        match groups.iter_mut().find(|(c, _)| *c == category) {
+            Some((_, group)) => group.push(row),
+            None => groups.push((category, vec![row])),
+        }
+    }
+    groups.sort_by_key(|(category, _)| (category.is_none(), *category));
+
+    let mut header = vec![
+        "Name",
+        "Status",
+        "Version",
+        "Description",
+        "Tags",
+        "Last update",
+    ];
+    if long {
+        header.extend(["Added", "Modified"]);
+    }
+    let header: Vec<String> = header.into_iter().map(str::to_string).collect();
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.chars().count()).collect();
+    for (_, row) in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    println!("{}", format_row(&header, &widths).bold());
+    let show_headers = groups.iter().any(|(category, _)| category.is_some());
+    for (category, rows) in groups {
+        if show_headers {
+            println!(
+                "{}",
+                format!("{}:", category.unwrap_or("Uncategorized"))
+                    .cyan()
+                    .bold()
+            );
+        }
+        for row in rows {
+            let line = format_row(row, &widths);
+            match row[1].as_str() {
+                "installed" => println!("{}", line),
+                "missing" => println!("{}", line.yellow()),
+                _ => println!("{}", line.dimmed()),
+            }
+        }
+    }
+    Ok(())
+}
+
+const DESCRIPTION_WIDTH: usize = 40;
+
+fn list_row(
+    name: &str,
+    tool: &ToolConfig,
+    state: &State,
+    versions: &mut VersionCache,
+) -> Vec<String> {
+    let name = if tool.aliases.is_empty() {
+        name.to_string()
+    } else {
+        format!("{} ({})", name, tool.aliases.join(", "))
+    };
+    let status = if tool.disabled {
+        "disabled"
+    } else if tool.installed {
+        "installed"
+    } else {
+        "missing"
+    };
+    let version = tool
+        .package
+        .as_ref()
+        .filter(|_| tool.installed)
+        .and_then(|spec| versions.version_of(spec))
+        .unwrap_or_else(|| "-".to_string());
+    let desc = tool.description.as_deref().unwrap_or("No description");
+    let desc = if desc.chars().count() > DESCRIPTION_WIDTH {
+        let cut: String = desc.chars().take(DESCRIPTION_WIDTH - 1).collect();
+        format!("{}…", cut)
+    } else {
+        desc.to_string()
+    };
+    let last_update = state.tools.get(&tool.name).and_then(|stats| {
+        stats
+            .last_update
+            .as_deref()
+            .or(stats.last_install.as_deref())
+    });
+
+    vec![
+        name,
+        status.to_string(),
+        version,
+        desc,
+        tool.tags.join(", "),
+        format_timestamp(last_update),
+    ]
+}
+
+fn format_row(cells: &[String], widths: &[usize]) -> String {
+    let padded: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+        .collect();
+    format!("  {}", padded.join("  ").trim_end())
+}
+
+pub fn show_tool(tool_name: &str) -> Result<()> {
+    let config = Config::load()?;
+    let tool_name = &config.resolve_name(tool_name);
+    let tool = config
+        .tools
+        .get(tool_name)
+        .ok_or_else(|| tool_not_found(tool_name))?;
+
+    println!("{}", format!("Tool '{}':", tool_name).blue().bold());
+    println!("  Added: {}", format_timestamp(tool.created_at.as_deref()));
+    println!(
+        "  Modified: {}",
+        format_timestamp(tool.updated_at.as_deref())
+    );
+    let overridden = config.overridden_fields(tool_name);
+    if !overridden.is_empty() {
+        println!("  Overridden on this machine: {}", overridden.join(", "));
+    }
+    println!();
+    print_tool_definition(tool)?;
+    Ok(())
+}
+
+pub fn print_env(shell: Option<Shell>) -> Result<()> {
+    let config = Config::load()?;
+
+    let shell = shell.unwrap_or_else(Shell::detect);
+    print!("{}", shellenv::render(active_tools(&config), shell));
+    if get_bin_dir()?.exists() {
+        println!("{}", shellenv::path_line(&[BIN_DIR], shell));
+    }
+    Ok(())
+}
+
+/// Prints where a tool's executable is: the one tkit installed for it, or
+/// else the first match of its binary on PATH.
+pub fn which_tool(tool: &str) -> Result<()> {
+    let config = Config::load()?;
+    let tool_name = config.resolve_name(tool);
+    let tool = config
+        .get_tool(&tool_name)
+        .ok_or_else(|| tool_not_found(&tool_name))?;
+
+    let managed = installed_files(&tool_name, tool)?
+        .into_iter()
+        .find(|file| file.exists());
+    let path = match managed {
+        Some(path) => {
+            if let Some(dir) = path.parent()
+                && !on_path(dir)
+            {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "⚠️  {} is not on PATH; 'tkit path --install' adds it",
+                        dir.display()
+                    )
+                    .yellow()
+                );
+            }
+            path
+        }
+        None => which::which(tool.binary_name()).map_err(|_| {
+            anyhow!(
+                "No '{}' executable found for tool '{}'",
+                tool.binary_name(),
+                tool_name
+            )
+        })?,
+    };
+    println!("{}", path.display());
+    Ok(())
+}
+
+/// Prints the line that puts tkit's bin directory on PATH, or with `install`
+/// adds it to the shell's startup file.
+pub fn print_path(shell: Option<Shell>, install: bool) -> Result<()> {
+    let shell = shell.unwrap_or_else(Shell::detect);
+    let line = shellenv::path_line(&[BIN_DIR], shell);
+    if !install {
+        println!("{}", line);
+        return Ok(());
+    }
+
+    let home = dirs::home_dir().ok_or_else(|| anyhow!("Could not determine home directory"))?;
+    let rc = home.join(shell.rc_file());
+    if shellenv::add_to_rc(&rc, &line)? {
+        println!(
+            "{}",
+            format!("✓ Added {} to PATH in {}", BIN_DIR, rc.display()).green()
+        );
+        println!("  Open a new shell (or source {}) to use it", rc.display());
+    } else {
+        println!(
+            "{}",
+            format!("{} already puts {} on PATH", rc.display(), BIN_DIR).dimmed()
+        );
+    }
+    Ok(())
+}
+
+/// Installed, enabled tools in name order; these are the ones whose shell
+/// integration is active.
+fn active_tools(config: &Config) -> Vec<&ToolConfig> {
+    let mut tools: Vec<&ToolConfig> = config
+        .tools
+        .values()
+        .filter(|tool| tool.installed && !tool.disabled)
+        .collect();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    tools
+}
+
+pub fn sync_aliases() -> Result<()> {
+    let config = Config::load()?;
+    let tools = active_tools(&config);
+
+    let valid = |name: &str| {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_.:-".contains(c))
+    };
+    for tool in &tools {
+        if let Some(name) = tool.shell_aliases.keys().find(|name| !valid(name)) {
+            return Err(anyhow!(
+                "Invalid alias name '{}' in tool '{}'",
+                name,
+                tool.name
+            ));
+        }
+    }
+
+    let config_path = get_config_path()?;
+    let posix_path = config_path.with_file_name("aliases.sh");
+    let fish_path = config_path.with_file_name("aliases.fish");
+    fs::write(
+        &posix_path,
+        shellenv::render_aliases(tools.clone(), Shell::Bash),
+    )?;
+    fs::write(
+        &fish_path,
+        shellenv::render_aliases(tools.clone(), Shell::Fish),
+    )?;
+
+    let count: usize = tools.iter().map(|tool| tool.shell_aliases.len()).sum();
+    println!("{}", format!("✓ Wrote {} aliases", count).green().bold());
+    println!("  bash/zsh: source {}", posix_path.display());
+    println!("  fish:     source {}", fish_path.display());
+    Ok(())
+}
+
+pub fn list_aliases() -> Result<()> {
+    let config = Config::load()?;
+
+    let names: Vec<&String> = config.tools.keys().collect();
+
+    let mut any = false;
+    for name in names {
+        let tool = &config.tools[name];
+        if tool.shell_aliases.is_empty() {
+            continue;
+        }
+        any = true;
+        let status = if tool.installed && !tool.disabled {
+            String::new()
+        } else {
+            " (inactive)".dimmed().to_string()
+        };
+        println!("{}{}", name.bold(), status);
+        for (alias, command) in &tool.shell_aliases {
+            println!("  {} = {}", alias.green(), command);
+        }
+    }
+
+    if !any {
+        println!("{}", "No tools declare shell aliases.".yellow());
+    }
+    Ok(())
+}
+
+/// Scheduled run entries of installed, enabled tools as `(tool, entry, schedule)`.
+fn scheduled_entries(config: &Config) -> Vec<(String, String, Result<Schedule>)> {
+    active_tools(config)
+        .into_iter()
+        .flat_map(|tool| {
+            tool.run_commands.scheduled().map(|(entry, expr)| {
+                (
+                    tool.name.clone(),
+                    entry.to_string(),
+                    expr.parse::<Schedule>(),
+                )
+            })
+        })
+        .collect()
+}
+
+fn get_scheduler_log_path() -> Result<std::path::PathBuf> {
+    Ok(get_log_dir()?.join("scheduler.log"))
+}
+
+pub fn list_schedules() -> Result<()> {
+    let config = Config::load()?;
+    let mut any = false;
+    for tool in active_tools(&config) {
+        for (entry, expr) in tool.run_commands.scheduled() {
+            any = true;
+            let status = match expr.parse::<Schedule>() {
+                Ok(_) => String::new(),
+                Err(e) => format!("  {}", e.to_string().red()),
+            };
+            let target = format!("{}:{}", tool.name, entry);
+            println!("{:<30} {}{}", target.bold(), expr, status);
+        }
+    }
+
+    if !any {
+        println!("{}", "No scheduled run commands.".yellow());
+        return Ok(());
+    }
+    println!("  log: {}", get_scheduler_log_path()?.display());
+    Ok(())
+}
+
+fn append_log(path: &std::path::Path, line: &str) -> Result<fs::File> {
+    use std::io::Write;
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(
+        file,
+        "[{}] {}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        line
+    )?;
+    Ok(file)
+}
+
+/// Runs due entries once a minute as `tkit run tool:entry`, appending their
+/// output and exit status to the scheduler log. The config is re-read every
+/// minute, so edits take effect without a restart.
+pub async fn run_scheduler() -> Result<()> {
+    let log_path = get_scheduler_log_path()?;
+    if let Some(parent) = log_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let exe = std::env::current_exe()?;
+
+    let config = Config::load()?;
+    for (tool, entry, schedule) in scheduled_entries(&config) {
+        if let Err(e) = schedule {
+            println!("{} {}:{}: {}", "⚠".yellow(), tool, entry, e);
+        }
+    }
+    println!(
+        "{}",
+        "Scheduler running; press Ctrl+C to stop.".cyan().bold()
+    );
+    println!("  log: {}", log_path.display());
+
+    loop {
+        let now = chrono::Local::now();
+        let config = match Config::load() {
+            Ok(config) => config,
+            Err(e) => {
+                append_log(&log_path, &format!("could not load config: {}", e))?;
+                Config::default()
+            }
+        };
+
+        for (tool, entry, schedule) in scheduled_entries(&config) {
+            if !schedule.is_ok_and(|schedule| schedule.matches(&now)) {
+                continue;
+            }
+            let target = format!("{}:{}", tool, entry);
+            println!("{} {}", "▶".cyan(), target);
+            let log = append_log(&log_path, &format!("{}: started", target))?;
+            let mut child = tokio::process::Command::new(&exe)
+                .arg("run")
+                .arg(&target)
+                .stdout(log.try_clone()?)
+                .stderr(log)
+                .spawn()?;
+            let log_path = log_path.clone();
+            tokio::spawn(async move {
+                let line = match child.wait().await {
+                    Ok(status) if status.success() => format!("{}: succeeded", target),
+                    Ok(status) => format!("{}: failed ({})", target, status),
+                    Err(e) => format!("{}: failed ({})", target, e),
+                };
+                let _ = append_log(&log_path, &line);
+            });
+        }
+
+        let seconds = 60 - u64::from(chrono::Local::now().timestamp().rem_euclid(60) as u32);
+        tokio::time::sleep(std::time::Duration::from_secs(seconds)).await;
+    }
+}
+
+/// Enabled tools with managed files, in name order, optionally just `tool`.
+fn tools_with_files<'a>(config: &'a Config, tool: Option<&str>) -> Result<Vec<&'a ToolConfig>> {
+    if let Some(name) = tool {
+        let name = config.resolve_name(name);
+        let tool = config
+            .tools
+            .get(&name)
+            .ok_or_else(|| tool_not_found(&name))?;
+        return Ok(vec![tool]);
+    }
+
+    let mut tools: Vec<&ToolConfig> = config
+        .tools
+        .values()
+        .filter(|tool| !tool.disabled && !tool.files.is_empty())
+        .collect();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(tools)
+}
+
+pub fn apply_files(tool: Option<&str>, force: bool) -> Result<()> {
+    let config = Config::load()?;
+    let files_dir = config.settings.files_dir()?;
+
+    let mut failed = 0;
+    let mut changed = 0;
+    for tool in tools_with_files(&config, tool)? {
+        for file in &tool.files {
+            let target = file.target_path();
+            match file.apply(&files_dir, force) {
+                Ok(FileState::UpToDate) => {}
+                Ok(_) => {
+                    changed += 1;
+                    println!("  {} {} ({})", "✓".green(), target.display(), tool.name);
+                }
+                Err(e) => {
+                    failed += 1;
+                    println!("  {} {}", "✗".red(), e);
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("{} file(s) could not be applied", failed));
+    }
+    println!(
+        "{}",
+        format!("✓ Files applied ({} changed)", changed)
+            .green()
+            .bold()
+    );
+    Ok(())
+}
+
+pub fn show_files_status(tool: Option<&str>) -> Result<()> {
+    let config = Config::load()?;
+    let files_dir = config.settings.files_dir()?;
+
+    println!("{}", "Managed files:".blue().bold());
+    println!("  Source directory: {}", files_dir.display());
+    let tools = tools_with_files(&config, tool)?;
+    if tools.iter().all(|tool| tool.files.is_empty()) {
+        println!("  {}", "No tools declare files.".yellow());
+        return Ok(());
+    }
+
+    for tool in tools {
+        println!("{}", tool.name.bold());
+        for file in &tool.files {
+            let state = file.state(&files_dir);
+            let label = match state {
+                FileState::UpToDate => state.to_string().green(),
+                FileState::Missing => state.to_string().yellow(),
+                FileState::Differs | FileState::SourceMissing => state.to_string().red(),
+            };
+            println!(
+                "  {} -> {} [{}]",
+                file.source,
+                file.target_path().display(),
+                label
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Shortens an RFC 3339 timestamp to a local date and time for display.
+fn format_timestamp(timestamp: Option<&str>) -> String {
+    timestamp
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| "never".to_string())
+}
+
+pub fn show_stats(days: Option<u32>) -> Result<()> {
+    let config = Config::load()?;
+    let state = State::load()?;
+
+    if config.tools.is_empty() {
+        println!(
+            "{}",
+            "No tools configured. Use 'tkit add <tool>' to add some!".yellow()
+        );
+        return Ok(());
+    }
+
+    let names: Vec<&String> = config.tools.keys().collect();
+    let width = names
+        .iter()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    let empty = ToolStats::default();
+
+    println!(
+        "{}",
+        format!(
+            "  {:<width$}  {:>5}  {:<16}  {:<16}  {:<16}  {:>9}",
+            "Tool", "Runs", "Last run", "Last install", "Last update", "Time"
+        )
+        .bold()
+    );
+    for name in names {
+        let stats = state.tools.get(name).unwrap_or(&empty);
+        let row = format!(
+            "  {:<width$}  {:>5}  {:<16}  {:<16}  {:<16}  {:>8.1}s",
+            name,
+            stats.runs,
+            format_timestamp(stats.last_run.as_deref()),
+            format_timestamp(stats.last_install.as_deref()),
+            format_timestamp(stats.last_update.as_deref()),
+            stats.install_seconds
+        );
+        if stats.last_used().is_none() {
+            println!("{}", row.dimmed());
+        } else {
+            println!("{}", row);
+        }
+    }
+
+    let since = days.map(|days| chrono::Utc::now() - chrono::Duration::days(days as i64));
+    let summary = Summary::from_events(&history::load(&history::get_history_path()?)?, since);
+    println!();
+    match days {
+        Some(days) => println!(
+            "{}",
+            format!("Activity (last {} days):", days).blue().bold()
+        ),
+        None => println!("{}", "Activity:".blue().bold()),
+    }
+    if summary.is_empty() {
+        println!("  {}", "Nothing recorded yet.".dimmed());
+        return Ok(());
+    }
+    print_activity(&summary);
+    Ok(())
+}
+
+/// A bar of up to `width` blocks, `value`'s share of `max`.
+fn bar(value: usize, max: usize, width: usize) -> String {
+    if max == 0 {
+        return String::new();
+    }
+    "█".repeat((value * width).div_ceil(max))
+}
+
+fn print_activity(summary: &Summary) {
+    let most_run = summary.most_run();
+    if !most_run.is_empty() {
+        println!("  {}", "Most run:".bold());
+        let width = most_run
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        let max = most_run[0].1;
+        for (name, runs) in most_run.iter().take(5) {
+            println!(
+                "    {:<width$}  {:>4}  {}",
+                name,
+                runs,
+                bar(*runs, max, 20).cyan()
+            );
+        }
+    }
+
+    let tools: Vec<_> = summary
+        .tools
+        .iter()
+        .filter(|(_, stats)| stats.average_install.is_some() || stats.failures > 0)
+        .collect();
+    if !tools.is_empty() {
+        let width = tools
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        println!(
+            "  {}",
+            format!(
+                "{:<width$}  {:>12}  {:>8}",
+                "Tool", "Avg install", "Failures"
+            )
+            .bold()
+        );
+        for (name, stats) in tools {
+            let average = stats
+                .average_install
+                .map(|seconds| format!("{:.1}s", seconds))
+                .unwrap_or_else(|| "-".to_string());
+            let failures = format!("{:.0}%", stats.failure_rate() * 100.0);
+            let failures = if stats.failures > 0 {
+                format!("{:>8}", failures).red().to_string()
+            } else {
+                format!("{:>8}", failures)
+            };
+            println!(
+                "  {:<width$}  {:>12}  {}  {}",
+                name,
+                average,
+                failures,
+                format!("({} of {})", stats.failures, stats.attempts).dimmed()
+            );
+        }
+    }
+
+    println!("  {}", "Busiest days (UTC):".bold());
+    let days = summary.busiest_days();
+    let max = days[0].1;
+    for (day, count) in days.iter().filter(|(_, count)| *count > 0) {
+        println!("    {}  {:>4}  {}", day, count, bar(*count, max, 20).cyan());
+    }
+
+    let frequency = match summary.syncs_per_week() {
+        Some(per_week) => format!(", {:.1} per week", per_week),
+        None => String::new(),
+    };
+    println!(
+        "  {} {} pushes, {} pulls{}",
+        "Syncs:".bold(),
+        summary.pushes,
+        summary.pulls,
+        frequency
+    );
+}
+
+/// Lists the latest recorded timing of each tool and action, slowest first,
+/// optionally with the steps that made them up.
+pub fn show_timings_report(steps: bool) -> Result<()> {
+    let state = State::load()?;
+    let mut timings: Vec<(&String, &String, &Timing)> = state
+        .tools
+        .iter()
+        .flat_map(|(name, stats)| {
+            stats
+                .timings
+                .iter()
+                .map(move |(action, timing)| (name, action, timing))
+        })
+        .collect();
+    if timings.is_empty() {
+        println!(
+            "{}",
+            "No timings recorded yet. Install or update a tool first.".yellow()
+        );
+        return Ok(());
+    }
+    timings.sort_by(|a, b| b.2.seconds.total_cmp(&a.2.seconds));
+
+    let width = timings
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    println!(
+        "{}",
+        format!(
+            "  {:<width$}  {:<8}  {:>9}  {:<16}  {}",
+            "Tool", "Action", "Time", "When", "Slowest step"
+        )
+        .bold()
+    );
+    for (name, action, timing) in timings {
+        let slowest = timing
+            .steps
+            .iter()
+            .max_by(|a, b| a.seconds.total_cmp(&b.seconds))
+            .map(|step| format!("{} ({:.1}s)", step.command, step.seconds))
+            .unwrap_or_default();
+        println!(
+            "  {:<width$}  {:<8}  {:>8.1}s  {:<16}  {}",
+            name,
+            action,
+            timing.seconds,
+            format_timestamp(Some(&timing.at)),
+            slowest.dimmed()
+        );
+        if steps {
+            for (i, step) in timing.steps.iter().enumerate() {
+                println!(
+                    "  {:<width$}  {:<8}  {:>8.1}s  {} {}",
+                    "",
+                    "",
+                    step.seconds,
+                    format!("Step {}:", i + 1).dimmed(),
+                    step.command
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs one step quietly, failing on a non-zero exit or when it outlives
+/// `timeout` or its own, shorter one. Returns what it printed on stdout.
+/// `script:` steps run with `vars` in scope and fail when they throw.
+async fn run_check_command(
+    step: &Step,
+    tool: &ToolConfig,
+    settings: &Settings,
+    timeout: std::time::Duration,
+    vars: &BTreeMap<String, String>,
+) -> Result<String> {
+    if step.script {
+        let (env, cwd, target) = (step.env.clone(), step.cwd.clone(), tool.target);
+        let run = script::run(&step.command, vars, move |command| {
+            script_exec(command, target, &env, cwd.as_deref())
+        });
+        return match run.error {
+            Some(error) => Err(anyhow!("script failed: {}", error)),
+            None => Ok(run.lines.join("\n")),
+        };
+    }
+    let cmd = step.command.as_str();
+    let shell = step.shell.or(tool.shell).or(settings.shell);
+    let (_, program, args) = step_invocation(
+        cmd,
+        shell,
+        step.sudo,
+        tool.target,
+        &[],
+        &step.env,
+        &BTreeMap::new(),
+    )?;
+    let timeout = step
+        .timeout
+        .map(std::time::Duration::from_secs)
+        .map_or(timeout, |own| own.min(timeout));
+
+    if let Some(mock) = mock::active() {
+        let response = mock.respond(cmd);
+        if response.delay > timeout {
+            tokio::time::sleep(timeout).await;
+            return Err(anyhow!("timed out after {}s", timeout.as_secs()));
+        }
+        tokio::time::sleep(response.delay).await;
+        if !response.success() {
+            let reason = response.stderr.first().map_or("", |line| line.trim());
+            return Err(anyhow!("'{}' failed: {}", cmd, reason));
+        }
+        return Ok(response.stdout.join("\n"));
+    }
+
+    let mut command = tokio::process::Command::new(program);
+    command
+        .args(args)
+        .envs(&step.env)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true);
+    if let Some(cwd) = &step.cwd {
+        command.current_dir(expand_tilde(cwd));
+    }
+    let output = command.output();
+    let output = tokio::time::timeout(timeout, output)
+        .await
+        .map_err(|_| anyhow!("timed out after {}s", timeout.as_secs()))??;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().next().unwrap_or("").trim();
+        return Err(anyhow!("'{}' failed: {}", cmd, reason));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Lines of each release's notes shown before pointing to the release page.
+const RELEASE_NOTE_LINES: usize = 20;
+
+/// The version of a tool recorded in tkit.lock, or the one it's pinned to.
+fn installed_version(lock: &Lockfile, tool_name: &str, tool: &ToolConfig) -> Option<String> {
+    lock.tools
+        .get(tool_name)
+        .and_then(|locked| locked.version.clone())
+        .or_else(|| tool.pinned.clone())
+}
+
+/// Prints the notes of the tool's releases newer than the installed version.
+async fn show_release_notes(tool_name: &str, tool: &ToolConfig, token: Option<&str>) -> Result<()> {
+    let repo = tool.release_repo().ok_or_else(|| {
+        anyhow!(
+            "Tool '{}' has no github_repo or release to read releases from",
+            tool_name
+        )
+    })?;
+    let releases = releases::fetch_releases(repo, token).await?;
+    let installed = installed_version(&Lockfile::load().unwrap_or_default(), tool_name, tool);
+    let since = releases::releases_since(&releases, installed.as_deref());
+    if since.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "  No releases of {} since {}",
+                repo,
+                installed.unwrap_or_default()
+            )
+            .dimmed()
+        );
+        return Ok(());
+    }
+    println!("{}", format!("Release notes of {}:", repo).cyan().bold());
+    print_release_notes(&since);
+    Ok(())
+}
+
+fn print_release_notes(releases: &[&Release]) {
+    for release in releases {
+        let title = release
+            .name
+            .as_deref()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or(&release.tag_name);
+        match release.date() {
+            Some(date) => println!("  {} {}", title.bold(), format!("({})", date).dimmed()),
+            None => println!("  {}", title.bold()),
+        }
+        let body = release.body.as_deref().unwrap_or_default().trim();
+        let lines: Vec<&str> = body
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        for line in lines.iter().take(RELEASE_NOTE_LINES) {
+            println!("    {}", render_markdown_line(line));
+        }
+        if lines.len() > RELEASE_NOTE_LINES {
+            println!(
+                "{}",
+                format!(
+                    "    ... {} more lines at {}",
+                    lines.len() - RELEASE_NOTE_LINES,
+                    release.html_url
+                )
+                .dimmed()
+            );
+        }
+    }
+}
+
+/// One line of Markdown release notes for the terminal: headings in bold,
+/// list items with a bullet, emphasis markers dropped.
+fn render_markdown_line(line: &str) -> String {
+    let line = line.trim_end().replace("**", "").replace('`', "");
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('#') {
+        return trimmed.trim_start_matches('#').trim().bold().to_string();
+    }
+    match trimmed
+        .strip_prefix("* ")
+        .or_else(|| trimmed.strip_prefix("- "))
+    {
+        Some(item) => format!("{}• {}", &line[..line.len() - trimmed.len()], item),
+        None => line,
+    }
+}
+
+/// Lists installed tools with a GitHub repository whose latest release is newer
+/// than the version in tkit.lock.
+pub async fn show_outdated(changelog: bool) -> Result<()> {
+    let config = Config::load()?;
+    let tools: Vec<(&String, &ToolConfig)> = config
+        .tools
+        .iter()
+        .filter(|(_, tool)| tool.installed && tool.release_repo().is_some())
+        .collect();
+    if tools.is_empty() {
+        println!(
+            "{}",
+            "No installed tools have a github_repo or release to check.".yellow()
+        );
+        return Ok(());
+    }
+
+    let lock = Lockfile::load().unwrap_or_default();
+    let token = config.sync.token.as_deref();
+    let width = tools.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let mut outdated = 0;
+    for (name, tool) in tools {
+        let repo = tool.release_repo().unwrap_or_default();
+        let releases = match releases::fetch_releases(repo, token).await {
+            Ok(releases) => releases,
+            Err(e) => {
+                println!("  {:<width$}  {}", name, e.to_string().red());
+                continue;
+            }
+        };
+        let Some(latest) = releases::latest(&releases) else {
+            println!("  {:<width$}  {}", name, "no releases".dimmed());
+            continue;
+        };
+        let installed = installed_version(&lock, name, tool);
+        if !releases::is_newer(latest, installed.as_deref()) {
+            println!(
+                "  {:<width$}  {} {}",
+                name,
+                latest.version(),
+                "up to date".dimmed()
+            );
+            continue;
+        }
+        outdated += 1;
+        println!(
+            "  {:<width$}  {} → {}",
+            name,
+            installed.as_deref().unwrap_or("unknown"),
+            latest.version().green().bold()
+        );
+        if changelog {
+            print_release_notes(&releases::releases_since(&releases, installed.as_deref()));
+        }
+    }
+
+    println!();
+    if outdated == 0 {
+        println!("{}", "✓ Everything is up to date".green().bold());
+    } else {
+        println!(
+            "{}",
+            format!(
+                "{} tool(s) have a newer release; run 'tkit update <tool>'",
+                outdated
+            )
+            .yellow()
+        );
+    }
+    Ok(())
+}
+
+/// Checks whether installed tools have newer releases and whether the synced
+/// config changed. With `quiet`, as meant for shell startup files, the last
+/// result is printed as a one-line nudge (or nothing) and refreshed in the
+/// background once it is older than `settings.notify_interval`, so a new
+/// shell never waits on the network.
+pub async fn notify(quiet: bool) -> Result<()> {
+    if quiet {
+        // A broken config or cache must not get in the way of a shell starting
+        let _ = notify_from_cache();
+        return Ok(());
+    }
+
+    let config = Config::load()?;
+    let cache = check_for_updates(&config).await;
+    cache.save()?;
+    match cache.message() {
+        Some(message) => println!("{}", message.yellow()),
+        None => println!(
+            "{}",
+            "✓ Tools are up to date and the config is in sync".green()
+        ),
+    }
+    Ok(())
+}
+
+fn notify_from_cache() -> Result<()> {
+    let config = Config::load()?;
+    let mut cache = NotifyCache::load()?;
+    if let Some(message) = cache.message() {
+        println!("{}", message.yellow());
+    }
+
+    let now = chrono::Utc::now();
+    if !cache.is_fresh(now, config.settings.notify_interval()) {
+        // Claimed up front so shells started meanwhile don't check as well
+        cache.checked_at = Some(now.to_rfc3339());
+        cache.save()?;
+        Command::new(std::env::current_exe()?)
+            .arg("notify")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+    }
+    Ok(())
+}
+
+/// What `tkit notify` reports. A check that fails, e.g. while offline,
+/// finds nothing to report.
+async fn check_for_updates(config: &Config) -> NotifyCache {
+    let lock = Lockfile::load().unwrap_or_default();
+    let token = config.sync.token.as_deref();
+    let mut outdated = Vec::new();
+    for (name, tool) in &config.tools {
+        if !tool.installed || tool.disabled {
+            continue;
+        }
+        let Some(repo) = tool.release_repo() else {
+            continue;
+        };
+        if let Ok(found) = releases::fetch_releases(repo, token).await
+            && let Some(latest) = releases::latest(&found)
+            && releases::is_newer(latest, installed_version(&lock, name, tool).as_deref())
+        {
+            outdated.push(name.clone());
+        }
+    }
+
+    NotifyCache {
+        checked_at: Some(chrono::Utc::now().to_rfc3339()),
+        outdated,
+        remote_changed: remote_changed(config).await.unwrap_or(false),
+    }
+}
+
+/// Whether the main remote's config differs from what this machine last
+/// pushed or pulled. An encrypted config is only compared when its
+/// passphrase is in the environment, as nobody is there to type it.
+async fn remote_changed(config: &Config) -> Result<bool> {
+    let (Some(remote), Some(base)) = (
+        config.sync.all_remotes().into_iter().next(),
+        merge::load_base(),
+    ) else {
+        return Ok(false);
+    };
+    let Some(content) = fetch_remote_content(&remote).await? else {
+        return Ok(false);
+    };
+    if crypto::is_encrypted(&content) && std::env::var(PASSPHRASE_ENV).is_err() {
+        return Ok(false);
+    }
+    let remote = parse_remote_config(&content)?.for_push()?;
+    Ok(serde_yaml::to_string(&remote.tools)? != serde_yaml::to_string(&base.tools)?)
+}
+
+pub async fn check_tools(timeout_secs: Option<u64>) -> Result<()> {
+    let config = Config::load()?;
+    let timeout = std::time::Duration::from_secs(
+        timeout_secs.unwrap_or_else(|| config.settings.check_timeout()),
+    );
+
+    let names: Vec<&String> = config
+        .tools
+        .iter()
+        .filter(|(_, tool)| tool.installed && !tool.disabled)
+        .map(|(name, _)| name)
+        .collect();
+
+    if names.is_empty() {
+        println!("{}", "No installed tools to check.".yellow());
+        return Ok(());
+    }
+
+    println!("{}", "Checking installed tools...".blue().bold());
+    let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+    let mut failed = 0;
+
+    // Up to `settings.jobs` tools are checked at once; results print in order
+    let jobs = Arc::new(Semaphore::new(config.settings.jobs()));
+    let checks: Vec<_> = names
+        .iter()
+        .map(|name| {
+            let tool = config.tools[*name].clone();
+            let settings = config.settings.clone();
+            let vars = template_vars(&config.variables, &tool.param_values);
+            let jobs = jobs.clone();
+            tokio::spawn(async move {
+                let _permit = jobs.acquire_owned().await?;
+                let commands = if tool.verify_commands.is_empty() {
+                    tool.run_commands.default_commands()
+                } else {
+                    &tool.verify_commands
+                };
+                if commands.is_empty() {
+                    return Err(anyhow!("no verify or run commands defined"));
+                }
+                let mut all = template_values(&vars);
+                let mut registered = BTreeMap::new();
+                for step in render_commands(commands, &vars) {
+                    let step = step.render(&registered);
+                    let shell = step.shell.or(tool.shell).or(settings.shell);
+                    let skipped = step.skip_reason(&all, |condition| {
+                        condition_holds(condition, shell, tool.target)
+                    })?;
+                    let stdout = if skipped.is_some() {
+                        String::new()
+                    } else {
+                        let checked =
+                            run_check_command(&step, &tool, &settings, timeout, &all).await;
+                        match checked {
+                            Err(_) if step.allow_failure => String::new(),
+                            checked => checked?,
+                        }
+                    };
+                    if let Some(name) = step.register {
+                        let value = stdout.trim().to_string();
+                        all.insert(name.clone(), value.clone());
+                        registered.insert(name, value);
+                    }
+                }
+                Ok(())
+            })
+        })
+        .collect();
+
+    for (name, check) in names.iter().zip(checks) {
+        match check.await? {
+            Ok(()) => println!("  {:<width$}  {}", name, "PASS".green().bold()),
+            Err(e) => {
+                failed += 1;
+                println!("  {:<width$}  {}  {}", name, "FAIL".red().bold(), e);
+            }
+        }
+    }
+
+    println!();
+    if failed > 0 {
+        return Err(anyhow!("{} of {} tool checks failed", failed, names.len()));
+    }
+    println!(
+        "{}",
+        format!("✓ All {} tools passed", names.len()).green().bold()
+    );
+    Ok(())
+}
+
+pub fn github_headers(token: &str) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        AUTHORIZATION,
+        HeaderValue::from_str(&format!("Bearer {}", token))?,
+    );
+    headers.insert(USER_AGENT, HeaderValue::from_static("tkit/0.1.0"));
+    Ok(headers)
+}
+
+pub async fn validate_github_access(repo: &str, token: &str) -> Result<()> {
+    let client = http_client()?;
+    let url = format!("https://api.github.com/repos/{}", repo);
+
+    let headers = github_headers(token)?;
+
+    let response = client.get(&url).headers(headers).send().await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to access repository '{}'. Status: {}. Check your token and repository name.",
+            repo,
+            response.status()
+        ));
+    }
+
+    Ok(())
+}
+
+pub async fn setup_github_sync(
+    repo: Option<String>,
+    token: Option<String>,
+    ssh: Option<String>,
+    key: Option<String>,
+    branch: Option<String>,
+) -> Result<()> {
+    let Some(url) = ssh else {
+        return setup_token_sync(repo.unwrap_or_default(), token, branch).await;
+    };
+    let mut config = Config::load()?;
+
+    let key = match key {
+        Some(key) => key,
+        None => {
+            let path = get_config_path()?.with_file_name("deploy_key");
+            let public = if path.exists() {
+                gitsync::public_key(&path)?
+            } else {
+                gitsync::generate_deploy_key(&path)?
+            };
+            println!("{}", "Deploy key for tkit sync:".blue().bold());
+            println!("  {}", public);
+            match gitsync::github_repo(&url) {
+                Some(repo) => println!(
+                    "  Add it at https://github.com/{}/settings/keys/new with \"Allow write access\" checked.",
+                    repo
+                ),
+                None => println!("  Add it to the repository as a deploy key with write access."),
+            }
+            path.to_string_lossy().into_owned()
+        }
+    };
+
+    config.sync.repo = None;
+    config.sync.token = None;
+    config.sync.git = Some(url.clone());
+    config.sync.ssh_key = Some(key.clone());
+    config.sync.branch = branch;
+    config.save()?;
+
+    println!(
+        "{}",
+        format!("✓ Git sync configured for repository: {}", url)
+            .green()
+            .bold()
+    );
+    let git = GitRemote {
+        url: &url,
+        ssh_key: Some(&key),
+        branch: None,
+    };
+    if let Err(e) = git.check_access() {
+        println!(
+            "{}",
+            format!(
+                "  ⚠️  The repository isn't reachable with this key yet: {}",
+                redact(&e.to_string())
+            )
+            .yellow()
+        );
+        println!("  Once the deploy key is added, run 'tkit sync push'");
+    } else {
+        println!("  Use 'tkit sync push' to upload your config");
+        println!("  Use 'tkit sync pull' to download config from the repository");
+    }
+
+    Ok(())
+}
+
+async fn setup_token_sync(
+    repo: String,
+    token: Option<String>,
+    branch: Option<String>,
+) -> Result<()> {
+    let mut config = Config::load()?;
+
+    let token = if let Some(t) = token {
+        t
+    } else {
+        let input = read_line("Enter your GitHub Personal Access Token: ")?;
+        input.trim().to_string()
+    };
+
+    validate_github_access(&repo, &token).await?;
+
+    config.sync.repo = Some(repo.clone());
+    config.sync.token = Some(token);
+    config.sync.git = None;
+    config.sync.ssh_key = None;
+    config.sync.branch = branch;
+    config.save()?;
+
+    println!(
+        "{}",
+        format!("✓ GitHub sync configured for repository: {}", repo)
+            .green()
+            .bold()
+    );
+    println!("  Use 'tkit sync push' to upload your config");
+    println!("  Use 'tkit sync pull' to download config from GitHub");
+
+    Ok(())
+}
+
+pub async fn update_github_token(token: Option<String>) -> Result<()> {
+    let mut config = Config::load()?;
+
+    // Check if sync is already configured
+    let repo = config.sync.repo.as_ref().ok_or_else(|| {
+        anyhow!("GitHub sync not configured. Run 'tkit sync setup <repo>' first.")
+    })?;
+
+    let token = if let Some(t) = token {
+        t
+    } else {
+        let input = read_line("Enter your new GitHub Personal Access Token: ")?;
+        input.trim().to_string()
+    };
+
+    // Validate the new token
+    validate_github_access(repo, &token).await?;
+
+    // Update the token
+    config.sync.token = Some(token);
+    config.save()?;
+
+    println!("{}", "✓ GitHub token updated successfully!".green().bold());
+
+    Ok(())
+}
+
+/// Contents API URL of the synced file, on `branch` when given.
+fn github_config_url(repo: &str, branch: Option<&str>) -> String {
+    github_contents_url(repo, gitsync::CONFIG_FILE, branch)
+}
+
+fn github_contents_url(repo: &str, path: &str, branch: Option<&str>) -> String {
+    let url = format!("https://api.github.com/repos/{}/contents/{}", repo, path);
+    match branch {
+        Some(branch) => format!("{}?ref={}", url, branch),
+        None => url,
+    }
+}
+
+/// Sends a GitHub API request and parses its JSON response, failing with
+/// GitHub's message when it isn't successful.
+async fn github_json<T: serde::de::DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+    token: &str,
+) -> Result<T> {
+    let response = request.headers(github_headers(token)?).send().await?;
+    if !response.status().is_success() {
+        let error_text = response.text().await?;
+        return Err(anyhow!("GitHub request failed: {}", error_text));
+    }
+    Ok(response.json().await?)
+}
+
+/// The synced config of a GitHub repository, put back together from its
+/// tool files when it was pushed in the split layout.
+async fn fetch_github_config(
+    repo: &str,
+    branch: Option<&str>,
+    token: &str,
+) -> Result<Option<String>> {
+    let Some((index, _)) = fetch_github_file(&github_config_url(repo, branch), token).await? else {
+        return Ok(None);
+    };
+    if !layout::is_split(&index.content) {
+        return Ok(Some(index.content));
+    }
+
+    let mut tools = BTreeMap::new();
+    for name in github_tool_names(repo, branch, token).await? {
+        let url = github_contents_url(repo, &layout::tool_path(&name), branch);
+        if let Some((file, _)) = fetch_github_file(&url, token).await? {
+            tools.insert(name, file.content);
+        }
+    }
+    Ok(Some(layout::join(&index.content, &tools)?))
+}
+
+/// Tools with a file in a split layout's tools directory.
+async fn github_tool_names(repo: &str, branch: Option<&str>, token: &str) -> Result<Vec<String>> {
+    let url = github_contents_url(repo, layout::TOOLS_DIR, branch);
+    let response = http_client()?
+        .get(&url)
+        .headers(github_headers(token)?)
+        .send()
+        .await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(Vec::new());
+    }
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to list the tool files in '{}'. Status: {}",
+            repo,
+            response.status()
+        ));
+    }
+    let entries: Vec<GitHubTreeEntry> = response.json().await?;
+    Ok(entries
+        .iter()
+        .filter_map(|entry| layout::tool_name(&entry.path).map(str::to_string))
+        .collect())
+}
+
+/// Commits `files` (contents by repository path) to a GitHub repository in
+/// a single commit through the git data API, deleting tool files that are
+/// no longer among them. Nothing is committed when no file changed.
+async fn push_tree_to_github(
+    repo: &str,
+    branch: Option<&str>,
+    token: &str,
+    files: &BTreeMap<String, String>,
+    message: &str,
+) -> Result<()> {
+    let client = http_client()?;
+    let api = format!("https://api.github.com/repos/{}", repo);
+    let branch = match branch {
+        Some(branch) => {
+            ensure_github_branch(repo, token, branch).await?;
+            branch.to_string()
+        }
+        None => {
+            github_json::<GitHubRepo>(client.get(&api), token)
+                .await?
+                .default_branch
+        }
+    };
+
+    let head: GitHubRef = github_json(
+        client.get(format!("{}/git/ref/heads/{}", api, branch)),
+        token,
+    )
+    .await
+    .map_err(|_| anyhow!("Branch '{}' of '{}' has no commits yet", branch, repo))?;
+    let commit: GitHubCommit = github_json(
+        client.get(format!("{}/git/commits/{}", api, head.object.sha)),
+        token,
+    )
+    .await?;
+    let existing: GitHubTree = github_json(
+        client.get(format!("{}/git/trees/{}?recursive=1", api, commit.tree.sha)),
+        token,
+    )
+    .await?;
+
+    let mut changes: Vec<TreeChange> = files
+        .iter()
+        .map(|(path, content)| TreeChange {
+            path: path.clone(),
+            mode: "100644",
+            kind: "blob",
+            blob: TreeBlob::Content(content.clone()),
+        })
+        .collect();
+    for entry in &existing.tree {
+        if layout::tool_name(&entry.path).is_some() && !files.contains_key(&entry.path) {
+            changes.push(TreeChange {
+                path: entry.path.clone(),
+                mode: "100644",
+                kind: "blob",
+                blob: TreeBlob::Sha(None),
+            });
+        }
+    }
+
+    let tree: GitHubObject = github_json(
+        client
+            .post(format!("{}/git/trees", api))
+            .json(&CreateTreeRequest {
+                base_tree: commit.tree.sha.clone(),
+                tree: changes,
+            }),
+        token,
+    )
+    .await?;
+    if tree.sha == commit.tree.sha {
+        return Ok(());
+    }
+    let created: GitHubObject = github_json(
+        client
+            .post(format!("{}/git/commits", api))
+            .json(&CreateCommitRequest {
+                message: message.to_string(),
+                tree: tree.sha,
+                parents: vec![head.object.sha],
+            }),
+        token,
+    )
+    .await?;
+    let _: serde::de::IgnoredAny = github_json(
+        client
+            .patch(format!("{}/git/refs/heads/{}", api, branch))
+            .json(&UpdateRefRequest { sha: created.sha }),
+        token,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Creates `branch` in a GitHub repository, pointing at the head of its
+/// default branch, unless it exists already.
+async fn ensure_github_branch(repo: &str, token: &str, branch: &str) -> Result<()> {
+    let client = http_client()?;
+    let refs_url = format!("https://api.github.com/repos/{}/git/ref/heads/", repo);
+    let response = client
+        .get(format!("{}{}", refs_url, branch))
+        .headers(github_headers(token)?)
+        .send()
+        .await?;
+    if response.status().is_success() {
+        return Ok(());
+    }
+
+    let repository: GitHubRepo = client
+        .get(format!("https://api.github.com/repos/{}", repo))
+        .headers(github_headers(token)?)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let head: GitHubRef = client
+        .get(format!("{}{}", refs_url, repository.default_branch))
+        .headers(github_headers(token)?)
+        .send()
+        .await?
+        .error_for_status()
+        .map_err(|_| {
+            anyhow!(
+                "Could not create branch '{}': '{}' has no commits yet",
+                branch,
+                repo
+            )
+        })?
+        .json()
+        .await?;
+
+    let response = client
+        .post(format!("https://api.github.com/repos/{}/git/refs", repo))
+        .headers(github_headers(token)?)
+        .json(&CreateRefRequest {
+            reference: format!("refs/heads/{}", branch),
+            sha: head.object.sha,
+        })
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        let error_text = response.text().await?;
+        return Err(anyhow!(
+            "Failed to create branch '{}': {}",
+            branch,
+            error_text
+        ));
+    }
+    println!(
+        "{}",
+        format!("  Created branch '{}' in {}", branch, repo).dimmed()
+    );
+    Ok(())
+}
+
+/// Gets a repository file through the contents API, sending the cached ETag
+/// so an unchanged file costs a 304 (which GitHub doesn't count against the
+/// rate limit) instead of a download. Returns the file and whether it came
+/// from the cache, or `None` when it doesn't exist.
+async fn fetch_github_file(url: &str, token: &str) -> Result<Option<(CachedFile, bool)>> {
+    let mut cache = GitHubCache::load()?;
+    let mut headers = github_headers(token)?;
+    if let Some(etag) = cache.get(url).and_then(|file| file.etag.as_deref()) {
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_str(etag)?);
+    }
+
+    let response = http_client()?.get(url).headers(headers).send().await?;
+    if response.status() == StatusCode::NOT_MODIFIED
+        && let Some(file) = cache.get(url)
+    {
+        return Ok(Some((file.clone(), true)));
+    }
+    if response.status() == StatusCode::NOT_FOUND {
+        cache.remove(url);
+        cache.save()?;
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to fetch config from GitHub. Make sure the file exists and you have access."
+        ));
+    }
+
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let file: GitHubFile = response.json().await?;
+    let content = file
+        .content
+        .ok_or_else(|| anyhow!("No content in GitHub file"))?;
+    let decoded_content = general_purpose::STANDARD.decode(content.replace('\n', ""))?;
+    let file = CachedFile {
+        etag,
+        sha: file.sha,
+        content: String::from_utf8(decoded_content)?,
+    };
+    cache.insert(url, file.clone());
+    cache.save()?;
+    Ok(Some((file, false)))
+}
+
+/// Writes `content` to `tkit-config.yaml` in a GitHub repository, on
+/// `branch` (created if missing) when given.
+async fn push_to_github(
+    repo: &str,
+    branch: Option<&str>,
+    token: &str,
+    content: &str,
+    message: &str,
+) -> Result<()> {
+    let encoded_content = general_purpose::STANDARD.encode(content);
+
+    let client = http_client()?;
+    let url = github_config_url(repo, branch);
+
+    let headers = github_headers(token)?;
+
+    // The SHA of the existing file, if any, is needed to overwrite it
+    let sha = match fetch_github_file(&url, token).await {
+        Ok(file) => file.map(|(file, _)| file.sha),
+        Err(_) => None,
+    };
+    // Without the file, the branch may be missing too
+    if let Some(branch) = branch
+        && sha.is_none()
+    {
+        ensure_github_branch(repo, token, branch).await?;
+    }
+
+    let payload = GitHubCreateFile {
+        message: message.to_string(),
+        content: encoded_content,
+        sha,
+        branch: branch.map(str::to_string),
+    };
+
+    let response = client
+        .put(github_config_url(repo, None))
+        .headers(headers)
+        .json(&payload)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let error_text = response.text().await?;
+        return Err(anyhow!("Failed to push to GitHub: {}", error_text));
+    }
+
+    // Remember what was pushed so the next push doesn't download it again
+    let pushed: GitHubPutFile = response.json().await?;
+    let mut cache = GitHubCache::load()?;
+    cache.insert(
+        &url,
+        CachedFile {
+            etag: None,
+            sha: pushed.content.sha,
+            content: content.to_string(),
+        },
+    );
+    cache.save()?;
+    Ok(())
+}
+
+/// The remote as a plain git repository, when it is one.
+fn git_remote(remote: &SyncRemote) -> Option<GitRemote<'_>> {
+    remote.git.as_deref().map(|url| GitRemote {
+        url,
+        ssh_key: remote.ssh_key.as_deref(),
+        branch: remote.branch.as_deref(),
+    })
+}
+
+/// Pushes the config to one remote: `content` as a single file, or for
+/// repositories `files` instead when the split layout is in use. Local
+/// files always get `content`.
+async fn push_to_remote(
+    remote: &SyncRemote,
+    content: &str,
+    files: Option<&BTreeMap<String, String>>,
+    message: &str,
+) -> Result<()> {
+    if let Some(git) = git_remote(remote) {
+        let single = BTreeMap::from([(gitsync::CONFIG_FILE.to_string(), content.to_string())]);
+        git.push(files.unwrap_or(&single), message)?;
+        return Ok(());
+    }
+    match (&remote.repo, &remote.path) {
+        (Some(repo), _) => {
+            let token = remote.token.as_ref().ok_or_else(|| {
+                anyhow!("GitHub token not found. Run 'tkit sync setup <repo>' first.")
+            })?;
+            let branch = remote.branch.as_deref();
+            match files {
+                Some(files) => push_tree_to_github(repo, branch, token, files, message).await,
+                None => push_to_github(repo, branch, token, content, message).await,
+            }
+        }
+        (None, Some(path)) => {
+            if let Some(parent) = std::path::Path::new(path).parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)?;
+            Ok(())
+        }
+        (None, None) => Err(anyhow!(
+            "Remote '{}' has neither a repo nor a path",
+            remote.name
+        )),
+    }
+}
+
+/// The config as last pushed to a remote, without decrypting it, or `None`
+/// when nothing has been pushed there yet.
+async fn fetch_remote_content(remote: &SyncRemote) -> Result<Option<String>> {
+    if let Some(git) = git_remote(remote) {
+        return git.fetch();
+    }
+    match (&remote.repo, &remote.path, &remote.token) {
+        (Some(repo), _, Some(token)) => {
+            fetch_github_config(repo, remote.branch.as_deref(), token).await
+        }
+        (Some(_), _, None) => Err(anyhow!("GitHub token not found")),
+        (None, Some(path), _) if !std::path::Path::new(path).exists() => Ok(None),
+        (None, Some(path), _) => Ok(Some(fs::read_to_string(path)?)),
+        (None, None, _) => Ok(None),
+    }
+}
+
+async fn fetch_from_remote(remote: &SyncRemote) -> Result<Config> {
+    if let Some(git) = git_remote(remote) {
+        let content = git
+            .fetch()?
+            .ok_or_else(|| anyhow!("No config has been pushed to {} yet.", git.url))?;
+        return parse_remote_config(&content);
+    }
+    match (&remote.repo, &remote.path) {
+        (Some(repo), _) => {
+            let token = remote.token.as_ref().ok_or_else(|| {
+                anyhow!("GitHub token not found. Run 'tkit sync setup <repo>' first.")
+            })?;
+            fetch_remote_config(repo, remote.branch.as_deref(), token).await
+        }
+        (None, Some(path)) => {
+            let content = fs::read_to_string(path).map_err(|e| {
+                anyhow!("Could not read remote '{}' ({}): {}", remote.name, path, e)
+            })?;
+            parse_remote_config(&content)
+        }
+        (None, None) => Err(anyhow!(
+            "Remote '{}' has neither a repo nor a path",
+            remote.name
+        )),
+    }
+}
+
+/// Pushes the config to every remote whose copy may be out of date (every
+/// remote with `force`) and records the sync time if any succeeded. Returns
+/// each remote's name with whether it was pushed or already up to date.
+async fn push_to_remotes(
+    mut config: Config,
+    message: &str,
+    force: bool,
+) -> Result<Vec<(String, Result<bool>)>> {
+    let remotes = config.sync.all_remotes();
+    if remotes.is_empty() {
+        return Err(anyhow!(
+            "GitHub sync not configured. Run 'tkit sync setup <repo>' first."
+        ));
+    }
+
+    let fingerprint = config.push_fingerprint()?;
+    let mut state = State::load()?;
+    let (pending, current): (Vec<_>, Vec<_>) = remotes
+        .iter()
+        .partition(|remote| force || state.pushed.get(&remote.name) != Some(&fingerprint));
+    let mut results: Vec<(String, Result<bool>)> = current
+        .iter()
+        .map(|remote| (remote.name.clone(), Ok(false)))
+        .collect();
+    if pending.is_empty() {
+        return Ok(results);
+    }
+
+    if config.sync.encrypt && !config.sync.layout.is_single() {
+        return Err(anyhow!(
+            "The split sync layout can't be encrypted. Run 'tkit config set sync.layout single' or 'tkit sync encrypt --off'."
+        ));
+    }
+    let passphrase = match config.sync.encrypt {
+        true => Some(sync_passphrase()?),
+        false => None,
+    };
+    // Keep the reports of machines that pushed since this one last pulled
+    if let Ok(Some(content)) = fetch_remote_content(&remotes[0]).await {
+        let machines = remote_machines(&content, passphrase.as_deref());
+        fleet::merge_reports(&mut config.machines, machines);
+    }
+    let now = chrono::Utc::now().to_rfc3339();
+    let report = MachineReport::describe(&config, &now, converge::observe);
+    config.machines.insert(machine::name(), report);
+
+    let shared_config = config.for_push()?;
+    let shared = serde_yaml::to_string(&shared_config)?;
+    let mut content = shared.clone();
+    if let Some(passphrase) = &passphrase {
+        content = crypto::encrypt(&content, passphrase)?;
+    }
+    let files = match config.sync.layout {
+        SyncLayout::Single => None,
+        SyncLayout::Split => Some(layout::split(&shared_config)?),
+    };
+    for remote in pending {
+        let result = push_to_remote(remote, &content, files.as_ref(), message).await;
+        if result.is_ok() {
+            state
+                .pushed
+                .insert(remote.name.clone(), fingerprint.clone());
+        }
+        results.push((remote.name.clone(), result.map(|()| true)));
+    }
+
+    if results.iter().any(|(_, result)| matches!(result, Ok(true))) {
+        // Update last sync time
+        let mut updated_config = config;
+        updated_config.sync.last_sync = Some(now);
+        updated_config.save()?;
+        merge::save_base(&shared)?;
+        state.save()?;
+    }
+    let ok = results.iter().all(|(_, result)| result.is_ok());
+    record_history("sync push", None, ok, None);
+    Ok(results)
+}
+
+/// The machine reports in a pushed config; none when it can't be read.
+fn remote_machines(content: &str, passphrase: Option<&str>) -> BTreeMap<String, MachineReport> {
+    let content = match (crypto::is_encrypted(content), passphrase) {
+        (false, _) => content.to_string(),
+        (true, Some(passphrase)) => match crypto::decrypt(content, passphrase) {
+            Ok(content) => content,
+            Err(_) => return BTreeMap::new(),
+        },
+        (true, None) => return BTreeMap::new(),
+    };
+    serde_yaml::from_str::<serde_yaml::Value>(&content)
+        .ok()
+        .and_then(|value| serde_yaml::from_value(value["machines"].clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Lists every machine that has pushed the config, as the main remote
+/// currently has them.
+async fn show_machines(config: &Config) -> Result<()> {
+    let remotes = config.sync.all_remotes();
+    let remote = remotes.first().ok_or_else(|| {
+        anyhow!("GitHub sync not configured. Run 'tkit sync setup <repo>' first.")
+    })?;
+    let machines = match fetch_remote_content(remote).await {
+        Ok(Some(content)) => parse_remote_config(&content)?.machines,
+        Ok(None) => BTreeMap::new(),
+        Err(e) => {
+            println!(
+                "{}",
+                format!(
+                    "  ⚠️  Could not fetch {}, showing reports from the last pull: {}",
+                    remote.name,
+                    redact(&e.to_string())
+                )
+                .yellow()
+            );
+            config.machines.clone()
+        }
+    };
+
+    println!("{}", "Machines:".blue().bold());
+    if machines.is_empty() {
+        println!("  No machine has pushed the config yet.");
+        return Ok(());
+    }
+
+    let this = machine::name();
+    let width = machines.keys().map(String::len).max().unwrap_or(0);
+    println!(
+        "  {:<width$}  {:<14}  {:<16}  {:>9}  {:>7}  {:>5}",
+        "NAME",
+        "PLATFORM",
+        "LAST SYNC",
+        "INSTALLED",
+        "MISSING",
+        "DRIFT",
+        width = width
+    );
+    for (name, report) in &machines {
+        let drift = format!("{:>5}", report.drift);
+        println!(
+            "  {:<width$}  {:<14}  {:<16}  {:>9}  {:>7}  {}{}",
+            name.bold(),
+            format!("{}/{}", report.os, report.arch),
+            format_timestamp(Some(&report.last_sync)),
+            report.installed,
+            report.missing,
+            if report.drift > 0 {
+                drift.yellow()
+            } else {
+                drift.normal()
+            },
+            if *name == this {
+                " (this machine)".dimmed()
+            } else {
+                "".normal()
+            },
+            width = width
+        );
+    }
+    Ok(())
+}
+
+/// Message of a sync commit: `sync.commit_message_template` filled in, or
+/// "<fallback> tkit config from <machine> - <time>" without one.
+fn sync_commit_message(config: &Config, action: &str, tool: &str, fallback: &str) -> String {
+    let machine = machine::name();
+    let timestamp = chrono::Utc::now()
+        .format("%Y-%m-%d %H:%M:%S UTC")
+        .to_string();
+    config
+        .sync
+        .commit_message(action, tool, &machine, &timestamp)
+        .unwrap_or_else(|| format!("{} tkit config from {} - {}", fallback, machine, timestamp))
+}
+
+/// Turns per-remote push outcomes into a single error naming the failures.
+fn push_failures(results: Vec<(String, Result<bool>)>) -> Result<()> {
+    let failed: Vec<String> = results
+        .into_iter()
+        .filter_map(|(name, result)| result.err().map(|e| format!("{}: {}", name, e)))
+        .collect();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(ExitKind::Sync.error(format!("Push failed for {}", failed.join("; "))))
+    }
+}
+
+/// Marks an error from reaching a sync remote as a sync failure, unless it
+/// already has a more specific exit code.
+fn sync_failure(err: anyhow::Error) -> anyhow::Error {
+    match ExitKind::of(&err) {
+        Some(_) => err,
+        None => ExitKind::Sync.error(err),
+    }
+}
+
+pub async fn push_config_to_github(force: bool) -> Result<()> {
+    let config = Config::load()?;
+    let changed = config.changed_since_last_sync();
+
+    let queue = SyncQueue::load()?;
+    let message = queue.commit_message(&sync_commit_message(
+        &config,
+        "push",
+        &changed.join(", "),
+        "Update",
+    ));
+    let results = push_to_remotes(config, &message, force).await?;
+    if results.iter().all(|(_, result)| result.is_ok()) {
+        SyncQueue::default().save()?;
+    }
+
+    if results.len() > 1 {
+        for (name, result) in &results {
+            match result {
+                Ok(true) => println!("  {} {}", "✓".green(), name),
+                Ok(false) => println!("  {} {} {}", "✓".green(), name, "(up to date)".dimmed()),
+                Err(e) => println!("  {} {}: {}", "✗".red(), name, e),
+            }
+        }
+    }
+
+    if results
+        .iter()
+        .all(|(_, result)| matches!(result, Ok(false)))
+    {
+        println!(
+            "{}",
+            "✓ Already up to date; nothing pushed. Use --force to push anyway.".green()
+        );
+    } else if results.iter().all(|(_, result)| result.is_ok()) {
+        println!("{}", "✓ Configuration pushed successfully!".green().bold());
+        if !changed.is_empty() {
+            println!("  Changed since last sync: {}", changed.join(", "));
+        }
+    }
+
+    push_failures(results)
+}
+
+pub fn show_machine() -> Result<()> {
+    let machine = Machine::current()?;
+    println!("{}", "This machine:".blue().bold());
+    println!("  Name: {}", machine::name().green());
+    println!("  Platform: {}/{}", machine.os, machine.arch);
+    println!(
+        "  First seen: {}",
+        format_timestamp(Some(&machine.first_seen))
+    );
+    Ok(())
+}
+
+/// Renames this machine. Its report in the fleet view moves to the new name,
+/// so other machines see the rename on the next push.
+pub fn set_machine_name(name: &str) -> Result<()> {
+    machine::check_name(name)?;
+    let mut machine = Machine::current()?;
+    let old = std::mem::replace(&mut machine.name, name.to_string());
+    machine.save_to_path(&machine::get_machine_path()?)?;
 
+    let mut config = Config::load()?;
+    if let Some(report) = config.machines.remove(&old) {
+        config.machines.insert(name.to_string(), report);
+        config.save()?;
+    }
+
+    println!(
+        "{}",
+        format!("✓ This machine is now called '{}'", name)
+            .green()
+            .bold()
+    );
     Ok(())
 }
 
-pub async fn update_tool(tool_name: &str) -> Result<()> {
+pub async fn show_sync_status(machines: bool) -> Result<()> {
     let config = Config::load()?;
+    if machines {
+        return show_machines(&config).await;
+    }
 
-    let tool = config
-        .tools
-        .get(tool_name)
-        .ok_or_else(|| anyhow!("Tool '{}' not found.", tool_name))?;
+    println!("{}", "GitHub Sync Status:".blue().bold());
 
-    if !tool.installed {
+    let remotes = config.sync.all_remotes();
+    if remotes.is_empty() {
+        println!("  Status: {}", "Not configured".yellow());
+        println!("  Run 'tkit sync setup <username/repo>' to get started");
+        return Ok(());
+    }
+
+    if let Some(repo) = &config.sync.repo {
+        println!("  Repository: {}", repo.green());
+        println!(
+            "  Token: {}",
+            if config.sync.token.is_some() {
+                "✓ Configured".green()
+            } else {
+                "✗ Not set".red()
+            }
+        );
+        if let Some(token) = &config.sync.token {
+            let url = github_config_url(repo, config.sync.branch.as_deref());
+            match fetch_github_file(&url, token).await {
+                Ok(Some((file, cached))) => println!(
+                    "  Remote config: {} {}",
+                    file.sha.chars().take(7).collect::<String>().green(),
+                    if cached {
+                        "(unchanged since last fetch)".dimmed()
+                    } else {
+                        "(fetched)".dimmed()
+                    }
+                ),
+                Ok(None) => println!("  Remote config: {}", "Not pushed yet".yellow()),
+                Err(e) => println!("  Remote config: {} {}", "✗".red(), redact(&e.to_string())),
+            }
+        }
+    } else if let Some(url) = &config.sync.git {
+        println!(
+            "  Repository: {} {}",
+            url.green(),
+            "(git over SSH)".dimmed()
+        );
+        println!(
+            "  Deploy key: {}",
+            config.sync.ssh_key.as_deref().unwrap_or("ssh defaults")
+        );
+    }
+    if let Some(branch) = &config.sync.branch {
+        println!("  Branch: {}", branch.green());
+    }
+    for remote in &config.sync.remotes {
+        let target = remote
+            .repo
+            .as_ref()
+            .or(remote.git.as_ref())
+            .or(remote.path.as_ref());
+        println!(
+            "  Remote {}: {}",
+            remote.name.bold(),
+            target.map(String::as_str).unwrap_or("-").green()
+        );
+    }
+
+    if let Some(last_sync) = &config.sync.last_sync {
+        println!("  Last sync: {}", last_sync);
+        let changed = config.changed_since_last_sync();
+        if !changed.is_empty() {
+            println!("  Changed since last sync: {}", changed.join(", ").yellow());
+        }
+    } else {
+        println!("  Last sync: {}", "Never".yellow());
+    }
+    let queue = SyncQueue::load()?;
+    if !queue.pushes.is_empty() {
+        println!(
+            "  Queued while offline: {} (run 'tkit sync flush')",
+            format!("{} change(s)", queue.pushes.len()).yellow()
+        );
+    }
+
+    println!(
+        "  Auto-sync: {}",
+        if config.sync.auto_sync {
+            "✓ Enabled".green()
+        } else {
+            "✗ Disabled".red()
+        }
+    );
+    println!(
+        "  Encryption: {}",
+        if config.sync.encrypt {
+            "✓ Enabled".green()
+        } else {
+            "✗ Disabled".red()
+        }
+    );
+
+    Ok(())
+}
+
+/// Downloads and parses `tkit-config.yaml` from a sync repository.
+async fn fetch_remote_config(repo: &str, branch: Option<&str>, token: &str) -> Result<Config> {
+    let content = fetch_github_config(repo, branch, token)
+        .await?
+        .ok_or_else(|| {
+            anyhow!(
+                "Failed to fetch config from GitHub. Make sure the file exists and you have access."
+            )
+        })?;
+
+    parse_remote_config(&content)
+}
+
+/// The passphrase for encrypted sync, from `TKIT_SYNC_PASSPHRASE` or a prompt.
+fn sync_passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV)
+        && !passphrase.is_empty()
+    {
+        register_secret(&passphrase);
+        return Ok(passphrase);
+    }
+    let passphrase = read_secret("Sync passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(anyhow!("A passphrase is required for encrypted sync"));
+    }
+    register_secret(&passphrase);
+    Ok(passphrase)
+}
+
+/// Parses a config downloaded from a remote, decrypting it first if needed.
+fn parse_remote_config(content: &str) -> Result<Config> {
+    let mut config: Config = if crypto::is_encrypted(content) {
+        let content = crypto::decrypt(content, &sync_passphrase()?)?;
+        serde_yaml::from_str(&content)?
+    } else {
+        serde_yaml::from_str(content)?
+    };
+    config.migrate_installed_flags();
+    Ok(config)
+}
+
+pub fn set_sync_encryption(enabled: bool) -> Result<()> {
+    let mut config = Config::load()?;
+    config.sync.encrypt = enabled;
+    config.save()?;
+
+    if enabled {
         println!(
             "{}",
-            format!("Tool '{}' is not installed. Install it first.", tool_name).yellow()
+            "✓ Config will be encrypted before it is pushed"
+                .green()
+                .bold()
         );
-        return Ok(());
+        println!(
+            "  You will be asked for a passphrase on push and pull (or set {})",
+            PASSPHRASE_ENV
+        );
+        println!("  Run 'tkit sync push' to replace the plain copy on your remotes");
+    } else {
+        println!("{}", "✓ Config will be pushed unencrypted".green().bold());
     }
+    Ok(())
+}
 
-    execute_commands(&tool.update_commands, tool_name, "update").await?;
+/// Copies the current config next to itself before it is replaced.
+fn backup_config() -> Result<()> {
+    let backup_path = get_config_path()?.with_extension("yaml.backup");
+    if let Ok(current_content) = fs::read_to_string(get_config_path()?) {
+        fs::write(&backup_path, current_content)?;
+        println!(
+            "{}",
+            format!("✓ Current config backed up to: {}", backup_path.display()).yellow()
+        );
+    }
     Ok(())
 }
 
-pub fn list_tools() -> Result<()> {
+pub async fn pull_config_from_github(
+    remote: Option<String>,
+    strategy: PullStrategy,
+    tools: Vec<String>,
+) -> Result<()> {
     let config = Config::load()?;
+    let remote = select_remote(&config, remote.as_deref())?;
 
-    if config.tools.is_empty() {
+    if !tools.is_empty() {
+        return pull_tools(config, &remote, &tools, strategy).await;
+    }
+
+    let remote_config = fetch_from_remote(&remote).await.map_err(sync_failure)?;
+    let remote_shared = serde_yaml::to_string(&remote_config.for_push()?)?;
+    backup_config()?;
+
+    let local = config.without_overrides()?;
+    let base = merge::load_base();
+    let tools = merge_tools(
+        base.as_ref().map(|base| &base.tools),
+        &local.tools,
+        &remote_config.tools,
+        config.sync.last_sync.as_deref(),
+        strategy,
+    )?;
+
+    // Merge configurations (preserve local sync settings)
+    let mut merged_config = remote_config;
+    merged_config.tools = tools;
+    // What is installed here is this machine's business, whatever the remote says
+    merged_config.adopt_observed_state(&config);
+    let private = merged_config.restore_private_tools(&config.without_overrides()?);
+    merged_config.sync = config.sync; // Keep local sync settings
+    merged_config.sync.last_sync = Some(chrono::Utc::now().to_rfc3339());
+
+    merged_config.save_journaled(&format!("sync pull {}", remote.name))?;
+    // What the remote had is the common ancestor for the next pull
+    merge::save_base(&remote_shared)?;
+    record_history("sync pull", None, true, None);
+
+    println!(
+        "{}",
+        format!("✓ Configuration pulled from {} successfully!", remote.name)
+            .green()
+            .bold()
+    );
+    println!("  {} tools loaded", merged_config.tools.len());
+    if !private.is_empty() {
+        println!("  Kept private tools: {}", private.join(", "));
+    }
+
+    Ok(())
+}
+
+/// The remote called `name`, or the main one when no name is given.
+fn select_remote(config: &Config, name: Option<&str>) -> Result<SyncRemote> {
+    let remotes = config.sync.all_remotes();
+    match name {
+        Some(name) => config.sync.remote(name).ok_or_else(|| {
+            let names: Vec<&str> = remotes.iter().map(|r| r.name.as_str()).collect();
+            anyhow!(
+                "Unknown remote '{}'. Configured remotes: {}",
+                name,
+                names.join(", ")
+            )
+        }),
+        None => remotes.into_iter().next().ok_or_else(|| {
+            anyhow!("GitHub sync not configured. Run 'tkit sync setup <repo>' first.")
+        }),
+    }
+}
+
+/// Downloads a remote's config and checks it: that it parses with this
+/// version of tkit, which fields this version would ignore, and that no
+/// token was pushed with it.
+pub async fn verify_sync(remote: Option<String>) -> Result<()> {
+    let config = Config::load()?;
+    let remote = select_remote(&config, remote.as_deref())?;
+    println!(
+        "{}",
+        format!("Verifying the config on {}...", remote.name)
+            .blue()
+            .bold()
+    );
+
+    let pushed = fetch_remote_content(&remote)
+        .await?
+        .ok_or_else(|| anyhow!("Nothing has been pushed to {} yet.", remote.name))?;
+    let encrypted = crypto::is_encrypted(&pushed);
+    let content = match encrypted {
+        true => crypto::decrypt(&pushed, &sync_passphrase()?)?,
+        false => pushed.clone(),
+    };
+
+    let remote_config: Config = serde_yaml::from_str(&content).map_err(|e| {
+        anyhow!(
+            "The config on {} doesn't parse with this version of tkit: {}",
+            remote.name,
+            e
+        )
+    })?;
+    println!("  {} Parses with this version of tkit", "✓".green());
+    if encrypted {
+        println!("  {} Encrypted", "✓".green());
+    }
+    let tools = &remote_config.tools;
+    println!(
+        "  Tools: {} ({} disabled)",
+        tools.len(),
+        tools.values().filter(|tool| tool.disabled).count()
+    );
+    if !remote_config.machines.is_empty() {
+        println!("  Machines: {}", remote_config.machines.len());
+    }
+
+    let unknown = schema::unknown_fields::<Config>(&content)?;
+    if !unknown.is_empty() {
         println!(
             "{}",
-            "No tools configured. Use 'tkit add <tool>' to add some!".yellow()
+            format!(
+                "  ⚠️  Fields this version of tkit doesn't understand (a newer tkit pushed them, or they are misspelled): {}",
+                unknown.join(", ")
+            )
+            .yellow()
         );
-        return Ok(());
     }
 
-    println!("{}", "Available tools:".blue().bold());
-    for (name, tool) in &config.tools {
-        let status = if tool.installed {
-            "✓".green()
-        } else {
-            "✗".red()
+    let mut findings = audit::scan_synced_copy(&remote.name, &pushed, &config);
+    findings.extend(
+        audit::scan_text(&format!("remote {}", remote.name), &content)
+            .into_iter()
+            .filter(|finding| finding.severity == Severity::High),
+    );
+    for finding in &findings {
+        println!(
+            "{}",
+            format!("  ⚠️  {}: {}", finding.location, finding.message)
+                .red()
+                .bold()
+        );
+    }
+    if !findings.is_empty() {
+        return Err(anyhow!(
+            "The config on {} contains credentials; remove them and push again",
+            remote.name
+        ));
+    }
+
+    println!(
+        "{}",
+        format!("✓ The config on {} is valid", remote.name)
+            .green()
+            .bold()
+    );
+    Ok(())
+}
+
+/// Merges pulled tools into the local ones with `strategy`. Tools changed
+/// on only one side since the last sync keep that change; ones changed on
+/// both are settled by the strategy, or one by one.
+fn merge_tools(
+    base: Option<&BTreeMap<String, ToolConfig>>,
+    local: &BTreeMap<String, ToolConfig>,
+    remote: &BTreeMap<String, ToolConfig>,
+    last_sync: Option<&str>,
+    strategy: PullStrategy,
+) -> Result<BTreeMap<String, ToolConfig>> {
+    if strategy == PullStrategy::AppendNew {
+        return Ok(merge::append_new(local, remote));
+    }
+
+    let merge = merge::three_way(base, local, remote, last_sync);
+    let mut tools = merge.tools;
+    for conflict in &merge.conflicts {
+        let chosen = match strategy {
+            PullStrategy::Ours => conflict.local.clone(),
+            PullStrategy::Theirs => conflict.remote.clone(),
+            _ => resolve_conflict(conflict)?,
+        };
+        if let Some(tool) = chosen {
+            tools.insert(conflict.name.clone(), tool);
+        }
+    }
+    Ok(tools)
+}
+
+/// Pulls only the named tools, leaving the rest of the local config as it is.
+async fn pull_tools(
+    config: Config,
+    remote: &SyncRemote,
+    names: &[String],
+    strategy: PullStrategy,
+) -> Result<()> {
+    let pick = |tools: &BTreeMap<String, ToolConfig>| -> BTreeMap<String, ToolConfig> {
+        tools
+            .iter()
+            .filter(|(name, _)| names.contains(name))
+            .map(|(name, tool)| (name.clone(), tool.clone()))
+            .collect()
+    };
+    let remote_tools = fetch_remote_tools(remote, names).await?;
+    for name in names
+        .iter()
+        .filter(|name| !remote_tools.contains_key(*name))
+    {
+        println!(
+            "{}",
+            format!("  ⚠️  '{}' is not on {}", name, remote.name).yellow()
+        );
+    }
+    backup_config()?;
+
+    let mut merged_config = config.without_overrides()?;
+    let mut base = merge::load_base();
+    let tools = merge_tools(
+        base.as_ref().map(|base| pick(&base.tools)).as_ref(),
+        &pick(&merged_config.tools),
+        &remote_tools,
+        config.sync.last_sync.as_deref(),
+        strategy,
+    )?;
+    for name in names {
+        if merged_config
+            .tools
+            .get(name)
+            .is_some_and(|tool| tool.private)
+        {
+            continue;
+        }
+        match tools.get(name) {
+            Some(tool) => merged_config.tools.insert(name.clone(), tool.clone()),
+            None => merged_config.tools.remove(name),
         };
-        let desc = tool.description.as_deref().unwrap_or("No description");
-        println!("  {} {} - {}", status, name.bold(), desc);
+        // The remote's version is the common ancestor for the next pull
+        if let Some(base) = &mut base {
+            match remote_tools.get(name) {
+                Some(tool) => base.tools.insert(name.clone(), tool.clone()),
+                None => base.tools.remove(name),
+            };
+        }
     }
+    merged_config.adopt_observed_state(&config);
+
+    merged_config.save_journaled(&format!(
+        "sync pull {} --tool {}",
+        remote.name,
+        names.join(", ")
+    ))?;
+    if let Some(base) = &base {
+        merge::save_base(&serde_yaml::to_string(base)?)?;
+    }
+    record_history("sync pull", None, true, None);
+
+    println!(
+        "{}",
+        format!("✓ Pulled {} from {}", names.join(", "), remote.name)
+            .green()
+            .bold()
+    );
     Ok(())
 }
 
-pub async fn validate_github_access(repo: &str, token: &str) -> Result<()> {
-    let client = reqwest::Client::new();
-    let url = format!("https://api.github.com/repos/{}", repo);
+/// The named tools as a remote has them. From a GitHub repository in the
+/// split layout only their files are downloaded.
+async fn fetch_remote_tools(
+    remote: &SyncRemote,
+    names: &[String],
+) -> Result<BTreeMap<String, ToolConfig>> {
+    if let (None, Some(repo), Some(token)) = (&remote.git, &remote.repo, &remote.token) {
+        let branch = remote.branch.as_deref();
+        let index = fetch_github_file(&github_config_url(repo, branch), token).await?;
+        if index.is_some_and(|(index, _)| layout::is_split(&index.content)) {
+            let mut tools = BTreeMap::new();
+            for name in names {
+                let url = github_contents_url(repo, &layout::tool_path(name), branch);
+                if let Some((file, _)) = fetch_github_file(&url, token).await? {
+                    tools.insert(name.clone(), serde_yaml::from_str(&file.content)?);
+                }
+            }
+            return Ok(tools);
+        }
+    }
 
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
+    let mut tools = fetch_from_remote(remote).await.map_err(sync_failure)?.tools;
+    tools.retain(|name, _| names.contains(name));
+    Ok(tools)
+}
+
+/// Asks how to settle a tool changed both here and on the remote: keep
+/// this machine's version, take the remote's, or pick field by field. An
+/// empty answer takes the remote's, as a pull always did.
+fn resolve_conflict(conflict: &Conflict) -> Result<Option<ToolConfig>> {
+    let describe = |tool: &Option<ToolConfig>| match tool {
+        Some(_) => "changed",
+        None => "deleted",
+    };
+    println!(
+        "{}",
+        format!(
+            "⚠️  '{}' was {} here and {} on the remote since the last sync.",
+            conflict.name,
+            describe(&conflict.local),
+            describe(&conflict.remote)
+        )
+        .yellow()
+        .bold()
     );
-    headers.insert(USER_AGENT, HeaderValue::from_static("tkit/0.1.0"));
 
-    let response = client.get(&url).headers(headers).send().await?;
+    loop {
+        let can_merge = conflict.local.is_some() && conflict.remote.is_some();
+        let choices = if can_merge {
+            "[l]ocal, [r]emote, [d]iff, [m]erge fields"
+        } else {
+            "[l]ocal, [r]emote, [d]iff"
+        };
+        let answer = read_line(&format!("  Keep which? {} (default r): ", choices))?;
+        match answer.trim().to_lowercase().as_str() {
+            "l" | "local" => return Ok(conflict.local.clone()),
+            "" | "r" | "remote" => return Ok(conflict.remote.clone()),
+            "d" | "diff" => print_tool_diff(conflict)?,
+            "m" | "merge" if can_merge => {
+                let (local, remote) = (
+                    conflict.local.as_ref().unwrap(),
+                    conflict.remote.as_ref().unwrap(),
+                );
+                let mut take = Vec::new();
+                for (field, ours, theirs) in merge::differing_fields(local, remote)? {
+                    println!("  {}:", field.bold());
+                    println!("{}", format!("    local:  {}", field_value(&ours)).red());
+                    println!(
+                        "{}",
+                        format!("    remote: {}", field_value(&theirs)).green()
+                    );
+                    if confirm(&format!("  Take the remote {}?", field), true)? {
+                        take.push(field);
+                    }
+                }
+                return Ok(Some(merge::take_fields(local, remote, &take)?));
+            }
+            other => println!("  Unknown choice '{}'", other),
+        }
+    }
+}
 
-    if !response.status().is_success() {
+fn print_tool_diff(conflict: &Conflict) -> Result<()> {
+    let yaml = |tool: &Option<ToolConfig>| -> Result<String> {
+        Ok(match tool {
+            Some(tool) => serde_yaml::to_string(tool)?,
+            None => String::new(),
+        })
+    };
+    println!("{}", "  --- local".red());
+    println!("{}", "  +++ remote".green());
+    for line in merge::diff_lines(&yaml(&conflict.local)?, &yaml(&conflict.remote)?) {
+        match line {
+            DiffLine::Same(line) => println!("    {}", line),
+            DiffLine::Removed(line) => println!("{}", format!("  - {}", line).red()),
+            DiffLine::Added(line) => println!("{}", format!("  + {}", line).green()),
+        }
+    }
+    Ok(())
+}
+
+/// A field value on one line, or `(unset)`.
+fn field_value(value: &Option<serde_yaml::Value>) -> String {
+    match value {
+        Some(value) => serde_yaml::to_string(value)
+            .unwrap_or_default()
+            .trim_end()
+            .replace('\n', " / "),
+        None => "(unset)".to_string(),
+    }
+}
+
+pub async fn add_sync_remote(
+    name: &str,
+    repo: Option<String>,
+    path: Option<String>,
+    token: Option<String>,
+    branch: Option<String>,
+) -> Result<()> {
+    let mut config = Config::load()?;
+
+    if config.sync.remote(name).is_some() {
+        return Err(anyhow!("Remote '{}' already exists.", name));
+    }
+
+    if let Some(repo) = &repo {
+        let token = token
+            .as_ref()
+            .or(config.sync.token.as_ref())
+            .ok_or_else(|| {
+                anyhow!(
+                    "GitHub token not found. Pass --token or run 'tkit sync setup <repo>' first."
+                )
+            })?;
+        validate_github_access(repo, token).await?;
+    }
+
+    config.sync.remotes.push(SyncRemote {
+        name: name.to_string(),
+        repo,
+        token,
+        path,
+        branch,
+        ..Default::default()
+    });
+    config.save()?;
+
+    println!("{}", format!("✓ Remote '{}' added", name).green().bold());
+    println!("  'tkit sync push' now updates every remote");
+    Ok(())
+}
+
+pub fn remove_sync_remote(name: &str) -> Result<()> {
+    let mut config = Config::load()?;
+
+    if name == PRIMARY_REMOTE {
         return Err(anyhow!(
-            "Failed to access repository '{}'. Status: {}. Check your token and repository name.",
-            repo,
-            response.status()
+            "'{}' is the main sync repository; use 'tkit sync setup' to change it.",
+            PRIMARY_REMOTE
         ));
     }
+    let before = config.sync.remotes.len();
+    config.sync.remotes.retain(|r| r.name != name);
+    if config.sync.remotes.len() == before {
+        return Err(anyhow!("Remote '{}' not found.", name));
+    }
+    config.save()?;
+
+    println!("{}", format!("✓ Remote '{}' removed", name).green().bold());
+    Ok(())
+}
+
+pub async fn add_tool(tool_name: &str) -> Result<()> {
+    let mut config = Config::load()?;
+
+    if config.tools.contains_key(tool_name) {
+        println!(
+            "{}",
+            format!("Tool '{}' already exists.", tool_name).yellow()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        format!("Adding tool '{}'...", tool_name).blue().bold()
+    );
+
+    // Get description first (required)
+    let description = read_line("Description: ")?;
+    let description = description.trim().to_string();
+
+    if description.is_empty() {
+        return Err(anyhow!("Description is required"));
+    }
 
-    Ok(())
-}
+    let category = read_line("Category (optional): ")?;
+    let category = (!category.is_empty()).then_some(category);
 
-pub async fn setup_github_sync(repo: String, token: Option<String>) -> Result<()> {
-    let mut config = Config::load()?;
+    println!("Enter commands for each action (empty line to finish):");
 
-    let token = if let Some(t) = token {
-        t
-    } else {
-        use std::io::{self, Write};
-        print!("Enter your GitHub Personal Access Token: ");
-        io::stdout().flush()?;
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        input.trim().to_string()
+    let install_commands = read_commands("Install")?;
+    let remove_commands = read_commands("Remove")?;
+    let update_commands = read_commands("Update")?;
+    let run_commands = read_commands("Run")?;
+    let verify_commands = read_commands("Verify")?;
+
+    let tool_config = ToolConfig {
+        name: tool_name.to_string(),
+        description: Some(description),
+        category,
+        install_commands,
+        remove_commands,
+        update_commands,
+        run_commands: run_commands.into(),
+        verify_commands,
+        installed: false,
+        ..Default::default()
     };
 
-    validate_github_access(&repo, &token).await?;
+    config.add_tool(tool_name, tool_config)?;
+    config.save_journaled(&format!("add {}", tool_name))?;
 
-    config.sync.repo = Some(repo.clone());
-    config.sync.token = Some(token);
-    config.save()?;
+    // Auto-sync if enabled
+    auto_sync_if_enabled(&config, "add", tool_name).await?;
 
     println!(
         "{}",
-        format!("✓ GitHub sync configured for repository: {}", repo)
+        format!("✓ Tool '{}' added successfully!", tool_name)
             .green()
             .bold()
     );
-    println!("  Use 'tkit sync push' to upload your config");
-    println!("  Use 'tkit sync pull' to download config from GitHub");
-
     Ok(())
 }
 
-pub async fn update_github_token(token: Option<String>) -> Result<()> {
+pub async fn add_tool_from_package(
+    manager: PackageManager,
+    package: &str,
+    binary: Option<&str>,
+    description: Option<&str>,
+) -> Result<()> {
     let mut config = Config::load()?;
 
-    // Check if sync is already configured
-    let repo = config.sync.repo.as_ref().ok_or_else(|| {
-        anyhow!("GitHub sync not configured. Run 'tkit sync setup <repo>' first.")
-    })?;
-
-    let token = if let Some(t) = token {
-        t
-    } else {
-        use std::io::{self, Write};
-        print!("Enter your new GitHub Personal Access Token: ");
-        io::stdout().flush()?;
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        input.trim().to_string()
-    };
+    if config.tools.contains_key(package) {
+        println!("{}", format!("Tool '{}' already exists.", package).yellow());
+        return Ok(());
+    }
 
-    // Validate the new token
-    validate_github_access(repo, &token).await?;
+    let tool_config = ToolConfig::from_package(manager, package, binary, description);
+    config.add_tool(package, tool_config)?;
+    config.save_journaled(&format!("add {}", package))?;
 
-    // Update the token
-    config.sync.token = Some(token);
-    config.save()?;
+    // Auto-sync if enabled
+    auto_sync_if_enabled(&config, "add", package).await?;
 
     println!(
         "{}",
-        "✓ GitHub token updated successfully!"
+        format!("✓ Tool '{}' added successfully ({})!", package, manager)
             .green()
             .bold()
     );
-
+    println!("  Run 'tkit install {}' to install it", package);
     Ok(())
 }
 
-pub async fn push_config_to_github() -> Result<()> {
-    let config = Config::load()?;
+/// Downloads a text document over HTTP(S), e.g. a shared recipe or registry index.
+pub async fn fetch_text(url: &str) -> Result<String> {
+    let parsed = url::Url::parse(url).map_err(|e| anyhow!("Invalid URL '{}': {}", url, e))?;
+    if parsed.scheme() != "https" && parsed.scheme() != "http" {
+        return Err(anyhow!("Unsupported URL scheme '{}'", parsed.scheme()));
+    }
 
-    let repo = config.sync.repo.as_ref().ok_or_else(|| {
-        anyhow!("GitHub sync not configured. Run 'tkit sync setup <repo>' first.")
-    })?;
-    let token =
-        config.sync.token.as_ref().ok_or_else(|| {
-            anyhow!("GitHub token not found. Run 'tkit sync setup <repo>' first.")
-        })?;
+    let client = http_client()?;
+    let response = client
+        .get(parsed)
+        .header(USER_AGENT, "tkit/0.1.0")
+        .send()
+        .await?;
 
-    // Create a copy of config without the token for pushing to GitHub
-    let mut safe_config = config.clone();
-    safe_config.sync.token = None;
-    let config_content = serde_yaml::to_string(&safe_config)?;
-    let encoded_content = general_purpose::STANDARD.encode(config_content);
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Failed to download '{}'. Status: {}",
+            url,
+            response.status()
+        ));
+    }
 
-    let client = reqwest::Client::new();
-    let url = format!(
-        "https://api.github.com/repos/{}/contents/tkit-config.yaml",
-        repo
-    );
+    Ok(response.text().await?)
+}
 
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
-    );
-    headers.insert(USER_AGENT, HeaderValue::from_static("tkit/0.1.0"));
+pub async fn fetch_tool_definition(url: &str) -> Result<ToolConfig> {
+    let content = fetch_text(url).await?;
+    ToolConfig::from_yaml(&content)
+}
 
-    // Check if file exists to get SHA
-    let existing_response = client.get(&url).headers(headers.clone()).send().await;
-    let sha = if let Ok(response) = existing_response {
-        if response.status().is_success() {
-            let file: GitHubFile = response.json().await?;
-            Some(file.sha)
-        } else {
-            None
-        }
-    } else {
-        None
-    };
+pub fn print_tool_definition(tool: &ToolConfig) -> Result<()> {
+    for line in serde_yaml::to_string(tool)?.lines() {
+        println!("  {}", line);
+    }
+    Ok(())
+}
 
-    let payload = GitHubCreateFile {
-        message: format!(
-            "Update tkit config - {}",
-            chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
-        ),
-        content: encoded_content,
-        sha,
-    };
+pub async fn add_tool_from_url(
+    url: &str,
+    tool_name: Option<&str>,
+    params: &[(String, String)],
+) -> Result<()> {
+    let mut config = Config::load()?;
 
-    let response = client
-        .put(&url)
-        .headers(headers)
-        .json(&payload)
-        .send()
-        .await?;
+    println!("{}", format!("Downloading {}...", url).blue().bold());
+    let mut tool_config = fetch_tool_definition(url).await?;
+    if let Some(name) = tool_name {
+        tool_config.name = name.to_string();
+    }
+    let name = tool_config.name.clone();
 
-    if response.status().is_success() {
-        println!(
-            "{}",
-            "✓ Configuration pushed to GitHub successfully!"
-                .green()
-                .bold()
-        );
+    if config.tools.contains_key(&name) {
+        println!("{}", format!("Tool '{}' already exists.", name).yellow());
+        return Ok(());
+    }
 
-        // Update last sync time
-        let mut updated_config = config;
-        updated_config.sync.last_sync = Some(chrono::Utc::now().to_rfc3339());
-        updated_config.save()?;
-    } else {
-        let error_text = response.text().await?;
-        return Err(anyhow!("Failed to push to GitHub: {}", error_text));
+    // Downloaded commands will run on this machine, so always show them first
+    println!("{}", "Review the tool definition:".cyan().bold());
+    print_tool_definition(&tool_config)?;
+    println!();
+
+    if !confirm(&format!("Add tool '{}'?", name), false)? {
+        return Err(aborted());
+    }
+
+    if !tool_config.parameters.is_empty() {
+        println!("{}", "Recipe parameters:".cyan().bold());
+        tool_config.param_values = resolve_parameters(&tool_config, params, true)?;
     }
 
+    config.add_tool(&name, tool_config)?;
+    config.save_journaled(&format!("add {} from {}", name, url))?;
+
+    // Auto-sync if enabled
+    auto_sync_if_enabled(&config, "add", &name).await?;
+
+    println!(
+        "{}",
+        format!("✓ Tool '{}' added successfully!", name)
+            .green()
+            .bold()
+    );
     Ok(())
 }
 
-pub async fn show_sync_status() -> Result<()> {
-    let config = Config::load()?;
+pub async fn delete_tool(pattern: &str) -> Result<()> {
+    let mut config = Config::load()?;
 
-    println!("{}", "GitHub Sync Status:".blue().bold());
+    if !is_glob(pattern) && !config.tools.contains_key(&config.resolve_name(pattern)) {
+        println!("{}", format!("Tool '{}' not found.", pattern).yellow());
+        return Ok(());
+    }
 
-    if let Some(repo) = &config.sync.repo {
-        println!("  Repository: {}", repo.green());
-        println!(
-            "  Token: {}",
-            if config.sync.token.is_some() {
-                "✓ Configured".green()
-            } else {
-                "✗ Not set".red()
-            }
-        );
+    let names = select_tools(&config, pattern, "delete", false)?;
+    if names.is_empty() {
+        return Ok(());
+    }
 
-        if let Some(last_sync) = &config.sync.last_sync {
-            println!("  Last sync: {}", last_sync);
-        } else {
-            println!("  Last sync: {}", "Never".yellow());
+    let mut trash = Trash::load()?;
+    let now = chrono::Utc::now().to_rfc3339();
+    for name in &names {
+        if let Some(tool) = config.tools.remove(name) {
+            trash.put(tool, &now);
         }
+    }
+    config.save_journaled(&format!("delete {}", names.join(", ")))?;
+    trash.save()?;
+
+    let mut state = State::load()?;
+    let before = state.tools.len();
+    state.tools.retain(|name, _| !names.contains(name));
+    if state.tools.len() != before {
+        state.save()?;
+    }
+
+    // Auto-sync if enabled
+    auto_sync_if_enabled(&config, "delete", &names.join(", ")).await?;
 
+    for name in &names {
         println!(
-            "  Auto-sync: {}",
-            if config.sync.auto_sync {
-                "✓ Enabled".green()
-            } else {
-                "✗ Disabled".red()
-            }
+            "{}",
+            format!("✓ Tool '{}' deleted successfully!", name)
+                .green()
+                .bold()
         );
-    } else {
-        println!("  Status: {}", "Not configured".yellow());
-        println!("  Run 'tkit sync setup <username/repo>' to get started");
     }
-
+    println!("  Run 'tkit restore <tool>' to bring a deleted tool back.");
     Ok(())
 }
 
-pub async fn pull_config_from_github() -> Result<()> {
-    let config = Config::load()?;
-
-    let repo = config.sync.repo.as_ref().ok_or_else(|| {
-        anyhow!("GitHub sync not configured. Run 'tkit sync setup <repo>' first.")
-    })?;
-    let token =
-        config.sync.token.as_ref().ok_or_else(|| {
-            anyhow!("GitHub token not found. Run 'tkit sync setup <repo>' first.")
-        })?;
-
-    let client = reqwest::Client::new();
-    let url = format!(
-        "https://api.github.com/repos/{}/contents/tkit-config.yaml",
-        repo
-    );
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
-    );
-    headers.insert(USER_AGENT, HeaderValue::from_static("tkit/0.1.0"));
-
-    let response = client.get(&url).headers(headers).send().await?;
-
-    if !response.status().is_success() {
+pub async fn restore_tool(name: &str) -> Result<()> {
+    let mut config = Config::load()?;
+    if config.tools.contains_key(name) {
         return Err(anyhow!(
-            "Failed to fetch config from GitHub. Make sure the file exists and you have access."
+            "Tool '{}' already exists. Rename or delete it before restoring.",
+            name
         ));
     }
 
-    let file: GitHubFile = response.json().await?;
+    let mut trash = Trash::load()?;
+    config.add_tool(name, trash.take(name)?)?;
+    config.save_journaled(&format!("restore {}", name))?;
+    trash.save()?;
 
-    let content = file
-        .content
-        .ok_or_else(|| anyhow!("No content in GitHub file"))?;
-    let decoded_content = general_purpose::STANDARD.decode(content.replace('\n', ""))?;
-    let config_str = String::from_utf8(decoded_content)?;
+    println!(
+        "{}",
+        format!("✓ Tool '{}' restored from the trash", name)
+            .green()
+            .bold()
+    );
 
-    let remote_config: Config = serde_yaml::from_str(&config_str)?;
+    auto_sync_if_enabled(&config, "restore", name).await?;
+    Ok(())
+}
 
-    // Backup current config
-    let backup_path = get_config_path()?.with_extension("yaml.backup");
-    if let Ok(current_content) = fs::read_to_string(get_config_path()?) {
-        fs::write(&backup_path, current_content)?;
+pub fn list_trash() -> Result<()> {
+    let trash = Trash::load()?;
+    if trash.tools.is_empty() {
+        println!("{}", "The trash is empty.".yellow());
+        return Ok(());
+    }
+
+    println!("{}", "Deleted tools:".blue().bold());
+    for (name, trashed) in &trash.tools {
         println!(
-            "{}",
-            format!("✓ Current config backed up to: {}", backup_path.display()).yellow()
+            "  • {} {}",
+            name.bold(),
+            format!("(deleted {})", format_timestamp(Some(&trashed.deleted_at))).dimmed()
         );
     }
+    Ok(())
+}
+
+pub fn empty_trash() -> Result<()> {
+    let mut trash = Trash::load()?;
+    if trash.tools.is_empty() {
+        println!("{}", "The trash is empty.".yellow());
+        return Ok(());
+    }
+    if !confirm(
+        &format!("Permanently drop {} deleted tool(s)?", trash.tools.len()),
+        false,
+    )? {
+        return Err(aborted());
+    }
 
-    // Merge configurations (preserve local sync settings)
-    let mut merged_config = remote_config;
-    merged_config.sync = config.sync; // Keep local sync settings
-    merged_config.sync.last_sync = Some(chrono::Utc::now().to_rfc3339());
+    trash.tools.clear();
+    trash.save()?;
+    println!("{}", "✓ Trash emptied".green().bold());
+    Ok(())
+}
+
+pub async fn copy_tool(src: &str, dst: &str) -> Result<()> {
+    let mut config = Config::load()?;
 
-    merged_config.save()?;
+    config.copy_tool(src, dst)?;
+    config.save_journaled(&format!("copy {} to {}", src, dst))?;
+
+    // Auto-sync if enabled
+    auto_sync_if_enabled(&config, "copy", dst).await?;
 
     println!(
         "{}",
-        "✓ Configuration pulled from GitHub successfully!"
+        format!("✓ Tool '{}' copied to '{}'!", src, dst)
             .green()
             .bold()
     );
-    println!("  {} tools loaded", merged_config.tools.len());
-
+    println!("  Edit the copy in {}", get_config_path()?.display());
     Ok(())
 }
 
-pub async fn add_tool(tool_name: &str) -> Result<()> {
-    use std::io::{self, Write};
-
+pub async fn set_tool_disabled(tool_name: &str, disabled: bool) -> Result<()> {
     let mut config = Config::load()?;
 
-    if config.tools.contains_key(tool_name) {
+    let state = if disabled { "disabled" } else { "enabled" };
+    if !config.set_disabled(tool_name, disabled)? {
         println!(
             "{}",
-            format!("Tool '{}' already exists.", tool_name).yellow()
+            format!("Tool '{}' is already {}.", tool_name, state).yellow()
         );
         return Ok(());
     }
+    config.save_journaled(&format!(
+        "{} {}",
+        if disabled { "disable" } else { "enable" },
+        tool_name
+    ))?;
+
+    // Auto-sync if enabled
+    auto_sync_if_enabled(
+        &config,
+        if disabled { "disable" } else { "enable" },
+        tool_name,
+    )
+    .await?;
 
     println!(
         "{}",
-        format!("Adding tool '{}'...", tool_name).blue().bold()
+        format!("✓ Tool '{}' {}!", tool_name, state).green().bold()
     );
+    Ok(())
+}
 
-    // Get description first (required)
-    print!("Description: ");
-    io::stdout().flush()?;
-    let mut description = String::new();
-    std::io::stdin().read_line(&mut description)?;
-    let description = description.trim().to_string();
-
-    if description.is_empty() {
-        return Err(anyhow!("Description is required"));
-    }
-
-    println!("Enter commands for each action (empty line to finish):");
+/// The parameter a pinned version is substituted for.
+const PIN_PARAM: &str = "version";
 
-    let install_commands = read_commands("Install")?;
-    let remove_commands = read_commands("Remove")?;
-    let update_commands = read_commands("Update")?;
-    let run_commands = read_commands("Run")?;
+pub async fn pin_tool(tool_name: &str, version: Option<String>) -> Result<()> {
+    let mut config = Config::load()?;
+    let tool_name = &config.resolve_name(tool_name);
+    let tool = config
+        .tools
+        .get_mut(tool_name)
+        .ok_or_else(|| tool_not_found(tool_name))?;
 
-    let tool_config = ToolConfig {
-        name: tool_name.to_string(),
-        description: Some(description),
-        install_commands,
-        remove_commands,
-        update_commands,
-        run_commands,
-        installed: false,
+    let detected = || {
+        tool.param_values.get(PIN_PARAM).cloned().or_else(|| {
+            let spec = tool.package.as_ref()?;
+            spec.installed_version().ok().flatten()
+        })
     };
+    let version = version.or_else(detected).ok_or_else(|| {
+        anyhow!(
+            "Could not tell which version of '{}' is installed; pass one: tkit pin {} <version>",
+            tool_name,
+            tool_name
+        )
+    })?;
 
-    config.tools.insert(tool_name.to_string(), tool_config);
-    config.save()?;
-
-    // Auto-sync if enabled
-    auto_sync_if_enabled(&config).await?;
+    tool.pinned = Some(version.clone());
+    tool.touch();
+    config.save_journaled(&format!("pin {} to {}", tool_name, version))?;
+    auto_sync_if_enabled(&config, "pin", tool_name).await?;
 
     println!(
         "{}",
-        format!("✓ Tool '{}' added successfully!", tool_name)
+        format!("✓ Pinned '{}' to {}", tool_name, version)
             .green()
             .bold()
     );
     Ok(())
 }
 
-pub async fn delete_tool(tool_name: &str) -> Result<()> {
+pub async fn unpin_tool(tool_name: &str) -> Result<()> {
     let mut config = Config::load()?;
+    let tool_name = &config.resolve_name(tool_name);
+    let tool = config
+        .tools
+        .get_mut(tool_name)
+        .ok_or_else(|| tool_not_found(tool_name))?;
 
-    if !config.tools.contains_key(tool_name) {
-        println!("{}", format!("Tool '{}' not found.", tool_name).yellow());
+    if tool.pinned.take().is_none() {
+        println!(
+            "{}",
+            format!("Tool '{}' is not pinned.", tool_name).yellow()
+        );
         return Ok(());
     }
+    tool.touch();
+    config.save_journaled(&format!("unpin {}", tool_name))?;
+    auto_sync_if_enabled(&config, "unpin", tool_name).await?;
 
-    config.tools.remove(tool_name);
-    config.save()?;
+    println!("{}", format!("✓ Unpinned '{}'", tool_name).green().bold());
+    Ok(())
+}
+
+pub async fn rename_tool(old: &str, new: &str) -> Result<()> {
+    let mut config = Config::load()?;
+
+    let old = &config.resolve_name(old);
+    config.rename_tool(old, new)?;
+    config.save_journaled(&format!("rename {} to {}", old, new))?;
+    overrides::rename_in_overlay(&get_config_path()?, &overrides::hostname(), old, new)?;
+
+    let mut state = State::load()?;
+    if state.tools.contains_key(old) || state.observed.contains_key(old) {
+        state.rename(old, new);
+        state.save()?;
+    }
 
     // Auto-sync if enabled
-    auto_sync_if_enabled(&config).await?;
+    auto_sync_if_enabled(&config, "rename", new).await?;
 
     println!(
         "{}",
-        format!("✓ Tool '{}' deleted successfully!", tool_name)
+        format!("✓ Tool '{}' renamed to '{}'!", old, new)
             .green()
             .bold()
     );
     Ok(())
 }
 
-pub async fn run_tool(tool_name: &str) -> Result<()> {
+/// Runs one of a tool's named run entries. `tool:entry` picks an entry
+/// when `entry` is not given separately.
+pub async fn run_tool(target: &str, entry: Option<&str>, args: &[String]) -> Result<()> {
     let config = Config::load()?;
+    let (tool_name, entry) = match (entry, target.rsplit_once(':')) {
+        (None, Some((tool, name))) if !config.tools.contains_key(&config.resolve_name(target)) => {
+            (config.resolve_name(tool), Some(name))
+        }
+        _ => (config.resolve_name(target), entry),
+    };
+    let tool_name = &tool_name;
 
     let tool = config
         .tools
         .get(tool_name)
-        .ok_or_else(|| anyhow!("Tool '{}' not found.", tool_name))?;
+        .ok_or_else(|| tool_not_found(tool_name))?;
+
+    ensure_enabled(tool)?;
 
     if tool.run_commands.is_empty() {
         println!(
@@ -650,48 +6638,131 @@ pub async fn run_tool(tool_name: &str) -> Result<()> {
         return Ok(());
     }
 
-    execute_commands(&tool.run_commands, tool_name, "run").await?;
+    let run_commands = match entry {
+        Some(name) => tool.run_commands.get(name),
+        None => Some(tool.run_commands.default_commands()).filter(|c| !c.is_empty()),
+    }
+    .ok_or_else(|| {
+        anyhow!(
+            "Tool '{}' has no run command named '{}'. Available: {}",
+            tool_name,
+            entry.unwrap_or(DEFAULT_RUN),
+            tool.run_commands.names().join(", ")
+        )
+    })?;
+
+    let values = resolve_parameters(tool, &[], false)?;
+    let vars = template_vars(&config.variables, &values);
+    let commands = render_commands(run_commands, &vars);
+    record_usage(tool_name, |stats, now| stats.record_run(now));
+    let started = std::time::Instant::now();
+    let result = execute_commands_with_args(&commands, args, &vars, tool_name, "run").await;
+    let seconds = started.elapsed().as_secs_f64();
+    record_history("run", Some(tool_name), result.is_ok(), Some(seconds));
+    result?;
     Ok(())
 }
 
-pub fn read_commands(action: &str) -> Result<Vec<String>> {
-    use std::io::{self, Write};
-
+pub fn read_commands(action: &str) -> Result<Vec<Step>> {
     println!("{}", format!("{} commands:", action).cyan().bold());
     let mut commands = Vec::new();
     let mut line_num = 1;
 
     loop {
-        print!("  {}: ", line_num);
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
+        let input = read_line(&format!("  {}: ", line_num))?;
         let input = input.trim();
 
         if input.is_empty() {
             break;
         }
 
-        commands.push(input.to_string());
+        commands.push(Step::new(input));
         line_num += 1;
     }
 
     Ok(commands)
 }
 
-pub async fn init_config() -> Result<()> {
-    use std::io::{self, Write};
+/// Sets up a fresh machine from an existing config. A URL is downloaded
+/// as-is; an `owner/repo` is pulled through the GitHub API and becomes the
+/// sync repository.
+pub async fn init_from(source: &str, token: Option<String>, install: bool) -> Result<()> {
+    if get_config_path()?.exists() && !confirm("Configuration already exists. Replace it?", false)?
+    {
+        return Err(aborted());
+    }
+
+    let mut config = if source.starts_with("http://") || source.starts_with("https://") {
+        println!("{}", format!("Downloading {}...", source).blue().bold());
+        let content = fetch_text(source).await?;
+        let mut config = parse_remote_config(&content)
+            .map_err(|e| anyhow!("Invalid tkit config at '{}': {}", source, e))?;
+        // A config published at a URL must not carry someone else's credentials
+        config.sync = Default::default();
+        config
+    } else {
+        let token = match token {
+            Some(token) => token,
+            None => read_line("Enter your GitHub Personal Access Token: ")?,
+        };
+        validate_github_access(source, &token).await?;
+
+        println!(
+            "{}",
+            format!("Pulling config from {}...", source).blue().bold()
+        );
+        let mut config = fetch_remote_config(source, None, &token).await?;
+        config.sync = SyncConfig {
+            repo: Some(source.to_string()),
+            token: Some(token),
+            last_sync: Some(chrono::Utc::now().to_rfc3339()),
+            auto_sync: config.sync.auto_sync,
+            encrypt: config.sync.encrypt,
+            ..Default::default()
+        };
+        config
+    };
+
+    // Installation state describes the machine the config came from
+    for tool in config.tools.values_mut() {
+        tool.installed = false;
+        tool.param_values.clear();
+    }
+
+    backup_config()?;
+    config.save_journaled(&format!("init from {}", source))?;
+
+    println!(
+        "{}",
+        format!("✓ Configuration initialized from {}", source)
+            .green()
+            .bold()
+    );
+    println!("  {} tools loaded", config.tools.len());
+    if config.sync.repo.is_some() {
+        println!("  Sync configured for {}", source);
+    }
+
+    if config.tools.is_empty() {
+        return Ok(());
+    }
+    if install || confirm("Install all enabled tools now?", false)? {
+        println!();
+        install_tool("*", &[], true, false, false, false, false).await?;
+    } else {
+        println!("  Run 'tkit install --all' to install your tools");
+    }
+
+    Ok(())
+}
 
+pub async fn init_config(preset: Option<String>) -> Result<()> {
+    let preset = preset.as_deref().map(find_preset).transpose()?;
     let config_path = get_config_path()?;
 
     if config_path.exists() {
         println!("{}", "Configuration already exists.".yellow());
-        print!("Do you want to reset and start fresh? (y/N): ");
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
+        let input = read_line("Do you want to reset and start fresh? (y/N): ")?;
         if input.trim().to_lowercase() != "y" {
             return Ok(());
         }
@@ -707,74 +6778,48 @@ pub async fn init_config() -> Result<()> {
 
     // Step 1: Basic setup
     println!("{}", "Step 1: Basic Configuration".cyan().bold());
-    println!("First, let's add some essential tools to get you started.");
-    println!();
-
-    // Ask about example tools
-    let examples = vec![
-        (
-            "git",
-            "Version control system",
-            vec!["sudo apt-get update", "sudo apt-get install -y git"],
-            vec!["git --version"],
-        ),
-        (
-            "docker",
-            "Container platform",
-            vec![
-                "curl -fsSL https://get.docker.com -o get-docker.sh",
-                "sudo sh get-docker.sh",
-            ],
-            vec!["docker --version"],
-        ),
-        (
-            "node",
-            "Node.js runtime",
-            vec![
-                "curl -fsSL https://deb.nodesource.com/setup_lts.x | sudo -E bash -",
-                "sudo apt-get install -y nodejs",
-            ],
-            vec!["node --version", "npm --version"],
-        ),
-        (
-            "python",
-            "Python programming language",
-            vec![
-                "sudo apt-get update",
-                "sudo apt-get install -y python3 python3-pip",
-            ],
-            vec!["python3 --version"],
-        ),
-    ];
+    let preset = match preset {
+        Some(preset) => Some(preset),
+        None => {
+            println!("First, pick a preset of tools to get you started.");
+            println!();
+            for (i, preset) in PRESETS.iter().enumerate() {
+                println!(
+                    "{}. {} - {} ({})",
+                    i + 1,
+                    preset.name.bold(),
+                    preset.description,
+                    preset.tools.join(", ")
+                );
+            }
+            println!("{}. None, I'll add tools myself", PRESETS.len() + 1);
+
+            let choice = read_line(&format!("Choose option (1-{}): ", PRESETS.len() + 1))?;
+            choice
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| PRESETS.get(n.checked_sub(1)?))
+        }
+    };
 
-    for (name, desc, install_cmds, run_cmds) in &examples {
-        print!("Add {} ({})?  (Y/n): ", name.bold(), desc);
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim().to_lowercase();
-
-        if input.is_empty() || input == "y" || input == "yes" {
-            config.tools.insert(
-                name.to_string(),
-                ToolConfig {
-                    name: name.to_string(),
-                    description: Some(desc.to_string()),
-                    install_commands: install_cmds.iter().map(|s| s.to_string()).collect(),
-                    remove_commands: vec![format!("sudo apt-get remove -y {}", name)],
-                    update_commands: vec![
-                        "sudo apt-get update".to_string(),
-                        format!("sudo apt-get upgrade -y {}", name),
-                    ],
-                    run_commands: run_cmds.iter().map(|s| s.to_string()).collect(),
-                    installed: false,
-                },
-            );
+    // Each tool gets the recipe that works on this machine
+    if let Some(preset) = preset {
+        println!("Adding the {} preset:", preset.name.bold());
+        for tool in preset.tools_for(PackageManager::detect_host()) {
+            let name = tool.name.clone();
+            config.add_tool(&name, tool)?;
             println!("  ✓ Added {}", name.green());
         }
     }
 
+    println!();
+    let input = read_line("Scan this machine for tools that are already installed? (Y/n): ")?;
+    let input = input.trim().to_lowercase();
+    if input != "n" && input != "no" {
+        adopt_installed_tools(&mut config, false)?;
+    }
+
     println!();
 
     // Step 2: GitHub Integration
@@ -782,11 +6827,7 @@ pub async fn init_config() -> Result<()> {
     println!("TKIT can sync your configuration to GitHub for backup and sharing across machines.");
     println!();
 
-    print!("Set up GitHub sync? (y/N): ");
-    io::stdout().flush()?;
-
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+    let input = read_line("Set up GitHub sync? (y/N): ")?;
     let input = input.trim().to_lowercase();
 
     if input == "y" || input == "yes" {
@@ -796,41 +6837,25 @@ pub async fn init_config() -> Result<()> {
         println!("2. Use an existing repository");
         println!("3. Skip for now");
 
-        print!("Choose option (1-3): ");
-        io::stdout().flush()?;
-
-        let mut choice = String::new();
-        io::stdin().read_line(&mut choice)?;
+        let choice = read_line("Choose option (1-3): ")?;
 
         match choice.trim() {
             "1" => {
                 // Create new repo
-                print!("Enter GitHub Personal Access Token: ");
-                io::stdout().flush()?;
-
-                let mut token = String::new();
-                io::stdin().read_line(&mut token)?;
+                let token = read_line("Enter GitHub Personal Access Token: ")?;
                 let token = token.trim();
 
                 if !token.is_empty() {
                     config.sync.token = Some(token.to_string());
 
-                    print!("Repository name (default: tkit-config): ");
-                    io::stdout().flush()?;
-
-                    let mut repo_name = String::new();
-                    io::stdin().read_line(&mut repo_name)?;
+                    let repo_name = read_line("Repository name (default: tkit-config): ")?;
                     let repo_name = if repo_name.trim().is_empty() {
                         "tkit-config"
                     } else {
                         repo_name.trim()
                     };
 
-                    print!("Make repository private? (Y/n): ");
-                    io::stdout().flush()?;
-
-                    let mut private_input = String::new();
-                    io::stdin().read_line(&mut private_input)?;
+                    let private_input = read_line("Make repository private? (Y/n): ")?;
                     let private = private_input.trim().to_lowercase() != "n";
 
                     // Temporarily save config with token
@@ -852,19 +6877,11 @@ pub async fn init_config() -> Result<()> {
             }
             "2" => {
                 // Use existing repo
-                print!("Enter repository (username/repo-name): ");
-                io::stdout().flush()?;
-
-                let mut repo = String::new();
-                io::stdin().read_line(&mut repo)?;
+                let repo = read_line("Enter repository (username/repo-name): ")?;
                 let repo = repo.trim();
 
                 if !repo.is_empty() {
-                    print!("Enter GitHub Personal Access Token: ");
-                    io::stdout().flush()?;
-
-                    let mut token = String::new();
-                    io::stdin().read_line(&mut token)?;
+                    let token = read_line("Enter GitHub Personal Access Token: ")?;
                     let token = token.trim();
 
                     if !token.is_empty() {
@@ -889,11 +6906,8 @@ pub async fn init_config() -> Result<()> {
         // Auto-sync option
         if config.sync.repo.is_some() && config.sync.token.is_some() {
             println!();
-            print!("Enable automatic sync on configuration changes? (Y/n): ");
-            io::stdout().flush()?;
-
-            let mut auto_sync_input = String::new();
-            io::stdin().read_line(&mut auto_sync_input)?;
+            let auto_sync_input =
+                read_line("Enable automatic sync on configuration changes? (Y/n): ")?;
             let auto_sync = auto_sync_input.trim().to_lowercase() != "n";
 
             config.sync.auto_sync = auto_sync;
@@ -915,20 +6929,12 @@ pub async fn init_config() -> Result<()> {
             .cyan()
             .bold()
     );
-    print!("Would you like to add a custom tool now? (y/N): ");
-    io::stdout().flush()?;
-
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+    let input = read_line("Would you like to add a custom tool now? (y/N): ")?;
     let input = input.trim().to_lowercase();
 
     if input == "y" || input == "yes" {
         println!();
-        print!("Tool name: ");
-        io::stdout().flush()?;
-
-        let mut tool_name = String::new();
-        io::stdin().read_line(&mut tool_name)?;
+        let tool_name = read_line("Tool name: ")?;
         let tool_name = tool_name.trim();
 
         if !tool_name.is_empty() {
@@ -944,7 +6950,7 @@ pub async fn init_config() -> Result<()> {
     config.save()?;
 
     // Auto-sync if enabled
-    auto_sync_if_enabled(&config).await?;
+    auto_sync_if_enabled(&config, "init", "").await?;
 
     println!();
     println!("{}", "🎉 Setup Complete!".green().bold());
@@ -955,36 +6961,320 @@ pub async fn init_config() -> Result<()> {
     println!("  • Run 'tkit examples' to see more tool ideas");
     println!("  • Run 'tkit add <tool>' to add more custom tools");
 
-    if config.sync.repo.is_some() {
-        println!(
-            "  • Your config is synced to GitHub: {}",
-            config.sync.repo.as_ref().unwrap().cyan()
-        );
+    if let Some(repo) = &config.sync.repo {
+        println!("  • Your config is synced to GitHub: {}", repo.cyan());
     }
 
     Ok(())
 }
 
-pub fn reset_config() -> Result<()> {
-    use std::io::{self, Write};
+/// Adds the well-known tools found already installed on this machine to
+/// `config`, marked installed, after asking which ones (or all of them with
+/// `all`). Tools the config already has are only marked installed. Returns
+/// how many tools changed.
+fn adopt_installed_tools(config: &mut Config, all: bool) -> Result<usize> {
+    let host = PackageManager::detect_host();
+    println!("{}", "Scanning PATH and installed packages...".dimmed());
+
+    let mut adopted = 0;
+    let mut new = Vec::new();
+    for found in find_installed(host) {
+        match config.tools.get_mut(found.example.name) {
+            Some(tool) if !tool.installed => {
+                tool.installed = true;
+                tool.desired_state = DesiredState::Present;
+                tool.touch();
+                println!(
+                    "  ✓ {} is already installed ({})",
+                    found.example.name.green(),
+                    found.how
+                );
+                adopted += 1;
+            }
+            Some(_) => {}
+            None => new.push(found),
+        }
+    }
+    if new.is_empty() {
+        if adopted == 0 {
+            println!("  No other known tools found.");
+        }
+        return Ok(adopted);
+    }
+
+    let picked: Vec<usize> = if all {
+        (0..new.len()).collect()
+    } else {
+        let items: Vec<String> = new
+            .iter()
+            .map(|found| {
+                format!(
+                    "{} - {} ({})",
+                    found.example.name, found.example.description, found.how
+                )
+            })
+            .collect();
+        multi_select("Found these tools already installed; add them?", &items)?
+    };
+    for i in picked {
+        let example = new[i].example;
+        let mut tool = example.tool_for(host);
+        tool.installed = true;
+        tool.desired_state = DesiredState::Present;
+        config.add_tool(example.name, tool)?;
+        println!("  ✓ Added {} (installed)", example.name.green());
+        adopted += 1;
+    }
+    Ok(adopted)
+}
+
+/// Adds the well-known tools already installed on this machine to the
+/// config, so adopting tkit doesn't start from nothing.
+pub async fn scan_tools(all: bool) -> Result<()> {
+    let mut config = Config::load()?;
+    let adopted = adopt_installed_tools(&mut config, all)?;
+    if adopted == 0 {
+        return Ok(());
+    }
+
+    config.save_journaled("scan for installed tools")?;
+    auto_sync_if_enabled(&config, "scan", "").await?;
+    println!(
+        "{}",
+        format!("✓ {} tool(s) now tracked as installed", adopted)
+            .green()
+            .bold()
+    );
+    Ok(())
+}
+
+pub async fn undo_last_change() -> Result<()> {
+    let journal_path = journal::get_journal_path()?;
+    let journal = journal::Journal::load_from_path(&journal_path)?;
+    let Some(entry) = journal.entries.last() else {
+        println!("{}", "Nothing to undo.".yellow());
+        return Ok(());
+    };
+
+    let config_path = get_config_path()?;
+    let current = fs::read_to_string(&config_path).ok();
+    match &entry.machine {
+        Some(machine) => println!(
+            "Last change: {} ({} on {})",
+            redact(&entry.action).bold(),
+            format_timestamp(Some(&entry.timestamp)),
+            machine
+        ),
+        None => println!(
+            "Last change: {} ({})",
+            redact(&entry.action).bold(),
+            format_timestamp(Some(&entry.timestamp))
+        ),
+    }
+    if current.as_deref().map(journal::strip_secrets) != Some(journal::strip_secrets(&entry.after))
+        && !confirm(
+            "The configuration has changed since then (e.g. by an install). Undo anyway?",
+            false,
+        )?
+    {
+        return Err(aborted());
+    }
+
+    let Some(entry) = journal::pop(&journal_path)? else {
+        return Ok(());
+    };
+    match &entry.before {
+        Some(before) => write_atomic(
+            &config_path,
+            &journal::restore_secrets(before, current.as_deref()),
+        )?,
+        None => fs::remove_file(&config_path)?,
+    }
 
+    println!(
+        "{}",
+        format!("✓ Undid '{}'", redact(&entry.action))
+            .green()
+            .bold()
+    );
+
+    // Auto-sync if enabled
+    auto_sync_if_enabled(&Config::load()?, "undo", "").await?;
+    Ok(())
+}
+
+/// Formats a setting's value for printing: scalars bare, anything else as YAML.
+fn format_setting(value: &serde_yaml::Value) -> Result<String> {
+    Ok(match value {
+        serde_yaml::Value::String(s) => s.clone(),
+        serde_yaml::Value::Bool(b) => b.to_string(),
+        serde_yaml::Value::Number(n) => n.to_string(),
+        other => serde_yaml::to_string(other)?.trim_end().to_string(),
+    })
+}
+
+pub fn get_setting(key: &str) -> Result<()> {
+    let config = Config::load()?;
+    let value = config
+        .setting(key)?
+        .ok_or_else(|| anyhow!("'{}' is not set", key))?;
+    println!("{}", format_setting(&value)?);
+    Ok(())
+}
+
+pub async fn set_setting(key: &str, value: Option<&str>) -> Result<()> {
+    let mut config = Config::load()?;
+    config.set_setting(key, value)?;
+    // A new token is masked like the one loaded at startup
+    config.register_secrets();
+    let secret = SECRET_SETTING_KEYS.contains(&key);
+    match value {
+        Some(_) if secret => config.save_journaled(&format!("config set {}={}", key, MASK))?,
+        Some(value) => config.save_journaled(&format!("config set {}={}", key, value))?,
+        None => config.save_journaled(&format!("config unset {}", key))?,
+    }
+
+    match config.setting(key)? {
+        Some(_) if secret => println!("{}", format!("✓ {} = {}", key, MASK).green().bold()),
+        Some(value) => println!(
+            "{}",
+            format!("✓ {} = {}", key, redact(&format_setting(&value)?))
+                .green()
+                .bold()
+        ),
+        None => println!("{}", format!("✓ {} reset", key).green().bold()),
+    }
+
+    auto_sync_if_enabled(&config, "config", "").await?;
+    Ok(())
+}
+
+pub fn list_settings() -> Result<()> {
+    let config = Config::load()?;
+    let width = SETTING_KEYS
+        .iter()
+        .map(|(key, _)| key.len())
+        .chain(config.variables.keys().map(|name| name.len() + 10))
+        .max()
+        .unwrap_or(0);
+
+    let show = |key: &str, about: Option<&str>| -> Result<()> {
+        let value = match config.setting(key)? {
+            Some(_) if SECRET_SETTING_KEYS.contains(&key) => MASK.to_string(),
+            Some(value) => format_setting(&value)?,
+            None => "-".to_string(),
+        };
+        let about = about.map(|a| format!("  {}", a)).unwrap_or_default();
+        println!("  {:<width$}  {}{}", key, value.green(), about.dimmed());
+        Ok(())
+    };
+    for (key, about) in SETTING_KEYS {
+        show(key, Some(about))?;
+    }
+    for name in config.variables.keys() {
+        show(&format!("variables.{}", name), None)?;
+    }
+    Ok(())
+}
+
+/// Opens `path` in `editor`, which may carry arguments such as `code --wait`.
+fn run_editor(editor: &str, path: &std::path::Path) -> Result<()> {
+    let mut parts = editor.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow!("No editor configured"))?;
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .map_err(|e| anyhow!("Could not start editor '{}': {}", editor, e))?;
+    if !status.success() {
+        return Err(anyhow!("Editor '{}' exited with {}", editor, status));
+    }
+    Ok(())
+}
+
+/// Edits a copy of config.yaml and only replaces the real file once the
+/// copy parses and validates, offering to edit again when it doesn't.
+/// A file removed when this is dropped.
+struct Draft(PathBuf);
+
+impl Drop for Draft {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+pub async fn edit_config() -> Result<()> {
+    let config_path = get_config_path()?;
+    let original = match fs::read_to_string(&config_path) {
+        Ok(content) => content,
+        Err(_) => serde_yaml::to_string(&Config::new())?,
+    };
+    let editor = Config::load()
+        .map(|config| config.settings.editor())
+        .unwrap_or_else(|_| Settings::default().editor());
+
+    // The draft can hold sync tokens, so it is private and never outlives
+    // the edit
+    let draft = Draft(config_path.with_file_name("config.edit.yaml"));
+    write_private(&draft.0, &original)?;
+
+    let edited = loop {
+        run_editor(&editor, &draft.0)?;
+        let edited = fs::read_to_string(&draft.0)?;
+        if edited == original {
+            println!("{}", "No changes.".yellow());
+            return Ok(());
+        }
+        match Config::parse_and_validate(&edited) {
+            Ok(_) => break edited,
+            Err(e) => {
+                println!("{}", format!("✗ The edited config is invalid: {}", e).red());
+                if !confirm("Edit again?", true)? {
+                    println!(
+                        "{}",
+                        "config.yaml was not changed; your edits were discarded.".yellow()
+                    );
+                    return Ok(());
+                }
+            }
+        }
+    };
+
+    write_atomic(&config_path, &edited)?;
+    drop(draft);
+    journal::record(
+        &journal::get_journal_path()?,
+        "edit-config",
+        Some(original),
+        edited,
+    )?;
+    println!("{}", "✓ Configuration saved".green().bold());
+
+    auto_sync_if_enabled(&Config::load()?, "edit", "").await?;
+    Ok(())
+}
+
+pub fn reset_config() -> Result<()> {
     println!("{}", "⚠️  Reset Configuration".red().bold());
     println!("This will permanently delete:");
     println!("  • All configured tools");
     println!("  • GitHub sync settings");
+    println!("  • Usage statistics, activity and undo history");
     println!("  • All configuration data");
     println!();
 
-    print!("Are you sure you want to continue? Type 'yes' to confirm: ");
-    io::stdout().flush()?;
-
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+    let input = if assume_yes() {
+        println!("Are you sure you want to continue? Type 'yes' to confirm: yes");
+        "yes".to_string()
+    } else {
+        read_line("Are you sure you want to continue? Type 'yes' to confirm: ")?
+    };
     let input = input.trim().to_lowercase();
 
     if input != "yes" {
-        println!("{}", "Reset cancelled.".yellow());
-        return Ok(());
+        return Err(ExitKind::Aborted.error("Reset cancelled."));
     }
 
     // Remove config file
@@ -994,12 +7284,31 @@ pub fn reset_config() -> Result<()> {
         println!("{}", "✓ Configuration file deleted".green());
     }
 
+    let state_path = get_state_path()?;
+    if state_path.exists() {
+        std::fs::remove_file(&state_path)?;
+        println!("{}", "✓ Usage statistics deleted".green());
+    }
+
+    let journal_path = journal::get_journal_path()?;
+    if journal_path.exists() {
+        std::fs::remove_file(&journal_path)?;
+        println!("{}", "✓ Undo history deleted".green());
+    }
+
+    let history_path = history::get_history_path()?;
+    if history_path.exists() {
+        std::fs::remove_file(&history_path)?;
+        println!("{}", "✓ Activity history deleted".green());
+    }
+
     // Remove config directory if empty
-    if let Some(config_dir) = config_path.parent() {
-        if config_dir.exists() && config_dir.read_dir()?.next().is_none() {
-            std::fs::remove_dir(config_dir)?;
-            println!("{}", "✓ Configuration directory removed".green());
-        }
+    if let Some(config_dir) = config_path.parent()
+        && config_dir.exists()
+        && config_dir.read_dir()?.next().is_none()
+    {
+        std::fs::remove_dir(config_dir)?;
+        println!("{}", "✓ Configuration directory removed".green());
     }
 
     println!();
@@ -1009,93 +7318,89 @@ pub fn reset_config() -> Result<()> {
     Ok(())
 }
 
-async fn auto_sync_if_enabled(config: &Config) -> Result<()> {
+/// Pushes the changes auto-sync queued while offline.
+pub async fn flush_sync_queue() -> Result<()> {
+    let queue = SyncQueue::load()?;
+    if queue.pushes.is_empty() {
+        println!(
+            "{}",
+            "Nothing queued; every change has been pushed.".green()
+        );
+        return Ok(());
+    }
+    println!(
+        "{}",
+        format!("Pushing {} queued change(s)...", queue.pushes.len()).blue()
+    );
+    push_config_to_github(false).await
+}
+
+/// Whether a push failed because the remote couldn't be reached at all,
+/// rather than because it refused the push.
+fn is_offline_error(error: &anyhow::Error) -> bool {
+    const UNREACHABLE: &[&str] = &[
+        "Could not resolve host",
+        "Temporary failure in name resolution",
+        "Network is unreachable",
+        "Connection refused",
+        "Connection timed out",
+    ];
+    let unreachable = error.chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_connect() || e.is_timeout())
+    });
+    let message = error.to_string();
+    unreachable || UNREACHABLE.iter().any(|marker| message.contains(marker))
+}
+
+/// Pushes the config if auto-sync is on. `action` and `tool` say what
+/// changed, for the commit message; `tool` is empty when no tool is involved.
+pub async fn auto_sync_if_enabled(config: &Config, action: &str, tool: &str) -> Result<()> {
     if config.should_auto_sync() {
         println!("{}", "🔄 Auto-syncing to GitHub...".blue().dimmed());
-        if let Err(e) = push_config_to_github_silent().await {
-            println!(
+        match push_config_to_github_silent(action, tool).await {
+            Err(e) => println!(
                 "{}",
-                format!("⚠️  Auto-sync failed: {}", e).yellow().dimmed()
-            );
-        } else {
-            println!("{}", "✓ Auto-sync completed".green().dimmed());
+                format!("⚠️  Auto-sync failed: {}", redact(&e.to_string()))
+                    .yellow()
+                    .dimmed()
+            ),
+            Ok(0) => println!("{}", "✓ Auto-sync completed".green().dimmed()),
+            Ok(queued) => println!(
+                "{}",
+                format!(
+                    "⚠️  Offline: {} change(s) queued; they are pushed with the next sync or 'tkit sync flush'",
+                    queued
+                )
+                .yellow()
+                .dimmed()
+            ),
         }
     }
     Ok(())
 }
 
-pub async fn push_config_to_github_silent() -> Result<()> {
+/// Auto-sync's push. A push that can't reach a remote is queued rather than
+/// lost; returns how many pushes are queued (0 once everything went out).
+pub async fn push_config_to_github_silent(action: &str, tool: &str) -> Result<usize> {
     let config = Config::load()?;
 
-    let repo = config
-        .sync
-        .repo
-        .as_ref()
-        .ok_or_else(|| anyhow!("GitHub sync not configured"))?;
-    let token = config
-        .sync
-        .token
-        .as_ref()
-        .ok_or_else(|| anyhow!("GitHub token not found"))?;
-
-    // Create a copy of config without the token for pushing to GitHub
-    let mut safe_config = config.clone();
-    safe_config.sync.token = None;
-    let config_content = serde_yaml::to_string(&safe_config)?;
-    let encoded_content = general_purpose::STANDARD.encode(config_content);
-
-    let client = reqwest::Client::new();
-    let url = format!(
-        "https://api.github.com/repos/{}/contents/tkit-config.yaml",
-        repo
-    );
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
-    );
-    headers.insert(USER_AGENT, HeaderValue::from_static("tkit/0.1.0"));
-
-    // Check if file exists to get SHA
-    let existing_response = client.get(&url).headers(headers.clone()).send().await;
-    let sha = if let Ok(response) = existing_response {
-        if response.status().is_success() {
-            let file: GitHubFile = response.json().await?;
-            Some(file.sha)
-        } else {
-            None
-        }
-    } else {
-        None
-    };
-
-    let payload = GitHubCreateFile {
-        message: format!(
-            "Auto-sync tkit config - {}",
-            chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
-        ),
-        content: encoded_content,
-        sha,
-    };
-
-    let response = client
-        .put(&url)
-        .headers(headers)
-        .json(&payload)
-        .send()
-        .await?;
+    let mut queue = SyncQueue::load()?;
+    let latest = sync_commit_message(&config, action, tool, "Auto-sync");
+    let results = push_to_remotes(config, &queue.commit_message(&latest), false).await?;
 
-    if response.status().is_success() {
-        // Update last sync time
-        let mut updated_config = config;
-        updated_config.sync.last_sync = Some(chrono::Utc::now().to_rfc3339());
-        updated_config.save()?;
-        Ok(())
+    let (offline, results): (Vec<_>, Vec<_>) = results
+        .into_iter()
+        .partition(|(_, result)| result.as_ref().is_err_and(is_offline_error));
+    if offline.is_empty() {
+        queue.pushes.clear();
     } else {
-        let error_text = response.text().await?;
-        Err(anyhow!("Failed to push to GitHub: {}", error_text))
+        queue.push(&latest, &chrono::Utc::now().to_rfc3339());
     }
+    queue.save()?;
+    push_failures(results)?;
+    Ok(queue.pushes.len())
 }
 
 pub async fn create_github_repo(name: &str, private: bool) -> Result<()> {
@@ -1106,15 +7411,10 @@ pub async fn create_github_repo(name: &str, private: bool) -> Result<()> {
             anyhow!("GitHub token not found. Run 'tkit sync setup <repo>' first.")
         })?;
 
-    let client = reqwest::Client::new();
+    let client = http_client()?;
     let url = "https://api.github.com/user/repos";
 
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", token))?,
-    );
-    headers.insert(USER_AGENT, HeaderValue::from_static("tkit/0.1.0"));
+    let headers = github_headers(token)?;
 
     let request_body = CreateRepoRequest {
         name: name.to_string(),
@@ -1154,4 +7454,3 @@ pub async fn create_github_repo(name: &str, private: bool) -> Result<()> {
 
     Ok(())
 }
-
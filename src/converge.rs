@@ -0,0 +1,381 @@
+use std::fmt;
+
+use crate::lockfile::{Lockfile, recipe_hash};
+use crate::releases::release_executable;
+use crate::{Config, ToolConfig};
+
+/// The group of tools a machine should have, chosen by category and/or tag.
+/// An empty selection means "the tools whose `desired_state` is present".
+#[derive(Debug, Default, Clone)]
+pub struct Selection {
+    pub category: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl Selection {
+    pub fn is_empty(&self) -> bool {
+        self.category.is_none() && self.tag.is_none()
+    }
+
+    pub fn matches(&self, tool: &ToolConfig) -> bool {
+        let category = self.category.as_deref().is_none_or(|category| {
+            tool.category
+                .as_deref()
+                .is_some_and(|c| c.eq_ignore_ascii_case(category))
+        });
+        let tag = self
+            .tag
+            .as_deref()
+            .is_none_or(|tag| tool.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+        category && tag
+    }
+
+    /// Whether the tool should be present (`Some(true)`), absent
+    /// (`Some(false)`), or left alone (`None`).
+    pub fn desired(&self, tool: &ToolConfig) -> Option<bool> {
+        let wanted = match self.is_empty() {
+            true => tool.desired_state.is_present(),
+            false => self.matches(tool),
+        };
+        // Only tools tkit installed are ever removed
+        (wanted || tool.installed).then_some(wanted)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Install,
+    Update,
+    Remove,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Change::Install => "install",
+            Change::Update => "update",
+            Change::Remove => "remove",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedChange {
+    pub tool: String,
+    pub change: Change,
+    pub reason: String,
+}
+
+/// Whether a tool is present on this machine, judged by its release
+/// executable, its binary being on PATH or its package manager listing it;
+/// `None` when tkit can't tell.
+pub fn observe(tool: &ToolConfig) -> Option<bool> {
+    if tool.release.is_some() {
+        return Some(release_executable(tool).ok()?.exists());
+    }
+    if let Some(binary) = &tool.binary {
+        return Some(which::which(binary).is_ok());
+    }
+    let spec = tool.package.as_ref()?;
+    if !spec.manager.supports_version_detection() {
+        return None;
+    }
+    spec.installed_version()
+        .ok()
+        .map(|version| version.is_some())
+}
+
+/// Why an installed tool needs an update according to `tkit.lock`: it was
+/// pinned to another version, or its recipe changed since it was installed.
+fn update_reason(tool: &ToolConfig, lock: &Lockfile) -> Option<String> {
+    let locked = lock.tools.get(&tool.name)?;
+    if let Some(pinned) = &tool.pinned
+        && locked.version.as_ref() != Some(pinned)
+    {
+        return Some(format!(
+            "pinned to {}, installed {}",
+            pinned,
+            locked.version.as_deref().unwrap_or("unknown")
+        ));
+    }
+    (recipe_hash(tool) != locked.recipe_hash).then(|| "recipe changed since install".to_string())
+}
+
+/// The installs, updates and removals that bring the machine to `selection`,
+/// in install order (see [`Config::ordered_names`]). Disabled tools are
+/// never touched. `observe` reports what is actually present; where it can't
+/// tell, the recorded `installed` flag is used.
+pub fn plan(
+    config: &Config,
+    lock: &Lockfile,
+    selection: &Selection,
+    mut observe: impl FnMut(&ToolConfig) -> Option<bool>,
+) -> Vec<PlannedChange> {
+    let mut changes = Vec::new();
+    for name in config.ordered_names() {
+        let tool = &config.tools[name];
+        if tool.disabled {
+            continue;
+        }
+        let Some(desired) = selection.desired(tool) else {
+            continue;
+        };
+        let present = observe(tool).unwrap_or(tool.installed);
+        let (change, reason) = match (desired, present) {
+            (true, false) if tool.installed => (
+                Change::Install,
+                "recorded as installed but missing".to_string(),
+            ),
+            (true, false) if selection.is_empty() => {
+                (Change::Install, "desired but not installed".to_string())
+            }
+            (true, false) => (Change::Install, "selected but not installed".to_string()),
+            (false, true) if selection.is_empty() => {
+                (Change::Remove, "installed but not desired".to_string())
+            }
+            (false, true) => (Change::Remove, "installed but not selected".to_string()),
+            (true, true) => match update_reason(tool, lock) {
+                Some(reason) if tool.installed => (Change::Update, reason),
+                _ => continue,
+            },
+            (false, false) => continue,
+        };
+        changes.push(PlannedChange {
+            tool: name.clone(),
+            change,
+            reason,
+        });
+    }
+    changes
+}
+
+/// How a tool's state on the machine disagrees with what tkit recorded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drift {
+    /// Present although not recorded as installed.
+    Appeared,
+    /// Recorded as installed but no longer present.
+    Vanished,
+    /// Installed at another version than `tkit.lock` records.
+    Version { recorded: String, observed: String },
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drift::Appeared => f.write_str("installed outside tkit"),
+            Drift::Vanished => f.write_str("removed outside tkit"),
+            Drift::Version { recorded, observed } => {
+                write!(f, "version {} recorded, {} installed", recorded, observed)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolDrift {
+    pub tool: String,
+    pub drift: Drift,
+}
+
+/// The installed version of a tool as its package manager reports it.
+pub fn observe_version(tool: &ToolConfig) -> Option<String> {
+    let spec = tool.package.as_ref()?;
+    if !spec.manager.supports_version_detection() {
+        return None;
+    }
+    spec.installed_version().ok().flatten()
+}
+
+/// Tools whose observed state disagrees with the recorded `installed` flag
+/// or locked version, in tool name order. Tools that can't be observed and
+/// disabled tools are skipped.
+pub fn detect_drift(
+    config: &Config,
+    lock: &Lockfile,
+    mut observe: impl FnMut(&ToolConfig) -> Option<bool>,
+    mut observe_version: impl FnMut(&ToolConfig) -> Option<String>,
+) -> Vec<ToolDrift> {
+    let names: Vec<&String> = config.tools.keys().collect();
+
+    let mut drifts = Vec::new();
+    for name in names {
+        let tool = &config.tools[name];
+        if tool.disabled {
+            continue;
+        }
+        let Some(present) = observe(tool) else {
+            continue;
+        };
+        let drift = match (tool.installed, present) {
+            (false, true) => Drift::Appeared,
+            (true, false) => Drift::Vanished,
+            (true, true) => {
+                let recorded = lock.tools.get(name).and_then(|l| l.version.clone());
+                match (recorded, observe_version(tool)) {
+                    (Some(recorded), Some(observed)) if recorded != observed => {
+                        Drift::Version { recorded, observed }
+                    }
+                    _ => continue,
+                }
+            }
+            (false, false) => continue,
+        };
+        drifts.push(ToolDrift {
+            tool: name.clone(),
+            drift,
+        });
+    }
+    drifts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DesiredState;
+
+    fn tool(name: &str, category: &str, installed: bool) -> ToolConfig {
+        let desired_state = if installed {
+            DesiredState::Present
+        } else {
+            DesiredState::Absent
+        };
+        ToolConfig {
+            name: name.to_string(),
+            category: Some(category.to_string()),
+            installed,
+            desired_state,
+            ..Default::default()
+        }
+    }
+
+    fn config(tools: Vec<ToolConfig>) -> Config {
+        let mut config = Config::new();
+        for tool in tools {
+            config.tools.insert(tool.name.clone(), tool);
+        }
+        config
+    }
+
+    #[test]
+    fn test_plan_reinstalls_missing_tools() {
+        let config = config(vec![
+            tool("git", "vcs", true),
+            tool("rg", "search", true),
+            tool("fd", "search", false),
+        ]);
+        let changes = plan(
+            &config,
+            &Lockfile::default(),
+            &Selection::default(),
+            |tool| Some(tool.name != "rg"),
+        );
+        assert_eq!(
+            changes,
+            vec![PlannedChange {
+                tool: "rg".to_string(),
+                change: Change::Install,
+                reason: "recorded as installed but missing".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_plan_converges_to_selected_group() {
+        let mut disabled = tool("docker", "containers", true);
+        disabled.disabled = true;
+        let config = config(vec![
+            tool("git", "vcs", true),
+            tool("rg", "search", false),
+            tool("fd", "search", true),
+            disabled,
+        ]);
+        let selection = Selection {
+            category: Some("Search".to_string()),
+            tag: None,
+        };
+        let changes: Vec<(String, Change)> =
+            plan(&config, &Lockfile::default(), &selection, |_| None)
+                .into_iter()
+                .map(|c| (c.tool, c.change))
+                .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("git".to_string(), Change::Remove),
+                ("rg".to_string(), Change::Install),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_updates_changed_and_repinned_tools() {
+        let mut go = tool("go", "lang", true);
+        let mut lock = Lockfile::default();
+        lock.record(&go, Some("1.21".to_string()), "2026-01-01T00:00:00+00:00");
+        let rg = tool("rg", "search", true);
+        lock.record(&rg, None, "2026-01-01T00:00:00+00:00");
+        go.pinned = Some("1.22".to_string());
+        let mut rg = rg;
+        rg.install_commands = vec!["cargo install ripgrep".into()];
+
+        let changes = plan(&config(vec![go, rg]), &lock, &Selection::default(), |_| {
+            Some(true)
+        });
+        let reasons: Vec<(&str, Change, &str)> = changes
+            .iter()
+            .map(|c| (c.tool.as_str(), c.change, c.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                ("go", Change::Update, "pinned to 1.22, installed 1.21"),
+                ("rg", Change::Update, "recipe changed since install"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_drift() {
+        let go = tool("go", "lang", true);
+        let mut lock = Lockfile::default();
+        lock.record(&go, Some("1.21".to_string()), "2026-01-01T00:00:00+00:00");
+        let config = config(vec![
+            go,
+            tool("rg", "search", true),
+            tool("fd", "search", false),
+            tool("jq", "json", false),
+        ]);
+
+        let drifts = detect_drift(
+            &config,
+            &lock,
+            |tool| match tool.name.as_str() {
+                "rg" => Some(false),
+                "jq" => None,
+                _ => Some(true),
+            },
+            |_| Some("1.22".to_string()),
+        );
+        assert_eq!(
+            drifts,
+            vec![
+                ToolDrift {
+                    tool: "fd".to_string(),
+                    drift: Drift::Appeared,
+                },
+                ToolDrift {
+                    tool: "go".to_string(),
+                    drift: Drift::Version {
+                        recorded: "1.21".to_string(),
+                        observed: "1.22".to_string(),
+                    },
+                },
+                ToolDrift {
+                    tool: "rg".to_string(),
+                    drift: Drift::Vanished,
+                },
+            ]
+        );
+    }
+}
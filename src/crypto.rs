@@ -0,0 +1,116 @@
+use anyhow::{Result, anyhow};
+use base64::{Engine as _, engine::general_purpose};
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+
+/// Environment variable read for the sync passphrase before prompting.
+pub const PASSPHRASE_ENV: &str = "TKIT_SYNC_PASSPHRASE";
+
+const KDF_ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+
+/// A config encrypted with a passphrase (PBKDF2-SHA256 + AES-256-GCM), as
+/// stored on a remote in place of the plain YAML.
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedConfig {
+    tkit_encrypted: u32,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey> {
+    let iterations =
+        NonZeroU32::new(iterations).ok_or_else(|| anyhow!("Invalid iteration count"))?;
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key =
+        UnboundKey::new(&AES_256_GCM, &key).map_err(|_| anyhow!("Could not create cipher key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+pub fn encrypt(plaintext: &str, passphrase: &str) -> Result<String> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| anyhow!("Could not generate random bytes"))?;
+
+    let key = derive_key(passphrase, &salt, KDF_ITERATIONS)?;
+    let mut data = plaintext.as_bytes().to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+        .map_err(|_| anyhow!("Encryption failed"))?;
+
+    let encoded = EncryptedConfig {
+        tkit_encrypted: 1,
+        iterations: KDF_ITERATIONS,
+        salt: general_purpose::STANDARD.encode(salt),
+        nonce: general_purpose::STANDARD.encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(data),
+    };
+    Ok(serde_yaml::to_string(&encoded)?)
+}
+
+/// Whether `content` was produced by [`encrypt`].
+pub fn is_encrypted(content: &str) -> bool {
+    serde_yaml::from_str::<EncryptedConfig>(content).is_ok()
+}
+
+pub fn decrypt(content: &str, passphrase: &str) -> Result<String> {
+    let encoded: EncryptedConfig = serde_yaml::from_str(content)?;
+    if encoded.tkit_encrypted != 1 {
+        return Err(anyhow!(
+            "Unsupported encrypted config version {}",
+            encoded.tkit_encrypted
+        ));
+    }
+
+    let salt = general_purpose::STANDARD.decode(&encoded.salt)?;
+    let nonce: [u8; NONCE_LEN] = general_purpose::STANDARD
+        .decode(&encoded.nonce)?
+        .try_into()
+        .map_err(|_| anyhow!("Invalid nonce in encrypted config"))?;
+    let mut data = general_purpose::STANDARD.decode(&encoded.ciphertext)?;
+
+    let key = derive_key(passphrase, &salt, encoded.iterations)?;
+    let plaintext = key
+        .open_in_place(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+        .map_err(|_| anyhow!("Could not decrypt config: wrong passphrase or corrupted data"))?;
+    Ok(String::from_utf8(plaintext.to_vec())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let encrypted = encrypt("tools: {}\n", "hunter2").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("tools"));
+        assert_eq!(decrypt(&encrypted, "hunter2").unwrap(), "tools: {}\n");
+    }
+
+    #[test]
+    fn test_wrong_passphrase_fails() {
+        let encrypted = encrypt("tools: {}\n", "hunter2").unwrap();
+        let err = decrypt(&encrypted, "wrong").unwrap_err();
+        assert!(err.to_string().contains("wrong passphrase"));
+    }
+
+    #[test]
+    fn test_plain_config_is_not_encrypted() {
+        assert!(!is_encrypted("tools: {}\nsync: {}\n"));
+    }
+}
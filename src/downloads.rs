@@ -0,0 +1,156 @@
+use anyhow::{Result, anyhow};
+use ring::digest::{SHA256, digest};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A file tkit fetches itself before the install commands run. Its cached
+/// path is substituted into commands as `{{name}}`.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct Download {
+    pub name: String,
+    /// `https://` or `file://` URL.
+    pub url: String,
+    /// Expected SHA-256 of the file, in hex.
+    pub sha256: String,
+}
+
+impl Download {
+    /// File name taken from the last URL path segment, falling back to the download name.
+    pub fn file_name(&self) -> &str {
+        self.url
+            .split(['?', '#'])
+            .next()
+            .and_then(|url| url.rsplit('/').next())
+            .filter(|name| !name.is_empty())
+            .unwrap_or(&self.name)
+    }
+
+    /// Where the file lives in the cache; keyed by checksum so a changed
+    /// checksum never reuses a stale file.
+    pub fn cached_path(&self, cache_dir: &Path) -> PathBuf {
+        cache_dir
+            .join(self.sha256.to_lowercase())
+            .join(self.file_name())
+    }
+
+    /// The cached copy, if present and still matching the checksum.
+    pub fn cached(&self, cache_dir: &Path) -> Option<PathBuf> {
+        let path = self.cached_path(cache_dir);
+        let data = fs::read(&path).ok()?;
+        (sha256_hex(&data) == self.sha256.to_lowercase()).then_some(path)
+    }
+
+    /// Downloads the file, verifies it and stores it in the cache.
+    pub async fn fetch(&self, cache_dir: &Path) -> Result<PathBuf> {
+        let data = read_url(&self.url).await?;
+        let actual = sha256_hex(&data);
+        if actual != self.sha256.to_lowercase() {
+            return Err(anyhow!(
+                "Checksum mismatch for '{}' ({}): expected {}, got {}",
+                self.name,
+                self.url,
+                self.sha256,
+                actual
+            ));
+        }
+
+        let path = self.cached_path(cache_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write next to the final path and rename so an interrupted download
+        // never leaves a partial file behind.
+        let partial = path.with_extension("part");
+        fs::write(&partial, &data)?;
+        fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+}
+
+/// Reads an `https://` or `file://` URL; anything else is treated as a local path.
+pub async fn read_url(location: &str) -> Result<Vec<u8>> {
+    match url::Url::parse(location) {
+        Ok(url) if url.scheme() == "file" => {
+            let path = url
+                .to_file_path()
+                .map_err(|_| anyhow!("Invalid file URL '{}'", location))?;
+            Ok(fs::read(path)?)
+        }
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {
+            let response = crate::http_client()?
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(response.bytes().await?.to_vec())
+        }
+        _ => Ok(fs::read(location).map_err(|e| anyhow!("Could not read '{}': {}", location, e))?),
+    }
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    digest(&SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub fn get_download_cache_dir() -> Result<PathBuf> {
+    let cache_dir =
+        dirs::cache_dir().ok_or_else(|| anyhow!("Could not determine cache directory"))?;
+    Ok(cache_dir.join("tkit").join("downloads"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"hello"),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+    }
+
+    #[test]
+    fn test_file_name() {
+        let download = Download {
+            name: "terraform".to_string(),
+            url: "https://example.com/tf/terraform_1.5.0_linux_amd64.zip?x=1".to_string(),
+            sha256: String::new(),
+        };
+        assert_eq!(download.file_name(), "terraform_1.5.0_linux_amd64.zip");
+
+        let download = Download {
+            url: "https://example.com/".to_string(),
+            ..download
+        };
+        assert_eq!(download.file_name(), "terraform");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_verifies_and_caches() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("tool.tar.gz");
+        fs::write(&source, b"hello").unwrap();
+        let cache_dir = temp_dir.path().join("cache");
+
+        let mut download = Download {
+            name: "archive".to_string(),
+            url: url::Url::from_file_path(&source).unwrap().to_string(),
+            sha256: sha256_hex(b"hello"),
+        };
+        assert!(download.cached(&cache_dir).is_none());
+        let path = download.fetch(&cache_dir).await.unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"hello");
+        assert_eq!(download.cached(&cache_dir), Some(path));
+
+        download.sha256 = sha256_hex(b"other");
+        let err = download.fetch(&cache_dir).await.unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"));
+    }
+}
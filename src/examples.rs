@@ -1,119 +1,584 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use colored::*;
+use tkit::{CommandShell, Config, PackageManager, Step, ToolAction, ToolConfig};
 
-pub fn show_examples() -> Result<()> {
-    println!("{}", "Tool Configuration Examples:".blue().bold());
-    println!();
+use crate::commands::{auto_sync_if_enabled, print_tool_definition};
 
-    println!("{}", "Development Tools:".cyan().bold());
+use PackageManager::{Apt, Brew, Dnf, Pacman, Winget, Zypper};
 
-    println!("  {}", "Visual Studio Code:".green());
-    println!("    tkit add vscode");
-    println!("    Description: Visual Studio Code editor");
-    println!(
-        "    Install: wget -qO- https://packages.microsoft.com/keys/microsoft.asc | gpg --dearmor > packages.microsoft.gpg"
-    );
-    println!("    Run: code");
-    println!();
+/// A curated tool recipe. Tools packaged by the host's package manager use
+/// the package shorthand; the rest fall back to an install script, run by
+/// `sh` since most pipe one command into another.
+pub struct Example {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub category: &'static str,
+    pub binary: Option<&'static str>,
+    pub packages: &'static [(PackageManager, &'static str)],
+    pub script: &'static [&'static str],
+    /// Install scripts for particular package managers, used instead of
+    /// `script` on those hosts.
+    pub host_scripts: &'static [(PackageManager, &'static [&'static str])],
+    pub run: &'static [&'static str],
+}
 
-    println!("  {}", "Git:".green());
-    println!("    tkit add git");
-    println!("    Description: Version control system");
-    println!("    Install: sudo apt-get update && sudo apt-get install -y git");
-    println!("    Run: git --version");
-    println!();
+impl Example {
+    fn package_for(&self, host: Option<PackageManager>) -> Option<(PackageManager, &'static str)> {
+        let host = host?;
+        self.packages.iter().find(|(m, _)| *m == host).copied()
+    }
 
-    println!("  {}", "Docker:".green());
-    println!("    tkit add docker");
-    println!("    Description: Container platform");
-    println!(
-        "    Install: curl -fsSL https://get.docker.com -o get-docker.sh && sudo sh get-docker.sh"
-    );
-    println!("    Run: docker --version");
-    println!();
+    fn script_for(&self, host: Option<PackageManager>) -> &'static [&'static str] {
+        self.host_scripts
+            .iter()
+            .find(|(m, _)| Some(*m) == host)
+            .map_or(self.script, |(_, script)| script)
+    }
 
-    println!("{}", "Programming Languages:".cyan().bold());
+    /// Whether this recipe can install anything on a host using `host`.
+    pub fn available_on(&self, host: Option<PackageManager>) -> bool {
+        self.package_for(host).is_some()
+            || !self.script_for(host).is_empty()
+            || (self.packages.is_empty() && self.host_scripts.is_empty())
+    }
 
-    println!("  {}", "Python:".green());
-    println!("    tkit add python");
-    println!("    Description: Python programming language");
-    println!("    Install: sudo apt-get update && sudo apt-get install -y python3 python3-pip");
-    println!("    Run: python3 --version");
-    println!();
+    pub fn tool_for(&self, host: Option<PackageManager>) -> ToolConfig {
+        let mut tool = match self.package_for(host) {
+            Some((manager, package)) => {
+                ToolConfig::from_package(manager, package, self.binary, Some(self.description))
+            }
+            None => ToolConfig {
+                description: Some(self.description.to_string()),
+                install_commands: self
+                    .script_for(host)
+                    .iter()
+                    .map(|s| Step::new(*s))
+                    .collect(),
+                binary: self.binary.map(str::to_string),
+                shell: Some(CommandShell::Sh),
+                ..Default::default()
+            },
+        };
+        tool.name = self.name.to_string();
+        tool.category = Some(self.category.to_string());
+        tool.run_commands = self
+            .run
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into();
+        tool
+    }
+}
 
-    println!("  {}", "Rust:".green());
-    println!("    tkit add rust");
-    println!("    Description: Rust programming language");
-    println!(
-        "    Install: curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y"
-    );
-    println!("    Run: rustc --version");
-    println!();
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "vscode",
+        description: "Visual Studio Code editor",
+        category: "Development Tools",
+        binary: Some("code"),
+        packages: &[
+            (Brew, "visual-studio-code"),
+            (Winget, "Microsoft.VisualStudioCode"),
+            (Pacman, "code"),
+        ],
+        script: &[],
+        host_scripts: &[
+            (
+                Apt,
+                &[
+                    "wget -qO- https://packages.microsoft.com/keys/microsoft.asc | gpg --dearmor > packages.microsoft.gpg",
+                    "sudo install -o root -g root -m 644 packages.microsoft.gpg /etc/apt/trusted.gpg.d/",
+                    "echo 'deb [arch=amd64,arm64,armhf] https://packages.microsoft.com/repos/code stable main' | sudo tee /etc/apt/sources.list.d/vscode.list > /dev/null",
+                    "sudo apt-get update && sudo apt-get install -y code",
+                ],
+            ),
+            (
+                Dnf,
+                &[
+                    "sudo rpm --import https://packages.microsoft.com/keys/microsoft.asc",
+                    "printf '[code]\\nname=Visual Studio Code\\nbaseurl=https://packages.microsoft.com/yumrepos/vscode\\nenabled=1\\ngpgcheck=1\\ngpgkey=https://packages.microsoft.com/keys/microsoft.asc\\n' | sudo tee /etc/yum.repos.d/vscode.repo > /dev/null",
+                    "sudo dnf install -y code",
+                ],
+            ),
+            (
+                Zypper,
+                &[
+                    "sudo rpm --import https://packages.microsoft.com/keys/microsoft.asc",
+                    "printf '[code]\\nname=Visual Studio Code\\nbaseurl=https://packages.microsoft.com/yumrepos/vscode\\nenabled=1\\ntype=rpm-md\\ngpgcheck=1\\ngpgkey=https://packages.microsoft.com/keys/microsoft.asc\\n' | sudo tee /etc/zypp/repos.d/vscode.repo > /dev/null",
+                    "sudo zypper --non-interactive install code",
+                ],
+            ),
+        ],
+        run: &["code"],
+    },
+    Example {
+        name: "git",
+        description: "Version control system",
+        category: "Development Tools",
+        binary: None,
+        packages: &[
+            (Apt, "git"),
+            (Dnf, "git"),
+            (Pacman, "git"),
+            (Zypper, "git"),
+            (Brew, "git"),
+            (Winget, "Git.Git"),
+        ],
+        script: &[],
+        host_scripts: &[],
+        run: &["git --version"],
+    },
+    Example {
+        name: "docker",
+        description: "Container platform",
+        category: "Development Tools",
+        binary: None,
+        packages: &[
+            (Dnf, "moby-engine"),
+            (Pacman, "docker"),
+            (Zypper, "docker"),
+            (Brew, "docker"),
+            (Winget, "Docker.DockerDesktop"),
+        ],
+        script: &[
+            "curl -fsSL https://get.docker.com -o get-docker.sh",
+            "sudo sh get-docker.sh",
+        ],
+        host_scripts: &[],
+        run: &["docker --version"],
+    },
+    Example {
+        name: "python",
+        description: "Python programming language",
+        category: "Programming Languages",
+        binary: Some("python3"),
+        packages: &[
+            (Apt, "python3"),
+            (Dnf, "python3"),
+            (Pacman, "python"),
+            (Zypper, "python3"),
+            (Brew, "python"),
+            (Winget, "Python.Python.3.12"),
+        ],
+        script: &[],
+        host_scripts: &[],
+        run: &["python3 --version"],
+    },
+    Example {
+        name: "rust",
+        description: "Rust programming language",
+        category: "Programming Languages",
+        binary: Some("rustc"),
+        packages: &[(Winget, "Rustlang.Rustup")],
+        script: &["curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y"],
+        host_scripts: &[],
+        run: &["rustc --version"],
+    },
+    Example {
+        name: "golang",
+        description: "Go programming language",
+        category: "Programming Languages",
+        binary: Some("go"),
+        packages: &[
+            (Apt, "golang-go"),
+            (Dnf, "golang"),
+            (Pacman, "go"),
+            (Zypper, "go"),
+            (Brew, "go"),
+            (Winget, "GoLang.Go"),
+        ],
+        script: &[],
+        host_scripts: &[],
+        run: &["go version"],
+    },
+    Example {
+        name: "jupyter",
+        description: "JupyterLab notebooks",
+        category: "Data Science",
+        binary: Some("jupyter"),
+        packages: &[(Pacman, "jupyterlab"), (Brew, "jupyterlab")],
+        script: &["python3 -m pip install --user jupyterlab"],
+        host_scripts: &[],
+        run: &["jupyter lab"],
+    },
+    Example {
+        name: "sqlite",
+        description: "SQLite database shell",
+        category: "Data Science",
+        binary: Some("sqlite3"),
+        packages: &[
+            (Apt, "sqlite3"),
+            (Dnf, "sqlite"),
+            (Pacman, "sqlite"),
+            (Zypper, "sqlite3"),
+            (Brew, "sqlite"),
+            (Winget, "SQLite.SQLite"),
+        ],
+        script: &[],
+        host_scripts: &[],
+        run: &["sqlite3 --version"],
+    },
+    Example {
+        name: "curl-test",
+        description: "Test HTTP requests with curl",
+        category: "Utilities",
+        binary: None,
+        packages: &[],
+        script: &[],
+        host_scripts: &[],
+        run: &["curl -s https://httpbin.org/json"],
+    },
+    Example {
+        name: "sysinfo",
+        description: "Show system information",
+        category: "Utilities",
+        binary: None,
+        packages: &[],
+        script: &[],
+        host_scripts: &[],
+        run: &["uname -a", "df -h"],
+    },
+    Example {
+        name: "jq",
+        description: "Command-line JSON processor",
+        category: "Utilities",
+        binary: None,
+        packages: &[
+            (Apt, "jq"),
+            (Dnf, "jq"),
+            (Pacman, "jq"),
+            (Zypper, "jq"),
+            (Brew, "jq"),
+            (Winget, "jqlang.jq"),
+        ],
+        script: &[],
+        host_scripts: &[],
+        run: &["jq --version"],
+    },
+    Example {
+        name: "ripgrep",
+        description: "Fast recursive grep",
+        category: "Utilities",
+        binary: Some("rg"),
+        packages: &[
+            (Apt, "ripgrep"),
+            (Dnf, "ripgrep"),
+            (Pacman, "ripgrep"),
+            (Zypper, "ripgrep"),
+            (Brew, "ripgrep"),
+            (Winget, "BurntSushi.ripgrep.MSVC"),
+        ],
+        script: &[],
+        host_scripts: &[],
+        run: &["rg --version"],
+    },
+    Example {
+        name: "node",
+        description: "Node.js runtime",
+        category: "Web Development",
+        binary: None,
+        packages: &[
+            (Apt, "nodejs"),
+            (Dnf, "nodejs"),
+            (Pacman, "nodejs"),
+            (Zypper, "nodejs"),
+            (Brew, "node"),
+            (Winget, "OpenJS.NodeJS.LTS"),
+        ],
+        script: &[],
+        host_scripts: &[],
+        run: &["node --version", "npm --version"],
+    },
+    Example {
+        name: "nginx",
+        description: "Web server",
+        category: "Web Development",
+        binary: None,
+        packages: &[
+            (Apt, "nginx"),
+            (Dnf, "nginx"),
+            (Pacman, "nginx"),
+            (Zypper, "nginx"),
+            (Brew, "nginx"),
+        ],
+        script: &[],
+        host_scripts: &[],
+        run: &["nginx -v"],
+    },
+    Example {
+        name: "kubectl",
+        description: "Kubernetes command-line tool",
+        category: "DevOps Tools",
+        binary: None,
+        packages: &[
+            (Pacman, "kubectl"),
+            (Brew, "kubernetes-cli"),
+            (Winget, "Kubernetes.kubectl"),
+        ],
+        script: &[
+            "curl -LO https://dl.k8s.io/release/$(curl -L -s https://dl.k8s.io/release/stable.txt)/bin/linux/amd64/kubectl",
+            "sudo install -o root -g root -m 0755 kubectl /usr/local/bin/kubectl",
+        ],
+        host_scripts: &[],
+        run: &["kubectl version --client"],
+    },
+    Example {
+        name: "helm",
+        description: "Kubernetes package manager",
+        category: "DevOps Tools",
+        binary: None,
+        packages: &[
+            (Pacman, "helm"),
+            (Zypper, "helm"),
+            (Brew, "helm"),
+            (Winget, "Helm.Helm"),
+        ],
+        script: &[
+            "curl -fsSL https://raw.githubusercontent.com/helm/helm/main/scripts/get-helm-3 | bash",
+        ],
+        host_scripts: &[],
+        run: &["helm version"],
+    },
+    Example {
+        name: "terraform",
+        description: "Infrastructure as Code tool",
+        category: "DevOps Tools",
+        binary: None,
+        packages: &[
+            (Brew, "hashicorp/tap/terraform"),
+            (Winget, "Hashicorp.Terraform"),
+        ],
+        script: &[
+            "wget https://releases.hashicorp.com/terraform/1.5.0/terraform_1.5.0_linux_amd64.zip",
+            "unzip terraform_1.5.0_linux_amd64.zip",
+            "sudo mv terraform /usr/local/bin/",
+        ],
+        host_scripts: &[],
+        run: &["terraform version"],
+    },
+];
 
-    println!("  {}", "Go:".green());
-    println!("    tkit add golang");
-    println!("    Description: Go programming language");
-    println!(
-        "    Install: wget https://golang.org/dl/go1.21.0.linux-amd64.tar.gz && sudo tar -C /usr/local -xzf go1.21.0.linux-amd64.tar.gz"
-    );
-    println!("    Run: go version");
-    println!();
+pub fn find_example(name: &str) -> Option<&'static Example> {
+    EXAMPLES.iter().find(|example| example.name == name)
+}
 
-    println!("{}", "Utilities:".cyan().bold());
+/// An example found already installed on this machine, with how it was
+/// recognized.
+pub struct Found {
+    pub example: &'static Example,
+    pub how: String,
+}
 
-    println!("  {}", "Curl Example:".green());
-    println!("    tkit add curl-test");
-    println!("    Description: Test HTTP requests with curl");
-    println!("    Run: curl -s https://httpbin.org/json");
-    println!();
+/// The examples whose package the host's package manager lists as
+/// installed, or whose binary is on PATH. Examples that only run commands
+/// are left out, as there is nothing to find.
+pub fn find_installed(host: Option<PackageManager>) -> Vec<Found> {
+    let packages = host
+        .and_then(|manager| manager.installed_packages().ok())
+        .unwrap_or_default();
+    EXAMPLES
+        .iter()
+        .filter(|example| {
+            !example.packages.is_empty()
+                || !example.script.is_empty()
+                || !example.host_scripts.is_empty()
+        })
+        .filter(|example| example.available_on(host))
+        .filter_map(|example| {
+            if let Some((manager, package)) = example.package_for(host)
+                // Brew lists tap formulae without the tap
+                && packages.contains(package.rsplit('/').next().unwrap_or(package))
+            {
+                return Some(Found {
+                    example,
+                    how: format!("{} package {}", manager, package),
+                });
+            }
+            let binary = example.binary.unwrap_or(example.name);
+            which::which(binary).ok().map(|path| Found {
+                example,
+                how: path.display().to_string(),
+            })
+        })
+        .collect()
+}
 
-    println!("  {}", "System Info:".green());
-    println!("    tkit add sysinfo");
-    println!("    Description: Show system information");
-    println!("    Run: uname -a && df -h && free -h");
-    println!();
+/// A vetted set of examples for one kind of work, offered by `tkit init`.
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub tools: &'static [&'static str],
+}
 
-    println!("{}", "Web Development:".cyan().bold());
+impl Preset {
+    /// The preset's tools with the recipe for `host`, leaving out those
+    /// with none for this platform.
+    pub fn tools_for(&self, host: Option<PackageManager>) -> Vec<ToolConfig> {
+        self.tools
+            .iter()
+            .filter_map(|name| find_example(name))
+            .filter(|example| example.available_on(host))
+            .map(|example| example.tool_for(host))
+            .collect()
+    }
+}
 
-    println!("  {}", "Node.js:".green());
-    println!("    tkit add nodejs");
-    println!("    Description: Node.js runtime");
-    println!(
-        "    Install: curl -fsSL https://deb.nodesource.com/setup_lts.x | sudo -E bash - && sudo apt-get install -y nodejs"
-    );
-    println!("    Run: node --version && npm --version");
-    println!();
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "web-dev",
+        description: "Web development",
+        tools: &["git", "node", "docker", "nginx", "jq", "vscode"],
+    },
+    Preset {
+        name: "devops",
+        description: "Containers, Kubernetes and infrastructure",
+        tools: &["git", "docker", "kubectl", "helm", "terraform", "jq"],
+    },
+    Preset {
+        name: "data-science",
+        description: "Python, notebooks and data wrangling",
+        tools: &["git", "python", "jupyter", "sqlite", "jq", "vscode"],
+    },
+    Preset {
+        name: "rust-dev",
+        description: "Rust development",
+        tools: &["git", "rust", "ripgrep", "vscode"],
+    },
+];
 
-    println!("  {}", "Nginx:".green());
-    println!("    tkit add nginx");
-    println!("    Description: Web server");
-    println!("    Install: sudo apt-get update && sudo apt-get install -y nginx");
-    println!("    Run: nginx -v");
-    println!();
+pub fn find_preset(name: &str) -> Result<&'static Preset> {
+    PRESETS
+        .iter()
+        .find(|preset| preset.name == name)
+        .ok_or_else(|| {
+            let names: Vec<&str> = PRESETS.iter().map(|preset| preset.name).collect();
+            anyhow!(
+                "Unknown preset '{}'. Available presets: {}",
+                name,
+                names.join(", ")
+            )
+        })
+}
 
-    println!("{}", "DevOps Tools:".cyan().bold());
+fn require_example(name: &str) -> Result<&'static Example> {
+    find_example(name).ok_or_else(|| {
+        anyhow!(
+            "Example '{}' not found. Run 'tkit examples --list' to see the available examples.",
+            name
+        )
+    })
+}
 
-    println!("  {}", "Kubernetes kubectl:".green());
-    println!("    tkit add kubectl");
-    println!("    Description: Kubernetes command-line tool");
-    println!(
-        "    Install: curl -LO https://dl.k8s.io/release/$(curl -L -s https://dl.k8s.io/release/stable.txt)/bin/linux/amd64/kubectl && sudo install -o root -g root -m 0755 kubectl /usr/local/bin/kubectl"
-    );
-    println!("    Run: kubectl version --client");
+pub fn list_examples() -> Result<()> {
+    let host = PackageManager::detect_host();
+
+    println!("{}", "Available examples:".blue().bold());
+    for example in EXAMPLES {
+        let unavailable = if example.available_on(host) {
+            String::new()
+        } else {
+            " (not available on this platform)".dimmed().to_string()
+        };
+        println!(
+            "  {} - {}{}",
+            example.name.bold(),
+            example.description,
+            unavailable
+        );
+    }
     println!();
+    println!("  Use 'tkit examples --add <name>' to add one to your configuration");
+    Ok(())
+}
+
+pub fn show_example(name: &str) -> Result<()> {
+    let example = require_example(name)?;
+    let tool = example.tool_for(PackageManager::detect_host());
+
+    println!("{}", format!("Example '{}':", name).blue().bold());
+    print_tool_definition(&tool)?;
+    Ok(())
+}
+
+pub async fn add_example(name: &str) -> Result<()> {
+    let example = require_example(name)?;
+    let host = PackageManager::detect_host();
+    if !example.available_on(host) {
+        return Err(anyhow!(
+            "Example '{}' has no recipe for this platform.",
+            name
+        ));
+    }
+
+    let mut config = Config::load()?;
+    if config.tools.contains_key(name) {
+        println!("{}", format!("Tool '{}' already exists.", name).yellow());
+        return Ok(());
+    }
+
+    config.add_tool(name, example.tool_for(host))?;
+    config.save_journaled(&format!("add example {}", name))?;
+
+    // Auto-sync if enabled
+    auto_sync_if_enabled(&config, "add", name).await?;
 
-    println!("  {}", "Terraform:".green());
-    println!("    tkit add terraform");
-    println!("    Description: Infrastructure as Code tool");
     println!(
-        "    Install: wget https://releases.hashicorp.com/terraform/1.5.0/terraform_1.5.0_linux_amd64.zip && unzip terraform_1.5.0_linux_amd64.zip && sudo mv terraform /usr/local/bin/"
+        "{}",
+        format!("✓ Example '{}' added to your configuration!", name)
+            .green()
+            .bold()
     );
-    println!("    Run: terraform version");
+    println!("  Run 'tkit install {}' to install it", name);
+    Ok(())
+}
+
+fn join_steps(steps: &[Step]) -> String {
+    let commands: Vec<&str> = steps.iter().map(|step| step.command.as_str()).collect();
+    commands.join(" && ")
+}
+
+pub fn show_examples() -> Result<()> {
+    let host = PackageManager::detect_host();
+
+    println!("{}", "Tool Configuration Examples:".blue().bold());
+    match host {
+        Some(manager) => println!("  Showing recipes for {}", manager.to_string().bold()),
+        None => println!(
+            "  {}",
+            "No supported package manager detected; showing install scripts.".yellow()
+        ),
+    }
     println!();
 
+    let mut category = "";
+    for example in EXAMPLES.iter().filter(|e| e.available_on(host)) {
+        if example.category != category {
+            category = example.category;
+            println!("{}", format!("{}:", category).cyan().bold());
+        }
+
+        let tool = example.tool_for(host);
+        println!("  {}", format!("{}:", example.name).green());
+        println!("    tkit add {}", example.name);
+        println!("    Description: {}", example.description);
+        if let Some(spec) = &tool.package {
+            println!("    Package: {} ({})", spec.name, spec.manager);
+        }
+        let install = tool.commands_for(ToolAction::Install);
+        if !install.is_empty() {
+            println!("    Install: {}", join_steps(&install));
+        }
+        println!(
+            "    Run: {}",
+            join_steps(tool.run_commands.default_commands())
+        );
+        println!();
+    }
+
     println!("{}", "Usage:".yellow().bold());
-    println!("  Copy any example above and run the commands to add tools to your configuration.");
-    println!("  You can modify the install, remove, update, and run commands as needed.");
+    println!("  Run 'tkit examples --add <name>' to add an example to your configuration.");
+    println!("  Use 'tkit examples --show <name>' to see the exact definition first.");
     println!("  Run 'tkit list' to see your configured tools.");
 
     Ok(())
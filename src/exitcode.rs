@@ -0,0 +1,95 @@
+use std::fmt;
+
+/// Classes of failure that end tkit with their own exit code, so scripts
+/// wrapping it can react to each differently. Anything unclassified exits
+/// with 1, and clap's usage errors with 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitKind {
+    /// The config file (or a machine overlay) can't be read or is invalid.
+    Config,
+    /// A named tool isn't configured.
+    ToolNotFound,
+    /// A tool's command failed or timed out.
+    CommandFailed,
+    /// Pushing, pulling or another network request failed.
+    Sync,
+    /// The user answered no to a confirmation.
+    Aborted,
+}
+
+impl ExitKind {
+    pub fn code(self) -> i32 {
+        match self {
+            ExitKind::Config => 3,
+            ExitKind::ToolNotFound => 4,
+            ExitKind::CommandFailed => 5,
+            ExitKind::Sync => 6,
+            ExitKind::Aborted => 130,
+        }
+    }
+
+    /// An error of this kind that reads as `message`.
+    pub fn error(self, message: impl fmt::Display) -> anyhow::Error {
+        Failure {
+            kind: self,
+            message: message.to_string(),
+        }
+        .into()
+    }
+
+    /// The kind `err` was tagged with, or `Sync` for a failed HTTP request
+    /// anywhere in its chain.
+    pub fn of(err: &anyhow::Error) -> Option<ExitKind> {
+        if let Some(failure) = err.chain().find_map(|e| e.downcast_ref::<Failure>()) {
+            return Some(failure.kind);
+        }
+        err.chain()
+            .any(|e| e.is::<reqwest::Error>())
+            .then_some(ExitKind::Sync)
+    }
+}
+
+/// An error tagged with its [`ExitKind`].
+#[derive(Debug)]
+pub struct Failure {
+    pub kind: ExitKind,
+    pub message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// The error for a tool name that isn't in the config.
+pub fn tool_not_found(name: &str) -> anyhow::Error {
+    ExitKind::ToolNotFound.error(format!("Tool '{}' not found.", name))
+}
+
+/// The error for a declined confirmation.
+pub fn aborted() -> anyhow::Error {
+    ExitKind::Aborted.error("Aborted.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_kind_of() {
+        let err = tool_not_found("jq");
+        assert_eq!(err.to_string(), "Tool 'jq' not found.");
+        assert_eq!(ExitKind::of(&err), Some(ExitKind::ToolNotFound));
+
+        let err = ExitKind::Config
+            .error("bad yaml")
+            .context("Could not load the config");
+        assert_eq!(ExitKind::of(&err), Some(ExitKind::Config));
+
+        assert_eq!(ExitKind::of(&anyhow::anyhow!("something else")), None);
+        assert_eq!(ExitKind::of(&aborted()).map(ExitKind::code), Some(130));
+    }
+}
@@ -0,0 +1,194 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How a managed file is placed at its target.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileMode {
+    #[default]
+    Link,
+    Copy,
+}
+
+/// A config file that travels with a tool, e.g. `.tmux.conf` for tmux.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct ManagedFile {
+    /// Path relative to the files directory (or absolute), e.g. `tmux/tmux.conf`.
+    pub source: String,
+    /// Where the file belongs, e.g. `~/.tmux.conf`.
+    pub target: String,
+    #[serde(default, skip_serializing_if = "is_link")]
+    pub mode: FileMode,
+}
+
+fn is_link(mode: &FileMode) -> bool {
+    *mode == FileMode::Link
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileState {
+    /// The target is the tkit-managed link or an identical copy.
+    UpToDate,
+    Missing,
+    /// The target exists but is something else; applying needs `--force`.
+    Differs,
+    SourceMissing,
+}
+
+impl fmt::Display for FileState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FileState::UpToDate => "up to date",
+            FileState::Missing => "missing",
+            FileState::Differs => "differs",
+            FileState::SourceMissing => "source missing",
+        })
+    }
+}
+
+/// Expands a leading `~` to the home directory.
+pub fn expand_tilde(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+impl ManagedFile {
+    pub fn source_path(&self, files_dir: &Path) -> PathBuf {
+        files_dir.join(expand_tilde(&self.source))
+    }
+
+    pub fn target_path(&self) -> PathBuf {
+        expand_tilde(&self.target)
+    }
+
+    pub fn state(&self, files_dir: &Path) -> FileState {
+        let source = self.source_path(files_dir);
+        let target = self.target_path();
+        if !source.exists() {
+            return FileState::SourceMissing;
+        }
+        if fs::symlink_metadata(&target).is_err() {
+            return FileState::Missing;
+        }
+
+        let up_to_date = match self.mode {
+            FileMode::Link => fs::read_link(&target).is_ok_and(|link| link == source),
+            FileMode::Copy => match (fs::read(&source), fs::read(&target)) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => false,
+            },
+        };
+        if up_to_date {
+            FileState::UpToDate
+        } else {
+            FileState::Differs
+        }
+    }
+
+    /// Links or copies the source to the target. A differing target is only
+    /// replaced with `force`, after being moved aside to `<target>.tkit-backup`.
+    pub fn apply(&self, files_dir: &Path, force: bool) -> Result<FileState> {
+        let state = self.state(files_dir);
+        let source = self.source_path(files_dir);
+        let target = self.target_path();
+        match state {
+            FileState::UpToDate => return Ok(state),
+            FileState::SourceMissing => {
+                return Err(anyhow!("Source {} does not exist", source.display()));
+            }
+            FileState::Differs if !force => {
+                return Err(anyhow!(
+                    "{} already exists and differs; use --force to replace it",
+                    target.display()
+                ));
+            }
+            FileState::Differs => {
+                let mut backup = target.clone().into_os_string();
+                backup.push(".tkit-backup");
+                fs::rename(&target, backup)?;
+            }
+            FileState::Missing => {}
+        }
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match self.mode {
+            FileMode::Link => symlink(&source, &target)?,
+            FileMode::Copy => {
+                fs::copy(&source, &target)?;
+            }
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, target: &Path) -> Result<()> {
+    Ok(std::os::unix::fs::symlink(source, target)?)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, target: &Path) -> Result<()> {
+    Ok(std::os::windows::fs::symlink_file(source, target)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn managed(dir: &TempDir, mode: FileMode) -> ManagedFile {
+        ManagedFile {
+            source: "tmux/tmux.conf".to_string(),
+            target: dir.path().join("home/.tmux.conf").display().to_string(),
+            mode,
+        }
+    }
+
+    #[test]
+    fn test_link_apply_and_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let files_dir = temp_dir.path().join("files");
+        let file = managed(&temp_dir, FileMode::Link);
+        assert_eq!(file.state(&files_dir), FileState::SourceMissing);
+
+        fs::create_dir_all(files_dir.join("tmux")).unwrap();
+        fs::write(files_dir.join("tmux/tmux.conf"), "set -g mouse on").unwrap();
+        assert_eq!(file.state(&files_dir), FileState::Missing);
+
+        file.apply(&files_dir, false).unwrap();
+        assert_eq!(file.state(&files_dir), FileState::UpToDate);
+        assert_eq!(
+            fs::read_to_string(file.target_path()).unwrap(),
+            "set -g mouse on"
+        );
+    }
+
+    #[test]
+    fn test_differing_target_needs_force() {
+        let temp_dir = TempDir::new().unwrap();
+        let files_dir = temp_dir.path().join("files");
+        fs::create_dir_all(files_dir.join("tmux")).unwrap();
+        fs::write(files_dir.join("tmux/tmux.conf"), "new").unwrap();
+        let file = managed(&temp_dir, FileMode::Copy);
+        fs::create_dir_all(temp_dir.path().join("home")).unwrap();
+        fs::write(file.target_path(), "old").unwrap();
+
+        assert_eq!(file.state(&files_dir), FileState::Differs);
+        assert!(file.apply(&files_dir, false).is_err());
+        file.apply(&files_dir, true).unwrap();
+        assert_eq!(fs::read_to_string(file.target_path()).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("home/.tmux.conf.tkit-backup")).unwrap(),
+            "old"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::converge::{self, Drift};
+use crate::{Config, DesiredState, Lockfile, ToolConfig};
+
+/// What a machine reported about itself the last time it pushed the config,
+/// shown by `tkit sync status --machines`.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct MachineReport {
+    /// RFC 3339 time of the push.
+    pub last_sync: String,
+    pub os: String,
+    pub arch: String,
+    /// Tools installed there.
+    pub installed: usize,
+    /// Tools the config wants present that aren't installed there.
+    pub missing: usize,
+    /// Tools installed or removed there outside tkit.
+    pub drift: usize,
+}
+
+impl MachineReport {
+    /// Describes this machine. Presence is judged with `observe`; versions
+    /// are left alone, since asking package managers would slow every push.
+    pub fn describe(
+        config: &Config,
+        now: &str,
+        observe: impl FnMut(&ToolConfig) -> Option<bool>,
+    ) -> Self {
+        let tools = config.tools.values().filter(|tool| !tool.disabled);
+        let drift = converge::detect_drift(config, &Lockfile::default(), observe, |_| None);
+        MachineReport {
+            last_sync: now.to_string(),
+            os: crate::os().to_string(),
+            arch: crate::arch().to_string(),
+            installed: tools.clone().filter(|tool| tool.installed).count(),
+            missing: tools
+                .filter(|tool| tool.desired_state == DesiredState::Present && !tool.installed)
+                .count(),
+            drift: drift
+                .iter()
+                .filter(|d| matches!(d.drift, Drift::Appeared | Drift::Vanished))
+                .count(),
+        }
+    }
+}
+
+/// Adds the reports in `other` that are newer than (or missing from) `ours`,
+/// so a push doesn't drop machines that pushed since this one last pulled.
+pub fn merge_reports(
+    ours: &mut BTreeMap<String, MachineReport>,
+    other: BTreeMap<String, MachineReport>,
+) {
+    for (name, report) in other {
+        let newer = ours
+            .get(&name)
+            .is_none_or(|existing| existing.last_sync < report.last_sync);
+        if newer {
+            ours.insert(name, report);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(last_sync: &str, installed: usize) -> MachineReport {
+        MachineReport {
+            last_sync: last_sync.to_string(),
+            installed,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_describe_counts_tools() {
+        let mut config = Config::new();
+        for (name, installed, desired) in [
+            ("jq", true, DesiredState::Present),
+            ("rg", false, DesiredState::Present),
+            ("fd", false, DesiredState::Absent),
+        ] {
+            config.tools.insert(
+                name.to_string(),
+                ToolConfig {
+                    name: name.to_string(),
+                    installed,
+                    desired_state: desired,
+                    ..Default::default()
+                },
+            );
+        }
+
+        // fd turned up without tkit installing it
+        let report = MachineReport::describe(&config, "2026-01-01T00:00:00+00:00", |tool| {
+            Some(tool.name != "rg")
+        });
+        assert_eq!((report.installed, report.missing, report.drift), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_merge_keeps_newest() {
+        let mut ours = BTreeMap::from([
+            ("laptop".to_string(), report("2026-01-02T00:00:00+00:00", 3)),
+            ("server".to_string(), report("2026-01-01T00:00:00+00:00", 5)),
+        ]);
+        let theirs = BTreeMap::from([
+            ("laptop".to_string(), report("2026-01-01T00:00:00+00:00", 1)),
+            ("server".to_string(), report("2026-01-03T00:00:00+00:00", 6)),
+            (
+                "desktop".to_string(),
+                report("2026-01-01T00:00:00+00:00", 2),
+            ),
+        ]);
+        merge_reports(&mut ours, theirs);
+        assert_eq!(ours["laptop"].installed, 3);
+        assert_eq!(ours["server"].installed, 6);
+        assert_eq!(ours.len(), 3);
+    }
+}
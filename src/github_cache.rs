@@ -0,0 +1,110 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Files last seen in GitHub sync repositories, keyed by their contents API
+/// URL, so requests can be conditional (`If-None-Match`) and an unchanged
+/// file is answered with a 304 instead of being downloaded again.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct GitHubCache {
+    #[serde(default)]
+    pub files: BTreeMap<String, CachedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct CachedFile {
+    /// ETag of the last GET; unknown right after a push.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// Blob SHA, needed to overwrite the file.
+    pub sha: String,
+    /// Decoded file content.
+    pub content: String,
+}
+
+impl GitHubCache {
+    pub fn load() -> Result<Self> {
+        Self::load_from_path(&get_github_cache_path()?)
+    }
+
+    /// Loads the cache; a missing or unreadable one is empty, since it can
+    /// always be rebuilt from GitHub.
+    pub fn load_from_path(path: &PathBuf) -> Result<Self> {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Ok(GitHubCache::default());
+        };
+        Ok(serde_yaml::from_str(&content).unwrap_or_default())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to_path(&get_github_cache_path()?)
+    }
+
+    pub fn save_to_path(&self, path: &PathBuf) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, url: &str) -> Option<&CachedFile> {
+        self.files.get(url)
+    }
+
+    pub fn insert(&mut self, url: &str, file: CachedFile) {
+        self.files.insert(url.to_string(), file);
+    }
+
+    pub fn remove(&mut self, url: &str) {
+        self.files.remove(url);
+    }
+}
+
+pub fn get_github_cache_path() -> Result<PathBuf> {
+    let cache_dir =
+        dirs::cache_dir().ok_or_else(|| anyhow!("Could not determine cache directory"))?;
+    Ok(cache_dir.join("tkit").join("github.yaml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("github.yaml");
+        let url = "https://api.github.com/repos/me/dotfiles/contents/tkit-config.yaml";
+
+        let mut cache = GitHubCache::default();
+        cache.insert(
+            url,
+            CachedFile {
+                etag: Some("\"abc\"".to_string()),
+                sha: "123".to_string(),
+                content: "tools: {}\n".to_string(),
+            },
+        );
+        cache.save_to_path(&path).unwrap();
+
+        let loaded = GitHubCache::load_from_path(&path).unwrap();
+        assert_eq!(loaded, cache);
+        assert_eq!(loaded.get(url).unwrap().sha, "123");
+    }
+
+    #[test]
+    fn test_corrupt_cache_is_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("github.yaml");
+        fs::write(&path, "files: [not, a, map]").unwrap();
+        assert_eq!(
+            GitHubCache::load_from_path(&path).unwrap(),
+            GitHubCache::default()
+        );
+    }
+}
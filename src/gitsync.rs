@@ -0,0 +1,280 @@
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use crate::layout;
+
+/// File the config is kept in, the same as in GitHub API sync.
+pub const CONFIG_FILE: &str = "tkit-config.yaml";
+
+/// A repository reached with plain `git` (normally over SSH with a deploy
+/// key) instead of the GitHub API, for setups without a personal access token.
+/// It is worked on through a checkout in tkit's cache directory.
+pub struct GitRemote<'a> {
+    pub url: &'a str,
+    /// Private key used for this repository only; ssh's defaults when unset.
+    pub ssh_key: Option<&'a str>,
+    /// Branch the config is kept on, started from the default branch when
+    /// the repository doesn't have it yet; the default branch when unset.
+    pub branch: Option<&'a str>,
+}
+
+impl GitRemote<'_> {
+    /// Where the repository is checked out, one directory per URL.
+    pub fn checkout_dir(&self) -> Result<PathBuf> {
+        let cache_dir =
+            dirs::cache_dir().ok_or_else(|| anyhow!("Could not determine cache directory"))?;
+        let mut hasher = DefaultHasher::new();
+        self.url.hash(&mut hasher);
+        Ok(cache_dir
+            .join("tkit")
+            .join("git")
+            .join(format!("{:016x}", hasher.finish())))
+    }
+
+    fn git(&self, dir: Option<&Path>, args: &[&str]) -> Result<Output> {
+        let mut cmd = Command::new("git");
+        if let Some(dir) = dir {
+            cmd.current_dir(dir);
+        }
+        if let Some(key) = self.ssh_key {
+            // Only the deploy key, so an agent's other keys don't pick a different account.
+            cmd.env(
+                "GIT_SSH_COMMAND",
+                format!(
+                    "ssh -i '{}' -o IdentitiesOnly=yes -o StrictHostKeyChecking=accept-new",
+                    expand_home(key).display()
+                ),
+            );
+        }
+        let output = cmd
+            .args(args)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .map_err(|e| anyhow!("Could not run git: {}", e))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "git {} failed: {}",
+                args.first().copied().unwrap_or_default(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(output)
+    }
+
+    /// Checks that the repository can be reached with the key.
+    pub fn check_access(&self) -> Result<()> {
+        self.git(None, &["ls-remote", "--heads", self.url])?;
+        Ok(())
+    }
+
+    /// Brings the checkout up to date with the remote, cloning it first if needed.
+    fn update_checkout(&self) -> Result<PathBuf> {
+        let dir = self.checkout_dir()?;
+        if !dir.join(".git").exists() {
+            if let Some(parent) = dir.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let _ = fs::remove_dir_all(&dir);
+            self.git(
+                None,
+                &["clone", "--quiet", self.url, &dir.to_string_lossy()],
+            )?;
+        } else {
+            self.git(Some(&dir), &["fetch", "--quiet", "origin"])?;
+        }
+
+        if let Some(branch) = self.branch {
+            self.switch_branch(&dir, branch)?;
+        }
+        // An empty repository has no upstream to reset to yet.
+        if self
+            .git(Some(&dir), &["rev-parse", "--verify", "--quiet", "@{u}"])
+            .is_ok()
+        {
+            self.git(Some(&dir), &["reset", "--quiet", "--hard", "@{u}"])?;
+        }
+        Ok(dir)
+    }
+
+    /// Checks out `branch`, tracking the remote's when it has one and
+    /// otherwise starting it where the checkout is, for the push to create.
+    fn switch_branch(&self, dir: &Path, branch: &str) -> Result<()> {
+        let upstream = format!("origin/{}", branch);
+        if self
+            .git(Some(dir), &["rev-parse", "--verify", "--quiet", &upstream])
+            .is_ok()
+        {
+            self.git(
+                Some(dir),
+                &["checkout", "--quiet", "-B", branch, "--track", &upstream],
+            )?;
+            return Ok(());
+        }
+
+        let current = self.git(Some(dir), &["symbolic-ref", "--short", "HEAD"])?;
+        if String::from_utf8_lossy(&current.stdout).trim() != branch {
+            self.git(Some(dir), &["checkout", "--quiet", "-b", branch])?;
+        }
+        Ok(())
+    }
+
+    /// The config as last pushed, put back together when it was pushed in
+    /// the split layout, or `None` when the repository doesn't have one.
+    pub fn fetch(&self) -> Result<Option<String>> {
+        let dir = self.update_checkout()?;
+        let path = dir.join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let index = fs::read_to_string(path)?;
+        if !layout::is_split(&index) {
+            return Ok(Some(index));
+        }
+
+        let mut tools = BTreeMap::new();
+        for (path, name) in tool_files(&dir)? {
+            tools.insert(name, fs::read_to_string(dir.join(path))?);
+        }
+        Ok(Some(layout::join(&index, &tools)?))
+    }
+
+    /// Commits `files` (contents by repository path) and pushes them, removing
+    /// tool files that are no longer among them. Returns false when the
+    /// repository already had exactly these files.
+    pub fn push(&self, files: &BTreeMap<String, String>, message: &str) -> Result<bool> {
+        let dir = self.update_checkout()?;
+        for (path, _) in tool_files(&dir)? {
+            if !files.contains_key(&path) {
+                self.git(
+                    Some(&dir),
+                    &["rm", "--quiet", "--cached", "--ignore-unmatch", &path],
+                )?;
+                fs::remove_file(dir.join(&path))?;
+            }
+        }
+        for (path, content) in files {
+            let path = dir.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)?;
+        }
+        let mut add = vec!["add", "--"];
+        add.extend(files.keys().map(String::as_str));
+        self.git(Some(&dir), &add)?;
+        if self
+            .git(Some(&dir), &["diff", "--cached", "--quiet"])
+            .is_ok()
+        {
+            return Ok(false);
+        }
+
+        let mut commit = Vec::new();
+        // Machines set up only for tkit may have no git identity.
+        if self.git(Some(&dir), &["config", "user.email"]).is_err() {
+            commit.extend(["-c", "user.name=tkit", "-c", "user.email=tkit@localhost"]);
+        }
+        commit.extend(["commit", "--quiet", "-m", message]);
+        self.git(Some(&dir), &commit)?;
+        self.git(Some(&dir), &["push", "--quiet", "-u", "origin", "HEAD"])?;
+        Ok(true)
+    }
+}
+
+/// Tool files in a checkout, as (repository path, tool name).
+fn tool_files(dir: &Path) -> Result<Vec<(String, String)>> {
+    let tools_dir = dir.join(layout::TOOLS_DIR);
+    if !tools_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(tools_dir)? {
+        let path = format!(
+            "{}/{}",
+            layout::TOOLS_DIR,
+            entry?.file_name().to_string_lossy()
+        );
+        if let Some(name) = layout::tool_name(&path) {
+            files.push((path.clone(), name.to_string()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Creates a passphrase-less ed25519 key pair for use as a deploy key and
+/// returns the public key.
+pub fn generate_deploy_key(path: &Path) -> Result<String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let output = Command::new("ssh-keygen")
+        .args([
+            "-q",
+            "-t",
+            "ed25519",
+            "-N",
+            "",
+            "-C",
+            "tkit deploy key",
+            "-f",
+        ])
+        .arg(path)
+        .output()
+        .map_err(|e| anyhow!("Could not run ssh-keygen: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ssh-keygen failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    public_key(path)
+}
+
+pub fn public_key(path: &Path) -> Result<String> {
+    let mut public = path.as_os_str().to_owned();
+    public.push(".pub");
+    Ok(fs::read_to_string(PathBuf::from(public))?
+        .trim()
+        .to_string())
+}
+
+/// `owner/repo` of a GitHub SSH or HTTPS URL, for linking to its deploy key settings.
+pub fn github_repo(url: &str) -> Option<String> {
+    let rest = url
+        .strip_prefix("git@github.com:")
+        .or_else(|| url.strip_prefix("ssh://git@github.com/"))
+        .or_else(|| url.strip_prefix("https://github.com/"))?;
+    let repo = rest.trim_end_matches('/').trim_end_matches(".git");
+    (repo.split('/').count() == 2).then(|| repo.to_string())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_repo() {
+        assert_eq!(
+            github_repo("git@github.com:me/dotfiles.git").as_deref(),
+            Some("me/dotfiles")
+        );
+        assert_eq!(
+            github_repo("ssh://git@github.com/me/dotfiles").as_deref(),
+            Some("me/dotfiles")
+        );
+        assert_eq!(github_repo("git@gitlab.com:me/dotfiles.git"), None);
+    }
+}
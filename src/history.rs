@@ -0,0 +1,302 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::get_config_path;
+
+/// One thing tkit did on this machine, appended to `history.jsonl` so
+/// `tkit stats` can report on it later.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HistoryEvent {
+    /// RFC 3339 time the action finished.
+    pub at: String,
+    /// `install`, `update`, `remove`, `run`, `sync push` or `sync pull`.
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    pub ok: bool,
+    /// How long the action took.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seconds: Option<f64>,
+}
+
+impl HistoryEvent {
+    pub fn new(action: &str, tool: Option<&str>, ok: bool, seconds: Option<f64>) -> Self {
+        HistoryEvent {
+            at: Utc::now().to_rfc3339(),
+            action: action.to_string(),
+            tool: tool.map(str::to_string),
+            ok,
+            seconds,
+        }
+    }
+
+    fn time(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.at)
+            .ok()
+            .map(|at| at.with_timezone(&Utc))
+    }
+}
+
+pub fn get_history_path() -> Result<PathBuf> {
+    Ok(get_config_path()?.with_file_name("history.jsonl"))
+}
+
+/// Adds an event to the end of the log, one JSON object per line so writes
+/// never have to read or rewrite what is already there.
+pub fn append(path: &Path, event: &HistoryEvent) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(event)?)?;
+    Ok(())
+}
+
+/// Reads the log; a missing file is empty and lines that don't parse (a
+/// write cut short, say) are skipped.
+pub fn load(path: &Path) -> Result<Vec<HistoryEvent>> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Ok(Vec::new());
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Per-tool totals built from the log.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ToolSummary {
+    pub runs: usize,
+    pub installs: usize,
+    /// Mean of the successful installs that were timed.
+    pub average_install: Option<f64>,
+    pub attempts: usize,
+    pub failures: usize,
+}
+
+impl ToolSummary {
+    pub fn failure_rate(&self) -> f64 {
+        if self.attempts == 0 {
+            0.0
+        } else {
+            self.failures as f64 / self.attempts as f64
+        }
+    }
+}
+
+/// What `tkit stats` reports from the history log.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Summary {
+    pub tools: BTreeMap<String, ToolSummary>,
+    /// Events per day of the week, Monday first.
+    pub weekdays: [usize; 7],
+    pub pushes: usize,
+    pub pulls: usize,
+    /// Days between the first and last event.
+    pub span_days: f64,
+}
+
+impl Summary {
+    /// Summarizes the events at or after `since`, or all of them.
+    pub fn from_events(events: &[HistoryEvent], since: Option<DateTime<Utc>>) -> Self {
+        let mut summary = Summary::default();
+        let mut install_seconds: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+        let mut first: Option<DateTime<Utc>> = None;
+        let mut last: Option<DateTime<Utc>> = None;
+
+        for event in events {
+            let Some(at) = event.time() else { continue };
+            if since.is_some_and(|since| at < since) {
+                continue;
+            }
+            first = Some(first.map_or(at, |first| first.min(at)));
+            last = Some(last.map_or(at, |last| last.max(at)));
+            summary.weekdays[at.weekday().num_days_from_monday() as usize] += 1;
+
+            match (event.action.as_str(), event.tool.as_deref()) {
+                ("sync push", _) => summary.pushes += 1,
+                ("sync pull", _) => summary.pulls += 1,
+                (action, Some(tool)) => {
+                    let stats = summary.tools.entry(tool.to_string()).or_default();
+                    stats.attempts += 1;
+                    if !event.ok {
+                        stats.failures += 1;
+                    }
+                    match action {
+                        "run" => stats.runs += 1,
+                        "install" => {
+                            stats.installs += 1;
+                            if event.ok
+                                && let Some(seconds) = event.seconds
+                            {
+                                install_seconds.entry(tool).or_default().push(seconds);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (tool, seconds) in install_seconds {
+            summary.tools.get_mut(tool).unwrap().average_install =
+                Some(seconds.iter().sum::<f64>() / seconds.len() as f64);
+        }
+        if let (Some(first), Some(last)) = (first, last) {
+            summary.span_days = (last - first).num_seconds() as f64 / 86400.0;
+        }
+        summary
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weekdays.iter().all(|count| *count == 0)
+    }
+
+    /// Tools by run count, most-run first, leaving out those never run.
+    pub fn most_run(&self) -> Vec<(&str, usize)> {
+        let mut runs: Vec<(&str, usize)> = self
+            .tools
+            .iter()
+            .filter(|(_, stats)| stats.runs > 0)
+            .map(|(name, stats)| (name.as_str(), stats.runs))
+            .collect();
+        runs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        runs
+    }
+
+    /// Days of the week with their event counts, busiest first.
+    pub fn busiest_days(&self) -> Vec<(Weekday, usize)> {
+        let mut days: Vec<(Weekday, usize)> = self
+            .weekdays
+            .iter()
+            .enumerate()
+            .map(|(i, count)| (Weekday::try_from(i as u8).unwrap(), *count))
+            .collect();
+        days.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        days
+    }
+
+    /// Pushes and pulls per week over the span of the log; `None` when it
+    /// covers less than a day.
+    pub fn syncs_per_week(&self) -> Option<f64> {
+        (self.span_days >= 1.0).then(|| (self.pushes + self.pulls) as f64 * 7.0 / self.span_days)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn event(
+        at: &str,
+        action: &str,
+        tool: Option<&str>,
+        ok: bool,
+        seconds: Option<f64>,
+    ) -> HistoryEvent {
+        HistoryEvent {
+            at: at.to_string(),
+            action: action.to_string(),
+            tool: tool.map(str::to_string),
+            ok,
+            seconds,
+        }
+    }
+
+    #[test]
+    fn test_history_append_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("history.jsonl");
+        assert!(load(&path).unwrap().is_empty());
+
+        let first = HistoryEvent::new("install", Some("jq"), true, Some(2.5));
+        let second = HistoryEvent::new("sync push", None, true, None);
+        append(&path, &first).unwrap();
+        append(&path, &second).unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"at\": \"2026-")
+            .unwrap();
+
+        assert_eq!(load(&path).unwrap(), vec![first, second]);
+    }
+
+    #[test]
+    fn test_history_summary() {
+        let events = vec![
+            // Monday
+            event(
+                "2026-01-05T09:00:00Z",
+                "install",
+                Some("jq"),
+                true,
+                Some(2.0),
+            ),
+            event("2026-01-05T09:05:00Z", "run", Some("jq"), true, Some(0.1)),
+            event(
+                "2026-01-05T10:00:00Z",
+                "install",
+                Some("docker"),
+                false,
+                Some(9.0),
+            ),
+            // Tuesday
+            event(
+                "2026-01-06T09:00:00Z",
+                "install",
+                Some("docker"),
+                true,
+                Some(30.0),
+            ),
+            event("2026-01-06T09:10:00Z", "run", Some("docker"), true, None),
+            event("2026-01-06T09:20:00Z", "run", Some("jq"), true, None),
+            event(
+                "2026-01-06T12:00:00Z",
+                "install",
+                Some("jq"),
+                true,
+                Some(4.0),
+            ),
+            event("2026-01-06T12:30:00Z", "sync push", None, true, None),
+            // A week later, Monday
+            event("2026-01-12T09:00:00Z", "sync pull", None, true, None),
+            event("not a time", "run", Some("jq"), true, None),
+        ];
+
+        let summary = Summary::from_events(&events, None);
+        assert_eq!(summary.most_run(), vec![("jq", 2), ("docker", 1)]);
+        assert_eq!(summary.tools["jq"].average_install, Some(3.0));
+        // The failed attempt's time doesn't count toward the average
+        assert_eq!(summary.tools["docker"].average_install, Some(30.0));
+        assert_eq!(summary.tools["docker"].failures, 1);
+        assert_eq!(summary.tools["docker"].failure_rate(), 1.0 / 3.0);
+        assert_eq!(summary.tools["jq"].failure_rate(), 0.0);
+        assert_eq!(summary.busiest_days()[0], (Weekday::Tue, 5));
+        assert_eq!(summary.busiest_days()[1], (Weekday::Mon, 4));
+        assert_eq!((summary.pushes, summary.pulls), (1, 1));
+        assert_eq!(summary.syncs_per_week(), Some(2.0));
+
+        let since = DateTime::parse_from_rfc3339("2026-01-06T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let recent = Summary::from_events(&events, Some(since));
+        assert_eq!(recent.tools["docker"].failures, 0);
+        assert_eq!(recent.tools["jq"].average_install, Some(4.0));
+        assert!(!recent.is_empty());
+        assert!(Summary::from_events(&[], None).is_empty());
+    }
+}
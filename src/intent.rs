@@ -0,0 +1,207 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{get_config_path, write_atomic};
+
+/// Changes to tools that have started but were not yet saved and synced.
+/// An entry left behind means tkit was killed, or the machine lost power, in
+/// the middle of one; the next run offers to finish it.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct Intents {
+    #[serde(default)]
+    pub pending: Vec<Intent>,
+}
+
+/// One `install`, `update`, `remove` or `apply` of some tools.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Intent {
+    /// The tkit process making the change.
+    pub pid: u32,
+    /// RFC 3339 time the change started.
+    pub started: String,
+    pub action: String,
+    pub tools: Vec<String>,
+    /// Of `tools`, those whose part finished, successfully or not.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub done: Vec<String>,
+}
+
+impl Intent {
+    /// The tools the change did not get to.
+    pub fn remaining(&self) -> Vec<String> {
+        self.tools
+            .iter()
+            .filter(|tool| !self.done.contains(tool))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether the process making the change is gone without finishing it.
+    pub fn interrupted(&self) -> bool {
+        !process_running(self.pid)
+    }
+}
+
+impl Intents {
+    pub fn load_from_path(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Intents::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&content)?)
+    }
+
+    /// Writes the intents, or removes the file once nothing is pending.
+    pub fn save_to_path(&self, path: &Path) -> Result<()> {
+        if self.pending.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+
+        write_atomic(path, &serde_yaml::to_string(self)?)
+    }
+}
+
+pub fn get_intents_path() -> Result<PathBuf> {
+    Ok(get_config_path()?.with_file_name("intents.yaml"))
+}
+
+/// Serializes the read-modify-write of the file between concurrent tasks.
+static LOCK: Mutex<()> = Mutex::new(());
+
+fn update(path: &Path, change: impl FnOnce(&mut Intents)) -> Result<()> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut intents = Intents::load_from_path(path)?;
+    change(&mut intents);
+    intents.save_to_path(path)
+}
+
+/// A change in progress, recorded until this is dropped.
+pub struct Pending {
+    path: PathBuf,
+    started: String,
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        let pid = std::process::id();
+        let _ = update(&self.path, |intents| {
+            intents
+                .pending
+                .retain(|intent| intent.pid != pid || intent.started != self.started);
+        });
+    }
+}
+
+/// Records that `action` is about to change `tools`. The entry stays until
+/// the returned guard is dropped, after the change was saved and synced.
+pub fn begin(path: &Path, action: &str, tools: &[String]) -> Result<Pending> {
+    let started = chrono::Utc::now().to_rfc3339();
+    update(path, |intents| {
+        intents.pending.push(Intent {
+            pid: std::process::id(),
+            started: started.clone(),
+            action: action.to_string(),
+            tools: tools.to_vec(),
+            done: Vec::new(),
+        })
+    })?;
+    Ok(Pending {
+        path: path.to_path_buf(),
+        started,
+    })
+}
+
+/// Notes that this process finished its part for `tool`.
+pub fn mark_done(path: &Path, tool: &str) -> Result<()> {
+    let pid = std::process::id();
+    update(path, |intents| {
+        for intent in &mut intents.pending {
+            if intent.pid == pid
+                && intent.tools.iter().any(|t| t == tool)
+                && !intent.done.iter().any(|t| t == tool)
+            {
+                intent.done.push(tool.to_string());
+            }
+        }
+    })
+}
+
+/// Changes whose process is gone without finishing them.
+pub fn interrupted(path: &Path) -> Result<Vec<Intent>> {
+    let intents = Intents::load_from_path(path)?;
+    Ok(intents
+        .pending
+        .into_iter()
+        .filter(Intent::interrupted)
+        .collect())
+}
+
+/// Forgets an interrupted change, once it was dealt with.
+pub fn discard(path: &Path, intent: &Intent) -> Result<()> {
+    update(path, |intents| {
+        intents.pending.retain(|other| other != intent)
+    })
+}
+
+#[cfg(unix)]
+fn process_running(pid: u32) -> bool {
+    pid == std::process::id()
+        || std::process::Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+}
+
+#[cfg(not(unix))]
+fn process_running(pid: u32) -> bool {
+    pid == std::process::id()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_intent_lifecycle() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("intents.yaml");
+        let tools = vec!["jq".to_string(), "fd".to_string()];
+
+        let pending = begin(&path, "install", &tools).unwrap();
+        mark_done(&path, "jq").unwrap();
+        let intents = Intents::load_from_path(&path).unwrap();
+        assert_eq!(intents.pending[0].remaining(), vec!["fd"]);
+        // This process is still running, so nothing was interrupted
+        assert!(interrupted(&path).unwrap().is_empty());
+        drop(pending);
+        assert!(!path.exists());
+
+        // An entry left behind by a process that is gone
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        let intent = Intent {
+            pid: child.id(),
+            started: chrono::Utc::now().to_rfc3339(),
+            action: "remove".to_string(),
+            tools,
+            done: Vec::new(),
+        };
+        Intents {
+            pending: vec![intent.clone()],
+        }
+        .save_to_path(&path)
+        .unwrap();
+        assert_eq!(interrupted(&path).unwrap(), vec![intent.clone()]);
+        discard(&path, &intent).unwrap();
+        assert!(!path.exists());
+    }
+}
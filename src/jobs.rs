@@ -0,0 +1,222 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use tkit::api::JobAction;
+use tkit::redact::redact;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::broadcast;
+
+/// Events kept for subscribers that fall behind before they are dropped.
+const EVENT_BUFFER: usize = 1024;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// An install, update or run started by `tkit serve` or `tkit rpc`. It runs
+/// as a child `tkit` process so its output can be captured and streamed.
+#[derive(Debug, Serialize, Clone)]
+pub struct Job {
+    pub id: u64,
+    pub tool: String,
+    pub action: &'static str,
+    pub status: JobStatus,
+    pub exit_code: Option<i32>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    #[serde(skip)]
+    pub output: Vec<String>,
+}
+
+/// A job with its output so far.
+#[derive(Debug, Serialize)]
+pub struct JobDetail<'a> {
+    #[serde(flatten)]
+    pub job: &'a Job,
+    pub output: &'a [String],
+}
+
+impl Job {
+    pub fn detail(&self) -> JobDetail<'_> {
+        JobDetail {
+            job: self,
+            output: &self.output,
+        }
+    }
+}
+
+/// What happened to a job, as sent to subscribers.
+#[derive(Debug, Clone)]
+pub enum JobEvent {
+    Output { job: u64, line: String },
+    Finished(Job),
+}
+
+#[derive(Debug, Default)]
+struct Jobs {
+    next_id: u64,
+    jobs: BTreeMap<u64, Job>,
+}
+
+/// The jobs started in this process.
+pub struct JobRunner {
+    jobs: Mutex<Jobs>,
+    events: broadcast::Sender<JobEvent>,
+}
+
+impl JobRunner {
+    pub fn new() -> Arc<Self> {
+        Arc::new(JobRunner {
+            jobs: Mutex::default(),
+            events: broadcast::channel(EVENT_BUFFER).0,
+        })
+    }
+
+    pub fn get(&self, id: u64) -> Option<Job> {
+        self.jobs.lock().unwrap().jobs.get(&id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().jobs.values().cloned().collect()
+    }
+
+    pub fn running(&self) -> usize {
+        self.jobs
+            .lock()
+            .unwrap()
+            .jobs
+            .values()
+            .filter(|job| job.status == JobStatus::Running)
+            .count()
+    }
+
+    /// The job's output from line `from` on, and the job once it has finished.
+    pub fn output_since(&self, id: u64, from: usize) -> Option<(Vec<String>, Option<Job>)> {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.jobs.get(&id)?;
+        let finished = (job.status != JobStatus::Running).then(|| job.clone());
+        Some((job.output[from.min(job.output.len())..].to_vec(), finished))
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.events.subscribe()
+    }
+
+    /// Starts `tkit <action> <tool>` in the background, refusing a second job
+    /// for a tool that already has one running.
+    pub fn start(
+        self: &Arc<Self>,
+        tool: &str,
+        action: JobAction,
+        entry: Option<&str>,
+    ) -> Result<u64> {
+        let mut command = Command::new(std::env::current_exe()?);
+        command.arg(action.as_str()).arg(tool);
+        if action == JobAction::Run
+            && let Some(entry) = entry
+        {
+            command.arg("--cmd").arg(entry);
+        }
+        command
+            .env("NO_COLOR", "1")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let id = {
+            let mut jobs = self.jobs.lock().unwrap();
+            if jobs
+                .jobs
+                .values()
+                .any(|job| job.tool == tool && job.status == JobStatus::Running)
+            {
+                return Err(anyhow!("A job for '{}' is already running", tool));
+            }
+            jobs.next_id += 1;
+            let id = jobs.next_id;
+            jobs.jobs.insert(
+                id,
+                Job {
+                    id,
+                    tool: tool.to_string(),
+                    action: action.as_str(),
+                    status: JobStatus::Running,
+                    exit_code: None,
+                    started_at: chrono::Utc::now().to_rfc3339(),
+                    finished_at: None,
+                    output: Vec::new(),
+                },
+            );
+            id
+        };
+
+        let runner = self.clone();
+        tokio::spawn(async move {
+            let exit_code = match command.spawn() {
+                Ok(mut child) => {
+                    let stdout = child.stdout.take().map(|out| runner.capture(out, id));
+                    let stderr = child.stderr.take().map(|err| runner.capture(err, id));
+                    for reader in [stdout, stderr].into_iter().flatten() {
+                        let _ = reader.await;
+                    }
+                    child.wait().await.ok().and_then(|status| status.code())
+                }
+                Err(e) => {
+                    runner.append(id, format!("Could not start tkit: {}", e));
+                    None
+                }
+            };
+            runner.finish(id, exit_code);
+        });
+        Ok(id)
+    }
+
+    /// Appends each line `reader` produces to the job's output.
+    fn capture<R: AsyncRead + Unpin + Send + 'static>(
+        self: &Arc<Self>,
+        reader: R,
+        id: u64,
+    ) -> tokio::task::JoinHandle<()> {
+        let runner = self.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                runner.append(id, line);
+            }
+        })
+    }
+
+    fn append(&self, id: u64, line: String) {
+        let line = redact(&line);
+        if let Some(job) = self.jobs.lock().unwrap().jobs.get_mut(&id) {
+            job.output.push(line.clone());
+        }
+        // Nobody listening is fine
+        let _ = self.events.send(JobEvent::Output { job: id, line });
+    }
+
+    fn finish(&self, id: u64, exit_code: Option<i32>) {
+        let finished = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let Some(job) = jobs.jobs.get_mut(&id) else {
+                return;
+            };
+            job.status = if exit_code == Some(0) {
+                JobStatus::Succeeded
+            } else {
+                JobStatus::Failed
+            };
+            job.exit_code = exit_code;
+            job.finished_at = Some(chrono::Utc::now().to_rfc3339());
+            job.clone()
+        };
+        let _ = self.events.send(JobEvent::Finished(finished));
+    }
+}
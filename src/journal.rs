@@ -0,0 +1,243 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{get_config_path, machine, write_private};
+
+/// How many changes `tkit undo` can step back through.
+pub const JOURNAL_LIMIT: usize = 20;
+
+/// One config mutation, with the config file as it was before and after,
+/// less its tokens (see [`strip_secrets`]).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct JournalEntry {
+    pub timestamp: String,
+    /// What happened, e.g. `delete kubectl`.
+    pub action: String,
+    /// Name of the machine the change was made on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub machine: Option<String>,
+    /// `None` when the config file did not exist yet.
+    pub before: Option<String>,
+    pub after: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Journal {
+    #[serde(default)]
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    pub fn load_from_path(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Journal::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&content)?)
+    }
+
+    pub fn save_to_path(&self, path: &Path) -> Result<()> {
+        write_private(path, &serde_yaml::to_string(self)?)
+    }
+
+    /// Appends an entry, dropping the oldest beyond `JOURNAL_LIMIT`.
+    pub fn push(&mut self, entry: JournalEntry) {
+        self.entries.push(entry);
+        if self.entries.len() > JOURNAL_LIMIT {
+            let excess = self.entries.len() - JOURNAL_LIMIT;
+            self.entries.drain(..excess);
+        }
+    }
+}
+
+pub fn get_journal_path() -> Result<PathBuf> {
+    Ok(get_config_path()?.with_file_name("journal.yaml"))
+}
+
+/// Sync tokens in a config file, by where they sit: `sync` for the
+/// primary one and the remote's name for the others.
+fn tokens(config: &Value) -> Vec<(String, Value)> {
+    let mut tokens = Vec::new();
+    let Some(sync) = config.get("sync") else {
+        return tokens;
+    };
+    if let Some(token) = sync.get("token").filter(|token| !token.is_null()) {
+        tokens.push(("sync".to_string(), token.clone()));
+    }
+    for remote in sync
+        .get("remotes")
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+    {
+        if let (Some(name), Some(token)) = (
+            remote.get("name").and_then(Value::as_str),
+            remote.get("token").filter(|token| !token.is_null()),
+        ) {
+            tokens.push((name.to_string(), token.clone()));
+        }
+    }
+    tokens
+}
+
+/// Sets (or with `None`, removes) the token at `slot`, as named by [`tokens`].
+fn set_token(config: &mut Value, slot: &str, token: Option<Value>) {
+    let Some(sync) = config.get_mut("sync").and_then(Value::as_mapping_mut) else {
+        return;
+    };
+    let target = if slot == "sync" {
+        Some(sync)
+    } else {
+        sync.get_mut("remotes")
+            .and_then(Value::as_sequence_mut)
+            .into_iter()
+            .flatten()
+            .find(|remote| remote.get("name").and_then(Value::as_str) == Some(slot))
+            .and_then(Value::as_mapping_mut)
+    };
+    if let Some(target) = target {
+        match token {
+            Some(token) => target.insert("token".into(), token),
+            None => target.remove("token"),
+        };
+    }
+}
+
+/// A config file's text without its sync tokens, as the journal keeps it,
+/// so undo history never holds a credential. Text that doesn't parse is
+/// kept as it is.
+pub fn strip_secrets(config: &str) -> String {
+    let Ok(mut value) = serde_yaml::from_str::<Value>(config) else {
+        return config.to_string();
+    };
+    let slots = tokens(&value);
+    if slots.is_empty() {
+        return config.to_string();
+    }
+    for (slot, _) in slots {
+        set_token(&mut value, &slot, None);
+    }
+    serde_yaml::to_string(&value).unwrap_or_else(|_| config.to_string())
+}
+
+/// A config restored from the journal, with the tokens of the `current`
+/// config put back where the same remotes are.
+pub fn restore_secrets(config: &str, current: Option<&str>) -> String {
+    let current = current.and_then(|current| serde_yaml::from_str::<Value>(current).ok());
+    let slots = current.as_ref().map(tokens).unwrap_or_default();
+    let Ok(mut value) = serde_yaml::from_str::<Value>(config) else {
+        return config.to_string();
+    };
+    if slots.is_empty() {
+        return config.to_string();
+    }
+    for (slot, token) in slots {
+        set_token(&mut value, &slot, Some(token));
+    }
+    serde_yaml::to_string(&value).unwrap_or_else(|_| config.to_string())
+}
+
+/// Records a change to the config file; unchanged saves are skipped. The
+/// copies are stored without their tokens.
+pub fn record(
+    journal_path: &Path,
+    action: &str,
+    before: Option<String>,
+    after: String,
+) -> Result<()> {
+    if before.as_deref() == Some(after.as_str()) {
+        return Ok(());
+    }
+
+    let mut journal = Journal::load_from_path(journal_path)?;
+    journal.push(JournalEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        action: action.to_string(),
+        machine: Some(machine::name()),
+        before: before.as_deref().map(strip_secrets),
+        after: strip_secrets(&after),
+    });
+    journal.save_to_path(journal_path)
+}
+
+/// Removes and returns the most recent entry.
+pub fn pop(journal_path: &Path) -> Result<Option<JournalEntry>> {
+    let mut journal = Journal::load_from_path(journal_path)?;
+    let entry = journal.entries.pop();
+    if entry.is_some() {
+        journal.save_to_path(journal_path)?;
+    }
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_pop() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("journal.yaml");
+
+        record(&path, "add jq", None, "a".to_string()).unwrap();
+        record(&path, "no-op", Some("a".to_string()), "a".to_string()).unwrap();
+        record(&path, "delete jq", Some("a".to_string()), "b".to_string()).unwrap();
+
+        let entry = pop(&path).unwrap().unwrap();
+        assert_eq!(entry.action, "delete jq");
+        assert_eq!(entry.before.as_deref(), Some("a"));
+        assert_eq!(pop(&path).unwrap().unwrap().action, "add jq");
+        assert!(pop(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_secrets_are_not_journaled() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("journal.yaml");
+        let before = "tools: {}\nsync:\n  repo: me/tools\n  token: old-secret-token\n  remotes:\n  - name: backup\n    path: /srv/backup\n    token: remote-secret-token\n";
+        let after = before.replace("tools: {}", "tools: {jq: {name: jq}}");
+
+        record(&path, "add jq", Some(before.to_string()), after).unwrap();
+        let stored = fs::read_to_string(&path).unwrap();
+        assert!(!stored.contains("secret-token"), "{}", stored);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // Undo puts back the tokens the config has now
+        let entry = pop(&path).unwrap().unwrap();
+        let current = before.replace("old-secret-token", "new-secret-token");
+        let restored = restore_secrets(entry.before.as_deref().unwrap(), Some(&current));
+        let config: Value = serde_yaml::from_str(&restored).unwrap();
+        assert_eq!(config["sync"]["token"].as_str(), Some("new-secret-token"));
+        assert_eq!(
+            config["sync"]["remotes"][0]["token"].as_str(),
+            Some("remote-secret-token")
+        );
+        assert_eq!(config["sync"]["repo"].as_str(), Some("me/tools"));
+    }
+
+    #[test]
+    fn test_journal_is_capped() {
+        let mut journal = Journal::default();
+        for i in 0..JOURNAL_LIMIT + 5 {
+            journal.push(JournalEntry {
+                timestamp: String::new(),
+                action: i.to_string(),
+                machine: None,
+                before: None,
+                after: String::new(),
+            });
+        }
+        assert_eq!(journal.entries.len(), JOURNAL_LIMIT);
+        assert_eq!(journal.entries[0].action, "5");
+    }
+}
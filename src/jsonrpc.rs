@@ -0,0 +1,156 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// The named tool isn't in the config.
+pub const TOOL_NOT_FOUND: i64 = -32001;
+/// The tool already has a job running.
+pub const JOB_CONFLICT: i64 = -32002;
+
+/// A JSON-RPC 2.0 request, or a notification when it has no `id`.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Response<'a> {
+    jsonrpc: &'static str,
+    id: &'a Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<&'a Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a RpcError>,
+}
+
+#[derive(Debug, Serialize)]
+struct Notification<'a, T> {
+    jsonrpc: &'static str,
+    method: &'a str,
+    params: T,
+}
+
+/// Parameters of the methods that act on one tool.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ToolParams {
+    pub name: String,
+    /// Run entry to use instead of `default`.
+    #[serde(default)]
+    pub cmd: Option<String>,
+}
+
+impl RpcRequest {
+    /// Parses one line of input. The error comes with the id to answer it
+    /// under, `null` when there is none to be found.
+    pub fn parse(line: &str) -> Result<Self, (Value, RpcError)> {
+        let value: Value = serde_json::from_str(line)
+            .map_err(|e| (Value::Null, RpcError::new(PARSE_ERROR, e.to_string())))?;
+        let id = value.get("id").cloned().unwrap_or(Value::Null);
+        let request: RpcRequest = serde_json::from_value(value)
+            .map_err(|e| (id.clone(), RpcError::new(INVALID_REQUEST, e.to_string())))?;
+        if request.jsonrpc != "2.0" {
+            return Err((
+                id,
+                RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""),
+            ));
+        }
+        Ok(request)
+    }
+
+    /// The params as `T`; missing params read as an empty object.
+    pub fn params<T: DeserializeOwned>(&self) -> Result<T, RpcError> {
+        let params = match &self.params {
+            Value::Null => Value::Object(Default::default()),
+            params => params.clone(),
+        };
+        serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+    }
+}
+
+/// The line answering request `id`.
+pub fn response(id: &Value, result: &Result<Value, RpcError>) -> String {
+    let response = Response {
+        jsonrpc: "2.0",
+        id,
+        result: result.as_ref().ok(),
+        error: result.as_ref().err(),
+    };
+    serde_json::to_string(&response).unwrap_or_default()
+}
+
+/// The line of a notification sent to the client.
+pub fn notification<T: Serialize>(method: &str, params: T) -> String {
+    let notification = Notification {
+        jsonrpc: "2.0",
+        method,
+        params,
+    };
+    serde_json::to_string(&notification).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let request =
+            RpcRequest::parse(r#"{"jsonrpc":"2.0","id":7,"method":"show","params":{"name":"jq"}}"#)
+                .unwrap();
+        assert_eq!(request.id, Some(Value::from(7)));
+        assert_eq!(request.params::<ToolParams>().unwrap().name, "jq");
+        assert_eq!(
+            request.params::<Vec<String>>().unwrap_err().code,
+            INVALID_PARAMS
+        );
+
+        let notification = RpcRequest::parse(r#"{"jsonrpc":"2.0","method":"subscribe"}"#).unwrap();
+        assert_eq!(notification.id, None);
+
+        assert_eq!(RpcRequest::parse("{").unwrap_err().1.code, PARSE_ERROR);
+        let (id, error) =
+            RpcRequest::parse(r#"{"jsonrpc":"1.0","id":"a","method":"list"}"#).unwrap_err();
+        assert_eq!((id, error.code), (Value::from("a"), INVALID_REQUEST));
+    }
+
+    #[test]
+    fn test_response_lines() {
+        assert_eq!(
+            response(&Value::from(1), &Ok(Value::from(true))),
+            r#"{"jsonrpc":"2.0","id":1,"result":true}"#
+        );
+        assert_eq!(
+            response(&Value::Null, &Err(RpcError::new(METHOD_NOT_FOUND, "nope"))),
+            r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32601,"message":"nope"}}"#
+        );
+        assert_eq!(
+            notification("progress", [1]),
+            r#"{"jsonrpc":"2.0","method":"progress","params":[1]}"#
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::Config;
+use crate::gitsync::CONFIG_FILE;
+
+/// Directory that holds one file per tool in the split layout.
+pub const TOOLS_DIR: &str = "tools";
+
+/// How the config is laid out in a sync repository.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncLayout {
+    /// Everything in `tkit-config.yaml`.
+    #[default]
+    Single,
+    /// `tkit-config.yaml` as an index of the settings, plus `tools/<name>.yaml`
+    /// per tool, so the repository's history shows changes tool by tool.
+    Split,
+}
+
+impl SyncLayout {
+    pub fn is_single(&self) -> bool {
+        *self == SyncLayout::Single
+    }
+}
+
+pub fn tool_path(name: &str) -> String {
+    format!("{}/{}.yaml", TOOLS_DIR, name)
+}
+
+/// Name of the tool a repository path holds, if it is a tool file.
+pub fn tool_name(path: &str) -> Option<&str> {
+    path.strip_prefix(TOOLS_DIR)?
+        .strip_prefix('/')?
+        .strip_suffix(".yaml")
+        .filter(|name| !name.is_empty() && !name.contains('/'))
+}
+
+/// Whether a pushed `tkit-config.yaml` is the index of a split layout.
+/// Encrypted configs never are.
+pub fn is_split(index: &str) -> bool {
+    serde_yaml::from_str::<serde_yaml::Value>(index)
+        .ok()
+        .and_then(|value| value["sync"]["layout"].as_str().map(|l| l == "split"))
+        .unwrap_or(false)
+}
+
+/// The files `config` is pushed as in the split layout, by repository path.
+pub fn split(config: &Config) -> Result<BTreeMap<String, String>> {
+    let mut index = config.clone();
+    index.tools.clear();
+    let mut files = BTreeMap::from([(CONFIG_FILE.to_string(), serde_yaml::to_string(&index)?)]);
+    for (name, tool) in &config.tools {
+        files.insert(tool_path(name), serde_yaml::to_string(tool)?);
+    }
+    Ok(files)
+}
+
+/// Puts a split config back together from its index and tool files (by tool
+/// name), as the YAML a single-file push would have written.
+pub fn join(index: &str, tools: &BTreeMap<String, String>) -> Result<String> {
+    let mut value: serde_yaml::Value = serde_yaml::from_str(index)?;
+    let mut mapping = serde_yaml::Mapping::new();
+    for (name, content) in tools {
+        let tool: serde_yaml::Value = serde_yaml::from_str(content)
+            .map_err(|e| anyhow!("Invalid {}: {}", tool_path(name), e))?;
+        mapping.insert(name.as_str().into(), tool);
+    }
+    value
+        .as_mapping_mut()
+        .ok_or_else(|| anyhow!("{} is not a mapping", CONFIG_FILE))?
+        .insert("tools".into(), serde_yaml::Value::Mapping(mapping));
+    Ok(serde_yaml::to_string(&value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ToolConfig;
+
+    #[test]
+    fn test_split_and_join() {
+        let mut config = Config::new();
+        config.sync.layout = SyncLayout::Split;
+        for name in ["jq", "rg"] {
+            config.tools.insert(
+                name.to_string(),
+                ToolConfig {
+                    name: name.to_string(),
+                    install_commands: vec![format!("install {}", name).into()],
+                    ..Default::default()
+                },
+            );
+        }
+
+        let files = split(&config).unwrap();
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            [CONFIG_FILE, "tools/jq.yaml", "tools/rg.yaml"]
+        );
+        assert!(is_split(&files[CONFIG_FILE]));
+        assert!(!files[CONFIG_FILE].contains("install jq"));
+
+        let tools: BTreeMap<String, String> = files
+            .iter()
+            .filter_map(|(path, content)| Some((tool_name(path)?.to_string(), content.clone())))
+            .collect();
+        let joined: Config =
+            serde_yaml::from_str(&join(&files[CONFIG_FILE], &tools).unwrap()).unwrap();
+        assert_eq!(joined.tools.len(), 2);
+        assert_eq!(joined.tools["rg"].install_commands, ["install rg"]);
+    }
+
+    #[test]
+    fn test_tool_name() {
+        assert_eq!(tool_name("tools/jq.yaml"), Some("jq"));
+        assert_eq!(tool_name("tools/nested/jq.yaml"), None);
+        assert_eq!(tool_name("tkit-config.yaml"), None);
+    }
+}
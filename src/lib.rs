@@ -1,32 +1,897 @@
+pub mod api;
+pub mod audit;
+pub mod batch;
+pub mod converge;
+pub mod crypto;
+pub mod downloads;
+pub mod exitcode;
+pub mod files;
+pub mod fleet;
+pub mod github_cache;
+pub mod gitsync;
+pub mod history;
+pub mod intent;
+pub mod journal;
+pub mod jsonrpc;
+pub mod layout;
+pub mod lockfile;
+pub mod machine;
+pub mod merge;
+pub mod mock;
+pub mod notify;
+pub mod overrides;
+pub mod package;
+pub mod preflight;
+pub mod redact;
+pub mod releases;
+pub mod schedule;
+pub mod schema;
+pub mod script;
+pub mod shellenv;
+pub mod signature;
+pub mod snapshot;
+pub mod state;
+pub mod step;
+pub mod syncqueue;
+pub mod trash;
+
+pub use downloads::Download;
+pub use files::ManagedFile;
+pub use fleet::MachineReport;
+pub use layout::SyncLayout;
+pub use lockfile::Lockfile;
+pub use overrides::MachineOverrides;
+pub use package::{PackageManager, PackageSpec, VersionCache};
+pub use preflight::Requirements;
+pub use releases::ReleaseSpec;
+pub use schedule::Schedule;
+pub use script::Hooks;
+pub use shellenv::{CommandShell, ExecTarget};
+pub use signature::SignatureCheck;
+pub use snapshot::{SnapshotHook, SnapshotTrigger};
+pub use state::{Checkpoint, ObservedState, STATE_FILE, State, StepTiming, Timing, ToolStats};
+pub use step::{Condition, Step};
+
 use anyhow::{Result, anyhow};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use exitcode::{ExitKind, tool_not_found};
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct ToolConfig {
     pub name: String,
     pub description: Option<String>,
-    pub install_commands: Vec<String>,
-    pub remove_commands: Vec<String>,
-    pub update_commands: Vec<String>,
+    /// Heading the tool is grouped under in `tkit list`, e.g. `DevOps`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
     #[serde(default)]
-    pub run_commands: Vec<String>,
+    pub install_commands: Vec<Step>,
     #[serde(default)]
+    pub remove_commands: Vec<Step>,
+    #[serde(default)]
+    pub update_commands: Vec<Step>,
+    /// Named invocations run by `tkit run tool:name`; a plain list is the `default` entry.
+    #[serde(default)]
+    pub run_commands: RunCommands,
+    /// Undo steps for a failed install; the remove commands are used when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rollback_commands: Vec<Step>,
+    /// Checks run after install/update, e.g. `rg --version`; a failure means
+    /// the install is broken.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub verify_commands: Vec<Step>,
+    /// Rhai scripts run before and after the install, update and remove
+    /// commands, e.g. to check a prerequisite's version.
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+    /// Whether machines using this config should have the tool; `tkit apply`
+    /// converges to it.
+    #[serde(default, skip_serializing_if = "DesiredState::is_absent")]
+    pub desired_state: DesiredState,
+    /// Whether the tool is installed on this machine. This is kept in the
+    /// local state file, not the shared config; it is only read from the
+    /// config to migrate older files.
+    #[serde(default, skip_serializing)]
     pub installed: bool,
+    /// Executable the tool provides, when it differs from the tool name (e.g. `rg`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binary: Option<String>,
+    /// Generates install/remove/update commands for any of those lists left empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<PackageSpec>,
+    /// GitHub repository (`owner/name`) the tool is released from; `tkit
+    /// outdated` and `tkit update --changelog` read its releases.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_repo: Option<String>,
+    /// Installs the tool from a GitHub release asset when its install,
+    /// remove or update commands are left empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release: Option<ReleaseSpec>,
+    /// Labels shown in `tkit list`, e.g. `cli`, `k8s`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// When the tool was added to this config (RFC 3339).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    /// When the definition or its install state last changed (RFC 3339).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// Tools that must not be installed alongside this one, e.g. `podman-docker`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts_with: Vec<String>,
+    /// Files fetched and checksum-verified before install/update; each cached
+    /// path is available to commands as `{{name}}`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub downloads: Vec<Download>,
+    /// GPG signature checked before install/update, typically of one of the downloads.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify: Option<SignatureCheck>,
+    /// Directories `tkit env` adds to PATH once the tool is installed, e.g. `~/.cargo/bin`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Environment variables `tkit env` exports once the tool is installed, e.g. `GOPATH: ~/go`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub exports: BTreeMap<String, String>,
+    /// Config files placed by `tkit files apply`, e.g. `.tmux.conf`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<ManagedFile>,
+    /// Shell aliases written by `tkit alias sync`, e.g. `k: kubectl`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub shell_aliases: BTreeMap<String, String>,
+    /// Pre-flight checks (commands, hosts, disk space, OS version) run before installing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires: Option<Requirements>,
+    /// Alternative names accepted wherever a tool name is, e.g. `k` for kubectl.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Disabled tools keep their definition but are skipped by bulk operations.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disabled: bool,
+    /// Private tools stay on this machine: they are left out of pushed configs
+    /// and survive a pull.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub private: bool,
+    /// Values substituted into commands as `{{name}}`, e.g. a version number
+    /// or a license key. Also accepted as `prompts`.
+    #[serde(default, alias = "prompts", skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<ToolParameter>,
+    /// Parameter values chosen on this machine, reused by later update/remove
+    /// runs. Like `installed`, these are kept in the local state file and only
+    /// read from the config to migrate older files.
+    #[serde(default, skip_serializing)]
+    pub param_values: BTreeMap<String, String>,
+    /// Shell that runs this tool's commands, overriding `settings.shell`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<CommandShell>,
+    /// `wsl` runs the commands inside WSL when on Windows.
+    #[serde(default, skip_serializing_if = "ExecTarget::is_native")]
+    pub target: ExecTarget,
+    /// Commands replacing install/remove/update on one architecture, keyed
+    /// like `{{arch}}` (e.g. `arm64`) or by Rust's name (e.g. `aarch64`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub arch_commands: BTreeMap<String, ArchCommands>,
+    /// Version held by `tkit pin`: `update` skips the tool and a `version`
+    /// parameter always takes this value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned: Option<String>,
+    /// Position in batch installs and `tkit apply`: lower values go first
+    /// (e.g. `-10` for a package index refresh, `10` for tools built with a
+    /// compiler). Tools with the same priority run in name order.
+    #[serde(default, alias = "order", skip_serializing_if = "is_zero")]
+    pub priority: i32,
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct ArchCommands {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub install_commands: Vec<Step>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove_commands: Vec<Step>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub update_commands: Vec<Step>,
+}
+
+impl ArchCommands {
+    fn get(&self, action: ToolAction) -> &Vec<Step> {
+        match action {
+            ToolAction::Install => &self.install_commands,
+            ToolAction::Remove => &self.remove_commands,
+            ToolAction::Update => &self.update_commands,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DesiredState {
+    Present,
+    #[default]
+    Absent,
+}
+
+impl DesiredState {
+    pub fn is_absent(&self) -> bool {
+        *self == DesiredState::Absent
+    }
+
+    pub fn is_present(&self) -> bool {
+        *self == DesiredState::Present
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolAction {
+    Install,
+    Remove,
+    Update,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct ToolParameter {
+    pub name: String,
+    pub default: Option<String>,
+    /// Question shown when asking for a value; defaults to the parameter name.
+    #[serde(alias = "message")]
+    pub prompt: Option<String>,
+    /// Asked for without echo and masked wherever commands or output are shown.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub secret: bool,
+}
+
+/// Name of the run entry used when `tkit run` is not given one.
+pub const DEFAULT_RUN: &str = "default";
+
+/// A tool's run commands by name, e.g. `default`, `serve`, `logs`. Written as
+/// a plain list when only `default` is defined, which is also accepted on read.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RunCommands(pub BTreeMap<String, RunEntry>);
+
+/// One named invocation. Written as a plain list of commands unless it has a schedule.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RunEntry {
+    pub commands: Vec<Step>,
+    /// Cron expression (e.g. `0 3 * * *`) for `tkit scheduler run`.
+    pub schedule: Option<String>,
+}
+
+impl RunCommands {
+    pub fn is_empty(&self) -> bool {
+        self.0.values().all(|entry| entry.commands.is_empty())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[Step]> {
+        self.0.get(name).map(|entry| entry.commands.as_slice())
+    }
+
+    /// The `default` entry, or the only entry when there is just one.
+    pub fn default_commands(&self) -> &[Step] {
+        match self.0.get(DEFAULT_RUN) {
+            Some(entry) => &entry.commands,
+            None if self.0.len() == 1 => &self.0.values().next().unwrap().commands,
+            None => &[],
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).collect()
+    }
+
+    /// Entries with a schedule, as `(name, cron expression)`.
+    pub fn scheduled(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .filter_map(|(name, entry)| Some((name.as_str(), entry.schedule.as_deref()?)))
+    }
+}
+
+impl<T: Into<Step>> From<Vec<T>> for RunCommands {
+    fn from(commands: Vec<T>) -> Self {
+        if commands.is_empty() {
+            return RunCommands::default();
+        }
+        let entry = RunEntry {
+            commands: commands.into_iter().map(Into::into).collect(),
+            schedule: None,
+        };
+        RunCommands([(DEFAULT_RUN.to_string(), entry)].into())
+    }
+}
+
+impl Serialize for RunCommands {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.get(DEFAULT_RUN) {
+            _ if self.0.is_empty() => Vec::<String>::new().serialize(serializer),
+            Some(entry) if self.0.len() == 1 => entry.serialize(serializer),
+            _ => self.0.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RunCommands {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Single(RunEntry),
+            Named(BTreeMap<String, RunEntry>),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Single(entry) if entry.commands.is_empty() && entry.schedule.is_none() => {
+                RunCommands::default()
+            }
+            Repr::Single(entry) => RunCommands([(DEFAULT_RUN.to_string(), entry)].into()),
+            Repr::Named(named) => RunCommands(named),
+        })
+    }
+}
+
+impl Serialize for RunEntry {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        match &self.schedule {
+            None => self.commands.serialize(serializer),
+            Some(schedule) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("commands", &self.commands)?;
+                map.serialize_entry("schedule", schedule)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RunEntry {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Full {
+            #[serde(default)]
+            commands: Vec<Step>,
+            schedule: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            List(Vec<Step>),
+            Full(Full),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::List(commands) => RunEntry {
+                commands,
+                schedule: None,
+            },
+            Repr::Full(full) => RunEntry {
+                commands: full.commands,
+                schedule: full.schedule,
+            },
+        })
+    }
+}
+
+static TEMPLATE_VAR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([A-Za-z0-9_.-]+)\s*\}\}").unwrap());
+
+/// Replaces `{{name}}` placeholders with values from `vars`. Unknown
+/// placeholders are left untouched so the failure is visible in the command.
+pub fn render_template(template: &str, vars: &BTreeMap<String, String>) -> String {
+    TEMPLATE_VAR
+        .replace_all(template, |caps: &regex::Captures| {
+            vars.get(&caps[1])
+                .cloned()
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+/// Whether a tool argument is a shell-style pattern rather than a plain name.
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Matches `name` against a shell-style pattern supporting `*`, `?` and
+/// bracket classes such as `[abc]`, `[a-z]` and `[!x]`.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|i| matches(rest, &name[i..])),
+            Some(('?', rest)) => !name.is_empty() && matches(rest, &name[1..]),
+            Some(('[', rest)) => {
+                let Some(close) = rest.iter().skip(1).position(|&c| c == ']') else {
+                    return name.first() == Some(&'[') && matches(rest, &name[1..]);
+                };
+                let (class, rest) = (&rest[..close + 1], &rest[close + 2..]);
+                let Some((&c, name_rest)) = name.split_first() else {
+                    return false;
+                };
+                let (negated, class) = match class.split_first() {
+                    Some(('!' | '^', class)) => (true, class),
+                    _ => (false, class),
+                };
+                let mut found = false;
+                let mut i = 0;
+                while i < class.len() {
+                    if i + 2 < class.len() && class[i + 1] == '-' {
+                        found |= class[i] <= c && c <= class[i + 2];
+                        i += 3;
+                    } else {
+                        found |= class[i] == c;
+                        i += 1;
+                    }
+                }
+                found != negated && matches(rest, name_rest)
+            }
+            Some((&p, rest)) => name.first() == Some(&p) && matches(rest, &name[1..]),
+        }
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches(&pattern, &name)
+}
+
+/// This machine's CPU architecture as release archives usually name it:
+/// `amd64`, `arm64`, `arm` or `386`, otherwise Rust's own name.
+pub fn arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        other => other,
+    }
+}
+
+/// This machine's OS as release archives usually name it: `linux`, `darwin`
+/// or `windows`.
+pub fn os() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "darwin",
+        other => other,
+    }
+}
+
+/// `vars` plus the built-in `arch` and `os`, unless a parameter of the
+/// same name is set: what commands are rendered and conditions evaluated with.
+pub fn template_values(vars: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut all = BTreeMap::from([
+        ("arch".to_string(), arch().to_string()),
+        ("os".to_string(), os().to_string()),
+    ]);
+    all.extend(vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+    all
+}
+
+/// Renders commands with `vars`, plus the built-in `{{arch}}` and `{{os}}`
+/// unless a parameter of the same name is set.
+pub fn render_commands(commands: &[Step], vars: &BTreeMap<String, String>) -> Vec<Step> {
+    let all = template_values(vars);
+    commands.iter().map(|cmd| cmd.render(&all)).collect()
+}
+
+impl ToolConfig {
+    /// Parses a single tool definition, as shared in gists and blog posts.
+    /// Installed and desired state describe someone else's machines, so they
+    /// are always reset.
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        let mut tool: ToolConfig =
+            serde_yaml::from_str(content).map_err(|e| anyhow!("Invalid tool definition: {}", e))?;
+        if tool.name.trim().is_empty() {
+            return Err(anyhow!("Invalid tool definition: name must not be empty"));
+        }
+        tool.installed = false;
+        tool.desired_state = DesiredState::Absent;
+        Ok(tool)
+    }
+
+    /// The repository the tool's releases come from: `github_repo`, or the
+    /// one of its `release`.
+    pub fn release_repo(&self) -> Option<&str> {
+        self.github_repo
+            .as_deref()
+            .or(self.release.as_ref().map(|release| release.repo.as_str()))
+    }
+
+    /// The executable the tool provides: `binary`, or the tool's name.
+    pub fn binary_name(&self) -> &str {
+        self.binary.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Commands to run for an action. Those in `arch_commands` for this
+    /// machine's architecture come first, then the explicit commands; a
+    /// `package` shorthand fills in lists that are still empty.
+    pub fn commands_for(&self, action: ToolAction) -> Vec<Step> {
+        let for_arch = self
+            .arch_commands
+            .get(arch())
+            .or_else(|| self.arch_commands.get(std::env::consts::ARCH));
+        if let Some(commands) = for_arch.map(|commands| commands.get(action))
+            && !commands.is_empty()
+        {
+            return commands.clone();
+        }
+
+        let explicit = match action {
+            ToolAction::Install => &self.install_commands,
+            ToolAction::Remove => &self.remove_commands,
+            ToolAction::Update => &self.update_commands,
+        };
+        if !explicit.is_empty() {
+            return explicit.clone();
+        }
+
+        let generated = match &self.package {
+            Some(spec) => match action {
+                ToolAction::Install => spec.install_commands(),
+                ToolAction::Remove => spec.remove_commands(),
+                ToolAction::Update => spec.update_commands(),
+            },
+            None => Vec::new(),
+        };
+        generated.into_iter().map(Step::from).collect()
+    }
+
+    /// A complete tool entry for a package, with no prompts needed: the
+    /// package shorthand supplies install/remove/update commands and the
+    /// run command prints the binary's version.
+    pub fn from_package(
+        manager: PackageManager,
+        package: &str,
+        binary: Option<&str>,
+        description: Option<&str>,
+    ) -> Self {
+        let bin = binary.unwrap_or(package);
+        ToolConfig {
+            name: package.to_string(),
+            description: Some(
+                description
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("{} (installed with {})", package, manager)),
+            ),
+            run_commands: vec![format!("{} --version", bin)].into(),
+            binary: binary.map(str::to_string),
+            package: Some(PackageSpec::new(manager, package)),
+            ..Default::default()
+        }
+    }
+
+    /// Commands that undo a partial install.
+    /// Every step the tool defines, in any list.
+    pub fn steps(&self) -> impl Iterator<Item = &Step> {
+        let arch = self.arch_commands.values().flat_map(|commands| {
+            [
+                &commands.install_commands,
+                &commands.remove_commands,
+                &commands.update_commands,
+            ]
+        });
+        [
+            &self.install_commands,
+            &self.remove_commands,
+            &self.update_commands,
+            &self.rollback_commands,
+            &self.verify_commands,
+        ]
+        .into_iter()
+        .chain(arch)
+        .flatten()
+        .chain(
+            self.run_commands
+                .0
+                .values()
+                .flat_map(|entry| &entry.commands),
+        )
+    }
+
+    pub fn rollback_steps(&self) -> Vec<Step> {
+        if self.rollback_commands.is_empty() {
+            self.commands_for(ToolAction::Remove)
+        } else {
+            self.rollback_commands.clone()
+        }
+    }
+
+    /// Marks the tool as modified now.
+    pub fn touch(&mut self) {
+        self.updated_at = Some(chrono::Utc::now().to_rfc3339());
+    }
+
+    /// Whether the tool changed after `timestamp`; always true without one.
+    pub fn changed_since(&self, timestamp: Option<&str>) -> bool {
+        let parse = |t: &str| chrono::DateTime::parse_from_rfc3339(t).ok();
+        match (
+            timestamp.and_then(parse),
+            self.updated_at.as_deref().and_then(parse),
+        ) {
+            (Some(since), Some(updated)) => updated > since,
+            (None, _) => true,
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Returns a copy suitable for sharing with other machines and users,
+    /// without fields that only describe this machine.
+    pub fn shareable(&self) -> Self {
+        let mut tool = self.clone();
+        tool.installed = false;
+        tool.desired_state = DesiredState::Absent;
+        tool.created_at = None;
+        tool.updated_at = None;
+        tool.param_values.clear();
+        tool
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
-    pub tools: HashMap<String, ToolConfig>,
+    pub tools: BTreeMap<String, ToolConfig>,
     #[serde(default)]
     pub sync: SyncConfig,
+    #[serde(default, skip_serializing_if = "RegistryConfig::is_default")]
+    pub registry: RegistryConfig,
+    #[serde(default, skip_serializing_if = "Settings::is_default")]
+    pub settings: Settings,
+    #[serde(default, skip_serializing_if = "NetworkConfig::is_default")]
+    pub network: NetworkConfig,
+    /// Values for `{{name}}` placeholders in every tool's commands; a tool
+    /// parameter of the same name wins.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+    /// Changes applied only on the machine with the given hostname.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, MachineOverrides>,
+    /// Each machine's report from its last push, by hostname.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub machines: BTreeMap<String, MachineReport>,
+    /// Shared values of fields replaced by machine overrides, per tool, so
+    /// saving never writes one machine's overrides into the shared config.
+    #[serde(skip)]
+    overridden: BTreeMap<String, BTreeMap<String, Option<serde_yaml::Value>>>,
+}
+
+/// Keys `tkit config get/set/unset` accepts, with what they control. Any
+/// `variables.<name>` is accepted too.
+pub const SETTING_KEYS: &[(&str, &str)] = &[
+    (
+        "sync.repo",
+        "GitHub repository (owner/name) the config syncs with",
+    ),
+    ("sync.token", "GitHub token used for sync"),
+    ("sync.auto_sync", "Push the config after every change"),
+    ("sync.encrypt", "Encrypt the config before pushing it"),
+    ("sync.branch", "Branch the config is kept on"),
+    ("sync.layout", "single, or split for one file per tool"),
+    (
+        "sync.commit_message_template",
+        "Message of sync commits, with {{action}}, {{tool}}, {{machine}}, {{timestamp}}",
+    ),
+    ("settings.rollback_on_failure", "Undo a failed install"),
+    (
+        "settings.files_dir",
+        "Where the sources of managed files live",
+    ),
+    ("settings.output_lines", "Lines of output shown per step"),
+    ("settings.shell", "Shell that runs tool commands"),
+    ("settings.timeout", "Seconds 'tkit check' gives each tool"),
+    ("settings.color", "auto, always or never"),
+    ("settings.editor", "Editor for the config"),
+    ("settings.confirm", "ask, or yes to skip confirmations"),
+    ("settings.jobs", "Tools 'tkit check' checks at once"),
+    (
+        "settings.notify_interval",
+        "Hours between 'tkit notify --quiet' checks",
+    ),
+    (
+        "settings.snapshot",
+        "Command taking a system snapshot before apply, batch installs and removals",
+    ),
+    ("registry.index_url", "Index of shared recipes"),
+    (
+        "registry.repo",
+        "Repository 'tkit publish' opens pull requests against",
+    ),
+    ("network.proxy", "Proxy for every request"),
+    ("network.no_proxy", "Hosts reached without the proxy"),
+    ("network.ca_cert", "Extra CA certificate to trust"),
+];
+
+/// Keys whose values are hidden when shown.
+pub const SECRET_SETTING_KEYS: &[&str] = &["sync.token"];
+
+fn check_setting_key(key: &str) -> Result<()> {
+    let is_variable = key
+        .strip_prefix("variables.")
+        .is_some_and(|name| !name.is_empty() && !name.contains('.'));
+    if is_variable || SETTING_KEYS.iter().any(|(k, _)| *k == key) {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Unknown setting '{}'. Run 'tkit config list' to see the available keys.",
+        key
+    ))
+}
+
+/// Reads `field` of a settings section serialized as YAML.
+fn section_value<T: Serialize>(section: &T, field: &str) -> Result<Option<serde_yaml::Value>> {
+    let value = serde_yaml::to_value(section)?;
+    Ok(value.get(field).filter(|value| !value.is_null()).cloned())
+}
+
+/// Sets (or with `None` removes) `field` of a settings section and parses the
+/// section back, so a value of the wrong type is rejected. A raw value is
+/// read as YAML (`true`, `30`), or as a plain string when that doesn't fit.
+fn edit_section<T: Serialize + serde::de::DeserializeOwned>(
+    section: &mut T,
+    field: &str,
+    raw: Option<&str>,
+) -> Result<()> {
+    let mut value = serde_yaml::to_value(&*section)?;
+    let mapping = value
+        .as_mapping_mut()
+        .ok_or_else(|| anyhow!("Settings section is not a mapping"))?;
+    let key = serde_yaml::Value::String(field.to_string());
+    let Some(raw) = raw else {
+        mapping.remove(&key);
+        *section = serde_yaml::from_value(value)?;
+        return Ok(());
+    };
+
+    let parsed =
+        serde_yaml::from_str(raw).unwrap_or_else(|_| serde_yaml::Value::String(raw.to_string()));
+    mapping.insert(key.clone(), parsed);
+    if let Ok(updated) = serde_yaml::from_value(value.clone()) {
+        *section = updated;
+        return Ok(());
+    }
+    value
+        .as_mapping_mut()
+        .unwrap()
+        .insert(key, serde_yaml::Value::String(raw.to_string()));
+    *section = serde_yaml::from_value(value)
+        .map_err(|e| anyhow!("Invalid value '{}' for '{}': {}", raw, field, e))?;
+    Ok(())
+}
+
+/// Behaviour switches that apply to every tool.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct Settings {
+    /// Undo a failed install by running the tool's rollback (or remove) commands.
+    #[serde(default)]
+    pub rollback_on_failure: bool,
+    /// Where the sources of managed files live, e.g. a checkout of the sync
+    /// repository; `~/.config/tkit/files` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_dir: Option<String>,
+    /// Lines of output shown per step; the rest only goes to the tool's log
+    /// file, and a failed step shows its last lines instead. Unlimited when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_lines: Option<usize>,
+    /// Shell that runs commands of tools without their own `shell`; commands
+    /// are executed directly when neither is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<CommandShell>,
+    /// Seconds each tool's checks may take in `tkit check`; 30 when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Whether output is colored.
+    #[serde(default, skip_serializing_if = "ColorMode::is_auto")]
+    pub color: ColorMode,
+    /// Editor for editing the config; `$VISUAL`, then `$EDITOR`, when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editor: Option<String>,
+    /// Whether confirmation prompts are asked or answered yes.
+    #[serde(default, skip_serializing_if = "ConfirmMode::is_ask")]
+    pub confirm: ConfirmMode,
+    /// Tools `tkit check` checks at the same time; 1 when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+    /// Hours between the checks `tkit notify --quiet` makes; 24 when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_interval: Option<u64>,
+    /// Command taking a system snapshot before apply, batch installs and
+    /// removals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<SnapshotHook>,
+}
+
+/// Seconds `tkit check` gives each tool when `settings.timeout` is unset.
+pub const DEFAULT_CHECK_TIMEOUT: u64 = 30;
+
+/// Hours between `tkit notify --quiet` checks when
+/// `settings.notify_interval` is unset.
+pub const DEFAULT_NOTIFY_INTERVAL: u64 = 24;
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Color when writing to a terminal and `NO_COLOR` isn't set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn is_auto(&self) -> bool {
+        *self == ColorMode::Auto
+    }
+
+    /// Makes all further output follow this mode.
+    pub fn apply(&self) {
+        match self {
+            ColorMode::Auto => colored::control::unset_override(),
+            ColorMode::Always => colored::control::set_override(true),
+            ColorMode::Never => colored::control::set_override(false),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmMode {
+    /// Ask before batch operations and other changes that need confirming.
+    #[default]
+    Ask,
+    /// Answer every confirmation with yes, e.g. on machines set up by scripts.
+    Yes,
+}
+
+impl ConfirmMode {
+    pub fn is_ask(&self) -> bool {
+        *self == ConfirmMode::Ask
+    }
+}
+
+impl Settings {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn check_timeout(&self) -> u64 {
+        self.timeout.unwrap_or(DEFAULT_CHECK_TIMEOUT)
+    }
+
+    pub fn notify_interval(&self) -> u64 {
+        self.notify_interval.unwrap_or(DEFAULT_NOTIFY_INTERVAL)
+    }
+
+    pub fn jobs(&self) -> usize {
+        self.jobs.unwrap_or(1).max(1)
+    }
+
+    /// The editor to run: `editor`, `$VISUAL`, `$EDITOR`, then the platform's
+    /// usual default.
+    pub fn editor(&self) -> String {
+        self.editor
+            .clone()
+            .or_else(|| std::env::var("VISUAL").ok().filter(|e| !e.is_empty()))
+            .or_else(|| std::env::var("EDITOR").ok().filter(|e| !e.is_empty()))
+            .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string())
+    }
+
+    pub fn files_dir(&self) -> Result<PathBuf> {
+        match &self.files_dir {
+            Some(dir) => Ok(files::expand_tilde(dir)),
+            None => Ok(get_config_path()?.with_file_name("files")),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ConfigWithSync {
-    pub tools: HashMap<String, ToolConfig>,
+    pub tools: BTreeMap<String, ToolConfig>,
     pub sync: SyncConfig,
 }
 
@@ -43,37 +908,369 @@ impl From<Config> for ConfigWithSync {
 pub struct SyncConfig {
     pub repo: Option<String>,
     pub token: Option<String>,
+    /// Repository pushed to with plain `git` instead of the GitHub API, e.g.
+    /// `git@github.com:user/dotfiles.git`; used when `repo` is unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    /// Private key (a deploy key) ssh uses for `git`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_key: Option<String>,
+    /// Branch the config is kept on, created on first push; the repository's
+    /// default branch when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Whether tools are pushed in one file or one file each.
+    #[serde(default, skip_serializing_if = "SyncLayout::is_single")]
+    pub layout: SyncLayout,
     pub last_sync: Option<String>,
     #[serde(default)]
     pub auto_sync: bool,
+    /// Encrypt the config with a passphrase before pushing it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub encrypt: bool,
+    /// Extra places `tkit sync push` writes to alongside `repo`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remotes: Vec<SyncRemote>,
+    /// Message of sync commits, e.g. `{{action}} {{tool}} on {{machine}}`;
+    /// `{{timestamp}}` is also available.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_message_template: Option<String>,
+}
+
+/// Name under which the main `repo` is listed among the sync remotes.
+pub const PRIMARY_REMOTE: &str = "origin";
+
+/// An additional sync destination: a GitHub repository or a local file.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct SyncRemote {
+    pub name: String,
+    /// GitHub repository (owner/name).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Token for `repo`; the main sync token is used when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// File the config is written to instead, e.g. on a backup drive or a synced folder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Repository URL reached with `git` instead, e.g. over SSH.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    /// Private key ssh uses for `git`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_key: Option<String>,
+    /// Branch of `repo` or `git` the config is kept on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+}
+
+impl SyncConfig {
+    /// `commit_message_template` filled in, or `None` when there is none.
+    /// Spaces left by empty placeholders (e.g. no `{{tool}}`) are collapsed.
+    pub fn commit_message(
+        &self,
+        action: &str,
+        tool: &str,
+        machine: &str,
+        timestamp: &str,
+    ) -> Option<String> {
+        let vars = BTreeMap::from([
+            ("action".to_string(), action.to_string()),
+            ("tool".to_string(), tool.to_string()),
+            ("machine".to_string(), machine.to_string()),
+            ("timestamp".to_string(), timestamp.to_string()),
+        ]);
+        let message = render_template(self.commit_message_template.as_deref()?, &vars);
+        let lines: Vec<String> = message
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        Some(lines.join("\n").trim().to_string())
+    }
+
+    /// Every configured remote, starting with `repo` as `origin`. Tokens are
+    /// filled in from the main token where a remote has none of its own.
+    pub fn all_remotes(&self) -> Vec<SyncRemote> {
+        let primary = match (&self.repo, &self.git) {
+            (Some(repo), _) => Some(SyncRemote {
+                name: PRIMARY_REMOTE.to_string(),
+                repo: Some(repo.clone()),
+                branch: self.branch.clone(),
+                ..Default::default()
+            }),
+            (None, Some(git)) => Some(SyncRemote {
+                name: PRIMARY_REMOTE.to_string(),
+                git: Some(git.clone()),
+                ssh_key: self.ssh_key.clone(),
+                branch: self.branch.clone(),
+                ..Default::default()
+            }),
+            (None, None) => None,
+        };
+
+        primary
+            .into_iter()
+            .chain(self.remotes.iter().cloned())
+            .map(|mut remote| {
+                if remote.repo.is_some() && remote.token.is_none() {
+                    remote.token = self.token.clone();
+                }
+                remote
+            })
+            .collect()
+    }
+
+    pub fn remote(&self, name: &str) -> Option<SyncRemote> {
+        self.all_remotes().into_iter().find(|r| r.name == name)
+    }
+}
+
+pub const DEFAULT_REGISTRY_INDEX: &str =
+    "https://raw.githubusercontent.com/ThembinkosiThemba/tkit-registry/main/index.yaml";
+
+/// HTTP settings for registry, sync and download requests. The
+/// HTTP_PROXY/HTTPS_PROXY/NO_PROXY environment variables apply when no
+/// proxy is configured here.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct NetworkConfig {
+    /// Proxy for every request, e.g. `http://proxy.corp:8080`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Comma-separated hosts reached without the proxy, in NO_PROXY syntax.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+    /// PEM file with an extra CA certificate to trust, e.g. a corporate proxy's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<String>,
+}
+
+impl NetworkConfig {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| anyhow!("Invalid network.proxy '{}': {}", proxy, e))?
+                .no_proxy(
+                    self.no_proxy
+                        .as_deref()
+                        .and_then(reqwest::NoProxy::from_string),
+                );
+            builder = builder.proxy(proxy);
+        }
+        if let Some(path) = &self.ca_cert {
+            let pem = fs::read(path)
+                .map_err(|e| anyhow!("Could not read network.ca_cert '{}': {}", path, e))?;
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// An HTTP client honouring the `network` section of the user's config.
+pub fn http_client() -> Result<reqwest::Client> {
+    Config::load()?.network.client()
+}
+
+pub const DEFAULT_REGISTRY_REPO: &str = "ThembinkosiThemba/tkit-registry";
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct RegistryConfig {
+    /// Index of shared recipes; the community registry is used when unset.
+    pub index_url: Option<String>,
+    /// GitHub repository (owner/name) that `tkit publish` opens pull requests against.
+    pub repo: Option<String>,
+}
+
+impl RegistryConfig {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn index_url(&self) -> &str {
+        self.index_url.as_deref().unwrap_or(DEFAULT_REGISTRY_INDEX)
+    }
+
+    pub fn repo(&self) -> &str {
+        self.repo.as_deref().unwrap_or(DEFAULT_REGISTRY_REPO)
+    }
+}
+
+/// A registry index lists recipes; each `path` is relative to the index URL
+/// unless it is an absolute URL.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct RegistryIndex {
+    #[serde(default)]
+    pub tools: Vec<RegistryEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RegistryEntry {
+    pub name: String,
+    pub description: Option<String>,
+    pub path: String,
+}
+
+impl RegistryIndex {
+    pub fn find(&self, name: &str) -> Option<&RegistryEntry> {
+        self.tools.iter().find(|entry| entry.name == name)
+    }
+
+    /// Adds an entry, replacing any existing entry with the same name.
+    pub fn upsert(&mut self, entry: RegistryEntry) {
+        match self.tools.iter_mut().find(|e| e.name == entry.name) {
+            Some(existing) => *existing = entry,
+            None => {
+                self.tools.push(entry);
+                self.tools.sort_by(|a, b| a.name.cmp(&b.name));
+            }
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Config {
     pub fn new() -> Self {
         Self {
-            tools: HashMap::new(),
+            tools: BTreeMap::new(),
             sync: SyncConfig::default(),
+            registry: RegistryConfig::default(),
+            settings: Settings::default(),
+            network: NetworkConfig::default(),
+            variables: BTreeMap::new(),
+            overrides: BTreeMap::new(),
+            machines: BTreeMap::new(),
+            overridden: BTreeMap::new(),
         }
     }
 
     pub fn load() -> Result<Self> {
-        let config_path = get_config_path()?;
-        if !config_path.exists() {
-            return Ok(Config::new());
-        }
-
-        let content = fs::read_to_string(&config_path)?;
-        let config: Config = serde_yaml::from_str(&content)?;
-        Ok(config)
+        Self::load_from_path(&get_config_path()?)
     }
 
+    /// Loads the config with this machine's overrides applied.
     pub fn load_from_path(path: &PathBuf) -> Result<Self> {
+        Self::load_for_host(path, &overrides::hostname())
+    }
+
+    fn load_for_host(path: &PathBuf, host: &str) -> Result<Self> {
         if !path.exists() {
             return Ok(Config::new());
         }
 
         let content = fs::read_to_string(path)?;
-        let config: Config = serde_yaml::from_str(&content)?;
+        let mut config: Config = serde_yaml::from_str(&content).map_err(|e| {
+            ExitKind::Config.error(format!("Invalid config {}: {}", path.display(), e))
+        })?;
+        config.migrate_installed_flags();
+        let state = State::load_from_path(&path.with_file_name(STATE_FILE))?;
+        for (name, tool) in config.tools.iter_mut() {
+            if let Some(observed) = state.observed.get(name) {
+                tool.installed = observed.installed;
+                if !observed.param_values.is_empty() {
+                    tool.param_values = observed.param_values.clone();
+                }
+            }
+        }
+
+        if let Some(section) = config.overrides.get(host).cloned() {
+            config.apply_overrides(&section)?;
+        }
+        let overlay_path = overrides::overlay_path(path, host);
+        if overlay_path.exists() {
+            let overlay: MachineOverrides =
+                serde_yaml::from_str(&fs::read_to_string(&overlay_path)?).map_err(|e| {
+                    ExitKind::Config.error(format!(
+                        "Invalid overrides in {}: {}",
+                        overlay_path.display(),
+                        e
+                    ))
+                })?;
+            config.apply_overrides(&overlay)?;
+        }
+        Ok(config)
+    }
+
+    /// Configs written before desired and observed state were split carry an
+    /// `installed` flag per tool; such tools are wanted wherever the config is used.
+    pub fn migrate_installed_flags(&mut self) {
+        for tool in self.tools.values_mut() {
+            if tool.installed {
+                tool.desired_state = DesiredState::Present;
+            }
+        }
+    }
+
+    /// Takes which tools are installed, and the parameter values chosen, from
+    /// `local`, the config this one replaces on this machine.
+    pub fn adopt_observed_state(&mut self, local: &Config) {
+        for (name, tool) in self.tools.iter_mut() {
+            let local = local.tools.get(name);
+            tool.installed = local.is_some_and(|t| t.installed);
+            tool.param_values = local.map(|t| t.param_values.clone()).unwrap_or_default();
+        }
+    }
+
+    fn apply_overrides(&mut self, overlay: &MachineOverrides) -> Result<()> {
+        for (name, fields) in &overlay.tools {
+            let Some(tool) = self.tools.get_mut(name) else {
+                continue;
+            };
+            let mut value = serde_yaml::to_value(&*tool)?;
+            let mapping = value
+                .as_mapping_mut()
+                .ok_or_else(|| anyhow!("Tool '{}' is not a mapping", name))?;
+            let saved = self.overridden.entry(name.clone()).or_default();
+            for (key, new_value) in fields {
+                let key_name = key
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Invalid override key for tool '{}'", name))?;
+                saved
+                    .entry(key_name.to_string())
+                    .or_insert_with(|| mapping.get(key).cloned());
+                mapping.insert(key.clone(), new_value.clone());
+            }
+            *tool = serde_yaml::from_value(value)
+                .map_err(|e| anyhow!("Invalid override for tool '{}': {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Fields of a tool replaced by this machine's overrides.
+    pub fn overridden_fields(&self, name: &str) -> Vec<&str> {
+        self.overridden
+            .get(name)
+            .map(|fields| fields.keys().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// The shared config as stored on disk, with machine overrides undone.
+    pub fn without_overrides(&self) -> Result<Config> {
+        let mut config = self.clone();
+        for (name, fields) in &self.overridden {
+            let Some(tool) = config.tools.get_mut(name) else {
+                continue;
+            };
+            let mut value = serde_yaml::to_value(&*tool)?;
+            if let Some(mapping) = value.as_mapping_mut() {
+                for (key, shared) in fields {
+                    match shared {
+                        Some(shared) => mapping.insert(key.as_str().into(), shared.clone()),
+                        None => mapping.remove(key.as_str()),
+                    };
+                }
+            }
+            *tool = serde_yaml::from_value(value)?;
+        }
+        config.overridden.clear();
         Ok(config)
     }
 
@@ -82,14 +1279,121 @@ impl Config {
         self.save_to_path(&config_path)
     }
 
-    pub fn save_to_path(&self, path: &PathBuf) -> Result<()> {
+    /// Saves and records the change so `tkit undo` can revert it.
+    pub fn save_journaled(&self, action: &str) -> Result<()> {
+        self.save_to_path_journaled(&get_config_path()?, &journal::get_journal_path()?, action)
+    }
+
+    pub fn save_to_path_journaled(
+        &self,
+        path: &PathBuf,
+        journal_path: &std::path::Path,
+        action: &str,
+    ) -> Result<()> {
+        let before = fs::read_to_string(path).ok();
+        self.save_to_path(path)?;
+        journal::record(journal_path, action, before, fs::read_to_string(path)?)
+    }
+
+    pub fn save_to_path(&self, path: &Path) -> Result<()> {
+        self.validate_aliases()?;
+
         // Create config directory if it doesn't exist
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
 
-        let content = serde_yaml::to_string(self)?;
-        fs::write(path, content)?;
+        let content = serde_yaml::to_string(&self.without_overrides()?)?;
+        write_atomic(path, &content)?;
+
+        // What is installed, and with which parameter values, belongs to this
+        // machine, so it goes to the local state file
+        let state_path = path.with_file_name(STATE_FILE);
+        let mut state = State::load_from_path(&state_path)?;
+        state.observed = self
+            .tools
+            .iter()
+            .map(|(name, tool)| {
+                let observed = ObservedState {
+                    installed: tool.installed,
+                    param_values: tool.param_values.clone(),
+                };
+                (name.clone(), observed)
+            })
+            .collect();
+        state.save_to_path(&state_path)
+    }
+
+    /// Maps an alias to the tool it belongs to; any other name is returned as-is.
+    pub fn resolve_name(&self, name: &str) -> String {
+        self.alias_owner(name).unwrap_or(name).to_string()
+    }
+
+    fn alias_owner(&self, alias: &str) -> Option<&str> {
+        if self.tools.contains_key(alias) {
+            return None;
+        }
+        self.tools
+            .iter()
+            .find(|(_, tool)| tool.aliases.iter().any(|a| a == alias))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Parses a config as written by hand and checks what serde can't: tool
+    /// keys match their `name`, aliases are unambiguous, and schedules and
+    /// scripts parse.
+    pub fn parse_and_validate(content: &str) -> Result<Config> {
+        let config: Config = serde_yaml::from_str(content)?;
+        for (key, tool) in &config.tools {
+            if &tool.name != key {
+                return Err(anyhow!(
+                    "Tool '{}' has name '{}'; the key and name must match.",
+                    key,
+                    tool.name
+                ));
+            }
+            for (entry, expr) in tool.run_commands.scheduled() {
+                expr.parse::<Schedule>()
+                    .map_err(|e| anyhow!("Run entry '{}:{}': {}", key, entry, e))?;
+            }
+            for (name, hook) in tool.hooks.all() {
+                script::check(hook).map_err(|e| anyhow!("Hook '{}:{}': {}", key, name, e))?;
+            }
+            for step in tool.steps().filter(|step| step.script) {
+                script::check(&step.command)
+                    .map_err(|e| anyhow!("Script step of '{}': {}", key, e))?;
+            }
+        }
+        config.validate_aliases()?;
+        Ok(config)
+    }
+
+    /// Every alias must be unambiguous: not a tool name and not used twice.
+    pub fn validate_aliases(&self) -> Result<()> {
+        let mut seen: HashMap<&str, &str> = HashMap::new();
+        let names: Vec<&String> = self.tools.keys().collect();
+
+        for name in names {
+            for alias in &self.tools[name].aliases {
+                if self.tools.contains_key(alias) {
+                    return Err(anyhow!(
+                        "Alias '{}' of tool '{}' collides with the tool of that name.",
+                        alias,
+                        name
+                    ));
+                }
+                if let Some(other) = seen.insert(alias, name)
+                    && other != name
+                {
+                    return Err(anyhow!(
+                        "Alias '{}' is used by both '{}' and '{}'.",
+                        alias,
+                        other,
+                        name
+                    ));
+                }
+            }
+        }
         Ok(())
     }
 
@@ -97,10 +1401,93 @@ impl Config {
         if self.tools.contains_key(name) {
             return Err(anyhow!("Tool '{}' already exists.", name));
         }
+        if let Some(owner) = self.alias_owner(name) {
+            return Err(anyhow!("'{}' is already an alias of '{}'.", name, owner));
+        }
+        let mut tool_config = tool_config;
+        tool_config.touch();
+        if tool_config.created_at.is_none() {
+            tool_config.created_at = tool_config.updated_at.clone();
+        }
         self.tools.insert(name.to_string(), tool_config);
         Ok(())
     }
 
+    /// Duplicates a tool definition under a new name. The copy starts out
+    /// uninstalled since nothing has been installed under the new name yet.
+    pub fn copy_tool(&mut self, src: &str, dst: &str) -> Result<()> {
+        // From the shared definition: this machine's overrides stay with the
+        // original
+        let shared = self.without_overrides()?;
+        let source = shared
+            .tools
+            .get(&self.resolve_name(src))
+            .ok_or_else(|| tool_not_found(src))?;
+
+        let mut copy = source.shareable();
+        copy.name = dst.to_string();
+        // Aliases must stay unique, so they remain with the original
+        copy.aliases.clear();
+        self.add_tool(dst, copy)
+    }
+
+    /// Moves a tool to a new key, keeping its `name` field in step.
+    pub fn rename_tool(&mut self, old: &str, new: &str) -> Result<()> {
+        let old = self.resolve_name(old);
+        if old == new {
+            return Ok(());
+        }
+        if self.tools.contains_key(new) {
+            return Err(anyhow!("Tool '{}' already exists.", new));
+        }
+        if let Some(owner) = self.alias_owner(new)
+            && owner != old
+        {
+            return Err(anyhow!("'{}' is already an alias of '{}'.", new, owner));
+        }
+
+        let mut tool = self
+            .tools
+            .remove(&old)
+            .ok_or_else(|| tool_not_found(&old))?;
+        // Renaming a tool to one of its own aliases retires that alias
+        tool.aliases.retain(|alias| alias != new);
+        tool.touch();
+        tool.name = new.to_string();
+        self.tools.insert(new.to_string(), tool);
+        // Machine overrides follow the tool, so they are neither written into
+        // the shared definition nor left behind under the old name
+        if let Some(fields) = self.overridden.remove(&old) {
+            self.overridden.insert(new.to_string(), fields);
+        }
+        for overlay in self.overrides.values_mut() {
+            overlay.rename_tool(&old, new);
+        }
+
+        for other in self.tools.values_mut() {
+            for conflict in other.conflicts_with.iter_mut() {
+                if *conflict == old {
+                    *conflict = new.to_string();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether the flag actually changed.
+    pub fn set_disabled(&mut self, name: &str, disabled: bool) -> Result<bool> {
+        let tool = self
+            .tools
+            .get_mut(&self.resolve_name(name))
+            .ok_or_else(|| tool_not_found(name))?;
+        let changed = tool.disabled != disabled;
+        if changed {
+            tool.disabled = disabled;
+            tool.touch();
+        }
+        Ok(changed)
+    }
+
     pub fn remove_tool(&mut self, name: &str) -> Result<bool> {
         Ok(self.tools.remove(name).is_some())
     }
@@ -113,12 +1500,154 @@ impl Config {
         self.tools.get_mut(name)
     }
 
+    /// Names of the tools matching a shell-style pattern, sorted.
+    /// Tools matching `pattern`, in install order.
+    pub fn match_tools(&self, pattern: &str) -> Vec<String> {
+        self.ordered_names()
+            .into_iter()
+            .filter(|name| glob_match(pattern, name))
+            .cloned()
+            .collect()
+    }
+
+    /// Every tool name in install order: by `priority`, then by name.
+    pub fn ordered_names(&self) -> Vec<&String> {
+        let mut names: Vec<&String> = self.tools.keys().collect();
+        names.sort_by_key(|name| (self.tools[*name].priority, *name));
+        names
+    }
+
     pub fn list_tools(&self) -> Vec<(&String, &ToolConfig)> {
         self.tools.iter().collect()
     }
 
+    /// Installed tools that conflict with `name`, declared on either side, sorted.
+    pub fn installed_conflicts(&self, name: &str) -> Vec<String> {
+        let Some(tool) = self.tools.get(name) else {
+            return Vec::new();
+        };
+
+        let mut conflicts: Vec<String> = self
+            .tools
+            .iter()
+            .filter(|(other, other_tool)| {
+                *other != name
+                    && other_tool.installed
+                    && (tool
+                        .conflicts_with
+                        .iter()
+                        .any(|c| self.resolve_name(c) == **other)
+                        || other_tool.conflicts_with.iter().any(|c| c == name))
+            })
+            .map(|(other, _)| other.clone())
+            .collect();
+        conflicts.sort();
+        conflicts
+    }
+
+    /// Names of the tools modified since the last sync, sorted.
+    /// The config as it is pushed to sync remotes: private tools and
+    /// credentials are left out.
+    /// The value of a setting such as `sync.auto_sync` or `variables.region`,
+    /// or `None` when it isn't set.
+    pub fn setting(&self, key: &str) -> Result<Option<serde_yaml::Value>> {
+        check_setting_key(key)?;
+        let (section, field) = key.split_once('.').unwrap();
+        match section {
+            "sync" => section_value(&self.sync, field),
+            "settings" => section_value(&self.settings, field),
+            "registry" => section_value(&self.registry, field),
+            "network" => section_value(&self.network, field),
+            _ => section_value(&self.variables, field),
+        }
+    }
+
+    /// Sets a setting, or with `None` resets it to its default.
+    pub fn set_setting(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        check_setting_key(key)?;
+        let (section, field) = key.split_once('.').unwrap();
+        match section {
+            "sync" => edit_section(&mut self.sync, field, value),
+            "settings" => edit_section(&mut self.settings, field, value),
+            "registry" => edit_section(&mut self.registry, field, value),
+            "network" => edit_section(&mut self.network, field, value),
+            _ => {
+                match value {
+                    Some(value) => self.variables.insert(field.to_string(), value.to_string()),
+                    None => self.variables.remove(field),
+                };
+                Ok(())
+            }
+        }
+    }
+
+    /// Registers the values that must never be printed or logged: sync
+    /// tokens and the chosen values of parameters marked `secret`.
+    pub fn register_secrets(&self) {
+        let tokens = self.sync.token.iter();
+        for token in tokens.chain(self.sync.remotes.iter().filter_map(|r| r.token.as_ref())) {
+            redact::register_secret(token);
+        }
+        for tool in self.tools.values() {
+            for param in tool.parameters.iter().filter(|param| param.secret) {
+                if let Some(value) = tool.param_values.get(&param.name) {
+                    redact::register_secret(value);
+                }
+            }
+        }
+    }
+
+    pub fn for_push(&self) -> Result<Config> {
+        let mut config = self.without_overrides()?;
+        config.tools.retain(|_, tool| !tool.private);
+        for tool in config.tools.values_mut() {
+            let secrets: Vec<String> = tool
+                .parameters
+                .iter()
+                .filter(|param| param.secret)
+                .map(|param| param.name.clone())
+                .collect();
+            tool.param_values.retain(|name, _| !secrets.contains(name));
+        }
+        config.sync.token = None;
+        for remote in &mut config.sync.remotes {
+            remote.token = None;
+        }
+        Ok(config)
+    }
+
+    /// Hash of what this machine would push, leaving out the sync time and
+    /// the machine reports, which change on every push.
+    pub fn push_fingerprint(&self) -> Result<String> {
+        let mut config = self.for_push()?;
+        config.machines.clear();
+        config.sync.last_sync = None;
+        let content = format!("{}\n{}", machine::name(), serde_yaml::to_string(&config)?);
+        Ok(downloads::sha256_hex(content.as_bytes()))
+    }
+
+    /// Carries `local`'s private tools over into a freshly pulled config,
+    /// replacing any remote tool of the same name. Returns their names.
+    pub fn restore_private_tools(&mut self, local: &Config) -> Vec<String> {
+        let mut names = Vec::new();
+        for (name, tool) in local.tools.iter().filter(|(_, tool)| tool.private) {
+            self.tools.insert(name.clone(), tool.clone());
+            names.push(name.clone());
+        }
+        names
+    }
+
+    pub fn changed_since_last_sync(&self) -> Vec<String> {
+        self.tools
+            .iter()
+            .filter(|(_, tool)| !tool.private)
+            .filter(|(_, tool)| tool.changed_since(self.sync.last_sync.as_deref()))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
     pub fn should_auto_sync(&self) -> bool {
-        self.sync.auto_sync && self.sync.repo.is_some() && self.sync.token.is_some()
+        self.sync.auto_sync && !self.sync.all_remotes().is_empty()
     }
 }
 
@@ -128,11 +1657,86 @@ pub fn get_config_path() -> Result<PathBuf> {
     Ok(config_dir.join("tkit").join("config.yaml"))
 }
 
+/// Directory for command output logs, `~/.config/tkit/logs`.
+pub fn get_log_dir() -> Result<PathBuf> {
+    Ok(get_config_path()?.with_file_name("logs"))
+}
+
+/// Directory tkit owns for the executables it installs itself.
+pub const BIN_DIR: &str = "~/.local/share/tkit/bin";
+
+pub fn get_bin_dir() -> Result<PathBuf> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow!("Could not determine home directory"))?;
+    Ok(home.join(BIN_DIR.trim_start_matches("~/")))
+}
+
+/// Whether `dir` is one of the directories on PATH.
+pub fn on_path(dir: &Path) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|entry| entry == dir))
+}
+
+/// Writes `content` to a temporary file next to `path` and renames it into
+/// place, so a crash or power loss never leaves `path` half written. An
+/// existing file keeps its permissions.
+pub fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    write_replacing(path, content, false)
+}
+
+/// Like [`write_atomic`], for files that may hold secrets: on Unix they are
+/// readable by the user only (0600).
+pub fn write_private(path: &Path, content: &str) -> Result<()> {
+    write_replacing(path, content, true)
+}
+
+fn write_replacing(path: &Path, content: &str, private: bool) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".tmp");
+    let partial = PathBuf::from(partial);
+    {
+        use std::io::Write;
+
+        let mut file = fs::File::create(&partial)?;
+        // Restricted before anything is written to it
+        if private {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&partial, fs::Permissions::from_mode(0o600))?;
+            }
+        } else if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&partial, metadata.permissions())?;
+        }
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.yaml");
+        write_atomic(&path, "a").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        write_atomic(&path, "b").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "b");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
     #[test]
     fn test_config_new() {
         let config = Config::new();
@@ -146,11 +1750,12 @@ mod tests {
         let tool_config = ToolConfig {
             name: "test".to_string(),
             description: Some("Test tool".to_string()),
-            install_commands: vec!["echo install".to_string()],
-            remove_commands: vec!["echo remove".to_string()],
-            update_commands: vec!["echo update".to_string()],
-            run_commands: vec!["echo run".to_string()],
+            install_commands: vec!["echo install".into()],
+            remove_commands: vec!["echo remove".into()],
+            update_commands: vec!["echo update".into()],
+            run_commands: vec!["echo run".to_string()].into(),
             installed: false,
+            ..Default::default()
         };
 
         assert!(config.add_tool("test", tool_config).is_ok());
@@ -163,11 +1768,7 @@ mod tests {
         let tool_config = ToolConfig {
             name: "test".to_string(),
             description: Some("Test tool".to_string()),
-            install_commands: vec![],
-            remove_commands: vec![],
-            update_commands: vec![],
-            run_commands: vec![],
-            installed: false,
+            ..Default::default()
         };
 
         config.add_tool("test", tool_config.clone()).unwrap();
@@ -180,11 +1781,7 @@ mod tests {
         let tool_config = ToolConfig {
             name: "test".to_string(),
             description: Some("Test tool".to_string()),
-            install_commands: vec![],
-            remove_commands: vec![],
-            update_commands: vec![],
-            run_commands: vec![],
-            installed: false,
+            ..Default::default()
         };
 
         config.add_tool("test", tool_config).unwrap();
@@ -192,6 +1789,190 @@ mod tests {
         assert!(!config.tools.contains_key("test"));
     }
 
+    #[test]
+    fn test_copy_tool() {
+        let mut config = Config::new();
+        let tool_config = ToolConfig {
+            name: "node".to_string(),
+            install_commands: vec!["install node".into()],
+            installed: true,
+            ..Default::default()
+        };
+        config.add_tool("node", tool_config).unwrap();
+
+        config.copy_tool("node", "node-lts").unwrap();
+        let copy = config.get_tool("node-lts").unwrap();
+        assert_eq!(copy.name, "node-lts");
+        assert_eq!(copy.install_commands, vec!["install node"]);
+        assert!(!copy.installed);
+        assert!(config.get_tool("node").unwrap().installed);
+
+        assert!(config.copy_tool("node", "node-lts").is_err());
+        assert!(config.copy_tool("missing", "other").is_err());
+    }
+
+    #[test]
+    fn test_rename_tool() {
+        let mut config = Config::new();
+        for name in ["kubectl", "helm"] {
+            let tool_config = ToolConfig {
+                name: name.to_string(),
+                installed: true,
+                ..Default::default()
+            };
+            config.add_tool(name, tool_config).unwrap();
+        }
+
+        config.rename_tool("kubectl", "k8s-cli").unwrap();
+        assert!(config.get_tool("kubectl").is_none());
+        let renamed = config.get_tool("k8s-cli").unwrap();
+        assert_eq!(renamed.name, "k8s-cli");
+        assert!(renamed.installed);
+
+        assert!(config.rename_tool("k8s-cli", "helm").is_err());
+        assert!(config.rename_tool("missing", "other").is_err());
+    }
+
+    #[test]
+    fn test_timestamps_maintained() {
+        let mut config = Config::new();
+        config
+            .add_tool(
+                "jq",
+                ToolConfig {
+                    name: "jq".to_string(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let tool = config.get_tool("jq").unwrap();
+        assert!(tool.created_at.is_some());
+        assert_eq!(tool.created_at, tool.updated_at);
+        assert!(tool.shareable().created_at.is_none());
+
+        config.sync.last_sync = Some("2000-01-01T00:00:00+00:00".to_string());
+        assert_eq!(config.changed_since_last_sync(), vec!["jq"]);
+        config.sync.last_sync = Some("2999-01-01T00:00:00+00:00".to_string());
+        assert!(config.changed_since_last_sync().is_empty());
+
+        config.get_tool_mut("jq").unwrap().updated_at = Some("2001-01-01T00:00:00+00:00".into());
+        config.set_disabled("jq", true).unwrap();
+        assert!(
+            config
+                .get_tool("jq")
+                .unwrap()
+                .changed_since(Some("2002-01-01T00:00:00+00:00"))
+        );
+    }
+
+    #[test]
+    fn test_installed_conflicts() {
+        let mut config = Config::new();
+        for (name, conflicts, installed) in [
+            ("docker", vec!["podman-docker"], false),
+            ("podman-docker", vec![], true),
+            ("nvm", vec![], true),
+            ("fnm", vec!["nvm"], false),
+        ] {
+            config.tools.insert(
+                name.to_string(),
+                ToolConfig {
+                    name: name.to_string(),
+                    conflicts_with: conflicts.into_iter().map(String::from).collect(),
+                    installed,
+                    ..Default::default()
+                },
+            );
+        }
+
+        assert_eq!(config.installed_conflicts("docker"), vec!["podman-docker"]);
+        assert_eq!(config.installed_conflicts("fnm"), vec!["nvm"]);
+        assert!(config.installed_conflicts("nvm").is_empty());
+
+        config.tools.get_mut("fnm").unwrap().installed = true;
+        config.tools.get_mut("nvm").unwrap().installed = false;
+        assert_eq!(config.installed_conflicts("nvm"), vec!["fnm"]);
+
+        config.rename_tool("podman-docker", "podman").unwrap();
+        assert_eq!(config.tools["docker"].conflicts_with, vec!["podman"]);
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut config = Config::new();
+        config
+            .add_tool(
+                "kubectl",
+                ToolConfig {
+                    name: "kubectl".to_string(),
+                    aliases: vec!["k".to_string()],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert_eq!(config.resolve_name("k"), "kubectl");
+        assert_eq!(config.resolve_name("helm"), "helm");
+        assert!(config.add_tool("k", ToolConfig::default()).is_err());
+
+        config.set_disabled("k", true).unwrap();
+        assert!(config.get_tool("kubectl").unwrap().disabled);
+
+        config.rename_tool("k", "kube").unwrap();
+        assert_eq!(config.resolve_name("k"), "kube");
+        config.rename_tool("kube", "k").unwrap();
+        assert!(config.get_tool("k").unwrap().aliases.is_empty());
+    }
+
+    #[test]
+    fn test_alias_collisions_rejected_on_save() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.yaml");
+        let mut config = Config::new();
+        for (name, alias) in [("kubectl", "k"), ("k9s", "k")] {
+            config.tools.insert(
+                name.to_string(),
+                ToolConfig {
+                    name: name.to_string(),
+                    aliases: vec![alias.to_string()],
+                    ..Default::default()
+                },
+            );
+        }
+        assert!(config.save_to_path(&path).is_err());
+
+        config.tools.get_mut("k9s").unwrap().aliases = vec!["kubectl".to_string()];
+        assert!(config.save_to_path(&path).is_err());
+
+        config.tools.get_mut("k9s").unwrap().aliases.clear();
+        assert!(config.save_to_path(&path).is_ok());
+    }
+
+    #[test]
+    fn test_set_disabled() {
+        let mut config = Config::new();
+        config
+            .add_tool(
+                "docker",
+                ToolConfig {
+                    name: "docker".to_string(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert!(config.set_disabled("docker", true).unwrap());
+        assert!(!config.set_disabled("docker", true).unwrap());
+        assert!(config.get_tool("docker").unwrap().disabled);
+        assert!(config.set_disabled("missing", true).is_err());
+
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(yaml.contains("disabled: true"));
+        config.set_disabled("docker", false).unwrap();
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("disabled"));
+    }
+
     #[test]
     fn test_remove_nonexistent_tool() {
         let mut config = Config::new();
@@ -207,11 +1988,12 @@ mod tests {
         let tool_config = ToolConfig {
             name: "test".to_string(),
             description: Some("Test tool".to_string()),
-            install_commands: vec!["install cmd".to_string()],
-            remove_commands: vec!["remove cmd".to_string()],
-            update_commands: vec!["update cmd".to_string()],
-            run_commands: vec!["run cmd".to_string()],
+            install_commands: vec!["install cmd".into()],
+            remove_commands: vec!["remove cmd".into()],
+            update_commands: vec!["update cmd".into()],
+            run_commands: vec!["run cmd".to_string()].into(),
             installed: true,
+            ..Default::default()
         };
 
         config.add_tool("test", tool_config).unwrap();
@@ -220,7 +2002,7 @@ mod tests {
         let loaded_config = Config::load_from_path(&config_path).unwrap();
         assert_eq!(loaded_config.tools.len(), 1);
         assert!(loaded_config.tools.contains_key("test"));
-        
+
         let tool = loaded_config.get_tool("test").unwrap();
         assert_eq!(tool.name, "test");
         assert_eq!(tool.description, Some("Test tool".to_string()));
@@ -228,12 +2010,508 @@ mod tests {
         assert!(tool.installed);
     }
 
+    #[test]
+    fn test_save_is_deterministic() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+
+        let mut config = Config::new();
+        for name in ["zsh", "git", "kubectl", "asdf"] {
+            let tool_config = ToolConfig {
+                name: name.to_string(),
+                ..Default::default()
+            };
+            config.add_tool(name, tool_config).unwrap();
+        }
+        config.save_to_path(&config_path).unwrap();
+        let first = fs::read_to_string(&config_path).unwrap();
+
+        Config::load_from_path(&config_path)
+            .unwrap()
+            .save_to_path(&config_path)
+            .unwrap();
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), first);
+
+        let positions: Vec<usize> = ["asdf:", "git:", "kubectl:", "zsh:"]
+            .iter()
+            .map(|key| first.find(key).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_tool_from_yaml() {
+        let yaml = "name: rg\ndescription: ripgrep\ninstall_commands:\n  - cargo install ripgrep\ninstalled: true\n";
+        let tool = ToolConfig::from_yaml(yaml).unwrap();
+        assert_eq!(tool.name, "rg");
+        assert_eq!(tool.install_commands, vec!["cargo install ripgrep"]);
+        assert!(tool.remove_commands.is_empty());
+        assert!(!tool.installed);
+    }
+
+    #[test]
+    fn test_tool_from_yaml_invalid() {
+        assert!(ToolConfig::from_yaml("description: no name").is_err());
+        assert!(ToolConfig::from_yaml("name: ''").is_err());
+    }
+
+    #[test]
+    fn test_registry_defaults() {
+        let config = Config::new();
+        assert_eq!(config.registry.index_url(), DEFAULT_REGISTRY_INDEX);
+
+        let yaml = serde_yaml::to_string(&config).unwrap();
+        assert!(!yaml.contains("registry"));
+    }
+
+    #[test]
+    fn test_registry_index_find() {
+        let yaml =
+            "tools:\n  - name: ripgrep\n    description: Fast grep\n    path: tools/ripgrep.yaml\n";
+        let index: RegistryIndex = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(index.find("ripgrep").unwrap().path, "tools/ripgrep.yaml");
+        assert!(index.find("fd").is_none());
+    }
+
+    #[test]
+    fn test_registry_index_upsert() {
+        let mut index = RegistryIndex::default();
+        for name in ["zoxide", "bat"] {
+            index.upsert(RegistryEntry {
+                name: name.to_string(),
+                description: None,
+                path: format!("tools/{}.yaml", name),
+            });
+        }
+        index.upsert(RegistryEntry {
+            name: "bat".to_string(),
+            description: Some("cat clone".to_string()),
+            path: "tools/bat.yaml".to_string(),
+        });
+
+        assert_eq!(index.tools.len(), 2);
+        assert_eq!(index.tools[0].name, "bat");
+        assert_eq!(index.tools[0].description.as_deref(), Some("cat clone"));
+    }
+
+    #[test]
+    fn test_shareable_resets_installed() {
+        let tool = ToolConfig {
+            name: "test".to_string(),
+            description: None,
+            installed: true,
+            ..Default::default()
+        };
+        assert!(!tool.shareable().installed);
+    }
+
+    #[test]
+    fn test_commit_message_template() {
+        let mut sync = SyncConfig::default();
+        assert_eq!(
+            sync.commit_message("install", "docker", "laptop", "now"),
+            None
+        );
+
+        sync.commit_message_template = Some("{{action}} {{tool}} on {{machine}}".to_string());
+        assert_eq!(
+            sync.commit_message("install", "docker", "work-laptop", "now")
+                .as_deref(),
+            Some("install docker on work-laptop")
+        );
+        assert_eq!(
+            sync.commit_message("edit", "", "work-laptop", "now")
+                .as_deref(),
+            Some("edit on work-laptop")
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        let vars = BTreeMap::from([("version".to_string(), "1.5.0".to_string())]);
+        assert_eq!(
+            render_template("terraform_{{version}}_{{ version }}.zip", &vars),
+            "terraform_1.5.0_1.5.0.zip"
+        );
+        assert_eq!(
+            render_template("echo {{missing}}", &vars),
+            "echo {{missing}}"
+        );
+        assert_eq!(render_template("echo ${HOME}", &vars), "echo ${HOME}");
+    }
+
+    #[test]
+    fn test_parameters_roundtrip() {
+        let yaml = "name: terraform\nparameters:\n  - name: version\n    default: 1.5.0\ninstall_commands:\n  - wget terraform_{{version}}.zip\n";
+        let tool = ToolConfig::from_yaml(yaml).unwrap();
+        assert_eq!(tool.parameters[0].name, "version");
+        assert_eq!(tool.parameters[0].default.as_deref(), Some("1.5.0"));
+
+        let yaml = "name: app\nprompts:\n  - name: license_key\n    message: License key\n    secret: true\n";
+        let tool = ToolConfig::from_yaml(yaml).unwrap();
+        assert_eq!(tool.parameters[0].prompt.as_deref(), Some("License key"));
+        assert!(tool.parameters[0].secret);
+
+        let serialized = serde_yaml::to_string(&ToolConfig::default()).unwrap();
+        assert!(!serialized.contains("parameters"));
+        assert!(!serialized.contains("param_values"));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("k8s-*", "k8s-helm"));
+        assert!(glob_match("*-cli", "aws-cli"));
+        assert!(!glob_match("*-cli", "aws-cli-v2"));
+        assert!(glob_match("no?e", "node"));
+        assert!(glob_match("[gk]it", "git"));
+        assert!(glob_match("tool[0-9]", "tool7"));
+        assert!(!glob_match("tool[!0-9]", "tool7"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("git", "gitk"));
+        assert!(is_glob("k8s-*"));
+        assert!(!is_glob("kubectl"));
+    }
+
+    #[test]
+    fn test_match_tools_sorted() {
+        let mut config = Config::new();
+        for name in ["k8s-kind", "k8s-helm", "git"] {
+            let tool_config = ToolConfig {
+                name: name.to_string(),
+                ..Default::default()
+            };
+            config.add_tool(name, tool_config).unwrap();
+        }
+        assert_eq!(config.match_tools("k8s-*"), vec!["k8s-helm", "k8s-kind"]);
+        assert!(config.match_tools("*-cli").is_empty());
+    }
+
+    #[test]
+    fn test_settings_get_set_unset() {
+        let mut config = Config::new();
+        config.set_setting("sync.auto_sync", Some("true")).unwrap();
+        config
+            .set_setting("settings.output_lines", Some("20"))
+            .unwrap();
+        config.set_setting("settings.shell", Some("bash")).unwrap();
+        config
+            .set_setting("network.proxy", Some("http://proxy:8080"))
+            .unwrap();
+        config
+            .set_setting("variables.version", Some("1.20"))
+            .unwrap();
+        assert!(config.sync.auto_sync);
+        assert_eq!(config.settings.output_lines, Some(20));
+        assert_eq!(config.settings.shell, Some(CommandShell::Bash));
+        assert_eq!(config.variables["version"], "1.20");
+        assert_eq!(
+            config.setting("network.proxy").unwrap(),
+            Some(serde_yaml::Value::String("http://proxy:8080".to_string()))
+        );
+
+        config.set_setting("settings.output_lines", None).unwrap();
+        config.set_setting("variables.version", None).unwrap();
+        assert_eq!(config.settings.output_lines, None);
+        assert_eq!(config.setting("settings.output_lines").unwrap(), None);
+        assert!(config.variables.is_empty());
+
+        assert!(
+            config
+                .set_setting("settings.output_lines", Some("many"))
+                .is_err()
+        );
+        assert!(config.set_setting("sync.auto_sync", Some("maybe")).is_err());
+        assert!(config.set_setting("settings.colour", Some("auto")).is_err());
+        assert!(config.set_setting("tools.rg", Some("x")).is_err());
+        assert!(config.setting("variables.").is_err());
+    }
+
+    #[test]
+    fn test_settings_defaults_and_modes() {
+        let settings: Settings =
+            serde_yaml::from_str("timeout: 5\ncolor: never\neditor: nano\nconfirm: yes\njobs: 0\n")
+                .unwrap();
+        assert_eq!(settings.check_timeout(), 5);
+        assert_eq!(settings.color, ColorMode::Never);
+        assert_eq!(settings.editor(), "nano");
+        assert_eq!(settings.confirm, ConfirmMode::Yes);
+        assert_eq!(settings.jobs(), 1);
+
+        let defaults = Settings::default();
+        assert_eq!(defaults.check_timeout(), DEFAULT_CHECK_TIMEOUT);
+        assert!(defaults.is_default());
+        assert_eq!(
+            serde_yaml::to_string(&defaults).unwrap(),
+            "rollback_on_failure: false\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_and_validate() {
+        assert!(Config::parse_and_validate("tools:\n  rg:\n    name: rg\n").is_ok());
+        assert!(Config::parse_and_validate("tools: [").is_err());
+        assert!(Config::parse_and_validate("tools:\n  rg:\n    name: ripgrep\n").is_err());
+        assert!(
+            Config::parse_and_validate(
+                "tools:\n  rg:\n    name: rg\n    run_commands:\n      nightly:\n        commands: [rg]\n        schedule: soon\n"
+            )
+            .is_err()
+        );
+        for broken in [
+            "    hooks:\n      before_install: 'if {'\n",
+            "    arch_commands:\n      arm64:\n        install_commands:\n          - script: 'let = 1'\n",
+        ] {
+            let yaml = format!("tools:\n  rg:\n    name: rg\n{}", broken);
+            assert!(Config::parse_and_validate(&yaml).is_err(), "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn test_match_tools_by_priority() {
+        let mut config = Config::new();
+        for (name, priority) in [("apt-update", -10), ("go", 0), ("gopls", 10), ("git", 0)] {
+            let tool_config = ToolConfig {
+                name: name.to_string(),
+                priority,
+                ..Default::default()
+            };
+            config.add_tool(name, tool_config).unwrap();
+        }
+        assert_eq!(
+            config.match_tools("*"),
+            vec!["apt-update", "git", "go", "gopls"]
+        );
+
+        let tool = ToolConfig::from_yaml("name: gopls\norder: 5\n").unwrap();
+        assert_eq!(tool.priority, 5);
+    }
+
+    #[test]
+    fn test_commands_for_package() {
+        let yaml = "name: rg\npackage:\n  manager: brew\n  name: ripgrep\nupdate_commands:\n  - brew upgrade ripgrep --fetch-HEAD\n";
+        let tool = ToolConfig::from_yaml(yaml).unwrap();
+        assert_eq!(
+            tool.commands_for(ToolAction::Install),
+            vec!["brew install ripgrep"]
+        );
+        assert_eq!(
+            tool.commands_for(ToolAction::Update),
+            vec!["brew upgrade ripgrep --fetch-HEAD"]
+        );
+
+        let plain = ToolConfig::default();
+        assert!(plain.commands_for(ToolAction::Remove).is_empty());
+    }
+
+    #[test]
+    fn test_tool_from_package() {
+        let tool = ToolConfig::from_package(PackageManager::Cargo, "ripgrep", Some("rg"), None);
+        assert_eq!(tool.name, "ripgrep");
+        assert_eq!(tool.binary.as_deref(), Some("rg"));
+        assert_eq!(tool.run_commands.default_commands(), ["rg --version"]);
+        assert_eq!(
+            tool.commands_for(ToolAction::Remove),
+            vec!["cargo uninstall ripgrep"]
+        );
+        assert_eq!(
+            tool.description.as_deref(),
+            Some("ripgrep (installed with cargo)")
+        );
+    }
+
     #[test]
     fn test_load_empty_config() {
         let temp_dir = TempDir::new().unwrap();
         let config_path = temp_dir.path().join("nonexistent.yaml");
-        
+
         let config = Config::load_from_path(&config_path).unwrap();
         assert!(config.tools.is_empty());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_network_client() {
+        let network = NetworkConfig {
+            proxy: Some("http://proxy.example:8080".to_string()),
+            no_proxy: Some("localhost,.corp.example".to_string()),
+            ca_cert: None,
+        };
+        assert!(network.client().is_ok());
+
+        let network = NetworkConfig {
+            ca_cert: Some("/nonexistent/ca.pem".to_string()),
+            ..Default::default()
+        };
+        let err = network.client().unwrap_err();
+        assert!(err.to_string().contains("network.ca_cert"));
+    }
+
+    #[test]
+    fn test_all_remotes_lists_primary_first() {
+        let sync = SyncConfig {
+            repo: Some("me/tools".to_string()),
+            token: Some("main-token".to_string()),
+            remotes: vec![
+                SyncRemote {
+                    name: "team".to_string(),
+                    repo: Some("team/tools".to_string()),
+                    ..Default::default()
+                },
+                SyncRemote {
+                    name: "backup".to_string(),
+                    path: Some("/mnt/backup/tkit.yaml".to_string()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let remotes = sync.all_remotes();
+        let names: Vec<&str> = remotes.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec![PRIMARY_REMOTE, "team", "backup"]);
+        assert_eq!(remotes[1].token.as_deref(), Some("main-token"));
+        assert_eq!(remotes[2].token, None);
+        assert!(sync.remote("missing").is_none());
+    }
+
+    #[test]
+    fn test_private_tools_stay_local() {
+        let mut local = Config::new();
+        local.sync.token = Some("secret".to_string());
+        for (name, private) in [("jq", false), ("work-vpn", true)] {
+            local
+                .add_tool(
+                    name,
+                    ToolConfig {
+                        name: name.to_string(),
+                        private,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+        }
+
+        let pushed = local.for_push().unwrap();
+        assert!(pushed.tools.contains_key("jq"));
+        assert!(!pushed.tools.contains_key("work-vpn"));
+        assert!(pushed.sync.token.is_none());
+
+        let mut pulled = pushed.clone();
+        assert_eq!(pulled.restore_private_tools(&local), vec!["work-vpn"]);
+        assert!(pulled.tools["work-vpn"].private);
+    }
+
+    #[test]
+    fn test_machine_overrides_are_not_saved() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(
+            &config_path,
+            "tools:\n  docker:\n    name: docker\n    install_commands: ['apt install docker']\n  jq:\n    name: jq\nsync: {}\noverrides:\n  laptop:\n    tools:\n      jq:\n        disabled: true\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("config.laptop.yaml"),
+            "tools:\n  docker:\n    install_commands: ['brew install docker']\n",
+        )
+        .unwrap();
+
+        let mut config = Config::load_for_host(&config_path, "laptop").unwrap();
+        assert_eq!(
+            config.tools["docker"].install_commands,
+            vec!["brew install docker"]
+        );
+        assert!(config.tools["jq"].disabled);
+        assert_eq!(config.overridden_fields("docker"), vec!["install_commands"]);
+
+        config.tools.get_mut("docker").unwrap().desired_state = DesiredState::Present;
+        config.copy_tool("docker", "podman").unwrap();
+        assert_eq!(
+            config.tools["podman"].install_commands,
+            vec!["apt install docker"]
+        );
+        config.save_to_path(&config_path).unwrap();
+        let saved = fs::read_to_string(&config_path).unwrap();
+        assert!(saved.contains("apt install docker"));
+        assert!(!saved.contains("brew install docker"));
+        assert!(saved.contains("desired_state: present"));
+
+        let other = Config::load_for_host(&config_path, "desktop").unwrap();
+        assert!(!other.tools["jq"].disabled);
+    }
+
+    #[test]
+    fn test_installed_state_is_kept_out_of_the_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.yaml");
+        fs::write(
+            &config_path,
+            "tools:\n  rg:\n    name: rg\n    installed: true\n    param_values:\n      version: '14'\n  fd:\n    name: fd\nsync: {}\n",
+        )
+        .unwrap();
+
+        // An older config's installed flag becomes desired and observed state
+        let mut config = Config::load_from_path(&config_path).unwrap();
+        assert!(config.tools["rg"].installed);
+        assert!(config.tools["rg"].desired_state.is_present());
+        assert!(config.tools["fd"].desired_state.is_absent());
+
+        config.tools.get_mut("fd").unwrap().installed = true;
+        config.save_to_path(&config_path).unwrap();
+        let saved = fs::read_to_string(&config_path).unwrap();
+        assert!(!saved.contains("installed"));
+        assert!(!saved.contains("param_values"), "{}", saved);
+        let state = State::load_from_path(&temp_dir.path().join(STATE_FILE)).unwrap();
+        assert!(state.observed["fd"].installed);
+        assert_eq!(state.observed["rg"].param_values["version"], "14");
+
+        let loaded = Config::load_from_path(&config_path).unwrap();
+        assert!(loaded.tools["rg"].installed);
+        assert_eq!(loaded.tools["rg"].param_values["version"], "14");
+        assert!(loaded.tools["fd"].installed);
+        assert!(loaded.tools["fd"].desired_state.is_absent());
+    }
+
+    #[test]
+    fn test_run_commands_accept_list_or_map() {
+        let tool: ToolConfig =
+            serde_yaml::from_str("name: app\nrun_commands: ['app start']\n").unwrap();
+        assert_eq!(tool.run_commands.default_commands(), ["app start"]);
+        assert!(
+            serde_yaml::to_string(&tool)
+                .unwrap()
+                .contains("run_commands:\n- app start")
+        );
+
+        let tool: ToolConfig = serde_yaml::from_str(
+            "name: app\nrun_commands:\n  default: ['app start']\n  logs: ['app logs -f']\n",
+        )
+        .unwrap();
+        assert_eq!(tool.run_commands.names(), vec!["default", "logs"]);
+        assert_eq!(tool.run_commands.get("logs").unwrap(), ["app logs -f"]);
+        let round_trip: ToolConfig =
+            serde_yaml::from_str(&serde_yaml::to_string(&tool).unwrap()).unwrap();
+        assert_eq!(round_trip.run_commands, tool.run_commands);
+    }
+
+    #[test]
+    fn test_arch_commands_and_variables() {
+        let tool: ToolConfig = serde_yaml::from_str(&format!(
+            "name: go\ninstall_commands: ['tar -xzf go-{{{{os}}}}-{{{{arch}}}}.tar.gz']\narch_commands:\n  {}:\n    install_commands: ['special']\n",
+            arch()
+        ))
+        .unwrap();
+        assert_eq!(tool.commands_for(ToolAction::Install), vec!["special"]);
+        assert!(tool.commands_for(ToolAction::Remove).is_empty());
+
+        let rendered = render_commands(&tool.install_commands, &BTreeMap::new());
+        assert_eq!(
+            rendered,
+            vec![format!("tar -xzf go-{}-{}.tar.gz", os(), arch())]
+        );
+        let overridden = render_commands(
+            &tool.install_commands,
+            &BTreeMap::from([("arch".to_string(), "riscv64".to_string())]),
+        );
+        assert!(overridden[0].command.ends_with("-riscv64.tar.gz"));
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::downloads::sha256_hex;
+use crate::{ToolConfig, get_config_path};
+
+/// The exact versions installed on a machine, written to `tkit.lock` after
+/// installs and updates so `tkit install --locked` can reproduce them.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub tools: BTreeMap<String, LockedTool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct LockedTool {
+    /// Resolved version, when the recipe or its package manager tells us one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// RFC 3339 time of the install or update.
+    pub installed_at: String,
+    /// [`recipe_hash`] of the definition that was used.
+    pub recipe_hash: String,
+}
+
+impl Lockfile {
+    pub fn load() -> Result<Self> {
+        Self::load_from_path(&get_lockfile_path()?)
+    }
+
+    pub fn load_from_path(path: &PathBuf) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Lockfile::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to_path(&get_lockfile_path()?)
+    }
+
+    pub fn save_to_path(&self, path: &PathBuf) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, tool: &ToolConfig, version: Option<String>, now: &str) {
+        self.tools.insert(
+            tool.name.clone(),
+            LockedTool {
+                version,
+                installed_at: now.to_string(),
+                recipe_hash: recipe_hash(tool),
+            },
+        );
+    }
+}
+
+/// SHA-256 of a tool's definition without its machine-specific state
+/// (installed flag, desired state, timestamps, chosen parameter values, pin),
+/// so it only changes when the recipe itself does.
+pub fn recipe_hash(tool: &ToolConfig) -> String {
+    let recipe = ToolConfig {
+        installed: false,
+        desired_state: Default::default(),
+        created_at: None,
+        updated_at: None,
+        param_values: BTreeMap::new(),
+        pinned: None,
+        disabled: false,
+        ..tool.clone()
+    };
+    let yaml = serde_yaml::to_string(&recipe).unwrap_or_default();
+    sha256_hex(yaml.as_bytes())
+}
+
+pub fn get_lockfile_path() -> Result<PathBuf> {
+    Ok(get_config_path()?.with_file_name("tkit.lock"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_recipe_hash_ignores_machine_state() {
+        let tool = ToolConfig {
+            name: "rg".to_string(),
+            install_commands: vec!["cargo install ripgrep".into()],
+            ..Default::default()
+        };
+        let installed = ToolConfig {
+            installed: true,
+            updated_at: Some("2026-01-01T00:00:00+00:00".to_string()),
+            ..tool.clone()
+        };
+        let changed = ToolConfig {
+            install_commands: vec!["brew install ripgrep".into()],
+            ..tool.clone()
+        };
+        assert_eq!(recipe_hash(&tool), recipe_hash(&installed));
+        assert_ne!(recipe_hash(&tool), recipe_hash(&changed));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("tkit.lock");
+        let tool = ToolConfig {
+            name: "go".to_string(),
+            ..Default::default()
+        };
+
+        let mut lock = Lockfile::default();
+        lock.record(
+            &tool,
+            Some("1.22.1".to_string()),
+            "2026-01-01T00:00:00+00:00",
+        );
+        lock.save_to_path(&path).unwrap();
+
+        let loaded = Lockfile::load_from_path(&path).unwrap();
+        assert_eq!(loaded, lock);
+        assert_eq!(loaded.tools["go"].version.as_deref(), Some("1.22.1"));
+    }
+}
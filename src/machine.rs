@@ -0,0 +1,120 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::get_config_path;
+use crate::overrides::{self, HOSTNAME_ENV};
+
+/// Who this machine is in sync commits, history entries and the fleet view.
+/// Registered on first use, named after the hostname until
+/// `tkit machine set-name` gives it a better one.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Machine {
+    pub name: String,
+    pub os: String,
+    pub arch: String,
+    /// When tkit first ran here (RFC 3339).
+    pub first_seen: String,
+}
+
+impl Machine {
+    pub fn load_from_path(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(Some(serde_yaml::from_str(&content)?))
+    }
+
+    pub fn save_to_path(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// This machine's identity, registering it if it has none yet.
+    pub fn current() -> Result<Self> {
+        Self::current_at(&get_machine_path()?, &chrono::Utc::now().to_rfc3339())
+    }
+
+    pub fn current_at(path: &Path, now: &str) -> Result<Self> {
+        if let Some(machine) = Self::load_from_path(path)? {
+            return Ok(machine);
+        }
+
+        let machine = Machine {
+            name: overrides::hostname(),
+            os: crate::os().to_string(),
+            arch: crate::arch().to_string(),
+            first_seen: now.to_string(),
+        };
+        machine.save_to_path(path)?;
+        Ok(machine)
+    }
+}
+
+/// Checks a name given to `tkit machine set-name`.
+pub fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.chars().any(|c| c.is_whitespace() || c == ':') {
+        return Err(anyhow!(
+            "Invalid machine name '{}': use a single word, e.g. work-laptop",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Name of this machine: `TKIT_HOSTNAME` when set, then the registered
+/// name, then the hostname. Never registers, so it is safe to call anywhere.
+pub fn name() -> String {
+    if let Ok(name) = std::env::var(HOSTNAME_ENV)
+        && !name.is_empty()
+    {
+        return name;
+    }
+
+    get_machine_path()
+        .ok()
+        .and_then(|path| Machine::load_from_path(&path).ok().flatten())
+        .map(|machine| machine.name)
+        .unwrap_or_else(overrides::hostname)
+}
+
+pub fn get_machine_path() -> Result<PathBuf> {
+    Ok(get_config_path()?.with_file_name("machine.yaml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_registered_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("machine.yaml");
+
+        let first = Machine::current_at(&path, "2026-01-01T00:00:00+00:00").unwrap();
+        assert_eq!(first.os, crate::os());
+
+        let mut renamed = first.clone();
+        renamed.name = "work-laptop".to_string();
+        renamed.save_to_path(&path).unwrap();
+
+        let again = Machine::current_at(&path, "2026-02-01T00:00:00+00:00").unwrap();
+        assert_eq!(again.name, "work-laptop");
+        assert_eq!(again.first_seen, "2026-01-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("work-laptop").is_ok());
+        assert!(check_name("work laptop").is_err());
+        assert!(check_name("").is_err());
+    }
+}
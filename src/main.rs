@@ -1,5 +1,10 @@
 mod commands;
 mod examples;
+mod jobs;
+mod prompt;
+mod registry;
+mod rpc;
+mod serve;
 
 use anyhow::Result;
 
@@ -7,52 +12,355 @@ use clap::Parser;
 use colored::*;
 
 use commands::{
-    Commands, SyncAction, add_tool, create_github_repo, delete_tool, init_config,
-    install_tool, list_tools, pull_config_from_github, push_config_to_github,
-    remove_tool, reset_config, run_tool, setup_github_sync, show_sync_status, update_github_token,
-    update_tool,
+    AliasAction, CommandFailed, Commands, ConfigAction, ExportAction, FilesAction, ListFilter,
+    MachineAction, ReportAction, SchedulerAction, SyncAction, TrashAction, add_sync_remote,
+    add_tool, add_tool_from_package, add_tool_from_url, apply_config, apply_files, apply_from_url,
+    check_tools, ci_validate, copy_tool, create_github_repo, delete_tool, edit_config, empty_trash,
+    flush_sync_queue, get_setting, init_config, init_from, install_tool, list_aliases,
+    list_schedules, list_settings, list_tools, list_trash, notify, pin_tool, print_env, print_path,
+    pull_config_from_github, push_config_to_github, recover_interrupted, remove_sync_remote,
+    remove_tool, rename_tool, reset_config, restore_tool, run_audit, run_manifest, run_scheduler,
+    run_tool, scan_tools, set_machine_name, set_setting, set_show_timings, set_sync_encryption,
+    set_tool_disabled, setup_github_sync, show_drift, show_files_status, show_machine,
+    show_outdated, show_plan, show_stats, show_sync_status, show_timings_report, show_tool,
+    sync_aliases, undo_last_change, unpin_tool, update_github_token, update_tool, verify_sync,
+    which_tool,
 };
-use examples::show_examples;
+use examples::{add_example, list_examples, show_example, show_examples};
+use prompt::{set_assume_yes, set_non_interactive};
+use registry::{
+    export_tool, install_from_registry, publish_tool, search_registry, show_registry_info,
+};
+use rpc::serve_rpc;
+use serve::serve;
+use tkit::converge::Selection;
+use tkit::crypto::PASSPHRASE_ENV;
+use tkit::exitcode::ExitKind;
+use tkit::machine::Machine;
+use tkit::mock::{self, MockConfig, get_mock_path};
+use tkit::redact::{redact, register_secret};
+use tkit::{Config, ConfirmMode, get_config_path};
 
 #[derive(Parser)]
 #[command(name = "tkit")]
 #[command(about = "A customizable tool manager")]
 #[command(version = "0.1.1")]
-
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Print how long each step took after a tool's commands run
+    #[arg(long, global = true)]
+    timings: bool,
+    /// Answer yes to every confirmation and never wait for input
+    #[arg(short, long, global = true)]
+    yes: bool,
+    /// Take the default answer at every prompt instead of waiting for input
+    /// (also set by TKIT_NONINTERACTIVE)
+    #[arg(long, global = true)]
+    non_interactive: bool,
+    /// Simulate every command with the rules in mock.yaml instead of running
+    /// it (also set by TKIT_EXECUTOR=mock)
+    #[arg(long, global = true)]
+    mock: bool,
+}
+
+/// `--all` is shorthand for the pattern matching every tool.
+fn tool_or_all(tool: Option<String>, all: bool) -> String {
+    match tool {
+        Some(tool) if !all => tool,
+        _ => "*".to_string(),
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    set_show_timings(cli.timings);
+    // A broken config is reported by the command itself
+    let config = Config::load().unwrap_or_default();
+    config.register_secrets();
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        register_secret(&passphrase);
+    }
+    let settings = config.settings;
+    // Registers this machine on the first run with a config
+    if get_config_path().is_ok_and(|path| path.exists()) {
+        let _ = Machine::current();
+    }
+    settings.color.apply();
+    set_assume_yes(cli.yes || settings.confirm == ConfirmMode::Yes);
+    set_non_interactive(
+        cli.yes
+            || cli.non_interactive
+            || std::env::var("TKIT_NONINTERACTIVE")
+                .is_ok_and(|value| !value.is_empty() && value != "0"),
+    );
 
+    if cli.mock || mock::requested_by_env() {
+        match get_mock_path().and_then(|path| MockConfig::load(&path)) {
+            Ok(rules) => mock::enable(rules),
+            Err(e) => fail(&e, false),
+        }
+    }
+
+    let changes_tools = matches!(
+        cli.command,
+        Commands::Install { .. }
+            | Commands::Update { .. }
+            | Commands::Remove { .. }
+            | Commands::Apply { .. }
+            | Commands::Batch { .. }
+    );
+    if changes_tools && let Err(e) = recover_interrupted().await {
+        fail(&e, false);
+    }
+
+    // `tkit run` exits with the failed command's own code, like the tool would
+    let passthrough = matches!(cli.command, Commands::Run { .. });
     let result = match cli.command {
-        Commands::Install { tool } => install_tool(&tool).await,
-        Commands::Remove { tool } => remove_tool(&tool).await,
-        Commands::Update { tool } => update_tool(&tool).await,
-        Commands::List => list_tools(),
-        Commands::Add { tool } => add_tool(&tool).await,
+        Commands::Install {
+            tool,
+            all,
+            interactive,
+            continue_on_error,
+            force,
+            locked,
+            resume,
+            from_registry,
+            params,
+        } => {
+            let tool = tool_or_all(tool, all);
+            if from_registry {
+                install_from_registry(&tool, &params).await
+            } else {
+                install_tool(
+                    &tool,
+                    &params,
+                    continue_on_error,
+                    force,
+                    locked,
+                    interactive,
+                    resume,
+                )
+                .await
+            }
+        }
+        Commands::Remove {
+            tool,
+            interactive,
+            continue_on_error,
+            params,
+        } => {
+            let tool = tool_or_all(tool, false);
+            remove_tool(&tool, &params, continue_on_error, interactive).await
+        }
+        Commands::Update {
+            tool,
+            all,
+            interactive,
+            continue_on_error,
+            force,
+            changelog,
+            params,
+        } => {
+            let tool = tool_or_all(tool, all);
+            update_tool(
+                &tool,
+                &params,
+                continue_on_error,
+                force,
+                interactive,
+                changelog,
+            )
+            .await
+        }
+        Commands::List {
+            sort,
+            category,
+            installed,
+            missing,
+            long,
+        } => list_tools(
+            sort,
+            ListFilter {
+                category: category.as_deref(),
+                installed,
+                missing,
+            },
+            long,
+        ),
+        Commands::Show { tool } => show_tool(&tool),
+        Commands::Stats { days } => show_stats(days),
+        Commands::Report { action } => match action {
+            ReportAction::Timings { steps } => show_timings_report(steps),
+        },
+        Commands::Env { shell } => print_env(shell),
+        Commands::Path { shell, install } => print_path(shell, install),
+        Commands::Which { tool } => which_tool(&tool),
+        Commands::Scan { all } => scan_tools(all).await,
+        Commands::Notify { quiet } => notify(quiet).await,
+        Commands::Add {
+            tool,
+            from_url,
+            pm,
+            bin,
+            description,
+            params,
+        } => match (from_url, pm) {
+            (Some(url), _) => add_tool_from_url(&url, tool.as_deref(), &params).await,
+            (None, Some(manager)) => {
+                add_tool_from_package(
+                    manager,
+                    tool.as_deref().unwrap_or_default(),
+                    bin.as_deref(),
+                    description.as_deref(),
+                )
+                .await
+            }
+            (None, None) => add_tool(tool.as_deref().unwrap_or_default()).await,
+        },
         Commands::Delete { tool } => delete_tool(&tool).await,
-        Commands::Run { tool } => run_tool(&tool).await,
-        Commands::Examples => show_examples(),
-        Commands::Init => init_config().await,
+        Commands::Restore { tool } => restore_tool(&tool).await,
+        Commands::Trash { action } => match action {
+            TrashAction::List => list_trash(),
+            TrashAction::Empty => empty_trash(),
+        },
+        Commands::Copy { src, dst } => copy_tool(&src, &dst).await,
+        Commands::Disable { tool } => set_tool_disabled(&tool, true).await,
+        Commands::Enable { tool } => set_tool_disabled(&tool, false).await,
+        Commands::Pin { tool, version } => pin_tool(&tool, version).await,
+        Commands::Unpin { tool } => unpin_tool(&tool).await,
+        Commands::Rename { old, new } => rename_tool(&old, &new).await,
+        Commands::Run { tool, entry, args } => run_tool(&tool, entry.as_deref(), &args).await,
+        Commands::Apply {
+            category,
+            tag,
+            continue_on_error,
+            from_url,
+        } => match from_url {
+            Some(source) => {
+                apply_from_url(&source, Selection { category, tag }, continue_on_error).await
+            }
+            None => apply_config(Selection { category, tag }, continue_on_error).await,
+        },
+        Commands::Batch {
+            file,
+            continue_on_error,
+        } => run_manifest(&file, continue_on_error).await,
+        Commands::Plan { category, tag } => show_plan(Selection { category, tag }),
+        Commands::Drift { reconcile } => show_drift(reconcile).await,
+        Commands::Audit => run_audit().await,
+        Commands::CiValidate {
+            file,
+            install,
+            base,
+            image,
+        } => ci_validate(file, base, install, &image),
+        Commands::Check { timeout } => check_tools(timeout).await,
+        Commands::Outdated { changelog } => show_outdated(changelog).await,
+        Commands::Search { query } => search_registry(&query).await,
+        Commands::Info { name } => show_registry_info(&name).await,
+        Commands::Export { what } => match what {
+            ExportAction::Tool { name, gist } => export_tool(&name, gist).await,
+        },
+        Commands::Publish { tool } => publish_tool(&tool).await,
+        Commands::Examples { list, show, add } => match (show, add) {
+            (Some(name), _) => show_example(&name),
+            (None, Some(name)) => add_example(&name).await,
+            (None, None) if list => list_examples(),
+            (None, None) => show_examples(),
+        },
+        Commands::Init {
+            from,
+            token,
+            install,
+            preset,
+        } => match from {
+            Some(source) => init_from(&source, token, install).await,
+            None => init_config(preset).await,
+        },
+        Commands::Config { action } => match action {
+            ConfigAction::Get { key } => get_setting(&key),
+            ConfigAction::Set { key, value } => set_setting(&key, Some(&value)).await,
+            ConfigAction::Unset { key } => set_setting(&key, None).await,
+            ConfigAction::List => list_settings(),
+        },
+        Commands::EditConfig => edit_config().await,
+        Commands::Undo => undo_last_change().await,
         Commands::Reset => reset_config(),
+        Commands::Files { action } => match action {
+            FilesAction::Apply { tool, force } => apply_files(tool.as_deref(), force),
+            FilesAction::Status { tool } => show_files_status(tool.as_deref()),
+        },
+        Commands::Alias { action } => match action {
+            AliasAction::Sync => sync_aliases(),
+            AliasAction::List => list_aliases(),
+        },
+        Commands::Scheduler { action } => match action {
+            SchedulerAction::Run => run_scheduler().await,
+            SchedulerAction::List => list_schedules(),
+        },
+        Commands::Machine { action } => match action {
+            MachineAction::Show => show_machine(),
+            MachineAction::SetName { name } => set_machine_name(&name),
+        },
+        Commands::Serve { listen, token } => serve(&listen, token).await,
+        Commands::Rpc => serve_rpc().await,
         Commands::Sync { action } => match action {
-            SyncAction::Setup { repo, token } => setup_github_sync(repo, token).await,
+            SyncAction::Setup {
+                repo,
+                token,
+                ssh,
+                key,
+                branch,
+            } => setup_github_sync(repo, token, ssh, key, branch).await,
             SyncAction::CreateRepo { name, private } => create_github_repo(&name, private).await,
             SyncAction::UpdateToken { token } => update_github_token(token).await,
-            SyncAction::Push => push_config_to_github().await,
-            SyncAction::Pull => pull_config_from_github().await,
-            SyncAction::Status => show_sync_status().await,
+            SyncAction::Push { force } => push_config_to_github(force).await,
+            SyncAction::Pull {
+                remote,
+                strategy,
+                tools,
+            } => pull_config_from_github(remote, strategy, tools).await,
+            SyncAction::AddRemote {
+                name,
+                repo,
+                path,
+                token,
+                branch,
+            } => add_sync_remote(&name, repo, path, token, branch).await,
+            SyncAction::RemoveRemote { name } => remove_sync_remote(&name),
+            SyncAction::Encrypt { off } => set_sync_encryption(!off),
+            SyncAction::Status { machines } => show_sync_status(machines).await,
+            SyncAction::Flush => flush_sync_queue().await,
+            SyncAction::Verify { remote } => verify_sync(remote).await,
         },
     };
 
     if let Err(e) = result {
-        eprintln!("{} {}", "Error:".red().bold(), e);
-        std::process::exit(1);
+        fail(&e, passthrough);
     }
 
     Ok(())
 }
+
+/// Reports `e` and exits with its code.
+fn fail(e: &anyhow::Error, passthrough: bool) -> ! {
+    if ExitKind::of(e) == Some(ExitKind::Aborted) {
+        println!("{}", e.to_string().yellow());
+    } else {
+        eprintln!("{} {}", "Error:".red().bold(), redact(&e.to_string()));
+    }
+    std::process::exit(exit_code(e, passthrough));
+}
+
+/// The exit code for a failed command; see [`ExitKind`].
+fn exit_code(e: &anyhow::Error, passthrough: bool) -> i32 {
+    if let Some(failed) = e.downcast_ref::<CommandFailed>() {
+        return match failed.code {
+            Some(code) if passthrough && code != 0 => code,
+            _ => ExitKind::CommandFailed.code(),
+        };
+    }
+    ExitKind::of(e).map_or(1, ExitKind::code)
+}
@@ -0,0 +1,313 @@
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{Config, ToolConfig, get_config_path};
+
+/// Outcome of merging pulled tools into the local ones, before conflicts
+/// are resolved.
+#[derive(Debug, Default)]
+pub struct Merge {
+    /// Tools settled without asking: changed on one side only, or the same on both.
+    pub tools: BTreeMap<String, ToolConfig>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// A tool changed both here and on the remote since the last sync. `None`
+/// means that side deleted it.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub name: String,
+    pub local: Option<ToolConfig>,
+    pub remote: Option<ToolConfig>,
+}
+
+/// A tool as shared between machines: without install state and
+/// timestamps, which differ from machine to machine.
+fn shared_yaml(tool: Option<&ToolConfig>) -> Option<String> {
+    let tool = ToolConfig {
+        installed: false,
+        created_at: None,
+        updated_at: None,
+        ..tool?.clone()
+    };
+    serde_yaml::to_string(&tool).ok()
+}
+
+/// Three-way merge of tool definitions. `base` is the config as of the last
+/// sync; without it, a local tool counts as changed when edited since
+/// `last_sync` and every remote difference counts as a remote change.
+/// Private tools are left out; they never take part in sync.
+pub fn three_way(
+    base: Option<&BTreeMap<String, ToolConfig>>,
+    local: &BTreeMap<String, ToolConfig>,
+    remote: &BTreeMap<String, ToolConfig>,
+    last_sync: Option<&str>,
+) -> Merge {
+    let names: BTreeSet<&String> = local
+        .iter()
+        .filter(|(_, tool)| !tool.private)
+        .map(|(name, _)| name)
+        .chain(remote.keys())
+        .collect();
+
+    let mut merge = Merge::default();
+    for name in names {
+        let (ours, theirs) = (local.get(name), remote.get(name));
+        if ours.is_some_and(|tool| tool.private) {
+            continue;
+        }
+        let (ours_yaml, theirs_yaml) = (shared_yaml(ours), shared_yaml(theirs));
+        let (local_changed, remote_changed) = match base {
+            Some(base) => {
+                let base_yaml = shared_yaml(base.get(name));
+                (ours_yaml != base_yaml, theirs_yaml != base_yaml)
+            }
+            None => (ours.is_some_and(|tool| tool.changed_since(last_sync)), true),
+        };
+
+        let chosen = if ours_yaml == theirs_yaml || !local_changed {
+            theirs
+        } else if !remote_changed {
+            ours
+        } else {
+            merge.conflicts.push(Conflict {
+                name: name.clone(),
+                local: ours.cloned(),
+                remote: theirs.cloned(),
+            });
+            continue;
+        };
+        if let Some(tool) = chosen {
+            merge.tools.insert(name.clone(), tool.clone());
+        }
+    }
+    merge
+}
+
+/// The local tools plus the remote tools whose names aren't taken here;
+/// existing tools are left exactly as they are.
+pub fn append_new(
+    local: &BTreeMap<String, ToolConfig>,
+    remote: &BTreeMap<String, ToolConfig>,
+) -> BTreeMap<String, ToolConfig> {
+    let mut tools = local.clone();
+    for (name, tool) in remote {
+        if !tools.contains_key(name) {
+            tools.insert(name.clone(), tool.clone());
+        }
+    }
+    tools
+}
+
+/// A field's name with its local and remote values (`None` when unset).
+pub type FieldDiff = (String, Option<serde_yaml::Value>, Option<serde_yaml::Value>);
+
+/// Top-level fields whose shared values differ between two versions of a
+/// tool.
+pub fn differing_fields(local: &ToolConfig, remote: &ToolConfig) -> Result<Vec<FieldDiff>> {
+    let (ours, theirs) = (tool_mapping(local)?, tool_mapping(remote)?);
+    let keys: BTreeSet<String> = ours
+        .keys()
+        .chain(theirs.keys())
+        .filter_map(|key| key.as_str().map(str::to_string))
+        .collect();
+
+    Ok(keys
+        .into_iter()
+        .filter(|key| !matches!(key.as_str(), "created_at" | "updated_at"))
+        .filter_map(|key| {
+            let (a, b) = (ours.get(&key).cloned(), theirs.get(&key).cloned());
+            (a != b).then_some((key, a, b))
+        })
+        .collect())
+}
+
+/// `local` with the named fields taken from `remote`.
+pub fn take_fields(
+    local: &ToolConfig,
+    remote: &ToolConfig,
+    fields: &[String],
+) -> Result<ToolConfig> {
+    let mut ours = tool_mapping(local)?;
+    let theirs = tool_mapping(remote)?;
+    for field in fields {
+        match theirs.get(field.as_str()) {
+            Some(value) => ours.insert(field.as_str().into(), value.clone()),
+            None => ours.remove(field.as_str()),
+        };
+    }
+    let mut merged: ToolConfig = serde_yaml::from_value(serde_yaml::Value::Mapping(ours))?;
+    merged.installed = local.installed;
+    Ok(merged)
+}
+
+fn tool_mapping(tool: &ToolConfig) -> Result<serde_yaml::Mapping> {
+    match serde_yaml::to_value(tool)? {
+        serde_yaml::Value::Mapping(mapping) => Ok(mapping),
+        _ => Err(anyhow!("Tool '{}' is not a mapping", tool.name)),
+    }
+}
+
+/// One line of a diff between two texts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Line diff turning `old` into `new`, by longest common subsequence.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let (a, b): (Vec<&str>, Vec<&str>) = (old.lines().collect(), new.lines().collect());
+    // common[i][j]: length of the LCS of a[i..] and b[j..]
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push(DiffLine::Same(a[i].to_string()));
+            (i, j) = (i + 1, j + 1);
+        } else if i < a.len() && (j == b.len() || common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(DiffLine::Removed(a[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(b[j].to_string()));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// The shared config as last pushed or pulled, the common ancestor for the
+/// next pull's merge. Never synced.
+pub fn load_base() -> Option<Config> {
+    let content = fs::read_to_string(get_sync_base_path().ok()?).ok()?;
+    serde_yaml::from_str(&content).ok()
+}
+
+pub fn save_base(content: &str) -> Result<()> {
+    let path = get_sync_base_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+    Ok(())
+}
+
+pub fn get_sync_base_path() -> Result<PathBuf> {
+    Ok(get_config_path()?.with_file_name("sync-base.yaml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str, install: &str) -> ToolConfig {
+        ToolConfig {
+            name: name.to_string(),
+            install_commands: vec![install.into()],
+            ..Default::default()
+        }
+    }
+
+    fn tools(list: &[ToolConfig]) -> BTreeMap<String, ToolConfig> {
+        list.iter().map(|t| (t.name.clone(), t.clone())).collect()
+    }
+
+    #[test]
+    fn test_three_way_merge() {
+        let base = tools(&[
+            tool("jq", "v1"),
+            tool("rg", "v1"),
+            tool("fd", "v1"),
+            tool("bat", "v1"),
+        ]);
+        // jq edited here, rg edited there, fd edited on both, bat deleted here
+        let local = tools(&[tool("jq", "v2"), tool("rg", "v1"), tool("fd", "local")]);
+        let remote = tools(&[
+            tool("jq", "v1"),
+            tool("rg", "v2"),
+            tool("fd", "remote"),
+            tool("bat", "v1"),
+            tool("new", "v1"),
+        ]);
+
+        let merge = three_way(Some(&base), &local, &remote, None);
+        assert_eq!(merge.tools["jq"].install_commands, ["v2"]);
+        assert_eq!(merge.tools["rg"].install_commands, ["v2"]);
+        assert!(!merge.tools.contains_key("bat"));
+        assert!(merge.tools.contains_key("new"));
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(merge.conflicts[0].name, "fd");
+    }
+
+    #[test]
+    fn test_install_state_is_not_a_change() {
+        let base = tools(&[tool("jq", "v1")]);
+        let mut installed = tool("jq", "v1");
+        installed.installed = true;
+        installed.updated_at = Some("2026-01-01T00:00:00+00:00".to_string());
+        let merge = three_way(
+            Some(&base),
+            &tools(&[installed]),
+            &tools(&[tool("jq", "v2")]),
+            None,
+        );
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.tools["jq"].install_commands, ["v2"]);
+    }
+
+    #[test]
+    fn test_append_new_leaves_existing_tools() {
+        let local = tools(&[tool("jq", "local")]);
+        let remote = tools(&[tool("jq", "remote"), tool("rg", "v1")]);
+        let merged = append_new(&local, &remote);
+        assert_eq!(merged["jq"].install_commands, ["local"]);
+        assert!(merged.contains_key("rg"));
+    }
+
+    #[test]
+    fn test_take_fields() {
+        let mut local = tool("jq", "local");
+        local.description = Some("mine".to_string());
+        let mut remote = tool("jq", "remote");
+        remote.description = Some("theirs".to_string());
+
+        let fields: Vec<String> = differing_fields(&local, &remote)
+            .unwrap()
+            .into_iter()
+            .map(|(field, _, _)| field)
+            .collect();
+        assert_eq!(fields, ["description", "install_commands"]);
+
+        let merged = take_fields(&local, &remote, &["install_commands".to_string()]).unwrap();
+        assert_eq!(merged.install_commands, ["remote"]);
+        assert_eq!(merged.description.as_deref(), Some("mine"));
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines("a\nb\nc\n", "a\nx\nc\n");
+        assert_eq!(
+            diff,
+            [
+                DiffLine::Same("a".to_string()),
+                DiffLine::Removed("b".to_string()),
+                DiffLine::Added("x".to_string()),
+                DiffLine::Same("c".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::exitcode::ExitKind;
+use crate::{get_config_path, glob_match};
+
+/// Set to `mock` to simulate every command instead of running it.
+pub const EXECUTOR_ENV: &str = "TKIT_EXECUTOR";
+/// Path of the mock rules, instead of `~/.config/tkit/mock.yaml`.
+pub const MOCK_FILE_ENV: &str = "TKIT_MOCK_FILE";
+
+/// How mocked commands behave, read from `mock.yaml`. Commands no rule
+/// matches succeed silently after `delay_ms`.
+///
+/// ```yaml
+/// delay_ms: 200
+/// commands:
+///   - match: "curl *"
+///     output: "Downloading... done"
+///     delay_ms: 1500
+///   - match: "*apt-get install*"
+///     stderr: "E: Unable to locate package"
+///     exit_code: 100
+/// ```
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MockConfig {
+    #[serde(default)]
+    pub delay_ms: u64,
+    #[serde(default)]
+    pub commands: Vec<MockRule>,
+}
+
+/// A fake result for commands matching a shell-style pattern.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MockRule {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    #[serde(default)]
+    pub output: String,
+    #[serde(default)]
+    pub stderr: String,
+    #[serde(default)]
+    pub exit_code: i32,
+    /// Overrides the default delay for these commands.
+    #[serde(default)]
+    pub delay_ms: Option<u64>,
+}
+
+/// What a mocked command prints and how it exits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockResponse {
+    pub stdout: Vec<String>,
+    pub stderr: Vec<String>,
+    pub exit_code: i32,
+    pub delay: Duration,
+}
+
+impl MockResponse {
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+
+    /// Waits as long as the command is configured to take.
+    pub fn wait(&self) {
+        if !self.delay.is_zero() {
+            std::thread::sleep(self.delay);
+        }
+    }
+}
+
+impl MockConfig {
+    /// Reads the rules from `path`; a missing file means no rules.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(MockConfig::default());
+        }
+        let content = fs::read_to_string(path)?;
+        serde_yaml::from_str(&content).map_err(|e| {
+            ExitKind::Config.error(format!("Invalid mock rules {}: {}", path.display(), e))
+        })
+    }
+
+    /// The response of the first rule matching `command`, or a silent
+    /// success.
+    pub fn respond(&self, command: &str) -> MockResponse {
+        let command = command.trim();
+        let lines = |text: &str| text.lines().map(str::to_string).collect();
+        match self
+            .commands
+            .iter()
+            .find(|rule| glob_match(&rule.pattern, command))
+        {
+            Some(rule) => MockResponse {
+                stdout: lines(&rule.output),
+                stderr: lines(&rule.stderr),
+                exit_code: rule.exit_code,
+                delay: Duration::from_millis(rule.delay_ms.unwrap_or(self.delay_ms)),
+            },
+            None => MockResponse {
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+                exit_code: 0,
+                delay: Duration::from_millis(self.delay_ms),
+            },
+        }
+    }
+}
+
+pub fn get_mock_path() -> Result<PathBuf> {
+    match std::env::var_os(MOCK_FILE_ENV) {
+        Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+        _ => Ok(get_config_path()?.with_file_name("mock.yaml")),
+    }
+}
+
+/// Whether `TKIT_EXECUTOR` asks for the mock executor.
+pub fn requested_by_env() -> bool {
+    std::env::var(EXECUTOR_ENV).is_ok_and(|value| value.eq_ignore_ascii_case("mock"))
+}
+
+static MOCK: OnceLock<MockConfig> = OnceLock::new();
+
+/// Simulates commands from now on, with `config`'s rules.
+pub fn enable(config: MockConfig) {
+    let _ = MOCK.set(config);
+}
+
+/// The mock rules when commands are being simulated.
+pub fn active() -> Option<&'static MockConfig> {
+    MOCK.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_mock_responses() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("mock.yaml");
+        assert_eq!(MockConfig::load(&path).unwrap(), MockConfig::default());
+
+        fs::write(
+            &path,
+            "delay_ms: 5\ncommands:\n  - match: 'curl *'\n    output: |\n      fetching\n      done\n    delay_ms: 0\n  - match: '*install*'\n    stderr: no such package\n    exit_code: 100\n",
+        )
+        .unwrap();
+        let config = MockConfig::load(&path).unwrap();
+
+        let curl = config.respond("curl -fsSL https://example.com | sh");
+        assert_eq!(curl.stdout, vec!["fetching", "done"]);
+        assert!(curl.success());
+        assert!(curl.delay.is_zero());
+
+        let install = config.respond("  apt-get install jq");
+        assert_eq!(install.stderr, vec!["no such package"]);
+        assert_eq!(install.exit_code, 100);
+        assert_eq!(install.delay, Duration::from_millis(5));
+
+        let other = config.respond("echo hi");
+        assert!(other.success() && other.stdout.is_empty());
+
+        fs::write(&path, "commands:\n  - pattern: '*'\n").unwrap();
+        assert!(MockConfig::load(&path).is_err());
+    }
+}
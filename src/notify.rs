@@ -0,0 +1,129 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// What the last `tkit notify` check found, so shells started in between
+/// can print the nudge without going to the network.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct NotifyCache {
+    /// RFC 3339 time of the last check.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<String>,
+    /// Installed tools with a newer release.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outdated: Vec<String>,
+    /// Whether the synced config changed since this machine last pushed or
+    /// pulled it.
+    #[serde(default)]
+    pub remote_changed: bool,
+}
+
+impl NotifyCache {
+    pub fn load() -> Result<Self> {
+        Self::load_from_path(&get_notify_cache_path()?)
+    }
+
+    /// Loads the cache; a missing or unreadable one is empty, so the next
+    /// check just runs.
+    pub fn load_from_path(path: &PathBuf) -> Result<Self> {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Ok(NotifyCache::default());
+        };
+        Ok(serde_yaml::from_str(&content).unwrap_or_default())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to_path(&get_notify_cache_path()?)
+    }
+
+    pub fn save_to_path(&self, path: &PathBuf) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Whether the last check is less than `interval_hours` old at `now`.
+    pub fn is_fresh(&self, now: chrono::DateTime<chrono::Utc>, interval_hours: u64) -> bool {
+        self.checked_at
+            .as_deref()
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .is_some_and(|at| {
+                now.signed_duration_since(at) < chrono::Duration::hours(interval_hours as i64)
+            })
+    }
+
+    /// The one-line nudge, or `None` when there is nothing to act on.
+    pub fn message(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        match self.outdated.as_slice() {
+            [] => {}
+            [tool] => parts.push(format!("{} has an update ('tkit update {}')", tool, tool)),
+            tools => parts.push(format!(
+                "{} tools have updates: {} ('tkit outdated')",
+                tools.len(),
+                tools.join(", ")
+            )),
+        }
+        if self.remote_changed {
+            parts.push("the synced config changed ('tkit sync pull')".to_string());
+        }
+        (!parts.is_empty()).then(|| format!("tkit: {}", parts.join("; ")))
+    }
+}
+
+pub fn get_notify_cache_path() -> Result<PathBuf> {
+    let cache_dir =
+        dirs::cache_dir().ok_or_else(|| anyhow!("Could not determine cache directory"))?;
+    Ok(cache_dir.join("tkit").join("notify.yaml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_notify_message() {
+        assert_eq!(NotifyCache::default().message(), None);
+
+        let mut cache = NotifyCache {
+            outdated: vec!["jq".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            cache.message().unwrap(),
+            "tkit: jq has an update ('tkit update jq')"
+        );
+
+        cache.outdated.push("ripgrep".to_string());
+        cache.remote_changed = true;
+        assert_eq!(
+            cache.message().unwrap(),
+            "tkit: 2 tools have updates: jq, ripgrep ('tkit outdated'); the synced config changed ('tkit sync pull')"
+        );
+    }
+
+    #[test]
+    fn test_notify_cache_freshness() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notify.yaml");
+        let now = chrono::Utc::now();
+
+        let loaded = NotifyCache::load_from_path(&path).unwrap();
+        assert!(!loaded.is_fresh(now, 24));
+
+        let cache = NotifyCache {
+            checked_at: Some((now - chrono::Duration::hours(3)).to_rfc3339()),
+            remote_changed: true,
+            ..Default::default()
+        };
+        cache.save_to_path(&path).unwrap();
+        let loaded = NotifyCache::load_from_path(&path).unwrap();
+        assert_eq!(loaded, cache);
+        assert!(loaded.is_fresh(now, 24));
+        assert!(!loaded.is_fresh(now, 2));
+    }
+}
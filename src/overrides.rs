@@ -0,0 +1,74 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::write_atomic;
+
+/// Environment variable that overrides the detected machine name.
+pub const HOSTNAME_ENV: &str = "TKIT_HOSTNAME";
+
+/// Per-machine changes layered over the shared config when it is loaded,
+/// from the config's `overrides.<hostname>` section or `config.<hostname>.yaml`.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct MachineOverrides {
+    /// Tool fields to replace on this machine, e.g. `docker: {disabled: true}`.
+    #[serde(default)]
+    pub tools: BTreeMap<String, serde_yaml::Mapping>,
+}
+
+impl MachineOverrides {
+    /// Moves the fields overridden for `old` to `new`. Returns whether there
+    /// were any.
+    pub fn rename_tool(&mut self, old: &str, new: &str) -> bool {
+        match self.tools.remove(old) {
+            Some(fields) => {
+                self.tools.insert(new.to_string(), fields);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Name of this machine, used to pick its overrides.
+pub fn hostname() -> String {
+    if let Ok(name) = std::env::var(HOSTNAME_ENV)
+        && !name.is_empty()
+    {
+        return name;
+    }
+
+    let from_file = std::fs::read_to_string("/etc/hostname").ok();
+    let from_command = || {
+        std::process::Command::new("hostname")
+            .output()
+            .ok()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    from_file
+        .or_else(from_command)
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// `config.<host>.yaml` next to the main config file.
+pub fn overlay_path(config_path: &Path, host: &str) -> PathBuf {
+    config_path.with_file_name(format!("config.{}.yaml", host))
+}
+
+/// Follows a tool rename in `host`'s `config.<host>.yaml`, when it overrides
+/// the tool.
+pub fn rename_in_overlay(config_path: &Path, host: &str, old: &str, new: &str) -> Result<()> {
+    let path = overlay_path(config_path, host);
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Ok(());
+    };
+    let mut overlay: MachineOverrides = serde_yaml::from_str(&content)?;
+    if overlay.rename_tool(old, new) {
+        write_atomic(&path, &serde_yaml::to_string(&overlay)?)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,568 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::process::Command;
+use std::str::FromStr;
+
+/// A package manager tkit can generate install/remove/update commands for.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManager {
+    Apt,
+    Dnf,
+    Pacman,
+    Brew,
+    Winget,
+    Zypper,
+    Cargo,
+    Npm,
+    Pipx,
+    Mise,
+    Asdf,
+}
+
+impl PackageManager {
+    pub const ALL: [PackageManager; 11] = [
+        PackageManager::Apt,
+        PackageManager::Dnf,
+        PackageManager::Pacman,
+        PackageManager::Brew,
+        PackageManager::Winget,
+        PackageManager::Zypper,
+        PackageManager::Cargo,
+        PackageManager::Npm,
+        PackageManager::Pipx,
+        PackageManager::Mise,
+        PackageManager::Asdf,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PackageManager::Apt => "apt",
+            PackageManager::Dnf => "dnf",
+            PackageManager::Pacman => "pacman",
+            PackageManager::Brew => "brew",
+            PackageManager::Winget => "winget",
+            PackageManager::Zypper => "zypper",
+            PackageManager::Cargo => "cargo",
+            PackageManager::Npm => "npm",
+            PackageManager::Pipx => "pipx",
+            PackageManager::Mise => "mise",
+            PackageManager::Asdf => "asdf",
+        }
+    }
+
+    pub fn install_commands(&self, package: &str) -> Vec<String> {
+        PackageSpec::new(*self, package).install_commands()
+    }
+
+    pub fn remove_commands(&self, package: &str) -> Vec<String> {
+        PackageSpec::new(*self, package).remove_commands()
+    }
+
+    pub fn update_commands(&self, package: &str) -> Vec<String> {
+        PackageSpec::new(*self, package).update_commands()
+    }
+
+    /// The system package manager of this machine, used to pick example
+    /// recipes that work here. `TKIT_PACKAGE_MANAGER` overrides detection.
+    pub fn detect_host() -> Option<PackageManager> {
+        if let Ok(name) = std::env::var("TKIT_PACKAGE_MANAGER") {
+            return name.parse().ok();
+        }
+
+        let candidates: &[(PackageManager, &str)] = if cfg!(target_os = "macos") {
+            &[(PackageManager::Brew, "brew")]
+        } else if cfg!(windows) {
+            &[(PackageManager::Winget, "winget")]
+        } else {
+            &[
+                (PackageManager::Apt, "apt-get"),
+                (PackageManager::Dnf, "dnf"),
+                (PackageManager::Pacman, "pacman"),
+                (PackageManager::Zypper, "zypper"),
+                (PackageManager::Brew, "brew"),
+            ]
+        };
+        candidates
+            .iter()
+            .find(|(_, program)| which::which(program).is_ok())
+            .map(|(manager, _)| *manager)
+    }
+
+    /// Whether tkit can ask this manager which version of a package is installed.
+    pub fn supports_version_detection(&self) -> bool {
+        matches!(
+            self,
+            PackageManager::Cargo
+                | PackageManager::Npm
+                | PackageManager::Pipx
+                | PackageManager::Mise
+                | PackageManager::Asdf
+        )
+    }
+
+    /// Installed packages and their versions, as reported by the manager.
+    pub fn installed_versions(&self) -> Result<HashMap<String, String>> {
+        let (program, args): (&str, &[&str]) = match self {
+            PackageManager::Cargo => ("cargo", &["install", "--list"]),
+            PackageManager::Npm => ("npm", &["ls", "-g", "--json", "--depth=0"]),
+            PackageManager::Pipx => ("pipx", &["list", "--json"]),
+            PackageManager::Mise => ("mise", &["current"]),
+            PackageManager::Asdf => ("asdf", &["current"]),
+            _ => {
+                return Err(anyhow!("Version detection is not supported for {}", self));
+            }
+        };
+
+        let output = Command::new(program).args(args).output()?;
+        // `npm ls` exits non-zero for problems like extraneous packages but
+        // still prints the tree, so only give up when there is no output
+        if !output.status.success() && output.stdout.is_empty() {
+            return Err(anyhow!(
+                "'{} {}' failed: {}",
+                program,
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        match self {
+            PackageManager::Cargo => Ok(parse_cargo_install_list(&stdout)),
+            PackageManager::Npm => parse_npm_ls_json(&stdout),
+            PackageManager::Pipx => parse_pipx_list_json(&stdout),
+            PackageManager::Mise | PackageManager::Asdf => Ok(parse_current_versions(&stdout)),
+            _ => Ok(HashMap::new()),
+        }
+    }
+
+    /// Names of the packages a system package manager has installed, read
+    /// from its package database.
+    pub fn installed_packages(&self) -> Result<HashSet<String>> {
+        let (program, args): (&str, &[&str]) = match self {
+            PackageManager::Apt => ("dpkg-query", &["-W", "-f=${Package}\\n"]),
+            PackageManager::Dnf | PackageManager::Zypper => ("rpm", &["-qa", "--qf", "%{NAME}\\n"]),
+            PackageManager::Pacman => ("pacman", &["-Qq"]),
+            PackageManager::Brew => ("brew", &["list", "-1"]),
+            _ => {
+                return Err(anyhow!("Listing packages is not supported for {}", self));
+            }
+        };
+
+        let output = Command::new(program).args(args).output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "'{} {}' failed: {}",
+                program,
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(parse_package_names(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+}
+
+/// One package name per line, as `dpkg-query`, `rpm`, `pacman -Qq` and
+/// `brew list -1` print them.
+fn parse_package_names(output: &str) -> HashSet<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl PackageSpec {
+    pub fn installed_version(&self) -> Result<Option<String>> {
+        Ok(self.manager.installed_versions()?.remove(&self.name))
+    }
+}
+
+/// Installed versions per package manager, queried at most once so listing
+/// many tools from the same manager stays fast.
+#[derive(Debug, Default)]
+pub struct VersionCache {
+    versions: HashMap<PackageManager, Option<HashMap<String, String>>>,
+}
+
+impl VersionCache {
+    pub fn version_of(&mut self, spec: &PackageSpec) -> Option<String> {
+        if !spec.manager.supports_version_detection() {
+            return None;
+        }
+        self.versions
+            .entry(spec.manager)
+            .or_insert_with(|| spec.manager.installed_versions().ok())
+            .as_ref()?
+            .get(&spec.name)
+            .cloned()
+    }
+}
+
+/// Parses `cargo install --list`, whose package lines look like
+/// `ripgrep v14.1.0:` or `tkit v0.1.2 (/path/to/src):`, each followed by
+/// indented binary names.
+pub fn parse_cargo_install_list(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter(|line| !line.starts_with(char::is_whitespace))
+        .filter_map(|line| {
+            let mut parts = line.trim_end_matches(':').split_whitespace();
+            let name = parts.next()?;
+            let version = parts.next()?.strip_prefix('v')?;
+            Some((name.to_string(), version.to_string()))
+        })
+        .collect()
+}
+
+/// Parses `mise current` / `asdf current`, which print one active runtime per
+/// line as `<plugin> <version> ...`. asdf adds a `Name Version ...` header
+/// and marks plugins without a version with `______` or `No version`.
+pub fn parse_current_versions(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next()?;
+            let version = parts.next()?;
+            let valid = name != "Name"
+                && version != "No"
+                && version.chars().next()?.is_ascii_alphanumeric();
+            valid.then(|| (name.to_string(), version.to_string()))
+        })
+        .collect()
+}
+
+/// Parses `npm ls -g --json --depth=0`:
+/// `{"dependencies": {"typescript": {"version": "5.3.3"}}}`.
+pub fn parse_npm_ls_json(output: &str) -> Result<HashMap<String, String>> {
+    #[derive(Deserialize)]
+    struct NpmTree {
+        #[serde(default)]
+        dependencies: HashMap<String, NpmPackage>,
+    }
+
+    #[derive(Deserialize)]
+    struct NpmPackage {
+        version: Option<String>,
+    }
+
+    let tree: NpmTree = serde_json::from_str(output)?;
+    Ok(tree
+        .dependencies
+        .into_iter()
+        .filter_map(|(name, package)| Some((name, package.version?)))
+        .collect())
+}
+
+/// Parses `pipx list --json`, where each venv records its main package:
+/// `{"venvs": {"black": {"metadata": {"main_package": {"package": "black", "package_version": "24.1.0"}}}}}`.
+pub fn parse_pipx_list_json(output: &str) -> Result<HashMap<String, String>> {
+    #[derive(Deserialize)]
+    struct PipxList {
+        #[serde(default)]
+        venvs: HashMap<String, PipxVenv>,
+    }
+
+    #[derive(Deserialize)]
+    struct PipxVenv {
+        metadata: PipxMetadata,
+    }
+
+    #[derive(Deserialize)]
+    struct PipxMetadata {
+        main_package: PipxPackage,
+    }
+
+    #[derive(Deserialize)]
+    struct PipxPackage {
+        package: String,
+        package_version: String,
+    }
+
+    let list: PipxList = serde_json::from_str(output)?;
+    Ok(list
+        .venvs
+        .into_values()
+        .map(|venv| {
+            let package = venv.metadata.main_package;
+            (package.package, package.package_version)
+        })
+        .collect())
+}
+
+impl FromStr for PackageManager {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|manager| manager.as_str() == s.to_lowercase())
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|m| m.as_str()).collect();
+                format!(
+                    "unknown package manager '{}' (expected one of: {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Shorthand for a tool that is just a package, e.g.
+/// `package: {manager: apt, name: ripgrep}`. Runtime version managers take a
+/// plugin and version: `package: {manager: mise, plugin: nodejs, version: 20}`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PackageSpec {
+    pub manager: PackageManager,
+    #[serde(alias = "plugin")]
+    pub name: String,
+    /// Requested version for mise/asdf; `latest` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl PackageSpec {
+    pub fn new(manager: PackageManager, name: &str) -> Self {
+        PackageSpec {
+            manager,
+            name: name.to_string(),
+            version: None,
+        }
+    }
+
+    fn version(&self) -> &str {
+        self.version.as_deref().unwrap_or("latest")
+    }
+
+    pub fn install_commands(&self) -> Vec<String> {
+        let (package, version) = (&self.name, self.version());
+        match self.manager {
+            PackageManager::Apt => vec![
+                "sudo apt-get update".to_string(),
+                format!("sudo apt-get install -y {}", package),
+            ],
+            PackageManager::Dnf => vec![format!("sudo dnf install -y {}", package)],
+            PackageManager::Pacman => {
+                vec![format!("sudo pacman -S --needed --noconfirm {}", package)]
+            }
+            PackageManager::Brew => vec![format!("brew install {}", package)],
+            PackageManager::Winget => vec![format!(
+                "winget install --id {} --exact --accept-source-agreements --accept-package-agreements",
+                package
+            )],
+            PackageManager::Zypper => {
+                vec![format!("sudo zypper --non-interactive install {}", package)]
+            }
+            PackageManager::Cargo => vec![format!("cargo install {}", package)],
+            PackageManager::Npm => vec![format!("npm install -g {}", package)],
+            PackageManager::Pipx => vec![format!("pipx install {}", package)],
+            PackageManager::Mise => vec![
+                format!("mise install {}@{}", package, version),
+                format!("mise use --global {}@{}", package, version),
+            ],
+            PackageManager::Asdf => vec![
+                format!("asdf install {} {}", package, version),
+                format!("asdf set --home {} {}", package, version),
+            ],
+        }
+    }
+
+    pub fn remove_commands(&self) -> Vec<String> {
+        let (package, version) = (&self.name, self.version());
+        match self.manager {
+            PackageManager::Apt => vec![format!("sudo apt-get remove -y {}", package)],
+            PackageManager::Dnf => vec![format!("sudo dnf remove -y {}", package)],
+            PackageManager::Pacman => vec![format!("sudo pacman -R --noconfirm {}", package)],
+            PackageManager::Brew => vec![format!("brew uninstall {}", package)],
+            PackageManager::Winget => vec![format!("winget uninstall --id {} --exact", package)],
+            PackageManager::Zypper => {
+                vec![format!("sudo zypper --non-interactive remove {}", package)]
+            }
+            PackageManager::Cargo => vec![format!("cargo uninstall {}", package)],
+            PackageManager::Npm => vec![format!("npm uninstall -g {}", package)],
+            PackageManager::Pipx => vec![format!("pipx uninstall {}", package)],
+            PackageManager::Mise => vec![format!("mise uninstall {}@{}", package, version)],
+            PackageManager::Asdf => vec![format!("asdf uninstall {} {}", package, version)],
+        }
+    }
+
+    pub fn update_commands(&self) -> Vec<String> {
+        let (package, version) = (&self.name, self.version());
+        match self.manager {
+            PackageManager::Apt => vec![
+                "sudo apt-get update".to_string(),
+                format!("sudo apt-get install --only-upgrade -y {}", package),
+            ],
+            PackageManager::Dnf => vec![format!("sudo dnf upgrade -y {}", package)],
+            PackageManager::Pacman => vec![format!("sudo pacman -Syu --noconfirm {}", package)],
+            PackageManager::Brew => vec![format!("brew upgrade {}", package)],
+            PackageManager::Winget => vec![format!(
+                "winget upgrade --id {} --exact --accept-source-agreements --accept-package-agreements",
+                package
+            )],
+            PackageManager::Zypper => {
+                vec![format!("sudo zypper --non-interactive update {}", package)]
+            }
+            PackageManager::Cargo => vec![format!("cargo install --force {}", package)],
+            PackageManager::Npm => vec![format!("npm install -g {}@latest", package)],
+            PackageManager::Pipx => vec![format!("pipx upgrade {}", package)],
+            // Re-resolving the requested version picks up the newest release
+            // matching it, e.g. the latest 20.x for `version: 20`
+            PackageManager::Mise => vec![
+                format!("mise install {}@{}", package, version),
+                format!("mise use --global {}@{}", package, version),
+            ],
+            PackageManager::Asdf => vec![
+                format!("asdf install {} {}", package, version),
+                format!("asdf set --home {} {}", package, version),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_commands() {
+        assert_eq!(
+            PackageManager::Brew.install_commands("ripgrep"),
+            vec!["brew install ripgrep"]
+        );
+        assert_eq!(
+            PackageManager::Apt.update_commands("ripgrep"),
+            vec![
+                "sudo apt-get update",
+                "sudo apt-get install --only-upgrade -y ripgrep"
+            ]
+        );
+        assert_eq!(
+            PackageManager::Dnf.remove_commands("ripgrep"),
+            vec!["sudo dnf remove -y ripgrep"]
+        );
+        for manager in PackageManager::ALL {
+            assert!(!manager.install_commands("x").is_empty());
+            assert!(!manager.remove_commands("x").is_empty());
+            assert!(!manager.update_commands("x").is_empty());
+        }
+    }
+
+    #[test]
+    fn test_manager_from_str() {
+        assert_eq!("cargo".parse::<PackageManager>(), Ok(PackageManager::Cargo));
+        assert_eq!("Brew".parse::<PackageManager>(), Ok(PackageManager::Brew));
+        assert!("emerge".parse::<PackageManager>().is_err());
+    }
+
+    #[test]
+    fn test_parse_cargo_install_list() {
+        let output = "\
+bat v0.24.0:
+    bat
+ripgrep v14.1.0:
+    rg
+tkit v0.1.2 (/home/me/src/tkit):
+    tkit
+";
+        let versions = parse_cargo_install_list(output);
+        assert_eq!(versions.len(), 3);
+        assert_eq!(versions["ripgrep"], "14.1.0");
+        assert_eq!(versions["tkit"], "0.1.2");
+        assert!(!versions.contains_key("rg"));
+    }
+
+    #[test]
+    fn test_parse_npm_ls_json() {
+        let output = r#"{
+  "name": "lib",
+  "dependencies": {
+    "typescript": {"version": "5.3.3", "overridden": false},
+    "broken": {"missing": true}
+  }
+}"#;
+        let versions = parse_npm_ls_json(output).unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions["typescript"], "5.3.3");
+
+        assert!(parse_npm_ls_json("{}").unwrap().is_empty());
+        assert!(parse_npm_ls_json("not json").is_err());
+    }
+
+    #[test]
+    fn test_parse_pipx_list_json() {
+        let output = r#"{
+  "pipx_spec_version": "0.1",
+  "venvs": {
+    "black": {
+      "metadata": {
+        "main_package": {"package": "black", "package_version": "24.1.0", "apps": ["black"]},
+        "python_version": "Python 3.11.6"
+      }
+    }
+  }
+}"#;
+        let versions = parse_pipx_list_json(output).unwrap();
+        assert_eq!(versions["black"], "24.1.0");
+    }
+
+    #[test]
+    fn test_runtime_manager_commands() {
+        let spec: PackageSpec =
+            serde_yaml::from_str("manager: mise\nplugin: nodejs\nversion: '20'").unwrap();
+        assert_eq!(spec.name, "nodejs");
+        assert_eq!(
+            spec.install_commands(),
+            vec!["mise install nodejs@20", "mise use --global nodejs@20"]
+        );
+        assert_eq!(spec.remove_commands(), vec!["mise uninstall nodejs@20"]);
+
+        assert_eq!(
+            PackageManager::Asdf.install_commands("python"),
+            vec![
+                "asdf install python latest",
+                "asdf set --home python latest"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_current_versions() {
+        let mise = "node 20.11.0\npython 3.12.1\n";
+        let versions = parse_current_versions(mise);
+        assert_eq!(versions["node"], "20.11.0");
+        assert_eq!(versions["python"], "3.12.1");
+
+        let asdf = "Name            Version         Source              Installed\nnodejs          20.11.0         /home/me/.tool-versions true\nruby            ______          ______              \n";
+        let versions = parse_current_versions(asdf);
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions["nodejs"], "20.11.0");
+    }
+
+    #[test]
+    fn test_parse_package_names() {
+        let names = parse_package_names("git\nripgrep\n\n  jq  \n");
+        assert_eq!(names.len(), 3);
+        assert!(names.contains("jq"));
+    }
+
+    #[test]
+    fn test_spec_from_yaml() {
+        let spec: PackageSpec = serde_yaml::from_str("manager: pacman\nname: ripgrep").unwrap();
+        assert_eq!(spec.manager, PackageManager::Pacman);
+        assert_eq!(spec.name, "ripgrep");
+        assert!(serde_yaml::from_str::<PackageSpec>("manager: emerge\nname: x").is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv6Addr, TcpStream, ToSocketAddrs};
+use std::process::Command;
+use std::time::Duration;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Conditions checked before a tool's install commands run, e.g.
+/// `requires: {commands: [curl, unzip], min_free_disk_mb: 500}`.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct Requirements {
+    /// Programs that must be on PATH.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+    /// Hosts that must be reachable, as `host` (port 443) or `host:port`;
+    /// IPv6 addresses with a port go in brackets, as in `[::1]:8443`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_free_disk_mb: Option<u64>,
+    /// Filesystem checked by `min_free_disk_mb`; `/` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk_path: Option<String>,
+    /// Lowest OS release supported, compared numerically (e.g. `22.04`, `13.0`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_os_version: Option<String>,
+}
+
+impl Requirements {
+    /// Runs every check and describes the ones that failed.
+    pub fn check(&self) -> Vec<String> {
+        let mut failures = Vec::new();
+
+        for command in &self.commands {
+            if which::which(command).is_err() {
+                failures.push(format!(
+                    "required command '{}' was not found on PATH",
+                    command
+                ));
+            }
+        }
+
+        for host in &self.hosts {
+            if let Err(reason) = check_reachable(host) {
+                failures.push(format!("cannot reach {}: {}", host, reason));
+            }
+        }
+
+        if let Some(min_mb) = self.min_free_disk_mb {
+            let path = self.disk_path.as_deref().unwrap_or("/");
+            match free_disk_mb(path) {
+                Some(free) if free < min_mb => failures.push(format!(
+                    "{} MB free on {}, but at least {} MB is required",
+                    free, path, min_mb
+                )),
+                Some(_) => {}
+                None => failures.push(format!("could not determine free disk space on {}", path)),
+            }
+        }
+
+        if let Some(min_version) = &self.min_os_version {
+            match os_version() {
+                Some(version) if !version_at_least(&version, min_version) => {
+                    failures.push(format!(
+                        "OS version {} is older than the required {}",
+                        version, min_version
+                    ))
+                }
+                Some(_) => {}
+                None => failures.push("could not determine the OS version".to_string()),
+            }
+        }
+
+        failures
+    }
+}
+
+/// The `host:port` to connect to for an entry of `hosts`, which may leave
+/// out the port and may be an IPv6 address, bracketed or not.
+pub fn host_target(host: &str) -> String {
+    if host.parse::<Ipv6Addr>().is_ok() {
+        return format!("[{}]:443", host);
+    }
+    if let Some(rest) = host.strip_prefix('[') {
+        return match rest.split_once(']') {
+            Some((addr, "")) => format!("[{}]:443", addr),
+            _ => host.to_string(),
+        };
+    }
+    match host.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => host.to_string(),
+        _ => format!("{}:443", host),
+    }
+}
+
+fn check_reachable(host: &str) -> Result<(), String> {
+    let addr = host_target(host)
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or_else(|| "no address found".to_string())?;
+    TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn free_disk_mb(path: &str) -> Option<u64> {
+    let output = Command::new("df").args(["-Pk", path]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_df_available_kb(&String::from_utf8_lossy(&output.stdout)).map(|kb| kb / 1024)
+}
+
+/// Reads the "Available" column (in KiB) from POSIX `df -Pk` output.
+pub fn parse_df_available_kb(output: &str) -> Option<u64> {
+    output
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse()
+        .ok()
+}
+
+/// This machine's OS version: the macOS product version, or `VERSION_ID`
+/// from `/etc/os-release`.
+pub fn os_version() -> Option<String> {
+    if cfg!(target_os = "macos") {
+        let output = Command::new("sw_vers")
+            .arg("-productVersion")
+            .output()
+            .ok()?;
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        parse_os_release_version(&std::fs::read_to_string("/etc/os-release").ok()?)
+    }
+}
+
+/// The Linux distribution's `ID` from `/etc/os-release`, e.g. `ubuntu`.
+pub fn distro() -> Option<String> {
+    parse_os_release_field(&std::fs::read_to_string("/etc/os-release").ok()?, "ID")
+}
+
+/// Extracts `VERSION_ID` from `/etc/os-release`.
+pub fn parse_os_release_version(content: &str) -> Option<String> {
+    parse_os_release_field(content, "VERSION_ID")
+}
+
+fn parse_os_release_field(content: &str, key: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        line.strip_prefix(key)?
+            .strip_prefix('=')
+            .map(|v| v.trim_matches('"').to_string())
+    })
+}
+
+/// Compares dotted versions numerically, treating missing parts as 0.
+pub fn version_at_least(version: &str, minimum: &str) -> bool {
+    let parts = |v: &str| -> Vec<u64> {
+        v.split('.')
+            .map(|p| {
+                p.chars()
+                    .take_while(char::is_ascii_digit)
+                    .collect::<String>()
+                    .parse()
+                    .unwrap_or(0)
+            })
+            .collect()
+    };
+    let (version, minimum) = (parts(version), parts(minimum));
+    let len = version.len().max(minimum.len());
+    let padded = |v: &[u64]| {
+        (0..len)
+            .map(|i| v.get(i).copied().unwrap_or(0))
+            .collect::<Vec<_>>()
+    };
+    padded(&version) >= padded(&minimum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_df_available_kb() {
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n/dev/sda1       102400000  51200000  46080000      53% /\n";
+        assert_eq!(parse_df_available_kb(output), Some(46080000));
+        assert_eq!(parse_df_available_kb(""), None);
+    }
+
+    #[test]
+    fn test_parse_os_release_version() {
+        let content = "NAME=\"Ubuntu\"\nVERSION_ID=\"22.04\"\nID=ubuntu\n";
+        assert_eq!(parse_os_release_version(content).as_deref(), Some("22.04"));
+        assert_eq!(parse_os_release_version("ID=arch\n"), None);
+    }
+
+    #[test]
+    fn test_host_target() {
+        assert_eq!(host_target("github.com"), "github.com:443");
+        assert_eq!(host_target("github.com:22"), "github.com:22");
+        assert_eq!(host_target("10.0.0.1"), "10.0.0.1:443");
+        assert_eq!(host_target("::1"), "[::1]:443");
+        assert_eq!(
+            host_target("2001:db8::8a2e:370:7334"),
+            "[2001:db8::8a2e:370:7334]:443"
+        );
+        assert_eq!(host_target("[::1]"), "[::1]:443");
+        assert_eq!(host_target("[::1]:8443"), "[::1]:8443");
+        assert!("[::1]:8443".to_socket_addrs().is_ok());
+    }
+
+    #[test]
+    fn test_version_at_least() {
+        assert!(version_at_least("22.04", "20.04"));
+        assert!(version_at_least("13", "13.0"));
+        assert!(version_at_least("14.2.1", "14.2"));
+        assert!(!version_at_least("12.7", "13.0"));
+        assert!(!version_at_least("9", "10"));
+    }
+
+    #[test]
+    fn test_missing_command_fails() {
+        let requirements = Requirements {
+            commands: vec!["definitely-not-a-real-command-xyz".to_string()],
+            ..Default::default()
+        };
+        let failures = requirements.check();
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].contains("definitely-not-a-real-command-xyz"));
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::Result;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+static NON_INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Makes `confirm` answer yes without asking (`--yes`, `settings.confirm: yes`).
+pub fn set_assume_yes(enabled: bool) {
+    ASSUME_YES.store(enabled, Ordering::Relaxed);
+}
+
+pub fn assume_yes() -> bool {
+    ASSUME_YES.load(Ordering::Relaxed)
+}
+
+/// Makes every prompt take its default answer instead of reading stdin
+/// (`--non-interactive`, `TKIT_NONINTERACTIVE`), so scripts never hang on
+/// one. Prompts without a sensible default then fail on the empty answer.
+pub fn set_non_interactive(enabled: bool) {
+    NON_INTERACTIVE.store(enabled, Ordering::Relaxed);
+}
+
+fn non_interactive() -> bool {
+    NON_INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// Prints `message` and reads a single trimmed line from stdin. Reads as
+/// an empty line when running non-interactively.
+pub fn read_line(message: &str) -> Result<String> {
+    print!("{}", message);
+    if non_interactive() {
+        println!();
+        return Ok(String::new());
+    }
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+/// Asks a yes/no question. An empty answer picks `default`; with
+/// [`set_assume_yes`] the answer is always yes, and with
+/// [`set_non_interactive`] it is `default`.
+pub fn confirm(question: &str, default: bool) -> Result<bool> {
+    if assume_yes() {
+        println!("{} (y/n): y", question);
+        return Ok(true);
+    }
+    if non_interactive() {
+        println!("{} (y/n): {}", question, if default { "y" } else { "n" });
+        return Ok(default);
+    }
+    let hint = if default { "Y/n" } else { "y/N" };
+    let input = read_line(&format!("{} ({}): ", question, hint))?.to_lowercase();
+
+    Ok(match input.as_str() {
+        "" => default,
+        "y" | "yes" => true,
+        _ => false,
+    })
+}
+
+/// Like `read_line`, but the typed characters are not echoed when stdin is a terminal.
+pub fn read_secret(message: &str) -> Result<String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use crossterm::terminal;
+    use std::io::IsTerminal;
+
+    if !io::stdin().is_terminal() || non_interactive() {
+        return read_line(message);
+    }
+
+    print!("{}", message);
+    io::stdout().flush()?;
+
+    terminal::enable_raw_mode()?;
+    let mut input = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(anyhow::anyhow!("Interrupted"));
+                }
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    terminal::disable_raw_mode()?;
+    println!();
+
+    result.map(|_| input)
+}
+
+/// Parses a selection such as `1 3-5` or `all` into zero-based indices of
+/// `count` items, in order and without duplicates.
+fn parse_selection(input: &str, count: usize) -> Result<Vec<usize>> {
+    if input.eq_ignore_ascii_case("all") {
+        return Ok((0..count).collect());
+    }
+
+    let mut chosen = vec![false; count];
+    for part in input.split([' ', ',']).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((a, b)) => (a.trim().parse::<usize>()?, b.trim().parse::<usize>()?),
+            None => {
+                let n = part.parse::<usize>()?;
+                (n, n)
+            }
+        };
+        if start == 0 || end > count || start > end {
+            return Err(anyhow::anyhow!("'{}' is not between 1 and {}", part, count));
+        }
+        for flag in &mut chosen[start - 1..end] {
+            *flag = true;
+        }
+    }
+    Ok((0..count).filter(|&i| chosen[i]).collect())
+}
+
+/// Lets the user tick any of `items` and returns the indices picked. In a
+/// terminal this is a checkbox list (arrows or j/k to move, space to tick,
+/// `a` for all, enter to accept, esc or q to cancel); otherwise the items are
+/// numbered and a selection like `1 3-5` is read from stdin.
+pub fn multi_select(title: &str, items: &[String]) -> Result<Vec<usize>> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use crossterm::{cursor, terminal};
+    use std::io::IsTerminal;
+
+    println!("{}", title);
+    if non_interactive() {
+        println!("  (nothing selected when running non-interactively)");
+        return Ok(Vec::new());
+    }
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        for (i, item) in items.iter().enumerate() {
+            println!("  {:>3}) {}", i + 1, item);
+        }
+        let input = read_line("Select (e.g. 1 3-5, 'all', empty for none): ")?;
+        return parse_selection(&input, items.len());
+    }
+
+    let mut checked = vec![false; items.len()];
+    let mut current = 0;
+    let draw = |checked: &[bool], current: usize| -> Result<()> {
+        let mut out = io::stdout();
+        for (i, item) in items.iter().enumerate() {
+            let pointer = if i == current { '>' } else { ' ' };
+            let tick = if checked[i] { 'x' } else { ' ' };
+            write!(out, "{} [{}] {}\r\n", pointer, tick, item)?;
+        }
+        write!(
+            out,
+            "  (space to select, a for all, enter to accept, esc to cancel)\r\n"
+        )?;
+        out.flush()?;
+        Ok(())
+    };
+
+    terminal::enable_raw_mode()?;
+    let result = draw(&checked, current).and_then(|_| {
+        loop {
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => current = current.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => current = (current + 1).min(items.len() - 1),
+                KeyCode::Char(' ') => checked[current] = !checked[current],
+                KeyCode::Char('a') => {
+                    let all = checked.iter().all(|c| *c);
+                    checked.iter_mut().for_each(|c| *c = !all);
+                }
+                KeyCode::Enter => break Ok(true),
+                KeyCode::Esc | KeyCode::Char('q') => break Ok(false),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(anyhow::anyhow!("Interrupted"));
+                }
+                _ => continue,
+            }
+            crossterm::execute!(io::stdout(), cursor::MoveUp(items.len() as u16 + 1))?;
+            draw(&checked, current)?;
+        }
+    });
+    terminal::disable_raw_mode()?;
+
+    match result? {
+        true => Ok((0..items.len()).filter(|&i| checked[i]).collect()),
+        false => Ok(Vec::new()),
+    }
+}
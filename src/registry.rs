@@ -0,0 +1,505 @@
+use anyhow::{Result, anyhow};
+use base64::{Engine as _, engine::general_purpose};
+use colored::*;
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use reqwest::header::HeaderMap;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tkit::exitcode::{aborted, tool_not_found};
+use tkit::{Config, RegistryEntry, RegistryIndex, ToolConfig, http_client};
+
+use crate::commands::{
+    fetch_text, fetch_tool_definition, github_headers, install_tool, print_tool_definition,
+};
+use crate::prompt::confirm;
+
+const GITHUB_API: &str = "https://api.github.com";
+
+#[derive(Debug, Serialize)]
+struct GistFile {
+    content: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateGist {
+    description: String,
+    public: bool,
+    files: BTreeMap<String, GistFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Gist {
+    html_url: String,
+    files: BTreeMap<String, GistFileInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GistFileInfo {
+    raw_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubUser {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubRepoInfo {
+    full_name: String,
+    default_branch: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubRef {
+    object: GitHubObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubObject {
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubContent {
+    sha: String,
+    content: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateRef {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    sha: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PutContent {
+    message: String,
+    content: String,
+    branch: String,
+    sha: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct MergeUpstream {
+    branch: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CreatePullRequest {
+    title: String,
+    head: String,
+    base: String,
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequest {
+    html_url: String,
+}
+
+pub async fn fetch_index(config: &Config) -> Result<RegistryIndex> {
+    let index_url = config.registry.index_url();
+    let content = fetch_text(index_url).await?;
+    serde_yaml::from_str(&content)
+        .map_err(|e| anyhow!("Invalid registry index at '{}': {}", index_url, e))
+}
+
+/// Resolves an entry's recipe location against the index it came from.
+pub fn recipe_url(config: &Config, entry: &RegistryEntry) -> Result<String> {
+    let base = url::Url::parse(config.registry.index_url())?;
+    Ok(base.join(&entry.path)?.to_string())
+}
+
+async fn fetch_recipe(config: &Config, name: &str) -> Result<ToolConfig> {
+    let index = fetch_index(config).await?;
+    let entry = index.find(name).ok_or_else(|| {
+        anyhow!(
+            "Recipe '{}' not found in the registry. Use 'tkit search {}' to look for it.",
+            name,
+            name
+        )
+    })?;
+
+    let mut tool = fetch_tool_definition(&recipe_url(config, entry)?).await?;
+    tool.name = entry.name.clone();
+    Ok(tool)
+}
+
+pub async fn search_registry(query: &str) -> Result<()> {
+    let config = Config::load()?;
+    let index = fetch_index(&config).await?;
+
+    let matcher = SkimMatcherV2::default();
+    let mut matches: Vec<(i64, &RegistryEntry)> = index
+        .tools
+        .iter()
+        .filter_map(|entry| {
+            let haystack = format!(
+                "{} {}",
+                entry.name,
+                entry.description.as_deref().unwrap_or_default()
+            );
+            matcher
+                .fuzzy_match(&haystack, query)
+                .map(|score| (score, entry))
+        })
+        .collect();
+
+    if matches.is_empty() {
+        println!(
+            "{}",
+            format!("No recipes matching '{}' found in the registry.", query).yellow()
+        );
+        return Ok(());
+    }
+
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+
+    println!("{}", "Registry recipes:".blue().bold());
+    for (_, entry) in matches {
+        let installed = if config.tools.contains_key(&entry.name) {
+            " (in config)".dimmed().to_string()
+        } else {
+            String::new()
+        };
+        let desc = entry.description.as_deref().unwrap_or("No description");
+        println!("  {} - {}{}", entry.name.bold(), desc, installed);
+    }
+    println!();
+    println!("  Use 'tkit info <name>' to preview a recipe");
+    Ok(())
+}
+
+pub async fn show_registry_info(name: &str) -> Result<()> {
+    let config = Config::load()?;
+    let tool = fetch_recipe(&config, name).await?;
+
+    println!("{}", format!("Recipe '{}':", name).blue().bold());
+    print_tool_definition(&tool)?;
+    println!();
+    println!(
+        "  Use 'tkit install --from-registry {}' to import and install it",
+        name
+    );
+    Ok(())
+}
+
+pub async fn install_from_registry(name: &str, params: &[(String, String)]) -> Result<()> {
+    let mut config = Config::load()?;
+
+    if config.tools.contains_key(name) {
+        println!(
+            "{}",
+            format!(
+                "Tool '{}' is already in your config; installing the local definition.",
+                name
+            )
+            .yellow()
+        );
+        return install_tool(name, params, false, false, false, false, false).await;
+    }
+
+    let tool = fetch_recipe(&config, name).await?;
+
+    println!("{}", "Review the tool definition:".cyan().bold());
+    print_tool_definition(&tool)?;
+    println!();
+
+    if !confirm(&format!("Import and install '{}'?", name), false)? {
+        return Err(aborted());
+    }
+
+    config.add_tool(name, tool)?;
+    config.save_journaled(&format!("import {} from the registry", name))?;
+    println!("  ✓ Imported {}", name.green());
+
+    install_tool(name, params, false, false, false, false, false).await
+}
+
+async fn github_json<T: for<'de> Deserialize<'de>>(
+    request: reqwest::RequestBuilder,
+    what: &str,
+) -> Result<T> {
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(anyhow!(
+            "Failed to {}. Status: {}. {}",
+            what,
+            status,
+            error_text
+        ));
+    }
+    Ok(response.json().await?)
+}
+
+/// Forks the registry (a no-op if the fork exists) and waits until GitHub has created it.
+async fn ensure_fork(
+    client: &reqwest::Client,
+    headers: &HeaderMap,
+    registry: &str,
+) -> Result<GitHubRepoInfo> {
+    let fork: GitHubRepoInfo = github_json(
+        client
+            .post(format!("{}/repos/{}/forks", GITHUB_API, registry))
+            .headers(headers.clone()),
+        "fork the registry repository",
+    )
+    .await?;
+
+    for _ in 0..10 {
+        let response = client
+            .get(format!("{}/repos/{}", GITHUB_API, fork.full_name))
+            .headers(headers.clone())
+            .send()
+            .await?;
+        if response.status().is_success() {
+            // Bring an older fork up to date so the new branch starts from upstream
+            let _ = client
+                .post(format!(
+                    "{}/repos/{}/merge-upstream",
+                    GITHUB_API, fork.full_name
+                ))
+                .headers(headers.clone())
+                .json(&MergeUpstream {
+                    branch: fork.default_branch.clone(),
+                })
+                .send()
+                .await;
+            return Ok(fork);
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+
+    Err(anyhow!(
+        "Timed out waiting for the fork '{}' to become available",
+        fork.full_name
+    ))
+}
+
+/// Prints a tool's definition without machine-specific fields and chosen
+/// parameter values, ready for `tkit add --from-url`, or with `gist`
+/// uploads it as a secret GitHub Gist and prints its URLs.
+pub async fn export_tool(tool_name: &str, gist: bool) -> Result<()> {
+    let config = Config::load()?;
+    let tool_name = &config.resolve_name(tool_name);
+
+    let mut tool = config
+        .tools
+        .get(tool_name)
+        .ok_or_else(|| tool_not_found(tool_name))?
+        .shareable();
+    tool.private = false;
+    tool.pinned = None;
+    let content = serde_yaml::to_string(&tool)?;
+
+    if !gist {
+        print!("{}", content);
+        return Ok(());
+    }
+
+    let token =
+        config.sync.token.as_ref().ok_or_else(|| {
+            anyhow!("GitHub token not found. Run 'tkit sync setup <repo>' first.")
+        })?;
+    let file_name = format!("{}.yaml", tool_name);
+    let payload = CreateGist {
+        description: format!("tkit recipe for {}", tool_name),
+        public: false,
+        files: BTreeMap::from([(file_name.clone(), GistFile { content })]),
+    };
+    let created: Gist = github_json(
+        http_client()?
+            .post(format!("{}/gists", GITHUB_API))
+            .headers(github_headers(token)?)
+            .json(&payload),
+        "create the gist (the token needs the 'gist' scope)",
+    )
+    .await?;
+
+    println!("{}", "✓ Recipe uploaded as a secret gist".green().bold());
+    println!("  Gist: {}", created.html_url.cyan());
+    if let Some(file) = created.files.get(&file_name) {
+        println!("  Add it with: tkit add --from-url {}", file.raw_url);
+    }
+    Ok(())
+}
+
+pub async fn publish_tool(tool_name: &str) -> Result<()> {
+    let config = Config::load()?;
+    let tool_name = &config.resolve_name(tool_name);
+
+    let tool = config
+        .tools
+        .get(tool_name)
+        .ok_or_else(|| tool_not_found(tool_name))?
+        .shareable();
+    let token =
+        config.sync.token.as_ref().ok_or_else(|| {
+            anyhow!("GitHub token not found. Run 'tkit sync setup <repo>' first.")
+        })?;
+    let registry = config.registry.repo();
+
+    println!("{}", "Recipe to publish:".cyan().bold());
+    print_tool_definition(&tool)?;
+    println!();
+
+    if !confirm(
+        &format!(
+            "Open a pull request adding '{}' to {}?",
+            tool_name, registry
+        ),
+        false,
+    )? {
+        return Err(aborted());
+    }
+
+    let client = http_client()?;
+    let headers = github_headers(token)?;
+
+    let user: GitHubUser = github_json(
+        client
+            .get(format!("{}/user", GITHUB_API))
+            .headers(headers.clone()),
+        "look up the authenticated GitHub user",
+    )
+    .await?;
+    let upstream: GitHubRepoInfo = github_json(
+        client
+            .get(format!("{}/repos/{}", GITHUB_API, registry))
+            .headers(headers.clone()),
+        "access the registry repository",
+    )
+    .await?;
+    let fork = ensure_fork(&client, &headers, registry).await?;
+    println!("  ✓ Using fork {}", fork.full_name.green());
+
+    let base: GitHubRef = github_json(
+        client
+            .get(format!(
+                "{}/repos/{}/git/ref/heads/{}",
+                GITHUB_API, upstream.full_name, upstream.default_branch
+            ))
+            .headers(headers.clone()),
+        "read the registry's default branch",
+    )
+    .await?;
+
+    let branch = format!(
+        "add-{}-{}",
+        tool_name,
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    );
+    let _: IgnoredAny = github_json(
+        client
+            .post(format!("{}/repos/{}/git/refs", GITHUB_API, fork.full_name))
+            .headers(headers.clone())
+            .json(&CreateRef {
+                git_ref: format!("refs/heads/{}", branch),
+                sha: base.object.sha,
+            }),
+        "create a branch on the fork",
+    )
+    .await?;
+
+    let recipe_path = format!("tools/{}.yaml", tool_name);
+    let recipe_url = format!(
+        "{}/repos/{}/contents/{}",
+        GITHUB_API, fork.full_name, recipe_path
+    );
+    let existing_recipe = client
+        .get(format!("{}?ref={}", recipe_url, branch))
+        .headers(headers.clone())
+        .send()
+        .await?;
+    let recipe_sha = if existing_recipe.status().is_success() {
+        Some(existing_recipe.json::<GitHubContent>().await?.sha)
+    } else {
+        None
+    };
+
+    let _: IgnoredAny = github_json(
+        client
+            .put(&recipe_url)
+            .headers(headers.clone())
+            .json(&PutContent {
+                message: format!("Add {} recipe", tool_name),
+                content: general_purpose::STANDARD.encode(serde_yaml::to_string(&tool)?),
+                branch: branch.clone(),
+                sha: recipe_sha,
+            }),
+        "upload the recipe",
+    )
+    .await?;
+
+    // Keep the index in step with the recipe so `tkit search` finds it once merged
+    let index_url = format!(
+        "{}/repos/{}/contents/index.yaml",
+        GITHUB_API, fork.full_name
+    );
+    let index_file: GitHubContent = github_json(
+        client
+            .get(format!("{}?ref={}", index_url, branch))
+            .headers(headers.clone()),
+        "read the registry index",
+    )
+    .await?;
+    let index_content = index_file
+        .content
+        .ok_or_else(|| anyhow!("No content in registry index"))?;
+    let decoded = general_purpose::STANDARD.decode(index_content.replace('\n', ""))?;
+    let mut index: RegistryIndex = serde_yaml::from_str(&String::from_utf8(decoded)?)?;
+    index.upsert(RegistryEntry {
+        name: tool_name.to_string(),
+        description: tool.description.clone(),
+        path: recipe_path,
+    });
+
+    let _: IgnoredAny = github_json(
+        client
+            .put(&index_url)
+            .headers(headers.clone())
+            .json(&PutContent {
+                message: format!("Add {} to index", tool_name),
+                content: general_purpose::STANDARD.encode(serde_yaml::to_string(&index)?),
+                branch: branch.clone(),
+                sha: Some(index_file.sha),
+            }),
+        "update the registry index",
+    )
+    .await?;
+
+    let pull: PullRequest = github_json(
+        client
+            .post(format!("{}/repos/{}/pulls", GITHUB_API, upstream.full_name))
+            .headers(headers)
+            .json(&CreatePullRequest {
+                title: format!("Add {} recipe", tool_name),
+                head: format!("{}:{}", user.login, branch),
+                base: upstream.default_branch,
+                body: format!(
+                    "Adds the `{}` recipe{}.\n\nPublished with `tkit publish`.",
+                    tool_name,
+                    tool.description
+                        .as_deref()
+                        .map(|d| format!(" ({})", d))
+                        .unwrap_or_default()
+                ),
+            }),
+        "open the pull request",
+    )
+    .await?;
+
+    println!(
+        "{}",
+        format!("✓ Pull request opened: {}", pull.html_url)
+            .green()
+            .bold()
+    );
+    Ok(())
+}
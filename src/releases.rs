@@ -0,0 +1,357 @@
+use anyhow::{Result, anyhow};
+use reqwest::header::{ACCEPT, AUTHORIZATION, HeaderMap, HeaderValue, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{ToolConfig, get_bin_dir, glob_match, http_client, render_template};
+
+const GITHUB_API: &str = "https://api.github.com";
+
+/// A release of a GitHub repository, as the Releases API lists it.
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Release notes, in Markdown.
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub published_at: Option<String>,
+    #[serde(default)]
+    pub html_url: String,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+    #[serde(default)]
+    pub size: u64,
+}
+
+impl Release {
+    /// The version the tag names, e.g. `14.1.0` for `v14.1.0` or `ripgrep-14.1.0`.
+    pub fn version(&self) -> &str {
+        version_of(&self.tag_name)
+    }
+
+    pub fn is_stable(&self) -> bool {
+        !self.draft && !self.prerelease
+    }
+
+    /// The day it was published, from the RFC 3339 timestamp.
+    pub fn date(&self) -> Option<&str> {
+        self.published_at.as_deref().and_then(|at| at.get(..10))
+    }
+}
+
+/// A tool installed from a GitHub release instead of commands: the matching
+/// asset is downloaded, checked against a published checksum, unpacked, and
+/// its executable put on PATH.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct ReleaseSpec {
+    /// `owner/name`, e.g. `BurntSushi/ripgrep`.
+    pub repo: String,
+    /// Shell-style pattern picking the asset, e.g.
+    /// `*x86_64-unknown-linux-musl.tar.gz`; `{{os}}` and `{{arch}}` are filled in.
+    pub asset_pattern: String,
+}
+
+impl ReleaseSpec {
+    /// The asset of `release` matching the pattern, leaving out checksums and
+    /// signatures that share its name.
+    pub fn find_asset<'a>(
+        &self,
+        release: &'a Release,
+        vars: &BTreeMap<String, String>,
+    ) -> Option<&'a ReleaseAsset> {
+        let pattern = render_template(&self.asset_pattern, vars);
+        release
+            .assets
+            .iter()
+            .filter(|asset| !is_checksum_or_signature(&asset.name))
+            .find(|asset| glob_match(&pattern, &asset.name))
+    }
+}
+
+/// Whether an asset holds checksums or a signature rather than the tool.
+pub fn is_checksum_or_signature(name: &str) -> bool {
+    let name = name.to_lowercase();
+    [
+        ".sha256",
+        ".sha256sum",
+        ".sha512",
+        ".md5",
+        ".asc",
+        ".sig",
+        ".pem",
+        ".sbom",
+    ]
+    .iter()
+    .any(|ext| name.ends_with(ext))
+        || name.contains("checksums")
+        || name.contains("sha256sums")
+}
+
+/// The asset publishing the checksum of `asset`: a `<asset>.sha256` next to
+/// it, or a checksums file for the whole release.
+pub fn checksum_asset<'a>(release: &'a Release, asset: &ReleaseAsset) -> Option<&'a ReleaseAsset> {
+    let sidecars = [
+        format!("{}.sha256", asset.name),
+        format!("{}.sha256sum", asset.name),
+    ];
+    release
+        .assets
+        .iter()
+        .find(|a| sidecars.contains(&a.name))
+        .or_else(|| {
+            release.assets.iter().find(|a| {
+                let name = a.name.to_lowercase();
+                name.contains("checksums") || name.contains("sha256sums")
+            })
+        })
+}
+
+/// The SHA-256 of `file_name` in a checksum file of `<hash>  <name>` lines
+/// (`sha256sum` output), or its only hash when it names no file.
+pub fn parse_checksum(content: &str, file_name: &str) -> Option<String> {
+    let is_hash = |hash: &str| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+    let mut lone = None;
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let Some(hash) = parts.next().filter(|hash| is_hash(hash)) else {
+            continue;
+        };
+        match parts.next() {
+            // `*` marks binary mode; some files list paths
+            Some(name) if name.trim_start_matches('*').rsplit('/').next() == Some(file_name) => {
+                return Some(hash.to_lowercase());
+            }
+            Some(_) => {}
+            None => lone = Some(hash.to_lowercase()),
+        }
+    }
+    lone
+}
+
+/// How a release asset is packed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Archive {
+    Tar,
+    Zip,
+    /// The executable itself.
+    Plain,
+}
+
+impl Archive {
+    pub fn of(name: &str) -> Self {
+        let name = name.to_lowercase();
+        if [
+            ".tar.gz", ".tgz", ".tar.xz", ".txz", ".tar.bz2", ".tbz", ".tar",
+        ]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+        {
+            Archive::Tar
+        } else if name.ends_with(".zip") {
+            Archive::Zip
+        } else {
+            Archive::Plain
+        }
+    }
+}
+
+/// The file called `name` (or `name.exe`) anywhere under `dir`.
+pub fn find_binary(dir: &Path, name: &str) -> Option<PathBuf> {
+    let exe = format!("{}.exe", name);
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir).ok()?.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if entry.file_name() == name || entry.file_name() == exe.as_str() {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+/// Where the executable of a tool installed from a release goes.
+pub fn release_executable(tool: &ToolConfig) -> Result<PathBuf> {
+    Ok(get_bin_dir()?.join(format!(
+        "{}{}",
+        tool.binary_name(),
+        std::env::consts::EXE_SUFFIX
+    )))
+}
+
+/// A tag or version without what comes before its first digit, so `v1.2`,
+/// `jq-1.2` and `1.2` compare equal. Tags without digits are kept whole.
+pub fn version_of(tag: &str) -> &str {
+    match tag.trim_start_matches(|c: char| !c.is_ascii_digit()) {
+        "" => tag,
+        version => version,
+    }
+}
+
+/// The newest stable release in a list in the API's newest-first order.
+pub fn latest(releases: &[Release]) -> Option<&Release> {
+    releases.iter().find(|release| release.is_stable())
+}
+
+/// Whether `latest` is a different version than `installed`; an unknown
+/// installed version counts as out of date.
+pub fn is_newer(latest: &Release, installed: Option<&str>) -> bool {
+    installed.map(version_of) != Some(latest.version())
+}
+
+/// The stable releases newer than `installed`, newest first. Just the latest
+/// when `installed` is unknown or too old to be among `releases`.
+pub fn releases_since<'a>(releases: &'a [Release], installed: Option<&str>) -> Vec<&'a Release> {
+    let stable: Vec<&Release> = releases.iter().filter(|r| r.is_stable()).collect();
+    let installed = installed.map(version_of);
+    match stable.iter().position(|r| Some(r.version()) == installed) {
+        Some(pos) => stable[..pos].to_vec(),
+        None => stable.into_iter().take(1).collect(),
+    }
+}
+
+/// Lists the latest releases of `repo` (`owner/name`), newest first. A token
+/// is optional for public repositories but raises the rate limit.
+pub async fn fetch_releases(repo: &str, token: Option<&str>) -> Result<Vec<Release>> {
+    let url = format!("{}/repos/{}/releases?per_page=30", GITHUB_API, repo);
+    let response = http_client()?
+        .get(&url)
+        .headers(headers(token)?)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Could not list the releases of '{}': {}",
+            repo,
+            response.status()
+        ));
+    }
+    Ok(response.json().await?)
+}
+
+fn headers(token: Option<&str>) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static("tkit/0.1.0"));
+    headers.insert(
+        ACCEPT,
+        HeaderValue::from_static("application/vnd.github+json"),
+    );
+    if let Some(token) = token {
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token))?,
+        );
+    }
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag: &str) -> Release {
+        Release {
+            tag_name: tag.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn asset(name: &str) -> ReleaseAsset {
+        ReleaseAsset {
+            name: name.to_string(),
+            browser_download_url: format!("https://example.com/{}", name),
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn test_find_asset_and_checksum() {
+        let release = Release {
+            tag_name: "14.1.0".to_string(),
+            assets: vec![
+                asset("ripgrep-14.1.0-x86_64-unknown-linux-musl.tar.gz.sha256"),
+                asset("ripgrep-14.1.0-x86_64-unknown-linux-musl.tar.gz"),
+                asset("ripgrep-14.1.0-x86_64-apple-darwin.tar.gz"),
+            ],
+            ..Default::default()
+        };
+        let spec = ReleaseSpec {
+            repo: "BurntSushi/ripgrep".to_string(),
+            asset_pattern: "*x86_64-unknown-{{os}}-musl*".to_string(),
+        };
+        let vars = BTreeMap::from([("os".to_string(), "linux".to_string())]);
+        let found = spec.find_asset(&release, &vars).unwrap();
+        assert_eq!(
+            found.name,
+            "ripgrep-14.1.0-x86_64-unknown-linux-musl.tar.gz"
+        );
+        assert_eq!(
+            checksum_asset(&release, found).unwrap().name,
+            "ripgrep-14.1.0-x86_64-unknown-linux-musl.tar.gz.sha256"
+        );
+        assert_eq!(Archive::of(&found.name), Archive::Tar);
+        assert_eq!(Archive::of("jq-linux-amd64"), Archive::Plain);
+    }
+
+    #[test]
+    fn test_parse_checksum() {
+        let hash = "a".repeat(64);
+        let other = "b".repeat(64);
+        let sums = format!("{}  tool.zip\n{} *dist/tool.tar.gz\n", other, hash);
+        assert_eq!(parse_checksum(&sums, "tool.tar.gz"), Some(hash.clone()));
+        assert_eq!(parse_checksum(&sums, "missing.tar.gz"), None);
+        assert_eq!(parse_checksum(&format!("{}\n", hash), "tool"), Some(hash));
+        assert_eq!(parse_checksum("not a checksum", "tool"), None);
+    }
+
+    #[test]
+    fn test_version_of() {
+        assert_eq!(version_of("v14.1.0"), "14.1.0");
+        assert_eq!(version_of("jq-1.7.1"), "1.7.1");
+        assert_eq!(version_of("1.2"), "1.2");
+        assert_eq!(version_of("nightly"), "nightly");
+    }
+
+    #[test]
+    fn test_releases_since() {
+        let mut releases = vec![
+            release("v3.0.0-rc1"),
+            release("v2.1.0"),
+            release("v2.0.0"),
+            release("v1.0.0"),
+        ];
+        releases[0].prerelease = true;
+
+        let tags = |since: Vec<&Release>| -> Vec<String> {
+            since.iter().map(|r| r.tag_name.clone()).collect()
+        };
+        assert_eq!(
+            tags(releases_since(&releases, Some("1.0.0"))),
+            ["v2.1.0", "v2.0.0"]
+        );
+        assert!(releases_since(&releases, Some("v2.1.0")).is_empty());
+        assert_eq!(tags(releases_since(&releases, None)), ["v2.1.0"]);
+        assert_eq!(tags(releases_since(&releases, Some("0.9"))), ["v2.1.0"]);
+        assert_eq!(latest(&releases).unwrap().version(), "2.1.0");
+        assert!(!is_newer(&releases[1], Some("2.1.0")));
+        assert!(is_newer(&releases[1], Some("v2.0.0")));
+        assert!(is_newer(&releases[1], None));
+    }
+}
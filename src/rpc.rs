@@ -0,0 +1,179 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tkit::{
+    Config, ToolConfig,
+    api::{JobAction, StatusSummary, ToolSummary},
+    jsonrpc::{self, RpcError, RpcRequest, ToolParams},
+    redact::redact,
+};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::jobs::{JobEvent, JobRunner};
+
+#[derive(Debug, Serialize)]
+struct ShowResult<'a> {
+    #[serde(flatten)]
+    summary: ToolSummary,
+    definition: &'a ToolConfig,
+}
+
+#[derive(Debug, Serialize)]
+struct JobStarted {
+    job: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Progress<'a> {
+    job: u64,
+    line: &'a str,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct JobParams {
+    id: u64,
+}
+
+/// Speaks JSON-RPC 2.0 on stdin/stdout, one message per line, until stdin
+/// closes. Jobs still running then are waited for.
+pub async fn serve_rpc() -> Result<()> {
+    let runner = JobRunner::new();
+    let subscribed = Arc::new(AtomicBool::new(false));
+
+    // Everything written to stdout goes through one task so lines never interleave
+    let (out, mut pending) = mpsc::unbounded_channel::<String>();
+    let writer = tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(line) = pending.recv().await {
+            if stdout
+                .write_all(format!("{}\n", line).as_bytes())
+                .await
+                .is_err()
+            {
+                break;
+            }
+            let _ = stdout.flush().await;
+        }
+    });
+    let forwarder = {
+        let (out, subscribed) = (out.clone(), subscribed.clone());
+        let mut events = runner.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if !subscribed.load(Ordering::Relaxed) {
+                    continue;
+                }
+                let line = match &event {
+                    JobEvent::Output { job, line } => {
+                        jsonrpc::notification("progress", Progress { job: *job, line })
+                    }
+                    JobEvent::Finished(job) => jsonrpc::notification("done", job),
+                };
+                let _ = out.send(line);
+            }
+        })
+    };
+
+    let mut input = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = input.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = match RpcRequest::parse(&line) {
+            Ok(request) => request,
+            Err((id, error)) => {
+                let _ = out.send(jsonrpc::response(&id, &Err(error)));
+                continue;
+            }
+        };
+        let result = dispatch(&request, &runner, &subscribed);
+        if let Some(id) = &request.id {
+            let _ = out.send(jsonrpc::response(id, &result));
+        }
+    }
+
+    // The forwarder ends once the last job has finished and dropped the runner
+    drop(runner);
+    let _ = forwarder.await;
+    drop(out);
+    let _ = writer.await;
+    Ok(())
+}
+
+fn dispatch(
+    request: &RpcRequest,
+    runner: &Arc<JobRunner>,
+    subscribed: &AtomicBool,
+) -> Result<Value, RpcError> {
+    match request.method.as_str() {
+        "list" => to_value(ToolSummary::all(&load_config()?)),
+        "show" => {
+            let params: ToolParams = request.params()?;
+            let config = load_config()?;
+            let (name, tool) = find_tool(&config, &params.name)?;
+            to_value(ShowResult {
+                summary: ToolSummary::new(&name, tool),
+                definition: tool,
+            })
+        }
+        "status" => to_value(StatusSummary::new(&load_config()?, runner.running())),
+        "jobs" => to_value(runner.list()),
+        "job" => {
+            let params: JobParams = request.params()?;
+            match runner.get(params.id) {
+                Some(job) => to_value(job.detail()),
+                None => Err(RpcError::new(jsonrpc::INVALID_PARAMS, "No such job")),
+            }
+        }
+        "subscribe" | "unsubscribe" => {
+            subscribed.store(request.method == "subscribe", Ordering::Relaxed);
+            Ok(Value::Bool(true))
+        }
+        method => {
+            let action = JobAction::parse(method).ok_or_else(|| {
+                RpcError::new(
+                    jsonrpc::METHOD_NOT_FOUND,
+                    format!("Unknown method '{}'", method),
+                )
+            })?;
+            let params: ToolParams = request.params()?;
+            let config = load_config()?;
+            let (name, _) = find_tool(&config, &params.name)?;
+            let job = runner
+                .start(&name, action, params.cmd.as_deref())
+                .map_err(|e| RpcError::new(jsonrpc::JOB_CONFLICT, e.to_string()))?;
+            to_value(JobStarted { job })
+        }
+    }
+}
+
+fn load_config() -> Result<Config, RpcError> {
+    Config::load().map_err(internal)
+}
+
+fn find_tool<'a>(config: &'a Config, name: &str) -> Result<(String, &'a ToolConfig), RpcError> {
+    let name = config.resolve_name(name);
+    match config.get_tool(&name) {
+        Some(tool) => Ok((name, tool)),
+        None => Err(RpcError::new(
+            jsonrpc::TOOL_NOT_FOUND,
+            format!("Tool '{}' not found", name),
+        )),
+    }
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| internal(e.into()))
+}
+
+fn internal(e: anyhow::Error) -> RpcError {
+    RpcError::new(jsonrpc::INTERNAL_ERROR, redact(&e.to_string()))
+}
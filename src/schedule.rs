@@ -0,0 +1,148 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, TimeZone, Timelike};
+use std::str::FromStr;
+
+/// A five-field cron expression (`minute hour day-of-month month day-of-week`),
+/// with `*`, lists, ranges, steps and the `@hourly`/`@daily`/`@weekly`/`@monthly` shorthands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether day-of-month and day-of-week were both restricted, in which case
+    /// either one matching is enough (as in cron).
+    either_day: bool,
+}
+
+/// Parses one field into a bit set of the allowed values in `min..=max`.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>()?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(anyhow!("Step must be positive in '{}'", part));
+        }
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (a.parse()?, b.parse()?),
+                None if part.contains('/') => (range.parse()?, max),
+                None => {
+                    let value = range.parse()?;
+                    (value, value)
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err(anyhow!("'{}' is out of range ({}-{})", part, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let expr = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(anyhow!(
+                "Invalid schedule '{}': expected 5 fields (minute hour day month weekday)",
+                s
+            ));
+        };
+
+        let invalid = |e: anyhow::Error| anyhow!("Invalid schedule '{}': {}", s, e);
+        let mut weekdays = parse_field(weekday, 0, 7).map_err(invalid)?;
+        // Both 0 and 7 mean Sunday.
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Schedule {
+            minutes: parse_field(minute, 0, 59).map_err(invalid)?,
+            hours: parse_field(hour, 0, 23).map_err(invalid)?,
+            days: parse_field(day, 1, 31).map_err(invalid)?,
+            months: parse_field(month, 1, 12).map_err(invalid)?,
+            weekdays,
+            either_day: day != "*" && weekday != "*",
+        })
+    }
+}
+
+impl Schedule {
+    /// Whether the schedule fires in the minute containing `time`.
+    pub fn matches<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> bool {
+        let has = |bits: u64, value: u32| bits & (1 << value) != 0;
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+        let day_matches = if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        };
+        has(self.minutes, time.minute())
+            && has(self.hours, time.hour())
+            && has(self.months, time.month())
+            && day_matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_fields_and_steps() {
+        let schedule: Schedule = "*/15 9-17 * * 1-5".parse().unwrap();
+        // 2026-10-16 is a Friday.
+        assert!(schedule.matches(&at("2026-10-16T09:30:00Z")));
+        assert!(!schedule.matches(&at("2026-10-16T09:31:00Z")));
+        assert!(!schedule.matches(&at("2026-10-16T18:00:00Z")));
+        assert!(!schedule.matches(&at("2026-10-17T09:30:00Z")));
+    }
+
+    #[test]
+    fn test_shorthands_and_sunday() {
+        let daily: Schedule = "@daily".parse().unwrap();
+        assert!(daily.matches(&at("2026-10-16T00:00:00Z")));
+        assert!(!daily.matches(&at("2026-10-16T00:01:00Z")));
+        let sunday: Schedule = "0 3 * * 7".parse().unwrap();
+        assert!(sunday.matches(&at("2026-10-18T03:00:00Z")));
+    }
+
+    #[test]
+    fn test_day_of_month_or_weekday() {
+        let schedule: Schedule = "0 0 1 * 1".parse().unwrap();
+        assert!(schedule.matches(&at("2026-10-01T00:00:00Z")));
+        assert!(schedule.matches(&at("2026-10-19T00:00:00Z")));
+        assert!(!schedule.matches(&at("2026-10-20T00:00:00Z")));
+    }
+
+    #[test]
+    fn test_invalid_schedules() {
+        assert!("* * *".parse::<Schedule>().is_err());
+        assert!("60 * * * *".parse::<Schedule>().is_err());
+        assert!("*/0 * * * *".parse::<Schedule>().is_err());
+        assert!("a * * * *".parse::<Schedule>().is_err());
+    }
+}
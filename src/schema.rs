@@ -0,0 +1,151 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
+use std::fmt::Debug;
+
+/// A step into a YAML document: a mapping key or a sequence index.
+#[derive(Debug, Clone)]
+enum Step {
+    Key(Value),
+    Index(usize),
+}
+
+/// Paths (e.g. `tools.jq.retries`) of the fields in `yaml` that `T` doesn't
+/// understand: written by a newer tkit, or misspelled. `yaml` must parse as
+/// `T`. Fields that don't survive a round trip are unknown, at their
+/// default, or read but never written; changing the value tells them
+/// apart, since a field `T` knows changes what is parsed. Empty fields are
+/// left out either way.
+pub fn unknown_fields<T: DeserializeOwned + Serialize + Debug>(yaml: &str) -> Result<Vec<String>> {
+    let raw: Value = serde_yaml::from_str(yaml)?;
+    let parsed: T = serde_yaml::from_value(raw.clone())?;
+    let known = serde_yaml::to_value(&parsed)?;
+    let parsed = format!("{:?}", parsed);
+
+    let mut dropped = Vec::new();
+    collect_dropped(&raw, &known, &mut Vec::new(), &mut dropped);
+    Ok(dropped
+        .into_iter()
+        .filter(|path| is_ignored::<T>(&raw, &parsed, path))
+        .map(|path| display(&path))
+        .collect())
+}
+
+/// Paths present in `raw` but missing from `known`, not descending into them.
+fn collect_dropped(raw: &Value, known: &Value, path: &mut Vec<Step>, dropped: &mut Vec<Vec<Step>>) {
+    match (raw, known) {
+        (Value::Mapping(raw), Value::Mapping(known)) => {
+            for (key, value) in raw {
+                path.push(Step::Key(key.clone()));
+                match known.get(key) {
+                    Some(known) => collect_dropped(value, known, path, dropped),
+                    None => dropped.push(path.clone()),
+                }
+                path.pop();
+            }
+        }
+        (Value::Sequence(raw), Value::Sequence(known)) if raw.len() == known.len() => {
+            for (i, (value, known)) in raw.iter().zip(known).enumerate() {
+                path.push(Step::Index(i));
+                collect_dropped(value, known, path, dropped);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether changing the value at `path` leaves what `T` parses (`parsed`,
+/// as debug output) unchanged.
+fn is_ignored<T: DeserializeOwned + Debug>(raw: &Value, parsed: &str, path: &[Step]) -> bool {
+    let mut changed = raw.clone();
+    let Some(value) = lookup(&mut changed, path) else {
+        return false;
+    };
+    let Some(other) = perturb(value) else {
+        return false;
+    };
+    *value = other;
+    match serde_yaml::from_value::<T>(changed) {
+        Ok(changed) => format!("{:?}", changed) == parsed,
+        Err(_) => false,
+    }
+}
+
+fn lookup<'a>(value: &'a mut Value, path: &[Step]) -> Option<&'a mut Value> {
+    path.iter().try_fold(value, |value, step| match step {
+        Step::Key(key) => value.as_mapping_mut()?.get_mut(key),
+        Step::Index(i) => value.as_sequence_mut()?.get_mut(*i),
+    })
+}
+
+/// A different value of the same shape, or `None` for an empty one.
+fn perturb(value: &Value) -> Option<Value> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => Value::Bool(!b),
+        Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => Value::Number(i.wrapping_add(1).into()),
+            (None, Some(f)) => Value::Number((f + 1.0).into()),
+            _ => return None,
+        },
+        Value::String(s) if s.is_empty() => return None,
+        Value::String(s) => Value::String(format!("{}~", s)),
+        Value::Sequence(items) => {
+            let mut items = items.clone();
+            let first = perturb(items.first()?)?;
+            items[0] = first;
+            Value::Sequence(items)
+        }
+        Value::Mapping(mapping) => {
+            let mut mapping = mapping.clone();
+            let (key, first) = mapping.iter().next()?;
+            let (key, first) = (key.clone(), perturb(first)?);
+            mapping.insert(key, first);
+            Value::Mapping(mapping)
+        }
+        Value::Tagged(_) => return None,
+    })
+}
+
+fn display(path: &[Step]) -> String {
+    let mut out = String::new();
+    for step in path {
+        match step {
+            Step::Key(key) => {
+                if !out.is_empty() {
+                    out.push('.');
+                }
+                match key {
+                    Value::String(s) => out.push_str(s),
+                    other => {
+                        out.push_str(serde_yaml::to_string(other).unwrap_or_default().trim_end())
+                    }
+                }
+            }
+            Step::Index(i) => out.push_str(&format!("[{}]", i)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn test_unknown_fields() {
+        let yaml = "tools:\n  jq:\n    name: jq\n    install_commands: ['true']\n    installed: false\n    retries: 3\nsync:\n  auto_sync: false\n  shiny: true\nsettings:\n  color: auto\n";
+        assert_eq!(
+            unknown_fields::<Config>(yaml).unwrap(),
+            ["tools.jq.retries", "sync.shiny"]
+        );
+        assert!(
+            unknown_fields::<Config>("tools: {}\nsync: {}\n")
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use anyhow::{Result, anyhow};
+use rhai::{Dynamic, Engine, EvalAltResult, Map, Scope};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::PackageManager;
+use crate::preflight::{distro, os_version, version_at_least};
+
+/// Rhai scripts run around a tool's install, update and remove commands.
+/// Throwing from a `before_` hook stops the action before any command runs.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct Hooks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before_install: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_install: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before_update: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_update: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before_remove: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_remove: Option<String>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.all().next().is_none()
+    }
+
+    /// The hook run before `action`'s commands, with its name.
+    pub fn before(&self, action: &str) -> Option<(&'static str, &str)> {
+        let (name, hook) = match action {
+            "install" => ("before_install", &self.before_install),
+            "update" => ("before_update", &self.before_update),
+            "remove" => ("before_remove", &self.before_remove),
+            _ => return None,
+        };
+        Some((name, hook.as_deref()?))
+    }
+
+    /// The hook run once `action`'s commands succeeded, with its name.
+    pub fn after(&self, action: &str) -> Option<(&'static str, &str)> {
+        let (name, hook) = match action {
+            "install" => ("after_install", &self.after_install),
+            "update" => ("after_update", &self.after_update),
+            "remove" => ("after_remove", &self.after_remove),
+            _ => return None,
+        };
+        Some((name, hook.as_deref()?))
+    }
+
+    /// Every hook that is set, with its name.
+    pub fn all(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        ["install", "update", "remove"]
+            .into_iter()
+            .flat_map(|action| [self.before(action), self.after(action)])
+            .flatten()
+    }
+}
+
+/// What a command run with `exec` from a script did.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Exec {
+    pub code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// The lines a script printed, and why it failed if it did.
+#[derive(Debug, Default)]
+pub struct ScriptRun {
+    pub lines: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Checks that `source` parses, without running it.
+pub fn check(source: &str) -> Result<()> {
+    Engine::new()
+        .compile(source)
+        .map(|_| ())
+        .map_err(|e| anyhow!(e.to_string()))
+}
+
+/// Runs a Rhai script. Every entry of `vars` is a constant in scope (when
+/// its name is a valid identifier) and in the `vars` map, next to
+/// `os_version`, `distro` and `package_manager` (empty when unknown). Besides
+/// Rhai's own functions, scripts can call `exec(command)`, which runs
+/// `command` through `exec` and returns `#{ code, stdout, stderr, success }`;
+/// `which(name)`; `env(name)`; and `version_at_least(version, minimum)`.
+/// `print` output becomes the script's lines and `throw` fails it.
+pub fn run(
+    source: &str,
+    vars: &BTreeMap<String, String>,
+    exec: impl Fn(&str) -> Exec + 'static,
+) -> ScriptRun {
+    let mut engine = Engine::new();
+    let lines = Rc::new(RefCell::new(Vec::new()));
+    let printed = lines.clone();
+    engine.on_print(move |text| {
+        printed
+            .borrow_mut()
+            .extend(text.lines().map(str::to_string))
+    });
+    engine.register_fn("exec", move |command: &str| -> Map {
+        let output = exec(command);
+        Map::from([
+            ("code".into(), Dynamic::from(output.code as rhai::INT)),
+            ("stdout".into(), output.stdout.trim_end().into()),
+            ("stderr".into(), output.stderr.trim_end().into()),
+            ("success".into(), Dynamic::from(output.code == 0)),
+        ])
+    });
+    engine.register_fn("which", |name: &str| which::which(name).is_ok());
+    engine.register_fn("env", |name: &str| std::env::var(name).unwrap_or_default());
+    engine.register_fn("version_at_least", |version: &str, minimum: &str| {
+        version_at_least(version, minimum)
+    });
+
+    let mut values = BTreeMap::from([
+        ("os_version".to_string(), os_version().unwrap_or_default()),
+        ("distro".to_string(), distro().unwrap_or_default()),
+        (
+            "package_manager".to_string(),
+            PackageManager::detect_host()
+                .map(|manager| manager.to_string())
+                .unwrap_or_default(),
+        ),
+    ]);
+    values.extend(vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+    let mut scope = Scope::new();
+    for (name, value) in &values {
+        if is_identifier(name) {
+            scope.push_constant(name.as_str(), value.clone());
+        }
+    }
+    let map: Map = values
+        .into_iter()
+        .map(|(name, value)| (name.into(), value.into()))
+        .collect();
+    scope.push_constant("vars", map);
+
+    let error = engine
+        .run_with_scope(&mut scope, source)
+        .err()
+        .map(|e| match *e {
+            EvalAltResult::ErrorRuntime(value, _) => value.to_string(),
+            other => other.to_string(),
+        });
+    let lines = lines.take();
+    ScriptRun { lines, error }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_script() {
+        let vars = BTreeMap::from([
+            ("os".to_string(), "linux".to_string()),
+            ("tool-dir".to_string(), "/opt".to_string()),
+        ]);
+        let exec = |command: &str| Exec {
+            code: if command == "node --version" { 0 } else { 1 },
+            stdout: "v20.11.0\n".to_string(),
+            stderr: String::new(),
+        };
+        let result = run(
+            r#"
+            let node = exec("node --version");
+            if node.success && version_at_least(node.stdout.sub_string(1), "18") {
+                print(`node ${node.stdout} on ${os}`);
+            }
+            print(vars["tool-dir"]);
+            "#,
+            &vars,
+            exec,
+        );
+        assert_eq!(result.error, None);
+        assert_eq!(result.lines, vec!["node v20.11.0 on linux", "/opt"]);
+
+        let result = run(
+            r#"if !exec("false").success { throw "needs false to pass" }"#,
+            &vars,
+            exec,
+        );
+        assert_eq!(result.error.as_deref(), Some("needs false to pass"));
+        // Variables are constants
+        assert!(run("os = \"darwin\";", &vars, exec).error.is_some());
+
+        assert!(check("let x = 1; if x > 0 { print(x) }").is_ok());
+        assert!(check("if {").is_err());
+    }
+
+    #[test]
+    fn test_hooks() {
+        let hooks: Hooks =
+            serde_yaml::from_str("before_install: print(1)\nafter_remove: print(2)\n").unwrap();
+        assert_eq!(
+            hooks.before("install"),
+            Some(("before_install", "print(1)"))
+        );
+        assert_eq!(hooks.after("install"), None);
+        assert_eq!(hooks.after("remove"), Some(("after_remove", "print(2)")));
+        assert_eq!(hooks.before("run"), None);
+        assert_eq!(hooks.all().count(), 2);
+        assert!(Hooks::default().is_empty());
+    }
+}
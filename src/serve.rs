@@ -0,0 +1,213 @@
+use anyhow::{Result, anyhow};
+use colored::*;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tkit::{
+    Config,
+    api::{self, Request, Route, StatusSummary, ToolSummary},
+    redact::{redact, register_secret},
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::jobs::JobRunner;
+
+/// Largest request body read (and ignored); no endpoint takes one.
+const MAX_BODY_LEN: usize = 1024 * 1024;
+
+/// How often a log stream checks its job for new output.
+const LOG_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct JobStarted {
+    job: u64,
+    logs: String,
+}
+
+/// Serves the HTTP API on `listen` until interrupted.
+pub async fn serve(listen: &str, token: Option<String>) -> Result<()> {
+    let from_file = token.is_none() && std::env::var(api::TOKEN_ENV).is_err();
+    let token = match token {
+        Some(token) => token,
+        None => api::load_or_create_token()?,
+    };
+    register_secret(&token);
+
+    let listener = TcpListener::bind(listen)
+        .await
+        .map_err(|e| anyhow!("Could not listen on {}: {}", listen, e))?;
+    let addr = listener.local_addr()?;
+    println!("{} http://{}", "✓ Serving the tkit API on".green(), addr);
+    if from_file {
+        println!(
+            "  Clients authenticate with 'Authorization: Bearer <token>', the token in {}",
+            api::get_api_token_path()?.display()
+        );
+    }
+    if !addr.ip().is_loopback() {
+        println!(
+            "{}",
+            "⚠ Listening beyond this machine: anyone with the token can run install commands"
+                .yellow()
+        );
+    }
+
+    let token = Arc::new(token);
+    let runner = JobRunner::new();
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => accepted?.0,
+            _ = tokio::signal::ctrl_c() => {
+                println!("{}", "Stopped".dimmed());
+                return Ok(());
+            }
+        };
+        let (token, runner) = (token.clone(), runner.clone());
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, &token, &runner).await {
+                eprintln!("{} {}", "✗ Request failed:".red(), redact(&e.to_string()));
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, token: &str, runner: &Arc<JobRunner>) -> Result<()> {
+    let request = match read_request(&mut stream).await {
+        Ok(Some(request)) => request,
+        Ok(None) => return Ok(()),
+        Err(status) => return write_error(&mut stream, status, api::status_text(status)).await,
+    };
+    if !api::token_matches(token, request.token()) {
+        return write_error(&mut stream, 401, "Missing or wrong token").await;
+    }
+
+    let route = match api::route(&request.method, &request.path) {
+        Ok(route) => route,
+        Err(status) => return write_error(&mut stream, status, api::status_text(status)).await,
+    };
+    match route {
+        Route::Tools => write_json(&mut stream, 200, &ToolSummary::all(&Config::load()?)).await,
+        Route::Tool(name) => {
+            let config = Config::load()?;
+            let name = config.resolve_name(&name);
+            match config.get_tool(&name) {
+                Some(tool) => write_json(&mut stream, 200, &ToolSummary::new(&name, tool)).await,
+                None => write_error(&mut stream, 404, "No such tool").await,
+            }
+        }
+        Route::Status => {
+            let status = StatusSummary::new(&Config::load()?, runner.running());
+            write_json(&mut stream, 200, &status).await
+        }
+        Route::Start(name, action) => {
+            let config = Config::load()?;
+            let name = config.resolve_name(&name);
+            if config.get_tool(&name).is_none() {
+                return write_error(&mut stream, 404, "No such tool").await;
+            }
+            let entry = request.query.get("cmd").map(String::as_str);
+            match runner.start(&name, action, entry) {
+                Ok(id) => {
+                    let started = JobStarted {
+                        job: id,
+                        logs: format!("/jobs/{}/logs", id),
+                    };
+                    write_json(&mut stream, 202, &started).await
+                }
+                Err(e) => write_error(&mut stream, 409, &e.to_string()).await,
+            }
+        }
+        Route::Jobs => write_json(&mut stream, 200, &runner.list()).await,
+        Route::Job(id) => match runner.get(id) {
+            Some(job) => write_json(&mut stream, 200, &job.detail()).await,
+            None => write_error(&mut stream, 404, "No such job").await,
+        },
+        Route::JobLogs(id) => stream_logs(&mut stream, runner, id).await,
+    }
+}
+
+/// Reads a request head and discards any body. `None` when the client
+/// closed the connection without sending anything; the error is the status
+/// to answer a bad request with.
+async fn read_request(stream: &mut TcpStream) -> std::result::Result<Option<Request>, u16> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() > api::MAX_HEAD_LEN {
+            return Err(413);
+        }
+        let read = stream.read(&mut chunk).await.map_err(|_| 400u16)?;
+        if read == 0 {
+            return if buf.is_empty() { Ok(None) } else { Err(400) };
+        }
+        buf.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = std::str::from_utf8(&buf[..head_end]).map_err(|_| 400u16)?;
+    let request = Request::parse(head).map_err(|_| 400u16)?;
+    let body_len = request.content_length();
+    if body_len > MAX_BODY_LEN {
+        return Err(413);
+    }
+    let mut remaining = body_len.saturating_sub(buf.len() - head_end - 4);
+    while remaining > 0 {
+        let read = stream.read(&mut chunk).await.map_err(|_| 400u16)?;
+        if read == 0 {
+            break;
+        }
+        remaining = remaining.saturating_sub(read);
+    }
+    Ok(Some(request))
+}
+
+async fn write_json<T: Serialize>(stream: &mut TcpStream, status: u16, body: &T) -> Result<()> {
+    let body = serde_json::to_string(body)?;
+    stream
+        .write_all(api::response(status, "application/json", &body).as_bytes())
+        .await?;
+    Ok(())
+}
+
+async fn write_error(stream: &mut TcpStream, status: u16, error: &str) -> Result<()> {
+    write_json(stream, status, &ErrorBody { error }).await
+}
+
+/// Sends the job's output so far, then each new line as it arrives, as
+/// `log` events, ending with a `done` event carrying the finished job.
+async fn stream_logs(stream: &mut TcpStream, runner: &JobRunner, id: u64) -> Result<()> {
+    if runner.get(id).is_none() {
+        return write_error(stream, 404, "No such job").await;
+    }
+    stream.write_all(api::sse_head().as_bytes()).await?;
+
+    let mut sent = 0;
+    while let Some((lines, finished)) = runner.output_since(id, sent) {
+        sent += lines.len();
+        for line in lines {
+            // A client that went away just ends the stream
+            if stream
+                .write_all(api::sse_event("log", &line).as_bytes())
+                .await
+                .is_err()
+            {
+                return Ok(());
+            }
+        }
+        if let Some(job) = finished {
+            let event = api::sse_event("done", &serde_json::to_string(&job)?);
+            let _ = stream.write_all(event.as_bytes()).await;
+            return Ok(());
+        }
+        tokio::time::sleep(LOG_POLL_INTERVAL).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,564 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::ToolConfig;
+
+/// Shells `tkit env` can print a snippet for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    pub const ALL: [Shell; 3] = [Shell::Bash, Shell::Zsh, Shell::Fish];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        }
+    }
+
+    /// The startup file, relative to the home directory, that sets up PATH.
+    pub fn rc_file(&self) -> &'static str {
+        match self {
+            Shell::Bash => ".bashrc",
+            Shell::Zsh => ".zshrc",
+            Shell::Fish => ".config/fish/conf.d/tkit.fish",
+        }
+    }
+
+    /// The user's login shell from `$SHELL`, defaulting to bash.
+    pub fn detect() -> Shell {
+        std::env::var("SHELL")
+            .ok()
+            .and_then(|path| path.rsplit('/').next()?.parse().ok())
+            .unwrap_or(Shell::Bash)
+    }
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|shell| shell.as_str() == s.to_lowercase())
+            .ok_or_else(|| format!("unsupported shell '{}' (expected bash, zsh or fish)", s))
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Writes a leading `~` as `$HOME` so it expands inside double quotes.
+fn expand_home(value: &str) -> String {
+    match value.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("$HOME{}", rest),
+        _ => value.to_string(),
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", expand_home(value).replace('"', "\\\""))
+}
+
+/// The combined PATH additions and exports of `tools`, as shell code to `eval`.
+pub fn render<'a>(tools: impl IntoIterator<Item = &'a ToolConfig>, shell: Shell) -> String {
+    let mut paths: Vec<&str> = Vec::new();
+    let mut exports: Vec<(&str, &str)> = Vec::new();
+    for tool in tools {
+        for path in &tool.paths {
+            if !paths.contains(&path.as_str()) {
+                paths.push(path);
+            }
+        }
+        exports.extend(tool.exports.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+    }
+
+    let mut lines = Vec::new();
+    for (name, value) in exports {
+        lines.push(match shell {
+            Shell::Fish => format!("set -gx {} {}", name, quote(value)),
+            Shell::Bash | Shell::Zsh => format!("export {}={}", name, quote(value)),
+        });
+    }
+    if !paths.is_empty() {
+        lines.push(path_line(&paths, shell));
+    }
+
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+/// The line putting `paths` in front of PATH.
+pub fn path_line(paths: &[&str], shell: Shell) -> String {
+    match shell {
+        Shell::Fish => {
+            let quoted: Vec<String> = paths.iter().map(|p| quote(p)).collect();
+            format!("fish_add_path --global --path {}", quoted.join(" "))
+        }
+        Shell::Bash | Shell::Zsh => {
+            let joined: Vec<String> = paths.iter().map(|p| expand_home(p)).collect();
+            format!("export PATH=\"{}:$PATH\"", joined.join(":"))
+        }
+    }
+}
+
+/// Appends `line` to the shell startup file at `path` unless it already has
+/// it. Returns whether the file changed.
+pub fn add_to_rc(path: &Path, line: &str) -> io::Result<bool> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    if content.lines().any(|existing| existing.trim() == line) {
+        return Ok(false);
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let separator = if content.is_empty() || content.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    fs::write(
+        path,
+        format!("{}{}\n# Added by tkit\n{}\n", content, separator, line),
+    )?;
+    Ok(true)
+}
+
+fn single_quote(value: &str, shell: Shell) -> String {
+    match shell {
+        Shell::Fish => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'")),
+        Shell::Bash | Shell::Zsh => format!("'{}'", value.replace('\'', "'\\''")),
+    }
+}
+
+/// The shell aliases of `tools`, as a file to source.
+pub fn render_aliases<'a>(tools: impl IntoIterator<Item = &'a ToolConfig>, shell: Shell) -> String {
+    let mut out =
+        String::from("# Generated by 'tkit alias sync'; edit the tool definitions instead.\n");
+    for tool in tools {
+        for (name, command) in &tool.shell_aliases {
+            let command = single_quote(command, shell);
+            out.push_str(&match shell {
+                Shell::Fish => format!("alias {} {}\n", name, command),
+                Shell::Bash | Shell::Zsh => format!("alias {}={}\n", name, command),
+            });
+        }
+    }
+    out
+}
+
+/// Interpreter that runs a tool's commands. Without one, each command is split
+/// on whitespace and executed directly.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandShell {
+    /// `sh` on Unix, `cmd` on Windows.
+    Auto,
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+    Pwsh,
+    Cmd,
+}
+
+impl CommandShell {
+    fn resolve(self) -> CommandShell {
+        match self {
+            CommandShell::Auto if cfg!(windows) => CommandShell::Cmd,
+            CommandShell::Auto => CommandShell::Sh,
+            shell => shell,
+        }
+    }
+
+    /// Program and arguments that run `script` in this shell.
+    pub fn command(self, script: &str) -> (String, Vec<String>) {
+        let (program, flags): (&str, &[&str]) = match self.resolve() {
+            CommandShell::Pwsh => ("pwsh", &["-NoProfile", "-Command"]),
+            CommandShell::Cmd => ("cmd", &["/C"]),
+            CommandShell::Bash => ("bash", &["-c"]),
+            CommandShell::Zsh => ("zsh", &["-c"]),
+            CommandShell::Fish => ("fish", &["-c"]),
+            CommandShell::Sh | CommandShell::Auto => ("sh", &["-c"]),
+        };
+        let mut args: Vec<String> = flags.iter().map(|flag| flag.to_string()).collect();
+        args.push(script.to_string());
+        (program.to_string(), args)
+    }
+
+    /// `arg` quoted so the shell passes it through as one word.
+    pub fn quote(self, arg: &str) -> String {
+        match self.resolve() {
+            CommandShell::Fish => single_quote(arg, Shell::Fish),
+            CommandShell::Pwsh => format!("'{}'", arg.replace('\'', "''")),
+            CommandShell::Cmd => format!("\"{}\"", arg.replace('"', "\"\"")),
+            _ => single_quote(arg, Shell::Bash),
+        }
+    }
+}
+
+impl FromStr for CommandShell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s).map_err(|_| {
+            format!(
+                "unsupported shell '{}' (expected auto, sh, bash, zsh, fish, pwsh or cmd)",
+                s
+            )
+        })
+    }
+}
+
+/// Where a tool's commands run.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecTarget {
+    #[default]
+    Native,
+    /// Inside the default WSL distro when on Windows (`wsl.exe -- ...`);
+    /// natively elsewhere, since the recipe is written for Linux.
+    Wsl,
+}
+
+impl ExecTarget {
+    pub fn is_native(&self) -> bool {
+        *self == ExecTarget::Native
+    }
+
+    /// `program` and `args` adjusted to run on this target.
+    pub fn wrap(self, program: String, args: Vec<String>) -> Result<(String, Vec<String>), String> {
+        if self.is_native() || !cfg!(windows) {
+            return Ok((program, args));
+        }
+        if !wsl_available() {
+            return Err(
+                "This tool targets WSL, but WSL is not available on this machine".to_string(),
+            );
+        }
+        let mut wsl_args = vec!["--".to_string(), program];
+        wsl_args.extend(args);
+        Ok(("wsl.exe".to_string(), wsl_args))
+    }
+}
+
+/// Whether this is Windows with a working WSL install.
+pub fn wsl_available() -> bool {
+    cfg!(windows)
+        && std::process::Command::new("wsl.exe")
+            .arg("--status")
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+}
+
+/// Splits a step's `#!<shell> ` prefix, e.g. `#!fish set -x FOO bar`, from the command.
+pub fn split_step_shell(step: &str) -> Result<(Option<CommandShell>, &str), String> {
+    match step.strip_prefix("#!") {
+        Some(rest) => {
+            let (name, command) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            Ok((Some(name.parse()?), command.trim_start()))
+        }
+        None => Ok((None, step)),
+    }
+}
+
+/// Splits a command run without a shell into words as a POSIX shell would:
+/// quotes group words and a backslash escapes the next character, but
+/// nothing is expanded and there are no pipes or redirections.
+pub fn split_words(command: &str) -> Result<Vec<String>, String> {
+    shell_words::split(command).map_err(|_| format!("Unmatched quote in '{}'", command))
+}
+
+/// Expands `$NAME`, `${NAME}` and a leading `~` in a command run without a
+/// shell, before it is split into words. As in a shell, nothing expands
+/// inside single quotes or after a backslash, and an empty unquoted value
+/// drops out; unlike one, a value is never split into several words.
+/// Variables `lookup` doesn't know are left as written.
+pub fn expand_vars(command: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::with_capacity(command.len());
+    let (mut single, mut double) = (false, false);
+    let mut word_start = true;
+    let mut chars = command.char_indices();
+    while let Some((i, c)) = chars.next() {
+        let at_word_start = std::mem::replace(&mut word_start, false);
+        match c {
+            '\\' if !single => {
+                expanded.push(c);
+                if let Some((_, escaped)) = chars.next() {
+                    expanded.push(escaped);
+                }
+            }
+            '\'' if !double => {
+                single = !single;
+                expanded.push(c);
+            }
+            '"' if !single => {
+                double = !double;
+                expanded.push(c);
+            }
+            '$' if !single => match variable_name(&command[i + 1..])
+                .and_then(|(name, len)| Some((lookup(name)?, len)))
+            {
+                Some((value, len)) => {
+                    expanded.push_str(&escape_value(&value, double));
+                    // Names are ASCII, so bytes are chars
+                    chars.nth(len - 1);
+                }
+                _ => expanded.push(c),
+            },
+            '~' if at_word_start
+                && !single
+                && !double
+                && command[i + 1..]
+                    .chars()
+                    .next()
+                    .is_none_or(|next| next == '/' || next.is_whitespace()) =>
+            {
+                match dirs::home_dir() {
+                    Some(home) => expanded.push_str(&escape_value(&home.to_string_lossy(), false)),
+                    None => expanded.push(c),
+                }
+            }
+            c if c.is_whitespace() && !single && !double => {
+                word_start = true;
+                expanded.push(c);
+            }
+            c => expanded.push(c),
+        }
+    }
+    expanded
+}
+
+/// The variable name at the start of `rest`, after a `$`, and how many
+/// characters it takes up, braces included.
+fn variable_name(rest: &str) -> Option<(&str, usize)> {
+    let (name, len) = match rest.strip_prefix('{') {
+        Some(inner) => {
+            let end = inner.find('}')?;
+            (&inner[..end], end + 2)
+        }
+        None => {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            (&rest[..end], end)
+        }
+    };
+    let valid = name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some((name, len))
+}
+
+/// `value` quoted so splitting the command keeps it as written.
+fn escape_value(value: &str, in_double_quotes: bool) -> String {
+    if in_double_quotes {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            if matches!(c, '\\' | '"' | '$' | '`') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    } else if value.is_empty() {
+        String::new()
+    } else {
+        shell_words::quote(value).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn go() -> ToolConfig {
+        ToolConfig {
+            name: "go".to_string(),
+            paths: vec!["/usr/local/go/bin".to_string(), "~/go/bin".to_string()],
+            exports: [("GOPATH".to_string(), "~/go".to_string())].into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_posix() {
+        assert_eq!(
+            render([&go()], Shell::Bash),
+            "export GOPATH=\"$HOME/go\"\nexport PATH=\"/usr/local/go/bin:$HOME/go/bin:$PATH\"\n"
+        );
+    }
+
+    #[test]
+    fn test_render_fish() {
+        assert_eq!(
+            render([&go()], Shell::Fish),
+            "set -gx GOPATH \"$HOME/go\"\nfish_add_path --global --path \"/usr/local/go/bin\" \"$HOME/go/bin\"\n"
+        );
+    }
+
+    #[test]
+    fn test_add_to_rc() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let rc = dir.path().join(".bashrc");
+        fs::write(&rc, "alias ll='ls -l'").unwrap();
+        let line = path_line(&["~/.local/share/tkit/bin"], Shell::Bash);
+        assert_eq!(line, "export PATH=\"$HOME/.local/share/tkit/bin:$PATH\"");
+
+        assert!(add_to_rc(&rc, &line).unwrap());
+        assert!(!add_to_rc(&rc, &line).unwrap());
+        assert_eq!(
+            fs::read_to_string(&rc).unwrap(),
+            format!("alias ll='ls -l'\n\n# Added by tkit\n{}\n", line)
+        );
+
+        let fish = dir.path().join(".config/fish/conf.d/tkit.fish");
+        assert!(add_to_rc(&fish, &path_line(&["/opt/bin"], Shell::Fish)).unwrap());
+    }
+
+    #[test]
+    fn test_render_aliases() {
+        let kubectl = ToolConfig {
+            name: "kubectl".to_string(),
+            shell_aliases: [
+                ("k".to_string(), "kubectl".to_string()),
+                (
+                    "kgp".to_string(),
+                    "kubectl get pods -l 'app=web'".to_string(),
+                ),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let posix = render_aliases([&kubectl], Shell::Bash);
+        assert!(posix.contains("alias k='kubectl'\n"));
+        assert!(posix.contains("alias kgp='kubectl get pods -l '\\''app=web'\\'''\n"));
+        let fish = render_aliases([&kubectl], Shell::Fish);
+        assert!(fish.contains("alias kgp 'kubectl get pods -l \\'app=web\\''\n"));
+    }
+
+    #[test]
+    fn test_parse_shell() {
+        assert_eq!("ZSH".parse::<Shell>(), Ok(Shell::Zsh));
+        assert!("tcsh".parse::<Shell>().is_err());
+    }
+
+    #[test]
+    fn test_step_shell_prefix() {
+        assert_eq!(
+            split_step_shell("#!fish set -x FOO bar"),
+            Ok((Some(CommandShell::Fish), "set -x FOO bar"))
+        );
+        assert_eq!(split_step_shell("echo hi"), Ok((None, "echo hi")));
+        assert!(split_step_shell("#!tcsh echo hi").is_err());
+    }
+
+    #[test]
+    fn test_split_words() {
+        let words = |command: &str| split_words(command).unwrap();
+        assert_eq!(words("  git   clone  url "), ["git", "clone", "url"]);
+        assert_eq!(words(r#"bash -c "echo hi""#), ["bash", "-c", "echo hi"]);
+        assert_eq!(
+            words("cp '/tmp/My Files/a.txt' ~/b"),
+            ["cp", "/tmp/My Files/a.txt", "~/b"]
+        );
+        assert_eq!(words(r"ls /tmp/My\ Files"), ["ls", "/tmp/My Files"]);
+        assert_eq!(words(r#"echo "" x"#), ["echo", "", "x"]);
+        assert_eq!(
+            words(r#"git config --global user.name="Ada Lovelace""#).len(),
+            4
+        );
+        assert_eq!(
+            words(r#"echo "it's" 'say "hi"'"#),
+            ["echo", "it's", r#"say "hi""#]
+        );
+        assert_eq!(
+            words(r#"echo "a \"b\" \$HOME""#),
+            ["echo", r#"a "b" $HOME"#]
+        );
+        assert_eq!(words("echo 'a'\"b\"c"), ["echo", "abc"]);
+        assert_eq!(
+            words("curl https://example.com/#top"),
+            ["curl", "https://example.com/#top"]
+        );
+        assert!(words("").is_empty());
+        assert!(split_words("echo 'unterminated").is_err());
+        assert!(split_words(r#"echo "unterminated"#).is_err());
+    }
+
+    #[test]
+    fn test_expand_vars() {
+        let vars = BTreeMap::from([
+            ("HOME", "/home/ada"),
+            ("DATA", "/mnt/my data"),
+            ("QUOTE", "say \"hi\""),
+            ("EMPTY", ""),
+            ("version", "1.2"),
+        ]);
+        let expand = |command: &str| {
+            let expanded = expand_vars(command, |name| vars.get(name).map(|v| v.to_string()));
+            split_words(&expanded).unwrap()
+        };
+
+        assert_eq!(expand("ls $HOME/bin"), ["ls", "/home/ada/bin"]);
+        assert_eq!(expand("cp a ${DATA}/b"), ["cp", "a", "/mnt/my data/b"]);
+        assert_eq!(
+            expand("echo \"$QUOTE\" $QUOTE"),
+            ["echo", "say \"hi\"", "say \"hi\""]
+        );
+        assert_eq!(expand("tool-v$version.tar"), ["tool-v1.2.tar"]);
+        assert_eq!(expand("echo '$HOME' \\$HOME"), ["echo", "$HOME", "$HOME"]);
+        assert_eq!(expand("echo $EMPTY x \"$EMPTY\""), ["echo", "x", ""]);
+        assert_eq!(
+            expand("echo $UNKNOWN ${1} $ $5"),
+            ["echo", "$UNKNOWN", "${1}", "$", "$5"]
+        );
+        assert_eq!(expand("echo ${HOME"), ["echo", "${HOME"]);
+
+        let home = dirs::home_dir().unwrap().to_string_lossy().into_owned();
+        assert_eq!(
+            expand("ls ~/bin ~"),
+            ["ls", &format!("{}/bin", home), &home]
+        );
+        assert_eq!(
+            expand("echo a~ '~/x' ~user"),
+            ["echo", "a~", "~/x", "~user"]
+        );
+    }
+
+    #[test]
+    fn test_wsl_target_runs_natively_off_windows() {
+        if cfg!(windows) {
+            return;
+        }
+        let wrapped = ExecTarget::Wsl.wrap("apt".to_string(), vec!["update".to_string()]);
+        assert_eq!(wrapped, Ok(("apt".to_string(), vec!["update".to_string()])));
+    }
+
+    #[test]
+    fn test_command_shell_invocation() {
+        let (program, args) = CommandShell::Bash.command("echo a | tr a b");
+        assert_eq!(program, "bash");
+        assert_eq!(args, ["-c", "echo a | tr a b"]);
+        assert_eq!(CommandShell::Pwsh.quote("it's"), "'it''s'");
+        assert_eq!(CommandShell::Sh.quote("it's"), "'it'\\''s'");
+    }
+}
@@ -0,0 +1,156 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::downloads::read_url;
+
+/// A detached GPG signature check run before the install commands, e.g.
+/// `verify: {file: terraform, signature_url: https://…/SHA256SUMS.sig, key: https://…/pgp-key.txt}`.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct SignatureCheck {
+    /// Name of one of the tool's downloads, or a path.
+    pub file: String,
+    pub signature_url: String,
+    /// URL or path of the ASCII-armored public key to trust.
+    pub key: String,
+    /// When set, the key must have this fingerprint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+}
+
+impl SignatureCheck {
+    /// Verifies the signature against a throwaway keyring holding only `key`,
+    /// so the user's own keyring is never consulted or modified.
+    pub async fn verify(&self, downloads: &BTreeMap<String, String>) -> Result<()> {
+        if which::which("gpg").is_err() {
+            return Err(anyhow!("Signature verification requires 'gpg' on PATH"));
+        }
+
+        let file = downloads
+            .get(&self.file)
+            .cloned()
+            .unwrap_or_else(|| self.file.clone());
+        let key = read_url(&self.key).await?;
+        let signature = read_url(&self.signature_url).await?;
+
+        let home = TempHome::new()?;
+        let key_path = home.path.join("key.asc");
+        let signature_path = home.path.join("file.sig");
+        fs::write(&key_path, key)?;
+        fs::write(&signature_path, signature)?;
+
+        let import = gpg(&home.path, &["--import", &key_path.display().to_string()])?;
+        if !import.status.success() {
+            return Err(anyhow!(
+                "Could not import key '{}': {}",
+                self.key,
+                String::from_utf8_lossy(&import.stderr).trim()
+            ));
+        }
+
+        if let Some(expected) = &self.fingerprint {
+            let listing = gpg(&home.path, &["--with-colons", "--fingerprint"])?;
+            let fingerprints = parse_fingerprints(&String::from_utf8_lossy(&listing.stdout));
+            let expected = normalize_fingerprint(expected);
+            if !fingerprints.contains(&expected) {
+                return Err(anyhow!(
+                    "Key '{}' does not have the expected fingerprint {}",
+                    self.key,
+                    expected
+                ));
+            }
+        }
+
+        let verify = gpg(
+            &home.path,
+            &["--verify", &signature_path.display().to_string(), &file],
+        )?;
+        if !verify.status.success() {
+            return Err(anyhow!(
+                "Bad signature for '{}': {}",
+                self.file,
+                String::from_utf8_lossy(&verify.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn gpg(home: &Path, args: &[&str]) -> Result<std::process::Output> {
+    Ok(Command::new("gpg")
+        .arg("--batch")
+        .arg("--homedir")
+        .arg(home)
+        .args(args)
+        .output()?)
+}
+
+/// Fingerprints listed in `gpg --with-colons` output.
+pub fn parse_fingerprints(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("fpr:"))
+        .filter_map(|rest| rest.split(':').find(|field| !field.is_empty()))
+        .map(normalize_fingerprint)
+        .collect()
+}
+
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// A private GNUPGHOME removed when dropped.
+struct TempHome {
+    path: PathBuf,
+}
+
+impl TempHome {
+    fn new() -> Result<Self> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("tkit-gpg-{}-{}", std::process::id(), nanos));
+        fs::create_dir_all(&path)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o700))?;
+        }
+        Ok(TempHome { path })
+    }
+}
+
+impl Drop for TempHome {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fingerprints() {
+        let output = "pub:-:4096:1:34365D9472D7468F:1677532268:::-:::scESC::::::23::0:\nfpr:::::::::C874011F0AB405110D02105534365D9472D7468F:\nuid:-::::1677532268::hash::HashiCorp Security:\n";
+        assert_eq!(
+            parse_fingerprints(output),
+            vec!["C874011F0AB405110D02105534365D9472D7468F"]
+        );
+    }
+
+    #[test]
+    fn test_normalize_fingerprint() {
+        assert_eq!(
+            normalize_fingerprint("c874 011f 0ab4 0511"),
+            "C874011F0AB40511"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::render_template;
+
+/// A command that takes a system snapshot (timeshift, snapper, a btrfs or
+/// zfs snapshot) before operations that are hard to undo, so the machine can
+/// be rolled back at the OS level. Written as just the command, or as a map
+/// with the fields below.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(from = "Repr")]
+pub struct SnapshotHook {
+    /// Run through the shell, with `{{action}}`, `{{tools}}` and
+    /// `{{timestamp}}` (UTC, like `20260101-120000`) filled in.
+    pub command: String,
+    /// Operations that take a snapshot first; all of them when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<SnapshotTrigger>,
+    /// Go ahead when the snapshot command fails instead of stopping.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_failure: bool,
+}
+
+/// Operations that can be preceded by a snapshot.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotTrigger {
+    /// `tkit apply` with changes to make.
+    Apply,
+    /// Installs of more than one tool at once.
+    Install,
+    /// Removals.
+    Remove,
+}
+
+impl fmt::Display for SnapshotTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SnapshotTrigger::Apply => "apply",
+            SnapshotTrigger::Install => "install",
+            SnapshotTrigger::Remove => "remove",
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Repr {
+    Command(String),
+    Fields {
+        command: String,
+        #[serde(default)]
+        before: Vec<SnapshotTrigger>,
+        #[serde(default)]
+        allow_failure: bool,
+    },
+}
+
+impl From<Repr> for SnapshotHook {
+    fn from(repr: Repr) -> Self {
+        match repr {
+            Repr::Command(command) => SnapshotHook {
+                command,
+                ..Default::default()
+            },
+            Repr::Fields {
+                command,
+                before,
+                allow_failure,
+            } => SnapshotHook {
+                command,
+                before,
+                allow_failure,
+            },
+        }
+    }
+}
+
+impl SnapshotHook {
+    pub fn runs_before(&self, trigger: SnapshotTrigger) -> bool {
+        self.before.is_empty() || self.before.contains(&trigger)
+    }
+
+    /// The command to run before `trigger` changes `tools`.
+    pub fn render(&self, trigger: SnapshotTrigger, tools: &[String], now: &str) -> String {
+        let vars = BTreeMap::from([
+            ("action".to_string(), trigger.to_string()),
+            ("tools".to_string(), tools.join(" ")),
+            ("timestamp".to_string(), now.to_string()),
+        ]);
+        render_template(&self.command, &vars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_hook_forms() {
+        let hook: SnapshotHook = serde_yaml::from_str("sudo snapper create").unwrap();
+        assert_eq!(hook.command, "sudo snapper create");
+        assert!(hook.runs_before(SnapshotTrigger::Install));
+
+        let hook: SnapshotHook = serde_yaml::from_str(
+            "command: sudo timeshift --create --comments 'tkit {{action}} {{tools}}'\nbefore: [apply, remove]\n",
+        )
+        .unwrap();
+        assert!(hook.runs_before(SnapshotTrigger::Remove));
+        assert!(!hook.runs_before(SnapshotTrigger::Install));
+        assert_eq!(
+            hook.render(
+                SnapshotTrigger::Remove,
+                &["docker".to_string(), "jq".to_string()],
+                "20260101-120000"
+            ),
+            "sudo timeshift --create --comments 'tkit remove docker jq'"
+        );
+
+        let yaml = serde_yaml::to_string(&hook).unwrap();
+        assert_eq!(serde_yaml::from_str::<SnapshotHook>(&yaml).unwrap(), hook);
+    }
+}
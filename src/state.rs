@@ -0,0 +1,247 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::downloads::sha256_hex;
+use crate::{Step, write_private};
+
+/// File name of the state, next to the config.
+pub const STATE_FILE: &str = "state.yaml";
+
+/// Machine-local bookkeeping kept next to the config but never synced.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct State {
+    #[serde(default)]
+    pub tools: BTreeMap<String, ToolStats>,
+    /// What is actually installed on this machine, by tool name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub observed: BTreeMap<String, ObservedState>,
+    /// `Config::push_fingerprint` as last pushed, by remote name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pushed: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct ObservedState {
+    pub installed: bool,
+    /// Parameter values chosen when the tool was installed here.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub param_values: BTreeMap<String, String>,
+}
+
+/// Usage counters for one tool. Timestamps are RFC 3339.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct ToolStats {
+    #[serde(default)]
+    pub runs: u64,
+    pub last_run: Option<String>,
+    pub last_install: Option<String>,
+    pub last_update: Option<String>,
+    /// Total seconds spent in install and update commands.
+    #[serde(default)]
+    pub install_seconds: f64,
+    /// The latest timing of each action (`install`, `update`, `run`...).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub timings: BTreeMap<String, Timing>,
+    /// How far an install that didn't finish got, for `tkit install --resume`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<Checkpoint>,
+    /// Files tkit put in place itself for the tool, such as release
+    /// executables; they are deleted when the tool is removed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+}
+
+/// The steps of an unfinished install that already ran.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub at: String,
+    /// Identifies the rendered steps, so a changed recipe starts over.
+    pub fingerprint: String,
+    /// Steps done (or skipped), counted from the first.
+    pub completed: usize,
+    pub total: usize,
+    /// Values the completed steps `register`ed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub registered: BTreeMap<String, String>,
+}
+
+impl Checkpoint {
+    /// The fingerprint of `steps`, as they are about to run.
+    pub fn fingerprint(steps: &[Step]) -> String {
+        sha256_hex(serde_yaml::to_string(steps).unwrap_or_default().as_bytes())
+    }
+
+    /// Where to pick up `steps` from: the number of steps to skip, or `None`
+    /// when they changed since.
+    pub fn resume_at(&self, steps: &[Step]) -> Option<usize> {
+        (self.fingerprint == Checkpoint::fingerprint(steps) && self.completed <= steps.len())
+            .then_some(self.completed)
+    }
+}
+
+/// How long one run of a tool's commands took, step by step.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct Timing {
+    pub at: String,
+    pub seconds: f64,
+    #[serde(default)]
+    pub steps: Vec<StepTiming>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct StepTiming {
+    pub command: String,
+    pub seconds: f64,
+}
+
+impl State {
+    pub fn load() -> Result<Self> {
+        Self::load_from_path(&get_state_path()?)
+    }
+
+    pub fn load_from_path(path: &PathBuf) -> Result<Self> {
+        if !path.exists() {
+            return Ok(State::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to_path(&get_state_path()?)
+    }
+
+    /// Written private: the observed state holds the chosen parameter
+    /// values, secret ones included.
+    pub fn save_to_path(&self, path: &Path) -> Result<()> {
+        write_private(path, &serde_yaml::to_string(self)?)
+    }
+
+    pub fn stats_mut(&mut self, name: &str) -> &mut ToolStats {
+        self.tools.entry(name.to_string()).or_default()
+    }
+
+    /// Moves a tool's history along with a rename.
+    pub fn rename(&mut self, old: &str, new: &str) {
+        if let Some(stats) = self.tools.remove(old) {
+            self.tools.insert(new.to_string(), stats);
+        }
+        if let Some(observed) = self.observed.remove(old) {
+            self.observed.insert(new.to_string(), observed);
+        }
+    }
+}
+
+impl ToolStats {
+    pub fn record_run(&mut self, now: &str) {
+        self.runs += 1;
+        self.last_run = Some(now.to_string());
+    }
+
+    pub fn record_install(&mut self, now: &str, seconds: f64) {
+        self.last_install = Some(now.to_string());
+        self.install_seconds += seconds;
+    }
+
+    pub fn record_update(&mut self, now: &str, seconds: f64) {
+        self.last_update = Some(now.to_string());
+        self.install_seconds += seconds;
+    }
+
+    pub fn record_timing(&mut self, action: &str, now: &str, seconds: f64, steps: Vec<StepTiming>) {
+        let timing = Timing {
+            at: now.to_string(),
+            seconds,
+            steps,
+        };
+        self.timings.insert(action.to_string(), timing);
+    }
+
+    /// When the tool was last run, falling back to when it was installed or updated.
+    pub fn last_used(&self) -> Option<&str> {
+        [&self.last_run, &self.last_update, &self.last_install]
+            .into_iter()
+            .filter_map(|t| t.as_deref())
+            .max()
+    }
+}
+
+pub fn get_state_path() -> Result<PathBuf> {
+    let config_dir =
+        dirs::config_dir().ok_or_else(|| anyhow!("Could not determine config directory"))?;
+    Ok(config_dir.join("tkit").join(STATE_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("state.yaml");
+
+        let mut state = State::default();
+        state
+            .stats_mut("rg")
+            .record_install("2026-01-01T00:00:00+00:00", 2.5);
+        state
+            .stats_mut("rg")
+            .record_run("2026-01-02T00:00:00+00:00");
+        state
+            .stats_mut("rg")
+            .record_update("2026-01-03T00:00:00+00:00", 1.5);
+        state.save_to_path(&path).unwrap();
+
+        let loaded = State::load_from_path(&path).unwrap();
+        let stats = &loaded.tools["rg"];
+        assert_eq!(stats.runs, 1);
+        assert_eq!(stats.install_seconds, 4.0);
+        assert_eq!(stats.last_used(), Some("2026-01-03T00:00:00+00:00"));
+    }
+
+    #[test]
+    fn test_record_timing_keeps_latest_per_action() {
+        let mut stats = ToolStats::default();
+        let step = |seconds| StepTiming {
+            command: "make".to_string(),
+            seconds,
+        };
+        stats.record_timing("install", "2026-01-01T00:00:00+00:00", 9.0, vec![step(9.0)]);
+        stats.record_timing("install", "2026-01-02T00:00:00+00:00", 4.0, vec![step(4.0)]);
+        stats.record_timing("update", "2026-01-03T00:00:00+00:00", 1.0, Vec::new());
+        assert_eq!(stats.timings.len(), 2);
+        assert_eq!(stats.timings["install"].seconds, 4.0);
+        assert_eq!(stats.timings["install"].steps, vec![step(4.0)]);
+    }
+
+    #[test]
+    fn test_checkpoint_resume_at() {
+        let steps: Vec<Step> = vec!["curl -LO app.tgz".into(), "make install".into()];
+        let checkpoint = Checkpoint {
+            fingerprint: Checkpoint::fingerprint(&steps),
+            completed: 1,
+            total: 2,
+            ..Default::default()
+        };
+        assert_eq!(checkpoint.resume_at(&steps), Some(1));
+        let changed: Vec<Step> = vec!["curl -LO app2.tgz".into(), "make install".into()];
+        assert_eq!(checkpoint.resume_at(&changed), None);
+    }
+
+    #[test]
+    fn test_rename_moves_stats() {
+        let mut state = State::default();
+        state
+            .stats_mut("old")
+            .record_run("2026-01-01T00:00:00+00:00");
+        state.rename("old", "new");
+        assert!(!state.tools.contains_key("old"));
+        assert_eq!(state.tools["new"].runs, 1);
+    }
+}
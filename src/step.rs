@@ -0,0 +1,571 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::{CommandShell, render_template};
+
+/// One command of a tool's install, remove, update, run, rollback or verify
+/// list. Written as a plain string unless it sets any of the other fields.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Step {
+    /// The command, or the Rhai source of a `script:` step.
+    pub command: String,
+    /// The step is a Rhai script run by tkit, written as `script:` instead
+    /// of `command:`. Its `{{name}}` placeholders are left alone; the
+    /// variables are in scope instead.
+    pub script: bool,
+    /// Shown instead of the command while the step runs.
+    pub description: Option<String>,
+    /// Shell for this step, over the tool's.
+    pub shell: Option<CommandShell>,
+    /// Environment variables set for the step.
+    pub env: BTreeMap<String, String>,
+    /// Directory the step runs in; a leading `~` is the home directory.
+    pub cwd: Option<String>,
+    /// Runs the command through `sudo`.
+    pub sudo: bool,
+    /// Seconds after which the step is killed and counts as failed.
+    pub timeout: Option<u64>,
+    /// A failure is reported, but the remaining steps still run.
+    pub allow_failure: bool,
+    /// Name under which the step's trimmed stdout is available to later
+    /// steps as `{{name}}`; empty when the step is skipped.
+    pub register: Option<String>,
+    /// Runs the step only when this holds.
+    pub only_if: Option<Condition>,
+    /// Skips the step when this holds.
+    pub skip_if: Option<Condition>,
+}
+
+/// What gates a step: a command that holds when it exits 0, e.g.
+/// `id -nG | grep -qw docker`, or an expression over the template variables
+/// such as `{ expr: 'os == "linux" && arch != "arm64"' }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Condition {
+    Command(String),
+    Expr { expr: String },
+}
+
+impl Step {
+    pub fn new(command: impl Into<String>) -> Self {
+        Step {
+            command: command.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether nothing but the command is set, so it's written as a string.
+    pub fn is_plain(&self) -> bool {
+        !self.script
+            && self.description.is_none()
+            && self.shell.is_none()
+            && self.env.is_empty()
+            && self.cwd.is_none()
+            && !self.sudo
+            && self.timeout.is_none()
+            && !self.allow_failure
+            && self.register.is_none()
+            && self.only_if.is_none()
+            && self.skip_if.is_none()
+    }
+
+    /// The command, or the first line of a script, for one-line displays.
+    pub fn summary(&self) -> &str {
+        match self.script {
+            true => self
+                .command
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .unwrap_or_default(),
+            false => &self.command,
+        }
+    }
+
+    /// Fills in `{{name}}` placeholders in the command (unless it's a
+    /// script), description, env values, cwd and command conditions.
+    pub fn render(&self, vars: &BTreeMap<String, String>) -> Self {
+        let render = |condition: &Option<Condition>| {
+            condition.as_ref().map(|condition| match condition {
+                Condition::Command(command) => Condition::Command(render_template(command, vars)),
+                expr => expr.clone(),
+            })
+        };
+        Step {
+            command: match self.script {
+                true => self.command.clone(),
+                false => render_template(&self.command, vars),
+            },
+            description: self.description.as_ref().map(|d| render_template(d, vars)),
+            env: self
+                .env
+                .iter()
+                .map(|(k, v)| (k.clone(), render_template(v, vars)))
+                .collect(),
+            cwd: self.cwd.as_ref().map(|cwd| render_template(cwd, vars)),
+            only_if: render(&self.only_if),
+            skip_if: render(&self.skip_if),
+            ..self.clone()
+        }
+    }
+
+    /// Why the step doesn't run, if it doesn't: its `only_if` doesn't hold or
+    /// its `skip_if` does. Expressions are evaluated with `vars`; `holds`
+    /// runs command conditions.
+    pub fn skip_reason(
+        &self,
+        vars: &BTreeMap<String, String>,
+        mut holds: impl FnMut(&str) -> Result<bool>,
+    ) -> Result<Option<String>> {
+        if let Some(condition) = &self.only_if
+            && !condition.holds(vars, &mut holds)?
+        {
+            return Ok(Some(format!("only_if {}", condition)));
+        }
+        if let Some(condition) = &self.skip_if
+            && condition.holds(vars, &mut holds)?
+        {
+            return Ok(Some(format!("skip_if {}", condition)));
+        }
+        Ok(None)
+    }
+}
+
+impl Condition {
+    fn holds(
+        &self,
+        vars: &BTreeMap<String, String>,
+        holds: &mut impl FnMut(&str) -> Result<bool>,
+    ) -> Result<bool> {
+        match self {
+            Condition::Command(command) => holds(command),
+            Condition::Expr { expr } => evaluate(expr, vars),
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.command)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Command(command) => write!(f, "`{}`", command),
+            Condition::Expr { expr } => f.write_str(expr),
+        }
+    }
+}
+
+impl From<&str> for Step {
+    fn from(command: &str) -> Self {
+        Step::new(command)
+    }
+}
+
+impl From<String> for Step {
+    fn from(command: String) -> Self {
+        Step::new(command)
+    }
+}
+
+impl PartialEq<str> for Step {
+    fn eq(&self, other: &str) -> bool {
+        self.is_plain() && self.command == other
+    }
+}
+
+impl PartialEq<String> for Step {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<&str> for Step {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+/// The map form of a step.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepFields {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    script: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shell: Option<CommandShell>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    sudo: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timeout: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    allow_failure: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    register: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    only_if: Option<Condition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    skip_if: Option<Condition>,
+}
+
+impl Serialize for Step {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_plain() {
+            return self.command.serialize(serializer);
+        }
+        let step = self.clone();
+        let (command, script) = match step.script {
+            true => (String::new(), Some(step.command)),
+            false => (step.command, None),
+        };
+        StepFields {
+            command,
+            script,
+            description: step.description,
+            shell: step.shell,
+            env: step.env,
+            cwd: step.cwd,
+            sudo: step.sudo,
+            timeout: step.timeout,
+            allow_failure: step.allow_failure,
+            register: step.register,
+            only_if: step.only_if,
+            skip_if: step.skip_if,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Step {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Plain(String),
+            Fields(Box<StepFields>),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Plain(command) => Step::new(command),
+            Repr::Fields(fields) => Step {
+                script: match (fields.command.is_empty(), &fields.script) {
+                    (true, Some(_)) => true,
+                    (false, None) => false,
+                    (true, None) => return Err(D::Error::missing_field("command")),
+                    (false, Some(_)) => {
+                        return Err(D::Error::custom(
+                            "a step has either a `command` or a `script`, not both",
+                        ));
+                    }
+                },
+                command: fields.script.unwrap_or(fields.command),
+                description: fields.description,
+                shell: fields.shell,
+                env: fields.env,
+                cwd: fields.cwd,
+                sudo: fields.sudo,
+                timeout: fields.timeout,
+                allow_failure: fields.allow_failure,
+                register: fields.register,
+                only_if: fields.only_if,
+                skip_if: fields.skip_if,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Eq,
+    Ne,
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || "_.-".contains(c)
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '=' if chars.next_if_eq(&'=').is_some() => Token::Eq,
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Ne,
+            '!' => Token::Not,
+            '&' if chars.next_if_eq(&'&').is_some() => Token::And,
+            '|' if chars.next_if_eq(&'|').is_some() => Token::Or,
+            '"' | '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some(ch) => value.push(ch),
+                        None => return Err(anyhow!("Unterminated string in '{}'", expr)),
+                    }
+                }
+                Token::Str(value)
+            }
+            c if is_word_char(c) => {
+                let mut word = c.to_string();
+                while let Some(ch) = chars.next_if(|ch| is_word_char(*ch)) {
+                    word.push(ch);
+                }
+                Token::Word(word)
+            }
+            _ => return Err(anyhow!("Unexpected '{}' in '{}'", c, expr)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// A value met while evaluating: comparisons give booleans, variables and
+/// literals give strings.
+#[derive(Debug, Clone, PartialEq)]
+enum ExprValue {
+    Bool(bool),
+    Str(String),
+}
+
+impl ExprValue {
+    /// Strings are true unless empty, `false` or `0`.
+    fn truthy(&self) -> bool {
+        match self {
+            ExprValue::Bool(b) => *b,
+            ExprValue::Str(s) => !matches!(s.as_str(), "" | "false" | "0"),
+        }
+    }
+
+    fn as_string(&self) -> String {
+        match self {
+            ExprValue::Bool(b) => b.to_string(),
+            ExprValue::Str(s) => s.clone(),
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    vars: &'a BTreeMap<String, String>,
+    expr: &'a str,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<ExprValue> {
+        let mut value = self.and()?;
+        while self.eat(&Token::Or) {
+            let right = self.and()?;
+            value = ExprValue::Bool(value.truthy() || right.truthy());
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<ExprValue> {
+        let mut value = self.comparison()?;
+        while self.eat(&Token::And) {
+            let right = self.comparison()?;
+            value = ExprValue::Bool(value.truthy() && right.truthy());
+        }
+        Ok(value)
+    }
+
+    fn comparison(&mut self) -> Result<ExprValue> {
+        let left = self.unary()?;
+        if self.eat(&Token::Eq) {
+            let right = self.unary()?;
+            return Ok(ExprValue::Bool(left.as_string() == right.as_string()));
+        }
+        if self.eat(&Token::Ne) {
+            let right = self.unary()?;
+            return Ok(ExprValue::Bool(left.as_string() != right.as_string()));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<ExprValue> {
+        if self.eat(&Token::Not) {
+            return Ok(ExprValue::Bool(!self.unary()?.truthy()));
+        }
+        if self.eat(&Token::Open) {
+            let value = self.or()?;
+            if !self.eat(&Token::Close) {
+                return Err(anyhow!("Missing ')' in '{}'", self.expr));
+            }
+            return Ok(value);
+        }
+
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Str(value)) => Ok(ExprValue::Str(value)),
+            Some(Token::Word(word)) if word == "true" || word == "false" => {
+                Ok(ExprValue::Bool(word == "true"))
+            }
+            Some(Token::Word(word)) if word.starts_with(|c: char| c.is_ascii_digit()) => {
+                Ok(ExprValue::Str(word))
+            }
+            Some(Token::Word(word)) => match self.vars.get(&word) {
+                Some(value) => Ok(ExprValue::Str(value.clone())),
+                None => Err(anyhow!(
+                    "Unknown variable '{}' in '{}' (quote literal values, e.g. \"{}\")",
+                    word,
+                    self.expr,
+                    word
+                )),
+            },
+            _ => Err(anyhow!("Incomplete condition '{}'", self.expr)),
+        }
+    }
+}
+
+/// Evaluates a condition expression: variables, quoted strings, `true`,
+/// `false`, `==`, `!=`, `!`, `&&`, `||` and parentheses.
+pub fn evaluate(expr: &str, vars: &BTreeMap<String, String>) -> Result<bool> {
+    let mut parser = Parser {
+        tokens: tokenize(expr)?,
+        pos: 0,
+        vars,
+        expr,
+    };
+    let value = parser.or()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(anyhow!("Unexpected trailing input in '{}'", expr));
+    }
+    Ok(value.truthy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("os".to_string(), "linux".to_string()),
+            ("arch".to_string(), "amd64".to_string()),
+            ("gpu".to_string(), "".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let vars = vars();
+        assert!(evaluate(r#"os == "linux""#, &vars).unwrap());
+        assert!(!evaluate(r#"os == 'darwin'"#, &vars).unwrap());
+        assert!(evaluate(r#"os == "linux" && arch != "arm64""#, &vars).unwrap());
+        assert!(evaluate(r#"!(os == "darwin" || gpu)"#, &vars).unwrap());
+        assert!(!evaluate("gpu", &vars).unwrap());
+        assert!(evaluate("true", &vars).unwrap());
+        assert!(evaluate("os == linux", &vars).is_err());
+        assert!(evaluate("(os", &vars).is_err());
+        assert!(evaluate("os ==", &vars).is_err());
+    }
+
+    #[test]
+    fn test_step_yaml() {
+        let steps: Vec<Step> = serde_yaml::from_str(
+            "- apt update\n- command: usermod -aG docker me\n  skip_if: id -nG | grep -qw docker\n- command: brew install jq\n  only_if:\n    expr: os == \"darwin\"\n",
+        )
+        .unwrap();
+        assert_eq!(steps[0], "apt update");
+        assert_eq!(
+            steps[1].skip_if,
+            Some(Condition::Command("id -nG | grep -qw docker".to_string()))
+        );
+
+        let vars = vars();
+        let never = |_: &str| -> Result<bool> { unreachable!() };
+        assert_eq!(
+            steps[2].skip_reason(&vars, never).unwrap().as_deref(),
+            Some("only_if os == \"darwin\"")
+        );
+        assert_eq!(steps[1].skip_reason(&vars, |_| Ok(false)).unwrap(), None);
+        assert!(steps[1].skip_reason(&vars, |_| Ok(true)).unwrap().is_some());
+
+        let yaml = serde_yaml::to_string(&steps).unwrap();
+        assert!(yaml.starts_with("- apt update\n- command: usermod"));
+        assert_eq!(serde_yaml::from_str::<Vec<Step>>(&yaml).unwrap(), steps);
+    }
+
+    #[test]
+    fn test_step_fields() {
+        let step: Step = serde_yaml::from_str(
+            "command: make install\ndescription: Build {{version}}\nshell: bash\nenv:\n  PREFIX: ~/.local/{{version}}\ncwd: ~/src/app\nsudo: true\ntimeout: 600\nallow_failure: true\n",
+        )
+        .unwrap();
+        assert_eq!(step.shell, Some(CommandShell::Bash));
+        assert_eq!(
+            (step.sudo, step.timeout, step.allow_failure),
+            (true, Some(600), true)
+        );
+        assert!(!step.is_plain());
+        assert_ne!(step, "make install");
+
+        let vars = BTreeMap::from([("version".to_string(), "1.2".to_string())]);
+        let rendered = step.render(&vars);
+        assert_eq!(rendered.description.as_deref(), Some("Build 1.2"));
+        assert_eq!(rendered.env["PREFIX"], "~/.local/1.2");
+        assert!(rendered.sudo);
+
+        let yaml = serde_yaml::to_string(&step).unwrap();
+        assert!(!yaml.contains("only_if"));
+        assert_eq!(serde_yaml::from_str::<Step>(&yaml).unwrap(), step);
+        assert!(serde_yaml::from_str::<Step>("command: ls\nretries: 3\n").is_err());
+
+        let step: Step = serde_yaml::from_str("script: print(`{{x}} ${os}`)\n").unwrap();
+        assert!(step.script && !step.is_plain());
+        assert_eq!(step.summary(), "print(`{{x}} ${os}`)");
+        assert_eq!(step.render(&vars).command, "print(`{{x}} ${os}`)");
+        let yaml = serde_yaml::to_string(&step).unwrap();
+        assert!(yaml.starts_with("script:") && !yaml.contains("command"));
+        assert_eq!(serde_yaml::from_str::<Step>(&yaml).unwrap(), step);
+        assert!(serde_yaml::from_str::<Step>("command: ls\nscript: print(1)\n").is_err());
+        assert!(serde_yaml::from_str::<Step>("sudo: true\n").is_err());
+
+        let step: Step = serde_yaml::from_str("command: cat VERSION\nregister: version\n").unwrap();
+        assert_eq!(step.register.as_deref(), Some("version"));
+        assert!(
+            serde_yaml::to_string(&step)
+                .unwrap()
+                .contains("register: version")
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::get_config_path;
+
+/// Auto-sync pushes that couldn't reach a remote because this machine was
+/// offline. They go out with the next push that gets through, or with
+/// `tkit sync flush`.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct SyncQueue {
+    #[serde(default)]
+    pub pushes: Vec<QueuedPush>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct QueuedPush {
+    /// RFC 3339 time the push was attempted.
+    pub queued_at: String,
+    /// Commit message the push would have had.
+    pub message: String,
+}
+
+impl SyncQueue {
+    pub fn load() -> Result<Self> {
+        Self::load_from_path(&get_sync_queue_path()?)
+    }
+
+    pub fn load_from_path(path: &PathBuf) -> Result<Self> {
+        if !path.exists() {
+            return Ok(SyncQueue::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to_path(&get_sync_queue_path()?)
+    }
+
+    /// Writes the queue, or removes the file once nothing is waiting.
+    pub fn save_to_path(&self, path: &PathBuf) -> Result<()> {
+        if self.pushes.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn push(&mut self, message: &str, now: &str) {
+        self.pushes.push(QueuedPush {
+            queued_at: now.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    /// Commit message for a push that also carries the queued ones.
+    pub fn commit_message(&self, latest: &str) -> String {
+        if self.pushes.is_empty() {
+            return latest.to_string();
+        }
+        let queued: Vec<String> = self
+            .pushes
+            .iter()
+            .map(|push| format!("- {}", push.message.lines().next().unwrap_or_default()))
+            .collect();
+        format!(
+            "{}\n\nIncludes changes queued while offline:\n{}",
+            latest,
+            queued.join("\n")
+        )
+    }
+}
+
+pub fn get_sync_queue_path() -> Result<PathBuf> {
+    Ok(get_config_path()?.with_file_name("sync-queue.yaml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_queue_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("sync-queue.yaml");
+
+        let mut queue = SyncQueue::default();
+        queue.push("install docker on laptop", "2026-01-01T00:00:00+00:00");
+        queue.save_to_path(&path).unwrap();
+        let loaded = SyncQueue::load_from_path(&path).unwrap();
+        assert_eq!(loaded.pushes, queue.pushes);
+
+        assert_eq!(
+            loaded.commit_message("add jq on laptop"),
+            "add jq on laptop\n\nIncludes changes queued while offline:\n- install docker on laptop"
+        );
+
+        SyncQueue::default().save_to_path(&path).unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{ToolConfig, get_config_path};
+
+/// Tool definitions removed by `tkit delete`, kept on this machine until
+/// `tkit restore` brings one back or `tkit trash empty` drops them.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct Trash {
+    #[serde(default)]
+    pub tools: BTreeMap<String, TrashedTool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrashedTool {
+    /// RFC 3339 time of the delete.
+    pub deleted_at: String,
+    pub tool: ToolConfig,
+}
+
+impl Trash {
+    pub fn load() -> Result<Self> {
+        Self::load_from_path(&get_trash_path()?)
+    }
+
+    pub fn load_from_path(path: &PathBuf) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Trash::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to_path(&get_trash_path()?)
+    }
+
+    pub fn save_to_path(&self, path: &PathBuf) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Keeps a deleted tool, replacing an older copy of the same name.
+    pub fn put(&mut self, tool: ToolConfig, now: &str) {
+        self.tools.insert(
+            tool.name.clone(),
+            TrashedTool {
+                deleted_at: now.to_string(),
+                tool,
+            },
+        );
+    }
+
+    pub fn take(&mut self, name: &str) -> Result<ToolConfig> {
+        self.tools
+            .remove(name)
+            .map(|trashed| trashed.tool)
+            .ok_or_else(|| anyhow!("Tool '{}' is not in the trash.", name))
+    }
+}
+
+pub fn get_trash_path() -> Result<PathBuf> {
+    Ok(get_config_path()?.with_file_name("trash.yaml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_put_take_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("trash.yaml");
+        let tool = ToolConfig {
+            name: "rg".to_string(),
+            install_commands: vec!["cargo install ripgrep".into()],
+            ..Default::default()
+        };
+
+        let mut trash = Trash::default();
+        trash.put(tool.clone(), "2026-01-01T00:00:00+00:00");
+        trash.save_to_path(&path).unwrap();
+
+        let mut loaded = Trash::load_from_path(&path).unwrap();
+        assert_eq!(loaded.tools["rg"].deleted_at, "2026-01-01T00:00:00+00:00");
+        let restored = loaded.take("rg").unwrap();
+        assert_eq!(restored.install_commands, tool.install_commands);
+        assert!(loaded.take("rg").is_err());
+    }
+}